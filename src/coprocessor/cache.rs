@@ -0,0 +1,166 @@
+// Copyright 2017 PingCAP, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::VecDeque;
+
+use kvproto::coprocessor::{Request, Response};
+use kvproto::kvrpcpb::IsolationLevel;
+use protobuf::Message;
+
+use util::codec::bytes::BytesEncoder;
+use util::codec::number::NumberEncoder;
+use util::collections::HashMap;
+
+use super::metrics::*;
+
+/// `ResultCache` keeps the responses of the latest requests, so a request retried on
+/// the same snapshot is answered without being handled again. The keys and the encoded
+/// responses kept take at most `capacity` bytes, and the oldest response is evicted first.
+pub struct ResultCache {
+    capacity: usize,
+    size: usize,
+    responses: HashMap<Vec<u8>, Response>,
+    keys: VecDeque<Vec<u8>>,
+    hits: u64,
+}
+
+impl ResultCache {
+    pub fn new(capacity: usize) -> ResultCache {
+        ResultCache {
+            capacity: capacity,
+            size: 0,
+            responses: HashMap::default(),
+            keys: VecDeque::new(),
+            hits: 0,
+        }
+    }
+
+    /// `cache_key` returns the key of `req`, or `None` if its result can't be cached.
+    /// The request data contains the start ts, which pins the snapshot to read.
+    pub fn cache_key(req: &Request) -> Option<Vec<u8>> {
+        let ctx = req.get_context();
+        // read committed requests read the latest data rather than a snapshot.
+        if ctx.get_isolation_level() != IsolationLevel::SI {
+            return None;
+        }
+        let mut key = Vec::with_capacity(req.get_data().len() + 32);
+        key.encode_u64(ctx.get_region_id()).unwrap();
+        key.encode_u64(ctx.get_region_epoch().get_conf_ver()).unwrap();
+        key.encode_u64(ctx.get_region_epoch().get_version()).unwrap();
+        key.encode_i64(req.get_tp()).unwrap();
+        key.encode_compact_bytes(req.get_data()).unwrap();
+        for range in req.get_ranges() {
+            key.encode_compact_bytes(range.get_start()).unwrap();
+            key.encode_compact_bytes(range.get_end()).unwrap();
+        }
+        Some(key)
+    }
+
+    pub fn get(&mut self, key: &[u8]) -> Option<Response> {
+        let resp = self.responses.get(key).cloned();
+        if resp.is_some() {
+            self.hits += 1;
+            COPR_RESULT_CACHE_HIT.inc();
+        }
+        resp
+    }
+
+    pub fn insert(&mut self, key: Vec<u8>, resp: &Response) {
+        // errors may go away on retry, so only successful responses are kept.
+        if self.capacity == 0 || resp.has_region_error() || resp.has_locked() ||
+            !resp.get_other_error().is_empty()
+        {
+            return;
+        }
+        if self.responses.contains_key(&key) {
+            return;
+        }
+        let size = entry_size(&key, resp);
+        if size > self.capacity {
+            return;
+        }
+        while self.size + size > self.capacity {
+            let oldest = self.keys.pop_front().unwrap();
+            let resp = self.responses.remove(&oldest).unwrap();
+            self.size -= entry_size(&oldest, &resp);
+        }
+        self.size += size;
+        self.keys.push_back(key.clone());
+        self.responses.insert(key, resp.clone());
+    }
+
+    /// `hits` returns the number of requests served from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+}
+
+fn entry_size(key: &[u8], resp: &Response) -> usize {
+    key.len() + resp.compute_size() as usize
+}
+
+#[cfg(test)]
+mod test {
+    use kvproto::coprocessor::{Request, Response};
+    use kvproto::kvrpcpb::IsolationLevel;
+
+    use super::*;
+
+    fn new_request(data: &[u8]) -> Request {
+        let mut req = Request::new();
+        req.set_tp(103);
+        req.set_data(data.to_vec());
+        req
+    }
+
+    fn new_response(data: &[u8]) -> Response {
+        let mut resp = Response::new();
+        resp.set_data(data.to_vec());
+        resp
+    }
+
+    #[test]
+    fn test_result_cache() {
+        let keys: Vec<_> = [b"a", b"b", b"c"]
+            .iter()
+            .map(|d| ResultCache::cache_key(&new_request(*d)).unwrap())
+            .collect();
+        // there is room for two of the responses.
+        let mut cache = ResultCache::new(2 * entry_size(&keys[0], &new_response(b"1")));
+        cache.insert(keys[0].clone(), &new_response(b"1"));
+        cache.insert(keys[1].clone(), &new_response(b"2"));
+        assert_eq!(cache.get(&keys[0]).unwrap().get_data(), b"1");
+        // the oldest one is evicted.
+        cache.insert(keys[2].clone(), &new_response(b"3"));
+        assert!(cache.get(&keys[0]).is_none());
+        assert_eq!(cache.get(&keys[1]).unwrap().get_data(), b"2");
+        assert_eq!(cache.get(&keys[2]).unwrap().get_data(), b"3");
+        assert_eq!(cache.hits(), 3);
+
+        // a response larger than the cache is not kept.
+        cache.insert(keys[0].clone(), &new_response(&[0; 64]));
+        assert!(cache.get(&keys[0]).is_none());
+        assert_eq!(cache.get(&keys[1]).unwrap().get_data(), b"2");
+
+        // failed responses are not cached.
+        let mut resp = new_response(b"");
+        resp.set_other_error("error".to_owned());
+        cache.insert(keys[0].clone(), &resp);
+        assert!(cache.get(&keys[0]).is_none());
+
+        // neither are the results of read committed requests.
+        let mut req = new_request(b"a");
+        req.mut_context().set_isolation_level(IsolationLevel::RC);
+        assert!(ResultCache::cache_key(&req).is_none());
+    }
+}
@@ -183,6 +183,8 @@ pub fn bytes_to_f64(ctx: &EvalContext, bytes: &[u8]) -> Result<f64> {
     bytes_to_f64_without_context(vs.as_bytes())
 }
 
+const TRUNCATED_MSG: &'static str = "[1265] Data Truncated";
+
 #[inline]
 pub fn handle_truncate_as_error(ctx: &EvalContext) -> bool {
     !(ctx.ignore_truncate || ctx.truncate_as_warning)
@@ -190,11 +192,14 @@ pub fn handle_truncate_as_error(ctx: &EvalContext) -> bool {
 
 #[inline]
 pub fn handle_truncate(ctx: &EvalContext, is_truncated: bool) -> Result<()> {
-    if is_truncated && handle_truncate_as_error(ctx) {
-        Err(box_err!("[1265] Data Truncated"))
-    } else {
-        Ok(())
+    if !is_truncated || ctx.ignore_truncate {
+        return Ok(());
+    }
+    if ctx.truncate_as_warning {
+        ctx.append_warning(TRUNCATED_MSG.to_owned());
+        return Ok(());
     }
+    Err(box_err!("{}", TRUNCATED_MSG))
 }
 
 fn get_valid_int_prefix<'a>(ctx: &EvalContext, s: &'a str) -> Result<Cow<'a, str>> {
@@ -411,21 +416,25 @@ mod test {
                 tz: FixedOffset::east(0),
                 ignore_truncate: true,
                 truncate_as_warning: true,
+                ..Default::default()
             },
             EvalContext {
                 tz: FixedOffset::east(0),
                 ignore_truncate: true,
                 truncate_as_warning: false,
+                ..Default::default()
             },
             EvalContext {
                 tz: FixedOffset::east(0),
                 ignore_truncate: false,
                 truncate_as_warning: true,
+                ..Default::default()
             },
             EvalContext {
                 tz: FixedOffset::east(0),
                 ignore_truncate: false,
                 truncate_as_warning: false,
+                ..Default::default()
             },
         ];
 
@@ -437,6 +446,28 @@ mod test {
         assert!(super::handle_truncate(&ctxs[1], true).is_ok());
         assert!(super::handle_truncate(&ctxs[2], true).is_ok());
         assert!(super::handle_truncate(&ctxs[3], true).is_err());
+
+        // only ctxs[2] turns truncate into warnings.
+        for ctx in &ctxs[..2] {
+            assert_eq!(ctx.warnings.borrow().warning_cnt, 0);
+        }
+        assert_eq!(ctxs[2].warnings.borrow().warning_cnt, 1);
+    }
+
+    #[test]
+    fn test_truncate_warnings_cap() {
+        let mut ctx = EvalContext {
+            truncate_as_warning: true,
+            ..Default::default()
+        };
+        ctx.set_max_warning_cnt(3);
+        for _ in 0..5 {
+            super::handle_truncate(&ctx, true).unwrap();
+        }
+        let warnings = ctx.warnings.borrow();
+        assert_eq!(warnings.warning_cnt, 5);
+        assert_eq!(warnings.warnings.len(), 3);
+        assert!(warnings.truncated);
     }
 
     #[test]
@@ -462,6 +493,7 @@ mod test {
             tz: FixedOffset::east(0),
             ignore_truncate: true,
             truncate_as_warning: false,
+            ..Default::default()
         };
         for (i, o) in cases {
             assert_eq!(super::get_valid_float_prefix(&ctx, i).unwrap(), o);
@@ -82,21 +82,39 @@ impl Debug for Datum {
     }
 }
 
+/// `cmp_f64` compares two floats in a total order, in which -0.0 equals 0.0 and NaN
+/// is greater than any other value, just like how they are sorted after being encoded.
 #[inline]
 pub fn cmp_f64(l: f64, r: f64) -> Result<Ordering> {
-    l.partial_cmp(&r)
-        .ok_or_else(|| invalid_type!("{} and {} can't be compared", l, r))
+    let ord = match l.partial_cmp(&r) {
+        Some(ord) => ord,
+        None => l.is_nan().cmp(&r.is_nan()),
+    };
+    Ok(ord)
 }
 
 #[inline]
 fn checked_add_i64(l: u64, r: i64) -> Option<u64> {
     if r >= 0 {
-        Some(l + r as u64)
+        l.checked_add(r as u64)
     } else {
         l.checked_sub(r.overflowing_neg().0 as u64)
     }
 }
 
+/// `DIVISION_BY_ZERO_MSG` is the warning of a division or modulo by zero returning NULL.
+pub const DIVISION_BY_ZERO_MSG: &'static str = "[1365] Division by 0";
+
+/// `divided_by_zero` returns the result of a division or modulo by zero, which is an
+/// error in write statements like the strict sql mode, or NULL with a warning otherwise.
+fn divided_by_zero(ctx: &EvalContext) -> Result<Datum> {
+    if ctx.in_insert_stmt || ctx.in_update_or_delete_stmt {
+        return Err(box_err!("division by zero"));
+    }
+    ctx.append_warning(DIVISION_BY_ZERO_MSG.to_owned());
+    Ok(Datum::Null)
+}
+
 impl Datum {
     pub fn cmp(&self, ctx: &EvalContext, datum: &Datum) -> Result<Ordering> {
         if let Datum::Json(_) = *self {
@@ -141,6 +159,7 @@ impl Datum {
             } else {
                 Ok(u.cmp(&(i as u64)))
             },
+            Datum::Dec(ref d) => Ok(d.cmp(&i.into())),
             _ => self.cmp_f64(ctx, i as f64),
         }
     }
@@ -153,6 +172,7 @@ impl Datum {
                 Ok(i.cmp(&(u as i64)))
             },
             Datum::U64(uu) => Ok(uu.cmp(&u)),
+            Datum::Dec(ref d) => Ok(d.cmp(&u.into())),
             _ => self.cmp_f64(ctx, u as f64),
         }
     }
@@ -213,6 +233,9 @@ impl Datum {
     fn cmp_dec(&self, ctx: &EvalContext, dec: &Decimal) -> Result<Ordering> {
         match *self {
             Datum::Dec(ref d) => Ok(d.cmp(dec)),
+            // integers are compared exactly instead of as floats.
+            Datum::I64(i) => Ok(Decimal::from(i).cmp(dec)),
+            Datum::U64(u) => Ok(Decimal::from(u).cmp(dec)),
             Datum::Bytes(ref bs) => {
                 let s = str::from_utf8(bs)?;
                 let d = s.parse::<Decimal>()?;
@@ -506,15 +529,15 @@ impl Datum {
             (Datum::F64(f), d) => {
                 let f2 = d.into_f64(ctx)?;
                 if f2 == 0f64 {
-                    return Ok(Datum::Null);
+                    return divided_by_zero(ctx);
                 }
                 Ok(Datum::F64(f / f2))
             }
             (a, b) => {
                 let a = a.into_dec()?;
                 let b = b.into_dec()?;
-                match a / b {
-                    None => Ok(Datum::Null),
+                match a.div(b, ctx.div_frac_incr) {
+                    None => divided_by_zero(ctx),
                     Some(res) => {
                         let d = res.into_result()?;
                         Ok(Datum::Dec(d))
@@ -525,7 +548,7 @@ impl Datum {
     }
 
     /// Keep compatible with TiDB's `ComputePlus` function.
-    pub fn checked_add(self, _: &EvalContext, d: Datum) -> Result<Datum> {
+    pub fn checked_add(self, ctx: &EvalContext, d: Datum) -> Result<Datum> {
         let res: Datum = match (&self, &d) {
             (&Datum::I64(l), &Datum::I64(r)) => l.checked_add(r).into(),
             (&Datum::I64(l), &Datum::U64(r)) | (&Datum::U64(r), &Datum::I64(l)) => {
@@ -547,6 +570,9 @@ impl Datum {
             (l, r) => return Err(invalid_type!("{:?} and {:?} can't be add together.", l, r)),
         };
         if let Datum::Null = res {
+            if ctx.ignore_overflow {
+                return Ok(Datum::Null);
+            }
             return Err(box_err!("{:?} + {:?} overflow", self, d));
         }
         Ok(res)
@@ -603,10 +629,10 @@ impl Datum {
     }
 
     // `checked_rem` computes the result of a mod b.
-    pub fn checked_rem(self, _: &EvalContext, d: Datum) -> Result<Datum> {
+    pub fn checked_rem(self, ctx: &EvalContext, d: Datum) -> Result<Datum> {
         match d {
-            Datum::I64(0) | Datum::U64(0) => return Ok(Datum::Null),
-            Datum::F64(f) if f == 0f64 => return Ok(Datum::Null),
+            Datum::I64(0) | Datum::U64(0) => return divided_by_zero(ctx),
+            Datum::F64(f) if f == 0f64 => return divided_by_zero(ctx),
             _ => {}
         }
         match (self, d) {
@@ -620,7 +646,7 @@ impl Datum {
             (Datum::U64(l), Datum::U64(r)) => Ok(Datum::U64(l % r)),
             (Datum::F64(l), Datum::F64(r)) => Ok(Datum::F64(l % r)),
             (Datum::Dec(l), Datum::Dec(r)) => match l % r {
-                None => Ok(Datum::Null),
+                None => divided_by_zero(ctx),
                 Some(res) => {
                     let d = res.into_result()?;
                     Ok(Datum::Dec(d))
@@ -631,9 +657,9 @@ impl Datum {
     }
 
     // `checked_int_div` computes the result of a / b, both a and b are integer.
-    pub fn checked_int_div(self, _: &EvalContext, d: Datum) -> Result<Datum> {
+    pub fn checked_int_div(self, ctx: &EvalContext, d: Datum) -> Result<Datum> {
         match d {
-            Datum::I64(0) | Datum::U64(0) => return Ok(Datum::Null),
+            Datum::I64(0) | Datum::U64(0) => return divided_by_zero(ctx),
             _ => {}
         }
         match (self, d) {
@@ -664,7 +690,7 @@ impl Datum {
                 let a = l.into_dec()?;
                 let b = r.into_dec()?;
                 match a / b {
-                    None => Ok(Datum::Null),
+                    None => divided_by_zero(ctx),
                     Some(res) => {
                         let i = res.unwrap().as_i64().unwrap();
                         Ok(Datum::I64(i))
@@ -972,7 +998,7 @@ mod test {
 
     use std::cmp::Ordering;
     use std::time::Duration as StdDuration;
-    use std::{i16, i32, i64, i8, u16, u32, u64, u8};
+    use std::{f64, i16, i32, i64, i8, u16, u32, u64, u8};
 
     fn same_type(l: &Datum, r: &Datum) -> bool {
         match (l, r) {
@@ -1074,6 +1100,11 @@ mod test {
             (Datum::F64(1.0), Datum::Max, Ordering::Less),
             (Datum::F64(1.0), Datum::F64(1.0), Ordering::Equal),
             (Datum::F64(1.0), b"1".as_ref().into(), Ordering::Equal),
+            (Datum::F64(-0.0), Datum::F64(0.0), Ordering::Equal),
+            (Datum::F64(f64::NAN), Datum::F64(f64::NAN), Ordering::Equal),
+            (Datum::F64(f64::NAN), Datum::F64(f64::INFINITY), Ordering::Greater),
+            (Datum::F64(f64::NEG_INFINITY), Datum::F64(f64::NAN), Ordering::Less),
+            (Datum::I64(1), Datum::F64(f64::NAN), Ordering::Less),
             (Datum::I64(1), Datum::I64(1), Ordering::Equal),
             (Datum::I64(-1), Datum::I64(1), Ordering::Less),
             (Datum::I64(-1), b"-1".as_ref().into(), Ordering::Equal),
@@ -1100,6 +1131,26 @@ mod test {
                 b"1".as_ref().into(),
                 Ordering::Equal,
             ),
+            (
+                Datum::Dec("1.50".parse().unwrap()),
+                Datum::Dec("1.5".parse().unwrap()),
+                Ordering::Equal,
+            ),
+            (
+                Datum::Dec("10.1".parse().unwrap()),
+                Datum::Dec("9.99".parse().unwrap()),
+                Ordering::Greater,
+            ),
+            (
+                Datum::Dec("9007199254740993".parse().unwrap()),
+                Datum::I64(9007199254740992),
+                Ordering::Greater,
+            ),
+            (
+                Datum::U64(u64::MAX),
+                Datum::Dec("18446744073709551615.5".parse().unwrap()),
+                Ordering::Less,
+            ),
             (b"1".as_ref().into(), b"1".as_ref().into(), Ordering::Equal),
             (b"1".as_ref().into(), Datum::I64(-1), Ordering::Greater),
             (b"1".as_ref().into(), Datum::U64(1), Ordering::Equal),
@@ -1633,6 +1684,39 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_sentinel_cmp() {
+        let edges = vec![
+            Datum::I64(i64::MIN),
+            Datum::I64(i64::MAX),
+            Datum::U64(0),
+            Datum::U64(u64::MAX),
+            Datum::F64(f64::MIN),
+            Datum::F64(f64::MAX),
+            b"".as_ref().into(),
+            Datum::Bytes(vec![255; 20]),
+            Datum::Dec(i64::MIN.into()),
+            Datum::Dec(u64::MAX.into()),
+            Datum::Dur(Duration::parse(b"-838:59:59", 0).unwrap()),
+            Datum::Dur(Duration::parse(b"838:59:59", 0).unwrap()),
+            Datum::Time(Time::parse_utc_datetime("9999-12-31 23:59:59", 0).unwrap()),
+        ];
+        let ctx = Default::default();
+        let min_bs = encode_key(&[Datum::Min]).unwrap();
+        let max_bs = encode_key(&[Datum::Max]).unwrap();
+        for d in edges {
+            assert_eq!(Datum::Min.cmp(&ctx, &d).unwrap(), Ordering::Less, "{:?}", d);
+            assert_eq!(d.cmp(&ctx, &Datum::Min).unwrap(), Ordering::Greater, "{:?}", d);
+            assert_eq!(Datum::Max.cmp(&ctx, &d).unwrap(), Ordering::Greater, "{:?}", d);
+            assert_eq!(d.cmp(&ctx, &Datum::Max).unwrap(), Ordering::Less, "{:?}", d);
+
+            // sentinels are used as range bounds, so they must be true bounds when encoded.
+            let bs = encode_key(as_slice(&d)).unwrap();
+            assert!(min_bs < bs, "{:?}", d);
+            assert!(bs < max_bs, "{:?}", d);
+        }
+    }
+
     #[test]
     fn test_datum_to_bool() {
         let tests = vec![
@@ -1674,6 +1758,7 @@ mod test {
             tz: FixedOffset::east(0),
             ignore_truncate: true,
             truncate_as_warning: true,
+            ..Default::default()
         };
 
         for (d, b) in tests {
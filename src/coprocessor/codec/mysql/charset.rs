@@ -34,3 +34,25 @@ pub const COLLATION_LATIN1: &'static str = "latin1_bin";
 
 // All utf8 charsets.
 pub const UTF8_CHARSETS: &'static [&'static str] = &[CHARSET_UTF8, CHARSET_UTF8MB4, CHARSET_ASCII];
+
+// Ids of the case insensitive collations, see `information_schema.collations`.
+pub const COLLATION_LATIN1_SWEDISH_CI_ID: i32 = 8;
+pub const COLLATION_ASCII_GENERAL_CI_ID: i32 = 11;
+pub const COLLATION_UTF8_GENERAL_CI_ID: i32 = 33;
+pub const COLLATION_UTF8MB4_GENERAL_CI_ID: i32 = 45;
+pub const COLLATION_UTF8_UNICODE_CI_ID: i32 = 192;
+pub const COLLATION_UTF8MB4_UNICODE_CI_ID: i32 = 224;
+
+/// `is_ci_collation` checks whether the collation with `id` compares strings
+/// case insensitively.
+pub fn is_ci_collation(id: i32) -> bool {
+    match id {
+        COLLATION_LATIN1_SWEDISH_CI_ID |
+        COLLATION_ASCII_GENERAL_CI_ID |
+        COLLATION_UTF8_GENERAL_CI_ID |
+        COLLATION_UTF8MB4_GENERAL_CI_ID |
+        COLLATION_UTF8_UNICODE_CI_ID |
+        COLLATION_UTF8MB4_UNICODE_CI_ID => true,
+        _ => false,
+    }
+}
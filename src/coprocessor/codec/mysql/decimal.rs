@@ -111,7 +111,8 @@ const DIG_MASK: u32 = TEN_POW[8];
 const WORD_BASE: u32 = TEN_POW[9];
 const WORD_MAX: u32 = WORD_BASE - 1;
 const MAX_FRACTION: u8 = 30;
-const DEFAULT_DIV_FRAC_INCR: u8 = 4;
+/// `DEFAULT_DIV_FRAC_INCR` is the default `div_precision_increment` of MySQL.
+pub const DEFAULT_DIV_FRAC_INCR: u8 = 4;
 const DIG_2_BYTES: &'static [u8] = &[0, 1, 1, 2, 2, 3, 3, 4, 4, 4];
 const FRAC_MAX: &'static [u32] = &[
     900000000,
@@ -1103,6 +1104,35 @@ impl Decimal {
         self.round_with_word_buf_len(frac, WORD_BUF_LEN, round_mode)
     }
 
+    /// `round_half_to_even` rounds the decimal to "frac" digits like `RoundMode::HalfEven`,
+    /// except that a tie is rounded to the even neighbour instead of away from zero, which
+    /// is known as banker's rounding.
+    pub fn round_half_to_even(self, frac: i8) -> Res<Decimal> {
+        let truncated = match self.clone().round(frac, RoundMode::Truncate) {
+            Res::Ok(d) => d,
+            _ => return self.round(frac, RoundMode::HalfEven),
+        };
+        let is_tie = match (&self - &truncated, Decimal::from(5).shift(-(frac as isize) - 1)) {
+            (Res::Ok(diff), Res::Ok(half)) => diff.abs().unwrap() == half,
+            _ => false,
+        };
+        if !is_tie {
+            return self.round(frac, RoundMode::HalfEven);
+        }
+        let is_even = match truncated.clone().shift(frac as isize) {
+            Res::Ok(digits) => match digits % Decimal::from(2) {
+                Some(Res::Ok(rem)) => rem.is_zero(),
+                _ => false,
+            },
+            _ => false,
+        };
+        if is_even {
+            Res::Ok(truncated)
+        } else {
+            self.round(frac, RoundMode::HalfEven)
+        }
+    }
+
     pub fn round_with_word_buf_len(
         mut self,
         mut frac: i8,
@@ -1638,6 +1668,31 @@ impl Decimal {
         let len = word_cnt!(self.int_cnt) + word_cnt!(self.frac_cnt);
         self.word_buf[0..len as usize].iter().all(|&x| x == 0)
     }
+
+    /// `remove_trailing_zeroes` returns the decimal without the trailing zeroes of its
+    /// fraction, encoded with the least precision, or `None` if it's already so. Equal
+    /// decimals are encoded the same after it.
+    pub fn remove_trailing_zeroes(&self) -> Option<Decimal> {
+        let int_word_cnt = word_cnt!(self.int_cnt) as usize;
+        let mut frac_cnt = self.frac_cnt;
+        while frac_cnt > 0 {
+            let idx = (frac_cnt - 1) as usize;
+            let word = self.word_buf[int_word_cnt + idx / DIGITS_PER_WORD as usize];
+            let pow = TEN_POW[DIGITS_PER_WORD as usize - 1 - idx % DIGITS_PER_WORD as usize];
+            if word / pow % 10 != 0 {
+                break;
+            }
+            frac_cnt -= 1;
+        }
+        if frac_cnt == self.frac_cnt && self.precision == 0 {
+            return None;
+        }
+        let mut d = self.clone();
+        d.frac_cnt = frac_cnt;
+        d.result_frac_cnt = frac_cnt;
+        d.precision = 0;
+        Some(d)
+    }
 }
 
 macro_rules! enable_conv_for_int {
@@ -2564,6 +2619,32 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_round_half_to_even() {
+        let cases = vec![
+            ("2.5", 0, "2"),
+            ("3.5", 0, "4"),
+            ("-2.5", 0, "-2"),
+            ("-3.5", 0, "-4"),
+            ("2.51", 0, "3"),
+            ("2.49", 0, "2"),
+            ("1.25", 1, "1.2"),
+            ("1.35", 1, "1.4"),
+            ("1.250001", 1, "1.3"),
+            ("25", -1, "20"),
+            ("35", -1, "40"),
+            ("15.4", -1, "20"),
+            ("0.5", 0, "0"),
+            ("1.5", 3, "1.500"),
+        ];
+
+        for (dec_str, scale, exp) in cases {
+            let dec = dec_str.parse::<Decimal>().unwrap();
+            let res = dec.round_half_to_even(scale).map(|d| d.to_string());
+            assert_eq!(res, Res::Ok(exp.to_owned()), "{}", dec_str);
+        }
+    }
+
     #[test]
     fn test_string() {
         let cases = vec![
@@ -3118,4 +3199,27 @@ mod test {
             assert_eq!(got, exp);
         }
     }
+
+    #[test]
+    fn test_remove_trailing_zeroes() {
+        let cases = vec![
+            ("1.00", Some("1")),
+            ("-2.10", Some("-2.1")),
+            ("0.000", Some("0")),
+            ("10.0000000000", Some("10")),
+            ("0.1000000002000", Some("0.1000000002")),
+            ("1.5", None),
+            ("100", None),
+        ];
+        for (input, exp) in cases {
+            let dec: Decimal = input.parse().unwrap();
+            let got = dec.remove_trailing_zeroes();
+            assert_eq!(got.as_ref().map(|d| d.to_string()), exp.map(String::from));
+            if let Some(got) = got {
+                let exp: Decimal = exp.unwrap().parse().unwrap();
+                assert_eq!(got, dec);
+                assert_eq!(got.prec_and_frac(), exp.prec_and_frac());
+            }
+        }
+    }
 }
@@ -66,7 +66,8 @@ mod time;
 pub mod json;
 
 pub use self::duration::Duration;
-pub use self::decimal::{dec_encoded_len, Decimal, DecimalDecoder, DecimalEncoder, Res};
+pub use self::decimal::{dec_encoded_len, Decimal, DecimalDecoder, DecimalEncoder, Res,
+                        DEFAULT_DIV_FRAC_INCR};
 pub use self::types::{has_is_boolean_flag, has_not_null_flag, has_parse_to_json_flag,
                       has_unsigned_flag};
 pub use self::time::Time;
@@ -14,7 +14,7 @@
 
 use std::cmp::Ordering;
 use std::str;
-use std::fmt::{self, Display, Formatter};
+use std::fmt::{self, Display, Formatter, Write};
 
 use chrono::{DateTime, Datelike, Duration, FixedOffset, TimeZone, Timelike, Utc};
 
@@ -30,6 +30,21 @@ const ZERO_DATE_STR: &'static str = "0000-00-00";
 /// `-0001-11-30 00:00:00 +0000 UTC`, whose timestamp is -62169984000.
 const ZERO_TIMESTAMP: i64 = -62169984000;
 
+const MONTH_NAMES: [&'static str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
 #[inline]
 fn zero_time(tz: &FixedOffset) -> DateTime<FixedOffset> {
     tz.timestamp(ZERO_TIMESTAMP, 0)
@@ -387,6 +402,125 @@ impl Time {
             Ok(())
         }
     }
+
+    /// `date_format` formats the time according to the MySQL `DATE_FORMAT` layout.
+    /// Specifiers that need a real calendar day, like `%W` or `%j`, are invalid
+    /// for zero time.
+    pub fn date_format(&self, layout: &str) -> Result<String> {
+        let t = &self.time;
+        let (year, month, day) = if self.is_zero() {
+            (0, 0, 0)
+        } else {
+            (t.year(), t.month(), t.day())
+        };
+        let (hour, minute, second) = (t.hour(), t.minute(), t.second());
+        let micro = t.nanosecond() / 1000;
+        let mut res = String::with_capacity(layout.len());
+        let mut chars = layout.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                res.push(c);
+                continue;
+            }
+            let c = match chars.next() {
+                Some(c) => c,
+                None => break,
+            };
+            let r = match c {
+                'Y' => write!(res, "{:04}", year),
+                'y' => write!(res, "{:02}", year % 100),
+                'm' => write!(res, "{:02}", month),
+                'c' => write!(res, "{}", month),
+                'd' => write!(res, "{:02}", day),
+                'e' => write!(res, "{}", day),
+                'H' => write!(res, "{:02}", hour),
+                'k' => write!(res, "{}", hour),
+                'h' | 'I' => write!(res, "{:02}", (hour + 11) % 12 + 1),
+                'l' => write!(res, "{}", (hour + 11) % 12 + 1),
+                'i' => write!(res, "{:02}", minute),
+                's' | 'S' => write!(res, "{:02}", second),
+                'f' => write!(res, "{:06}", micro),
+                'p' => res.write_str(if hour < 12 { "AM" } else { "PM" }),
+                'T' => write!(res, "{:02}:{:02}:{:02}", hour, minute, second),
+                'r' => write!(
+                    res,
+                    "{:02}:{:02}:{:02} {}",
+                    (hour + 11) % 12 + 1,
+                    minute,
+                    second,
+                    if hour < 12 { "AM" } else { "PM" }
+                ),
+                'M' | 'b' | 'W' | 'a' | 'j' if self.is_zero() => {
+                    return Err(box_err!("invalid specifier %{} for zero time", c));
+                }
+                'M' => res.write_str(MONTH_NAMES[month as usize - 1]),
+                'b' => res.write_str(&MONTH_NAMES[month as usize - 1][..3]),
+                'W' => write!(res, "{}", t.format("%A")),
+                'a' => write!(res, "{}", t.format("%a")),
+                'j' => write!(res, "{:03}", t.ordinal()),
+                // like MySQL, `%x` outputs `x` for any unsupported specifier.
+                _ => {
+                    res.push(c);
+                    Ok(())
+                }
+            };
+            box_try!(r);
+        }
+        Ok(res)
+    }
+
+    /// `date_diff` returns the number of days from the date of `other` to the date of
+    /// this time, ignoring their time parts like `DATEDIFF`.
+    pub fn date_diff(&self, other: &Time) -> i64 {
+        self.time
+            .date()
+            .signed_duration_since(other.time.date())
+            .num_days()
+    }
+
+    /// `timestamp_diff` returns the difference from this time to `other` in `unit`, like
+    /// `TIMESTAMPDIFF`. Only the whole units are counted, so the result is truncated
+    /// toward zero, and a month only counts once its day and time are reached.
+    pub fn timestamp_diff(&self, other: &Time, unit: &str) -> Result<i64> {
+        let micros = match other
+            .time
+            .signed_duration_since(self.time)
+            .num_microseconds()
+        {
+            Some(micros) => micros,
+            None => return Err(box_err!("timestamp diff overflows")),
+        };
+        let res = match &*unit.to_uppercase() {
+            "MICROSECOND" => micros,
+            "SECOND" => micros / 1_000_000,
+            "MINUTE" => micros / (60 * 1_000_000),
+            "HOUR" => micros / (3600 * 1_000_000),
+            "DAY" => micros / (24 * 3600 * 1_000_000),
+            "WEEK" => micros / (7 * 24 * 3600 * 1_000_000),
+            "MONTH" => self.month_diff(other),
+            "QUARTER" => self.month_diff(other) / 3,
+            "YEAR" => self.month_diff(other) / 12,
+            _ => return Err(box_err!("unknown unit {} of timestamp diff", unit)),
+        };
+        Ok(res)
+    }
+
+    // month_diff returns the number of whole months from this time to `other`.
+    fn month_diff(&self, other: &Time) -> i64 {
+        let (from, to) = (&self.time, &other.time);
+        let months = i64::from(to.year() - from.year()) * 12 + i64::from(to.month()) -
+            i64::from(from.month());
+        let rest = |t: &DateTime<FixedOffset>| {
+            (t.day(), t.num_seconds_from_midnight(), t.nanosecond())
+        };
+        if months > 0 && rest(to) < rest(from) {
+            months - 1
+        } else if months < 0 && rest(to) > rest(from) {
+            months + 1
+        } else {
+            months
+        }
+    }
 }
 
 impl PartialOrd for Time {
@@ -844,4 +978,75 @@ mod test {
             assert_eq!(get, expect);
         }
     }
+
+    #[test]
+    fn test_date_format() {
+        let cases = vec![
+            (
+                "2010-01-07 23:12:34.12345",
+                "%Y-%m-%d %H:%i:%s",
+                "2010-01-07 23:12:34",
+            ),
+            (
+                "2010-01-07 23:12:34.12345",
+                "%b %M %c %e %y %k %h %I %l %p %f",
+                "Jan January 1 7 10 23 11 11 11 PM 123450",
+            ),
+            (
+                "2012-12-21 08:05:09",
+                "%a %W %j %T %r %S %%",
+                "Fri Friday 356 08:05:09 08:05:09 AM 09 %",
+            ),
+            ("2012-12-21", "abc %Q%", "abc Q"),
+            ("0000-00-00 00:00:00", "%Y-%m-%d", "0000-00-00"),
+        ];
+        for (s, layout, expect) in cases {
+            let t = Time::parse_utc_datetime(s, 6).unwrap();
+            assert_eq!(t.date_format(layout).unwrap(), expect);
+        }
+
+        let zero = Time::parse_utc_datetime("0000-00-00 00:00:00", 0).unwrap();
+        assert!(zero.date_format("%W").is_err());
+    }
+
+    #[test]
+    fn test_date_diff() {
+        let cases = vec![
+            ("2017-10-02 00:00:01", "2017-10-01 23:59:59", 1),
+            ("2017-10-01 23:59:59", "2017-10-02 00:00:01", -1),
+            ("2017-03-01", "2016-02-28", 367),
+            ("2017-10-01 12:00:00", "2017-10-01", 0),
+        ];
+        for (l, r, expect) in cases {
+            let l = Time::parse_utc_datetime(l, 0).unwrap();
+            let r = Time::parse_utc_datetime(r, 0).unwrap();
+            assert_eq!(l.date_diff(&r), expect);
+        }
+    }
+
+    #[test]
+    fn test_timestamp_diff() {
+        let cases = vec![
+            ("2017-10-01 00:00:00", "2017-10-01 00:00:01.5", "MICROSECOND", 1_500_000),
+            ("2017-10-01 00:00:00", "2017-10-01 00:01:59", "second", 119),
+            ("2017-10-01 00:00:00", "2017-10-01 00:01:59", "MINUTE", 1),
+            ("2017-10-01 12:00:00", "2017-10-01 00:30:00", "HOUR", -11),
+            ("2017-10-01 12:00:00", "2017-10-03 11:59:59", "DAY", 1),
+            ("2017-10-01", "2017-10-15", "WEEK", 2),
+            ("2017-01-31", "2017-02-28", "MONTH", 0),
+            ("2017-01-31", "2017-03-31", "MONTH", 2),
+            ("2017-03-31 12:00:00", "2017-01-31 13:00:00", "MONTH", -1),
+            ("2017-01-15", "2017-10-14", "QUARTER", 2),
+            ("2016-02-29", "2017-02-28", "YEAR", 0),
+            ("2016-02-29", "2018-03-01", "YEAR", 2),
+        ];
+        for (l, r, unit, expect) in cases {
+            let l = Time::parse_utc_datetime(l, 6).unwrap();
+            let r = Time::parse_utc_datetime(r, 6).unwrap();
+            assert_eq!(l.timestamp_diff(&r, unit).unwrap(), expect, "{} {}", unit, r);
+        }
+
+        let t = Time::parse_utc_datetime("2017-10-01", 0).unwrap();
+        assert!(t.timestamp_diff(&t, "FORTNIGHT").is_err());
+    }
 }
@@ -13,7 +13,9 @@
 
 
 use std::io::Write;
-use std::{cmp, u8};
+use std::{cmp, i64, u8};
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use tipb::schema::ColumnInfo;
 
 use coprocessor::select::xeval::EvalContext;
@@ -35,6 +37,9 @@ pub const RECORD_PREFIX_SEP: &'static [u8] = b"_r";
 pub const INDEX_PREFIX_SEP: &'static [u8] = b"_i";
 pub const SEP_LEN: usize = 2;
 pub const TABLE_PREFIX_LEN: usize = 1;
+/// `COMMIT_TS_COL_ID` is the id of the pseudo column a table scan fills with the commit ts
+/// of each row, so that rows can be filtered by when they were written.
+pub const COMMIT_TS_COL_ID: i64 = i64::MIN;
 
 
 trait TableEncoder: NumberEncoder {
@@ -139,6 +144,38 @@ pub fn encode_index_seek_key(table_id: i64, idx_id: i64, encoded: &[u8]) -> Vec<
     key
 }
 
+/// `encode_index_key_value` encodes an index entry of `values` for row `handle` into
+/// its key and value. A unique index keeps the handle in the value, so its key holds
+/// the index values only. A non-unique index, or a unique one with a NULL value, which
+/// may be repeated, appends the handle to the key instead.
+pub fn encode_index_key_value(
+    table_id: i64,
+    idx_id: i64,
+    values: &[Datum],
+    handle: i64,
+    unique: bool,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let distinct = unique && values.iter().all(|v| *v != Datum::Null);
+    let mut encoded = datum::encode_key(values)?;
+    let mut value = Vec::with_capacity(ID_LEN);
+    if distinct {
+        value.write_i64::<BigEndian>(handle)?;
+    } else {
+        encoded.append(&mut datum::encode_key(&[Datum::I64(handle)])?);
+        value.push(0);
+    }
+    Ok((encode_index_seek_key(table_id, idx_id, &encoded), value))
+}
+
+/// `decode_index_handle` gets the handle of an index entry, either the one cut from
+/// its key by `cut_idx_key`, or the one kept in its value by a unique index.
+pub fn decode_index_handle(handle_in_key: Option<i64>, mut value: &[u8]) -> Result<i64> {
+    match handle_in_key {
+        Some(handle) => Ok(handle),
+        None => Ok(value.read_i64::<BigEndian>()?),
+    }
+}
+
 // `decode_index_key` decodes datums from an index key.
 pub fn decode_index_key(
     ctx: &EvalContext,
@@ -359,7 +396,7 @@ pub fn cut_idx_key(key: Vec<u8>, col_ids: &[i64]) -> Result<(RowColsDict, Option
 
 #[cfg(test)]
 mod test {
-    use std::i64;
+    use std::{f64, i64};
 
     use tipb::schema::ColumnInfo;
 
@@ -499,6 +536,36 @@ mod test {
         assert!(datums.is_empty());
     }
 
+    #[test]
+    fn test_row_codec_f64() {
+        let cols = map![
+            1 => new_col_info(types::DOUBLE),
+            2 => new_col_info(types::DOUBLE),
+            3 => new_col_info(types::FLOAT),
+            4 => new_col_info(types::DOUBLE)
+        ];
+        let row = map![
+            1 => Datum::F64(-1.5),
+            2 => Datum::Null,
+            3 => Datum::F64(0.25),
+            4 => Datum::F64(f64::MAX)
+        ];
+        let col_ids: Vec<_> = row.iter().map(|(&id, _)| id).collect();
+        let col_values: Vec<_> = row.iter().map(|(_, v)| v.clone()).collect();
+        let bs = encode_row(col_values, &col_ids).unwrap();
+        let r = bs.as_slice()
+            .decode_row(&Default::default(), &cols)
+            .unwrap();
+        assert_eq!(row, r);
+
+        // a float column is narrowed to f32 when decoded.
+        let bs = encode_row(vec![Datum::F64(0.1)], &[3]).unwrap();
+        let r = bs.as_slice()
+            .decode_row(&Default::default(), &cols)
+            .unwrap();
+        assert_eq!(r[&3], Datum::F64(0.1f32 as f64));
+    }
+
     #[test]
     fn test_idx_codec() {
         let mut col_ids = vec![1, 2, 3];
@@ -561,4 +628,29 @@ mod test {
         assert!(res.0.is_empty());
         assert!(res.1.is_none());
     }
+
+    #[test]
+    fn test_unique_idx_codec() {
+        let name = Datum::Bytes(b"abc".to_vec());
+        let ids = vec![1];
+        let cases = vec![
+            (vec![name.clone()], false, true),
+            (vec![name.clone()], true, false),
+            // NULL can be repeated in a unique index, so the handle stays in the key.
+            (vec![Datum::Null], true, true),
+        ];
+        for (values, unique, handle_in_key) in cases {
+            let (key, value) = encode_index_key_value(1, 2, &values, 10, unique).unwrap();
+            let mut encoded = datum::encode_key(&values).unwrap();
+            if handle_in_key {
+                encoded.append(&mut datum::encode_key(&[Datum::I64(10)]).unwrap());
+            }
+            assert_eq!(key, encode_index_seek_key(1, 2, &encoded));
+
+            let (res, handle) = cut_idx_key_as_owned(&key, &ids);
+            assert_eq!(res[&1], datum::encode_key(&values).unwrap());
+            assert_eq!(handle.is_some(), handle_in_key);
+            assert_eq!(decode_index_handle(handle, &value).unwrap(), 10);
+        }
+    }
 }
@@ -11,23 +11,34 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cell::{Cell, RefCell};
+use std::mem;
 use std::rc::Rc;
+use std::time::Duration;
 
-use tipb::executor::{ExecType, Executor};
+use tipb::executor::{Aggregation, ExecType, Executor};
 use tipb::schema::ColumnInfo;
-use tipb::select::{DAGRequest, SelectResponse};
+use tipb::select::{Chunk, DAGRequest, SelectResponse};
 use kvproto::coprocessor::{KeyRange, Response};
 use protobuf::{Message as PbMsg, RepeatedField};
 
-use coprocessor::codec::mysql;
+use coprocessor::codec::{mysql, table};
 use coprocessor::codec::datum::{Datum, DatumEncoder};
 use coprocessor::select::xeval::EvalContext;
+use coprocessor::flags::{FLAG_COLLECT_EXECUTOR_OUTPUT, FLAG_HANDLES_ONLY, FLAG_IMPLICIT_GROUP_COUNT,
+                        FLAG_NULLS_ORDER_SHIFT, FLAG_NULL_AGGR_AS_ZERO, FLAG_RAW_GROUP_KEY,
+                        FLAG_REVERSE_OUTPUT, FLAG_ROLLUP, FLAG_ROW_KEY};
 use coprocessor::{Error, Result};
-use coprocessor::endpoint::{get_chunk, get_pk, to_pb_error, ReqContext};
+use coprocessor::endpoint::{get_chunk, get_pk, to_pb_error, to_pb_warning, ReqContext,
+                            DEADLINE_CHECK_ROWS};
 use storage::{Snapshot, SnapshotStore, Statistics};
+use util::codec::number::{self, NumberEncoder};
 
-use super::executor::{AggregationExecutor, Executor as DAGExecutor, IndexScanExecutor,
-                      LimitExecutor, Row, SelectionExecutor, TableScanExecutor, TopNExecutor};
+use super::executor::{group_by_index_prefix, AggregationExecutor, BudgetExecutor,
+                      DebugExecutor, DistinctExecutor, Executor as DAGExecutor,
+                      ExprColumnRefVisitor, IndexScanExecutor, LimitExecutor, MemoryQuota,
+                      ReverseExecutor, Row, RowFetchExecutor, SelectionExecutor, StageOutput,
+                      TableScanExecutor, TopNExecutor, DEFAULT_REVERSE_MEMORY_BUDGET};
 
 pub struct DAGContext<'s> {
     columns: Rc<Vec<ColumnInfo>>,
@@ -37,6 +48,9 @@ pub struct DAGContext<'s> {
     snap: &'s Snapshot,
     eval_ctx: Rc<EvalContext>,
     req_ctx: &'s ReqContext,
+    executor_timeout: Option<Duration>,
+    scan_bytes_limit: Option<u64>,
+    memory_quota: Option<Rc<MemoryQuota>>,
 }
 
 impl<'s> DAGContext<'s> {
@@ -55,34 +69,43 @@ impl<'s> DAGContext<'s> {
             has_aggr: false,
             eval_ctx: eval_ctx,
             req_ctx: req_ctx,
+            executor_timeout: None,
+            scan_bytes_limit: None,
+            memory_quota: None,
         }
     }
 
+    /// Limits the time every executor may spend on its own work, excluding the
+    /// time spent by its source.
+    pub fn set_executor_timeout(&mut self, timeout: Duration) {
+        self.executor_timeout = Some(timeout);
+    }
+
+    /// Fails the request once its scan reads more than `limit` bytes of keys and values.
+    pub fn set_scan_bytes_limit(&mut self, limit: u64) {
+        self.scan_bytes_limit = Some(limit);
+    }
+
+    /// Fails the request once the rows kept by its TopN and aggregation executors and
+    /// the response buffered take more than `bytes` in total.
+    pub fn set_memory_quota(&mut self, bytes: usize) {
+        self.memory_quota = Some(Rc::new(MemoryQuota::new(bytes)));
+    }
+
     pub fn handle_request(mut self, statistics: &'s mut Statistics) -> Result<Response> {
         self.validate_dag()?;
-        let mut exec = self.build_dag(statistics)?;
+        let (mut exec, stage_outputs) = self.build_dag(statistics)?;
+        let row_key_table = if (self.req.get_flags() & FLAG_ROW_KEY) > 0 {
+            Some(scan_table_id(&self.req.get_executors()[0]))
+        } else {
+            None
+        };
         let mut chunks = vec![];
+        let mut rows = 0;
         loop {
-            match exec.next() {
-                Ok(Some(row)) => {
-                    self.req_ctx.check_if_outdated()?;
-                    let chunk = get_chunk(&mut chunks);
-                    if self.has_aggr {
-                        chunk.mut_rows_data().extend_from_slice(&row.data.value);
-                    } else {
-                        let value =
-                            inflate_cols(&row, &self.columns, self.req.get_output_offsets())?;
-                        chunk.mut_rows_data().extend_from_slice(&value);
-                    }
-                }
-                Ok(None) => {
-                    let mut resp = Response::new();
-                    let mut sel_resp = SelectResponse::new();
-                    sel_resp.set_chunks(RepeatedField::from_vec(chunks));
-                    let data = box_try!(sel_resp.write_to_bytes());
-                    resp.set_data(data);
-                    return Ok(resp);
-                }
+            let row = match exec.next() {
+                Ok(Some(row)) => row,
+                Ok(None) => break,
                 Err(e) => if let Error::Other(_) = e {
                     let mut resp = Response::new();
                     let mut sel_resp = SelectResponse::new();
@@ -93,8 +116,61 @@ impl<'s> DAGContext<'s> {
                 } else {
                     return Err(e);
                 },
+            };
+            // the scan checks the deadline too, as the rows it reads may never come here.
+            if rows % DEADLINE_CHECK_ROWS == 0 {
+                self.req_ctx.check_if_outdated()?;
             }
+            let size = {
+                let chunk = get_chunk(&mut chunks);
+                if self.has_aggr {
+                    chunk.mut_rows_data().extend_from_slice(&row.data.value);
+                    row.data.value.len()
+                } else {
+                    let mut value =
+                        inflate_cols(&row, &self.columns, self.req.get_output_offsets())?;
+                    if let Some(table_id) = row_key_table {
+                        let mut handle = Vec::with_capacity(number::I64_SIZE);
+                        box_try!(handle.encode_i64(row.handle));
+                        let key = table::encode_row_key(table_id, &handle);
+                        box_try!(value.encode(&[Datum::Bytes(key)], false));
+                    }
+                    chunk.mut_rows_data().extend_from_slice(&value);
+                    value.len()
+                }
+            };
+            if let Some(ref quota) = self.memory_quota {
+                quota.alloc(size)?;
+            }
+            rows += 1;
+        }
+        let mut output_counts = Vec::with_capacity(stage_outputs.len());
+        for output in stage_outputs {
+            let mut output = output.borrow_mut();
+            if let Some(ref summary) = output.selection {
+                let summary = summary.get();
+                info!(
+                    "executor #{} passed {} of {} rows",
+                    output.index,
+                    summary.passed_rows,
+                    summary.input_rows
+                );
+            }
+            output_counts.push(output.row_count() as i64);
+            chunks.push(mem::replace(&mut output.chunk, Chunk::new()));
         }
+        let mut resp = Response::new();
+        let mut sel_resp = SelectResponse::new();
+        sel_resp.set_chunks(RepeatedField::from_vec(chunks));
+        sel_resp.set_output_counts(output_counts);
+        // the warnings kept are capped, so `warning_count` may exceed their number.
+        let warnings = self.eval_ctx.warnings.borrow();
+        let pb_warnings = warnings.warnings.iter().map(|w| to_pb_warning(w)).collect();
+        sel_resp.set_warnings(RepeatedField::from_vec(pb_warnings));
+        sel_resp.set_warning_count(warnings.warning_cnt as i64);
+        let data = box_try!(sel_resp.write_to_bytes());
+        resp.set_data(data);
+        Ok(resp)
     }
 
     fn validate_dag(&mut self) -> Result<()> {
@@ -130,11 +206,14 @@ impl<'s> DAGContext<'s> {
 
     // seperate first exec build action from `build_dag`
     // since it will generte mutable conflict when putting together
+    // `scan_columns`, if any, are the only columns the table scan reads, and the scan
+    // is returned too so the full rows can be fetched from it later.
     fn build_first(
         &'s self,
         mut first: Executor,
+        scan_columns: Option<Vec<ColumnInfo>>,
         statistics: &'s mut Statistics,
-    ) -> Box<DAGExecutor + 's> {
+    ) -> (Box<DAGExecutor + 's>, Option<Rc<RefCell<TableScanExecutor<'s>>>>) {
         let store = SnapshotStore::new(
             self.snap,
             self.req.get_start_ts(),
@@ -143,54 +222,275 @@ impl<'s> DAGContext<'s> {
         );
 
         match first.get_tp() {
-            ExecType::TypeTableScan => Box::new(TableScanExecutor::new(
-                first.get_tbl_scan(),
-                self.ranges.clone(),
-                store,
-                statistics,
-            )),
-            ExecType::TypeIndexScan => Box::new(IndexScanExecutor::new(
-                first.take_idx_scan(),
-                self.ranges.clone(),
-                store,
-                statistics,
-            )),
+            ExecType::TypeTableScan => {
+                let mut meta = first.take_tbl_scan();
+                if let Some(ref columns) = scan_columns {
+                    meta.set_columns(RepeatedField::from_vec(columns.clone()));
+                }
+                let mut exec =
+                    TableScanExecutor::new(&meta, self.ranges.clone(), store, statistics);
+                if (self.req.get_flags() & FLAG_HANDLES_ONLY) > 0 {
+                    exec.set_handles_only();
+                }
+                if let Some(limit) = self.scan_bytes_limit {
+                    exec.set_scan_bytes_limit(limit);
+                }
+                exec.set_req_ctx(self.req_ctx);
+                if scan_columns.is_none() {
+                    return (Box::new(exec), None);
+                }
+                let exec = Rc::new(RefCell::new(exec));
+                (Box::new(exec.clone()), Some(exec))
+            }
+            ExecType::TypeIndexScan => {
+                let mut exec = IndexScanExecutor::new(
+                    first.take_idx_scan(),
+                    self.ranges.clone(),
+                    store,
+                    statistics,
+                );
+                if let Some(limit) = self.scan_bytes_limit {
+                    exec.set_scan_bytes_limit(limit);
+                }
+                exec.set_req_ctx(self.req_ctx);
+                (Box::new(exec), None)
+            }
             _ => unreachable!(),
         }
     }
 
-    fn build_dag(&'s self, statistics: &'s mut Statistics) -> Result<Box<DAGExecutor + 's>> {
+    fn build_dag(
+        &'s self,
+        statistics: &'s mut Statistics,
+    ) -> Result<(Box<DAGExecutor + 's>, Vec<Rc<RefCell<StageOutput>>>)> {
+        let debug = (self.req.get_flags() & FLAG_COLLECT_EXECUTOR_OUTPUT) > 0;
+        let mut stage_outputs = vec![];
+        let mut src_elapsed = None;
         let mut execs = self.req.get_executors().to_vec().into_iter();
-        let mut src = self.build_first(execs.next().unwrap(), statistics);
-        for mut exec in execs {
-            let curr: Box<DAGExecutor> = match exec.get_tp() {
+        let first = execs.next().unwrap();
+        let first_tp = first.get_tp();
+        let scan_columns = if (self.req.get_flags() & FLAG_HANDLES_ONLY) > 0 {
+            None
+        } else {
+            topn_scan_columns(self.req.get_executors(), &self.columns)
+        };
+        let (mut src, mut late_scan) = self.build_first(first, scan_columns, statistics);
+        if let Some(timeout) = self.executor_timeout {
+            src = with_budget(first_tp, timeout, src, &mut src_elapsed);
+        }
+        if debug {
+            src = with_debug(0, src, &mut stage_outputs);
+        }
+        // the selections after an aggregation filter its groups.
+        let mut aggregated = false;
+        for (idx, mut exec) in execs.enumerate() {
+            let tp = exec.get_tp();
+            let mut selection = None;
+            let mut curr: Box<DAGExecutor> = match tp {
                 ExecType::TypeTableScan | ExecType::TypeIndexScan => {
                     return Err(box_err!("got too much *scan exec, should be only one"))
                 }
-                ExecType::TypeSelection => Box::new(SelectionExecutor::new(
-                    exec.take_selection(),
-                    self.eval_ctx.clone(),
-                    self.columns.clone(),
-                    src,
-                )?),
-                ExecType::TypeAggregation => Box::new(AggregationExecutor::new(
-                    exec.take_aggregation(),
-                    self.eval_ctx.clone(),
-                    self.columns.clone(),
-                    src,
-                )?),
-                ExecType::TypeTopN => Box::new(TopNExecutor::new(
-                    exec.take_topN(),
-                    self.eval_ctx.clone(),
-                    self.columns.clone(),
-                    src,
-                )?),
+                ExecType::TypeSelection => {
+                    let sel = if aggregated {
+                        SelectionExecutor::new_over_aggregation(
+                            exec.take_selection(),
+                            self.eval_ctx.clone(),
+                            src,
+                        )?
+                    } else {
+                        SelectionExecutor::new(
+                            exec.take_selection(),
+                            self.eval_ctx.clone(),
+                            self.columns.clone(),
+                            src,
+                        )?
+                    };
+                    selection = Some(sel.summary());
+                    Box::new(sel)
+                }
+                ExecType::TypeAggregation
+                    if is_distinct(exec.get_aggregation(), self.req.get_flags()) =>
+                {
+                    aggregated = true;
+                    let mut distinct = DistinctExecutor::new(
+                        exec.take_aggregation().take_group_by().into_vec(),
+                        self.eval_ctx.clone(),
+                        self.columns.clone(),
+                        src,
+                    )?;
+                    if let Some(ref quota) = self.memory_quota {
+                        distinct.set_memory_quota(quota.clone());
+                    }
+                    Box::new(distinct)
+                }
+                ExecType::TypeAggregation => {
+                    aggregated = true;
+                    let sorted = is_sorted_by_group(self.req.get_executors(), idx + 1);
+                    let mut aggr = AggregationExecutor::new(
+                        exec.take_aggregation(),
+                        self.eval_ctx.clone(),
+                        self.columns.clone(),
+                        src,
+                    )?;
+                    if (self.req.get_flags() & FLAG_IMPLICIT_GROUP_COUNT) > 0 {
+                        aggr.set_implicit_count();
+                    }
+                    if (self.req.get_flags() & FLAG_RAW_GROUP_KEY) > 0 {
+                        aggr.set_raw_group_key();
+                    }
+                    if (self.req.get_flags() & FLAG_NULL_AGGR_AS_ZERO) > 0 {
+                        aggr.set_null_as_zero();
+                    }
+                    let rollup = (self.req.get_flags() & FLAG_ROLLUP) > 0;
+                    if rollup {
+                        aggr.set_rollup();
+                    }
+                    if let Some(ref quota) = self.memory_quota {
+                        aggr.set_memory_quota(quota.clone());
+                    }
+                    if sorted && !rollup {
+                        // the streaming aggregation can't output the subtotals, which
+                        // span many groups.
+                        aggr.set_streaming();
+                    }
+                    Box::new(aggr)
+                }
+                ExecType::TypeTopN => {
+                    let mut topn = TopNExecutor::new(
+                        exec.take_topN(),
+                        self.eval_ctx.clone(),
+                        self.columns.clone(),
+                        src,
+                    )?;
+                    if let Some(ref quota) = self.memory_quota {
+                        topn.set_memory_quota(quota.clone());
+                    }
+                    let nulls_flipped = self.req.get_flags() >> FLAG_NULLS_ORDER_SHIFT;
+                    if nulls_flipped > 0 {
+                        topn.flip_nulls_order(nulls_flipped);
+                    }
+                    match late_scan.take() {
+                        Some(scan) => {
+                            Box::new(RowFetchExecutor::new(scan, &self.columns, Box::new(topn)))
+                        }
+                        None => Box::new(topn),
+                    }
+                }
                 ExecType::TypeLimit => Box::new(LimitExecutor::new(exec.take_limit(), src)),
             };
-            src = curr;
+            if let Some(timeout) = self.executor_timeout {
+                curr = with_budget(tp, timeout, curr, &mut src_elapsed);
+            }
+            src = if debug {
+                let curr = with_debug(idx + 1, curr, &mut stage_outputs);
+                stage_outputs.last().unwrap().borrow_mut().selection = selection;
+                curr
+            } else {
+                curr
+            };
+        }
+        if (self.req.get_flags() & FLAG_REVERSE_OUTPUT) > 0 {
+            let quota = match self.memory_quota {
+                Some(ref quota) => quota.clone(),
+                None => Rc::new(MemoryQuota::new(DEFAULT_REVERSE_MEMORY_BUDGET)),
+            };
+            src = Box::new(ReverseExecutor::new(quota, src));
+        }
+        Ok((src, stage_outputs))
+    }
+}
+
+// is_sorted_by_group checks if the rows fed to the aggregation at `pos` are sorted by
+// its group by columns, which holds if they lead the index scanned and only selections,
+// which keep the order, lie in between.
+fn is_sorted_by_group(execs: &[Executor], pos: usize) -> bool {
+    execs[0].get_tp() == ExecType::TypeIndexScan &&
+        execs[1..pos]
+            .iter()
+            .all(|exec| exec.get_tp() == ExecType::TypeSelection) &&
+        group_by_index_prefix(execs[pos].get_aggregation().get_group_by())
+}
+
+// is_distinct checks if `aggr` only outputs the distinct values of its group by, which
+// holds without aggregate functions unless the flags add the count or the raw key of
+// every group, or the subtotals.
+fn is_distinct(aggr: &Aggregation, flags: u64) -> bool {
+    aggr.get_agg_func().is_empty() && !aggr.get_group_by().is_empty() &&
+        (flags & (FLAG_IMPLICIT_GROUP_COUNT | FLAG_RAW_GROUP_KEY | FLAG_ROLLUP)) == 0
+}
+
+// topn_scan_columns returns the columns a table scan needs to read when only selections
+// lie between it and a TopN, so that the TopN picks its rows from the columns it sorts
+// and filters by, and only the rows kept are read in full. It returns `None` if every
+// column is needed anyway.
+fn topn_scan_columns(execs: &[Executor], columns: &[ColumnInfo]) -> Option<Vec<ColumnInfo>> {
+    if execs[0].get_tp() != ExecType::TypeTableScan {
+        return None;
+    }
+    let mut visitor = ExprColumnRefVisitor::new(columns.len());
+    for exec in &execs[1..] {
+        match exec.get_tp() {
+            ExecType::TypeSelection => {
+                if visitor
+                    .batch_visit(exec.get_selection().get_conditions())
+                    .is_err()
+                {
+                    return None;
+                }
+            }
+            ExecType::TypeTopN => {
+                for by_item in exec.get_topN().get_order_by() {
+                    if visitor.visit(by_item.get_expr()).is_err() {
+                        return None;
+                    }
+                }
+                let mut offsets = visitor.column_offsets();
+                if offsets.len() == columns.len() {
+                    return None;
+                }
+                offsets.sort();
+                return Some(offsets.into_iter().map(|i| columns[i].clone()).collect());
+            }
+            _ => return None,
         }
-        Ok(src)
     }
+    None
+}
+
+fn scan_table_id(scan: &Executor) -> i64 {
+    match scan.get_tp() {
+        ExecType::TypeTableScan => scan.get_tbl_scan().get_table_id(),
+        _ => scan.get_idx_scan().get_table_id(),
+    }
+}
+
+fn with_debug<'a>(
+    index: usize,
+    src: Box<DAGExecutor + 'a>,
+    outputs: &mut Vec<Rc<RefCell<StageOutput>>>,
+) -> Box<DAGExecutor + 'a> {
+    let exec = DebugExecutor::new(index, src);
+    outputs.push(exec.output());
+    Box::new(exec)
+}
+
+fn with_budget<'a>(
+    tp: ExecType,
+    budget: Duration,
+    src: Box<DAGExecutor + 'a>,
+    src_elapsed: &mut Option<Rc<Cell<Duration>>>,
+) -> Box<DAGExecutor + 'a> {
+    let name = match tp {
+        ExecType::TypeTableScan => "TableScan",
+        ExecType::TypeIndexScan => "IndexScan",
+        ExecType::TypeSelection => "Selection",
+        ExecType::TypeAggregation => "Aggregation",
+        ExecType::TypeTopN => "TopN",
+        ExecType::TypeLimit => "Limit",
+    };
+    let exec = BudgetExecutor::new(name, budget, src, src_elapsed.take());
+    *src_elapsed = Some(exec.elapsed());
+    Box::new(exec)
 }
 
 #[inline]
@@ -220,3 +520,342 @@ fn inflate_cols(row: &Row, cols: &[ColumnInfo], output_offsets: &[u32]) -> Resul
     }
     Ok(values)
 }
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use kvproto::kvrpcpb::IsolationLevel;
+    use protobuf;
+    use tipb::executor::TableScan;
+    use tipb::expression::{Expr, ExprType, ScalarFuncSig};
+
+    use coprocessor::codec::datum::DatumDecoder;
+    use coprocessor::codec::mysql::types;
+    use util::time::Instant;
+
+    use super::*;
+    use super::super::executor::test::{gen_table_data, get_range, new_col_info, TestStore};
+
+    fn col_ref(offset: i64) -> Expr {
+        let mut expr = Expr::new();
+        expr.set_tp(ExprType::ColumnRef);
+        expr.mut_val().encode_i64(offset).unwrap();
+        expr
+    }
+
+    // new_dag_request builds a request which runs the executors on top of
+    // a scan of the table.
+    fn new_dag_request(
+        tid: i64,
+        cis: &[ColumnInfo],
+        execs: Vec<Executor>,
+        output_offsets: Vec<u32>,
+    ) -> DAGRequest {
+        let mut table_scan = TableScan::new();
+        table_scan.set_table_id(tid);
+        table_scan.set_columns(RepeatedField::from_vec(cis.to_vec()));
+        let mut scan = Executor::new();
+        scan.set_tp(ExecType::TypeTableScan);
+        scan.set_tbl_scan(table_scan);
+        let mut executors = vec![scan];
+        executors.extend(execs);
+        let mut req = DAGRequest::new();
+        req.set_executors(RepeatedField::from_vec(executors));
+        req.set_output_offsets(output_offsets);
+        req
+    }
+
+    fn handle_dag_request(
+        store: &mut TestStore,
+        mut req: DAGRequest,
+        range: KeyRange,
+        eval_ctx: EvalContext,
+        memory_quota: Option<usize>,
+    ) -> Result<SelectResponse> {
+        let (snapshot, start_ts) = store.get_snapshot();
+        req.set_start_ts(start_ts);
+        let req_ctx = ReqContext {
+            deadline: Instant::now_coarse() + Duration::from_secs(60),
+            isolation_level: IsolationLevel::SI,
+            fill_cache: true,
+            table_scan: true,
+        };
+        let eval_ctx = Rc::new(eval_ctx);
+        let mut ctx = DAGContext::new(req, vec![range], snapshot, eval_ctx, &req_ctx);
+        if let Some(bytes) = memory_quota {
+            ctx.set_memory_quota(bytes);
+        }
+        let mut statistics = Statistics::default();
+        let resp = ctx.handle_request(&mut statistics)?;
+        Ok(protobuf::parse_from_bytes(resp.get_data()).unwrap())
+    }
+
+    fn handle_select_response(
+        store: &mut TestStore,
+        req: DAGRequest,
+        range: KeyRange,
+    ) -> SelectResponse {
+        handle_dag_request(store, req, range, EvalContext::default(), None).unwrap()
+    }
+
+    // handle_select returns the rows data of all the chunks of the response.
+    fn handle_select(store: &mut TestStore, req: DAGRequest, range: KeyRange) -> Vec<u8> {
+        let sel_resp = handle_select_response(store, req, range);
+        let mut data = vec![];
+        for chunk in sel_resp.get_chunks() {
+            data.extend_from_slice(chunk.get_rows_data());
+        }
+        data
+    }
+
+    #[test]
+    fn test_collect_executor_output() {
+        let tid = 1;
+        let cis = vec![
+            new_col_info(1, types::LONG_LONG),
+            new_col_info(2, types::LONG_LONG),
+        ];
+        let raw_data: Vec<_> = (1..8)
+            .map(|i| vec![Datum::I64(i), Datum::I64(i % 4)])
+            .collect();
+        let table_data = gen_table_data(tid, &cis, &raw_data);
+        let mut store = TestStore::new(&table_data);
+
+        // TableScan -> Selection(col2 > 1) -> Limit(2)
+        let mut cond = Expr::new();
+        cond.set_tp(ExprType::ScalarFunc);
+        cond.set_sig(ScalarFuncSig::GTInt);
+        cond.mut_children().push(col_ref(1));
+        let mut val = Expr::new();
+        val.set_tp(ExprType::Int64);
+        val.mut_val().encode_i64(1).unwrap();
+        cond.mut_children().push(val);
+        let mut selection = Executor::new();
+        selection.set_tp(ExecType::TypeSelection);
+        selection.mut_selection().mut_conditions().push(cond);
+        let mut limit = Executor::new();
+        limit.set_tp(ExecType::TypeLimit);
+        limit.mut_limit().set_limit(2);
+        let mut req = new_dag_request(tid, &cis, vec![selection, limit], vec![0]);
+
+        let range = get_range(tid, 0, 8);
+        let sel_resp = handle_select_response(&mut store, req.clone(), range.clone());
+        assert!(sel_resp.get_output_counts().is_empty());
+        assert_eq!(sel_resp.get_chunks().len(), 1);
+
+        // the output of every executor follows the result, and the scan stops
+        // as soon as the limit is reached.
+        req.set_flags(FLAG_COLLECT_EXECUTOR_OUTPUT);
+        let sel_resp = handle_select_response(&mut store, req, range);
+        assert_eq!(sel_resp.get_output_counts(), &[3, 2, 2]);
+        let chunks = sel_resp.get_chunks();
+        assert_eq!(chunks.len(), 4);
+        let datums = chunks[0].get_rows_data().decode().unwrap();
+        assert_eq!(datums, vec![Datum::I64(2), Datum::I64(3)]);
+        for (chunk, &cnt) in chunks[1..].iter().zip(sel_resp.get_output_counts()) {
+            assert_eq!(chunk.get_rows_meta().len(), cnt as usize);
+        }
+        let handles: Vec<_> = chunks[3]
+            .get_rows_meta()
+            .iter()
+            .map(|meta| meta.get_handle())
+            .collect();
+        assert_eq!(handles, vec![2, 3]);
+    }
+
+    #[test]
+    fn test_row_key() {
+        let tid = 1;
+        let cis = vec![
+            new_col_info(1, types::LONG_LONG),
+            new_col_info(2, types::VARCHAR),
+        ];
+        let raw_data: Vec<_> = (0..10)
+            .map(|i| vec![Datum::I64(i), Datum::Bytes(format!("{}", i).into_bytes())])
+            .collect();
+        let table_data = gen_table_data(tid, &cis, &raw_data);
+        let mut store = TestStore::new(&table_data);
+
+        let mut req = new_dag_request(tid, &cis, vec![], vec![1]);
+        req.set_flags(FLAG_ROW_KEY);
+
+        let data = handle_select(&mut store, req, get_range(tid, 0, 10));
+        let datums = data.as_slice().decode().unwrap();
+        assert_eq!(datums.len(), 2 * raw_data.len());
+        for (row, expect) in datums.chunks(2).zip(raw_data) {
+            assert_eq!(row[0], expect[1]);
+            let mut handle = vec![];
+            handle.encode_i64(expect[0].i64()).unwrap();
+            let key = table::encode_row_key(tid, &handle);
+            assert_eq!(row[1], Datum::Bytes(key));
+        }
+    }
+
+    #[test]
+    fn test_null_aggr_as_zero() {
+        let tid = 1;
+        let cis = vec![
+            new_col_info(1, types::LONG_LONG),
+            new_col_info(2, types::LONG_LONG),
+            new_col_info(3, types::LONG_LONG),
+        ];
+        let raw_data = vec![
+            vec![Datum::I64(0), Datum::I64(1), Datum::I64(0)],
+            vec![Datum::I64(1), Datum::Null, Datum::I64(1)],
+            vec![Datum::I64(2), Datum::I64(2), Datum::I64(0)],
+            vec![Datum::I64(3), Datum::Null, Datum::I64(1)],
+        ];
+        let table_data = gen_table_data(tid, &cis, &raw_data);
+        let mut store = TestStore::new(&table_data);
+
+        let mut sum = Expr::new();
+        sum.set_tp(ExprType::Sum);
+        sum.mut_children().push(col_ref(1));
+        let mut max = Expr::new();
+        max.set_tp(ExprType::Max);
+        max.mut_children().push(col_ref(1));
+        let mut aggregation = Aggregation::new();
+        aggregation.mut_agg_func().push(sum);
+        aggregation.mut_agg_func().push(max);
+        aggregation.mut_group_by().push(col_ref(2));
+        let mut aggr = Executor::new();
+        aggr.set_tp(ExecType::TypeAggregation);
+        aggr.set_aggregation(aggregation);
+        let mut req = new_dag_request(tid, &cis, vec![aggr], vec![0, 1, 2]);
+
+        // the values of group 1 are all NULL, only its sum becomes 0.
+        let cases = vec![
+            (0, Datum::Null),
+            (FLAG_NULL_AGGR_AS_ZERO, Datum::Dec(0.into())),
+        ];
+        for (flags, null_sum) in cases {
+            req.set_flags(flags);
+            let range = get_range(tid, 0, 4);
+            let data = handle_select(&mut store, req.clone(), range);
+            let datums = data.as_slice().decode().unwrap();
+            let expect = vec![
+                Datum::Dec(3.into()),
+                Datum::I64(2),
+                Datum::I64(0),
+                null_sum,
+                Datum::Null,
+                Datum::I64(1),
+            ];
+            assert_eq!(datums, expect);
+        }
+    }
+
+    #[test]
+    fn test_reverse_output() {
+        let tid = 1;
+        let cis = vec![
+            new_col_info(1, types::LONG_LONG),
+            new_col_info(2, types::VARCHAR),
+        ];
+        let raw_data: Vec<_> = (0..100)
+            .map(|i| vec![Datum::I64(i), Datum::Bytes(format!("{}", i).into_bytes())])
+            .collect();
+        let table_data = gen_table_data(tid, &cis, &raw_data);
+        let mut store = TestStore::new(&table_data);
+        let range = get_range(tid, 0, 100);
+
+        let mut req = new_dag_request(tid, &cis, vec![], vec![0]);
+        req.set_flags(FLAG_REVERSE_OUTPUT);
+
+        let data = handle_select(&mut store, req.clone(), range.clone());
+        let datums = data.as_slice().decode().unwrap();
+        let expect: Vec<_> = (0..100).rev().map(Datum::I64).collect();
+        assert_eq!(datums, expect);
+
+        // the rows buffered can't take more than the memory quota.
+        let eval_ctx = EvalContext::default();
+        let err = handle_dag_request(&mut store, req, range, eval_ctx, Some(1024)).unwrap_err();
+        assert!(format!("{}", err).contains("memory quota"), "{:?}", err);
+    }
+
+    #[test]
+    fn test_warnings() {
+        let tid = 1;
+        let cis = vec![
+            new_col_info(1, types::LONG_LONG),
+            new_col_info(2, types::VARCHAR),
+        ];
+        let raw_data: Vec<_> = (0..5)
+            .map(|i| vec![Datum::I64(i), Datum::Bytes(format!("{}x", i).into_bytes())])
+            .collect();
+        let table_data = gen_table_data(tid, &cis, &raw_data);
+        let mut store = TestStore::new(&table_data);
+
+        // every row truncates its second column when casting it to an int.
+        let mut cast = Expr::new();
+        cast.set_tp(ExprType::ScalarFunc);
+        cast.set_sig(ScalarFuncSig::CastStringAsInt);
+        cast.mut_children().push(col_ref(1));
+        let mut selection = Executor::new();
+        selection.set_tp(ExecType::TypeSelection);
+        selection.mut_selection().mut_conditions().push(cast);
+        let req = new_dag_request(tid, &cis, vec![selection], vec![0]);
+
+        let mut eval_ctx = EvalContext {
+            truncate_as_warning: true,
+            ..Default::default()
+        };
+        eval_ctx.set_max_warning_cnt(3);
+        let range = get_range(tid, 0, 5);
+        let sel_resp = handle_dag_request(&mut store, req, range, eval_ctx, None).unwrap();
+        let datums = sel_resp.get_chunks()[0].get_rows_data().decode().unwrap();
+        assert_eq!(datums, vec![Datum::I64(1), Datum::I64(2), Datum::I64(3), Datum::I64(4)]);
+        // only 3 of the 5 warnings are returned.
+        assert_eq!(sel_resp.get_warning_count(), 5);
+        assert_eq!(sel_resp.get_warnings().len(), 3);
+    }
+
+    #[test]
+    fn test_rollup() {
+        let tid = 1;
+        let cis = vec![
+            new_col_info(1, types::LONG_LONG),
+            new_col_info(2, types::LONG_LONG),
+            new_col_info(3, types::LONG_LONG),
+            new_col_info(4, types::LONG_LONG),
+        ];
+        let raw_data = vec![
+            vec![Datum::I64(0), Datum::I64(1), Datum::Null, Datum::I64(1)],
+            vec![Datum::I64(1), Datum::I64(2), Datum::Null, Datum::Null],
+            vec![Datum::I64(2), Datum::I64(4), Datum::I64(1), Datum::I64(1)],
+        ];
+        let table_data = gen_table_data(tid, &cis, &raw_data);
+        let mut store = TestStore::new(&table_data);
+
+        let mut sum = Expr::new();
+        sum.set_tp(ExprType::Sum);
+        sum.mut_children().push(col_ref(1));
+        let mut aggregation = Aggregation::new();
+        aggregation.mut_agg_func().push(sum);
+        aggregation.mut_group_by().push(col_ref(2));
+        aggregation.mut_group_by().push(col_ref(3));
+        let mut aggr = Executor::new();
+        aggr.set_tp(ExecType::TypeAggregation);
+        aggr.set_aggregation(aggregation);
+        let mut req = new_dag_request(tid, &cis, vec![aggr], vec![0, 1, 2, 3]);
+        req.set_flags(FLAG_ROLLUP);
+
+        let data = handle_select(&mut store, req, get_range(tid, 0, 3));
+        let datums = data.as_slice().decode().unwrap();
+        let rows: Vec<_> = datums.chunks(4).map(|row| row.to_vec()).collect();
+        let row = |sum: i64, c2: Datum, c3: Datum, level: i64| {
+            vec![Datum::Dec(sum.into()), c2, c3, Datum::I64(level)]
+        };
+        let expect = vec![
+            row(1, Datum::Null, Datum::I64(1), 0),
+            row(3, Datum::Null, Datum::Null, 1),
+            row(7, Datum::Null, Datum::Null, 2),
+            // the group whose values are NULL is told apart from the subtotals by level.
+            row(2, Datum::Null, Datum::Null, 0),
+            row(4, Datum::I64(1), Datum::I64(1), 0),
+            row(4, Datum::I64(1), Datum::Null, 1),
+        ];
+        assert_eq!(rows, expect);
+    }
+}
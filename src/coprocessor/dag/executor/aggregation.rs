@@ -11,15 +11,19 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::ascii::AsciiExt;
+use std::mem;
 use std::rc::Rc;
 
 use tipb::schema::ColumnInfo;
 use tipb::executor::Aggregation;
 use tipb::expression::{Expr, ExprType};
+use util::codec::number::NumberDecoder;
 use util::collections::{HashMap, HashMapEntry as Entry};
 
 use coprocessor::codec::table::RowColsDict;
 use coprocessor::codec::datum::{self, approximate_size, Datum, DatumEncoder};
+use coprocessor::codec::mysql::charset;
 use coprocessor::endpoint::SINGLE_GROUP;
 use coprocessor::select::aggregate::{self, AggrFunc};
 use coprocessor::select::xeval::EvalContext;
@@ -27,7 +31,7 @@ use coprocessor::dag::expr::Expression;
 use coprocessor::metrics::*;
 use coprocessor::Result;
 
-use super::{inflate_with_col_for_dag, Executor, ExprColumnRefVisitor, Row};
+use super::{inflate_with_col_for_dag, Executor, ExprColumnRefVisitor, MemoryQuota, Row};
 
 struct AggrFuncExpr {
     args: Vec<Expression>,
@@ -74,9 +78,30 @@ pub struct AggregationExecutor<'a> {
     aggr_func: Vec<AggrFuncExpr>,
     group_keys: Vec<Rc<Vec<u8>>>,
     group_key_aggrs: HashMap<Rc<Vec<u8>>, Vec<Box<AggrFunc>>>,
+    // group values of the first row in each group, only recorded when they
+    // differ from the group key because of a case insensitive collation.
+    group_vals: HashMap<Rc<Vec<u8>>, Vec<u8>>,
+    // row count of each group, only recorded when `implicit_count` is set.
+    group_cnts: HashMap<Rc<Vec<u8>>, u64>,
+    // handle of the row the `FIRST` results of each group come from, only recorded when
+    // there is a `FIRST`, which resolves to the row with the smallest handle.
+    first_handles: HashMap<Rc<Vec<u8>>, i64>,
+    has_first: bool,
+    implicit_count: bool,
+    // whether the group values are output as a single column of their encoded bytes.
+    raw_group_key: bool,
+    // whether NULL aggregation results are output as 0.
+    null_as_zero: bool,
+    // whether the subtotals of every prefix of the group by are output too.
+    rollup: bool,
+    // whether the source rows are sorted by group, so every group can be output
+    // as soon as the next one begins.
+    streaming: bool,
     cursor: usize,
     executed: bool,
     ctx: Rc<EvalContext>,
+    // every group is registered to `quota` until it is output.
+    quota: Option<Rc<MemoryQuota>>,
     cols: Rc<Vec<ColumnInfo>>,
     related_cols_offset: Vec<usize>, // offset of related columns
     src: Box<Executor + 'a>,
@@ -95,6 +120,9 @@ impl<'a> AggregationExecutor<'a> {
         visitor.batch_visit(&group_by)?;
         let aggr_func = meta.take_agg_func().into_vec();
         visitor.batch_visit(&aggr_func)?;
+        let has_first = aggr_func
+            .iter()
+            .any(|expr| expr.get_tp() == ExprType::First);
         COPR_EXECUTOR_COUNT
             .with_label_values(&["aggregation"])
             .inc();
@@ -103,113 +131,391 @@ impl<'a> AggregationExecutor<'a> {
             aggr_func: AggrFuncExpr::batch_build(ctx.as_ref(), aggr_func)?,
             group_keys: vec![],
             group_key_aggrs: map![],
+            group_vals: map![],
+            group_cnts: map![],
+            first_handles: map![],
+            has_first: has_first,
+            implicit_count: false,
+            raw_group_key: false,
+            null_as_zero: false,
+            rollup: false,
+            streaming: false,
             cursor: 0,
             executed: false,
             ctx: ctx,
+            quota: None,
             cols: columns,
             related_cols_offset: visitor.column_offsets(),
             src: src,
         })
     }
 
-    fn get_group_key(&self, row: &[Datum]) -> Result<Vec<u8>> {
+    // set_implicit_count makes every group output its row count as a trailing column.
+    pub fn set_implicit_count(&mut self) {
+        self.implicit_count = true;
+    }
+
+    // set_raw_group_key makes every group output its encoded group values as one bytes
+    // column, instead of a column for each of them.
+    pub fn set_raw_group_key(&mut self) {
+        self.raw_group_key = true;
+    }
+
+    // set_null_as_zero makes `SUM` output a decimal 0 instead of NULL for a group without
+    // non-NULL values. The other aggregates keep their NULL results, as there is no value
+    // of the group to take them from, and `COUNT` is never NULL.
+    pub fn set_null_as_zero(&mut self) {
+        self.null_as_zero = true;
+    }
+
+    // set_rollup makes the executor also output a group for every prefix of the group by,
+    // whose values after the prefix are NULL, like `GROUP BY ... WITH ROLLUP`. Every group
+    // outputs the number of values rolled up as a trailing group value, which is 0 for the
+    // groups of the full group by, so a NULL value rolled up can be told from a NULL value
+    // of the rows. It's ignored by the streaming aggregation.
+    pub fn set_rollup(&mut self) {
+        self.rollup = true;
+    }
+
+    // set_streaming makes the executor output every group once a row of another group
+    // is read, which is only correct if the source rows are sorted by group.
+    pub fn set_streaming(&mut self) {
+        self.streaming = true;
+    }
+
+    // set_memory_quota makes every group count against `quota` while it is kept.
+    pub fn set_memory_quota(&mut self, quota: Rc<MemoryQuota>) {
+        self.quota = Some(quota);
+    }
+
+    // group_size estimates the memory taken by a group with the given key and values,
+    // including its entries in `group_cnts` and `first_handles` if they are recorded.
+    fn group_size(&self, group_key: &[u8], group_vals: Option<&Vec<u8>>) -> usize {
+        let mut size = group_key.len() + group_vals.map_or(0, |v| v.len()) +
+            self.aggr_func.len() * mem::size_of::<Box<AggrFunc>>() +
+            mem::size_of::<Rc<Vec<u8>>>() + mem::size_of::<Vec<Box<AggrFunc>>>();
+        if self.implicit_count {
+            size += mem::size_of::<Rc<Vec<u8>>>() + mem::size_of::<u64>();
+        }
+        if self.has_first {
+            size += mem::size_of::<Rc<Vec<u8>>>() + mem::size_of::<i64>();
+        }
+        size
+    }
+
+    // get_group_key returns the key used to identify the group of `row`, and the encoded
+    // group values if they are different from the key.
+    fn get_group_key(&self, row: &[Datum]) -> Result<(Vec<u8>, Option<Vec<u8>>)> {
         if self.group_by.is_empty() {
             let single_group = Datum::Bytes(SINGLE_GROUP.to_vec());
-            return Ok(box_try!(datum::encode_value(&[single_group])));
-        }
-        let mut vals = Vec::with_capacity(self.group_by.len());
-        for expr in &self.group_by {
-            let v = box_try!(expr.eval(&self.ctx, row));
-            vals.push(v);
+            return Ok((box_try!(datum::encode_value(&[single_group])), None));
         }
-        let res = box_try!(datum::encode_value(&vals));
-        Ok(res)
+        eval_group_key(&self.ctx, &self.group_by, row)
     }
 
-    fn aggregate(&mut self) -> Result<()> {
-        while let Some(row) = self.src.next()? {
-            let cols = inflate_with_col_for_dag(
-                &self.ctx,
-                &row.data,
-                self.cols.clone(),
-                &self.related_cols_offset,
-                row.handle,
-            )?;
-            let group_key = Rc::new(self.get_group_key(&cols)?);
-            match self.group_key_aggrs.entry(group_key.clone()) {
-                Entry::Vacant(e) => {
-                    let mut aggrs = Vec::with_capacity(self.aggr_func.len());
-                    for expr in &self.aggr_func {
-                        let mut aggr = aggregate::build_aggr_func(expr.tp)?;
-                        aggr.update_with_expr(&self.ctx, expr, &cols)?;
-                        aggrs.push(aggr);
-                    }
-                    self.group_keys.push(group_key);
-                    e.insert(aggrs);
+    // next_row_cols returns the handle and the columns of the next source row.
+    fn next_row_cols(&mut self) -> Result<Option<(i64, Vec<Datum>)>> {
+        let row = match self.src.next()? {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+        let cols = inflate_with_col_for_dag(
+            &self.ctx,
+            &row.data,
+            self.cols.clone(),
+            &self.related_cols_offset,
+            row.handle,
+        )?;
+        Ok(Some((row.handle, cols)))
+    }
+
+    fn update_group(
+        &mut self,
+        group_key: Rc<Vec<u8>>,
+        group_vals: Option<Vec<u8>>,
+        handle: i64,
+        cols: &[Datum],
+    ) -> Result<()> {
+        if self.implicit_count {
+            *self.group_cnts.entry(group_key.clone()).or_insert(0) += 1;
+        }
+        // `FIRST` takes the row with the smallest handle rather than the first row read,
+        // so its result doesn't depend on the order of the scan.
+        let mut first_replaced = false;
+        if self.has_first {
+            let first_handle = self.first_handles.entry(group_key.clone()).or_insert(handle);
+            if handle < *first_handle {
+                *first_handle = handle;
+                first_replaced = true;
+            }
+        }
+        let size = self.group_size(&group_key, group_vals.as_ref());
+        match self.group_key_aggrs.entry(group_key.clone()) {
+            Entry::Vacant(e) => {
+                if let Some(ref quota) = self.quota {
+                    quota.alloc(size)?;
+                }
+                if let Some(vals) = group_vals {
+                    self.group_vals.insert(group_key.clone(), vals);
+                }
+                let mut aggrs = Vec::with_capacity(self.aggr_func.len());
+                for expr in &self.aggr_func {
+                    let mut aggr = aggregate::build_aggr_func(expr.tp)?;
+                    aggr.update_with_expr(&self.ctx, expr, cols)?;
+                    aggrs.push(aggr);
                 }
-                Entry::Occupied(e) => {
-                    let aggrs = e.into_mut();
-                    for (expr, aggr) in self.aggr_func.iter().zip(aggrs) {
-                        aggr.update_with_expr(&self.ctx, expr, &cols)?;
+                self.group_keys.push(group_key);
+                e.insert(aggrs);
+            }
+            Entry::Occupied(e) => {
+                let aggrs = e.into_mut();
+                for (expr, aggr) in self.aggr_func.iter().zip(aggrs) {
+                    if expr.tp == ExprType::First && first_replaced {
+                        *aggr = aggregate::build_aggr_func(expr.tp)?;
                     }
+                    aggr.update_with_expr(&self.ctx, expr, cols)?;
                 }
             }
         }
         Ok(())
     }
-}
 
-impl<'a> Executor for AggregationExecutor<'a> {
-    fn next(&mut self) -> Result<Option<Row>> {
-        if !self.executed {
-            self.aggregate()?;
-            self.executed = true;
+    fn aggregate(&mut self) -> Result<()> {
+        while let Some((handle, cols)) = self.next_row_cols()? {
+            if self.rollup && !self.group_by.is_empty() {
+                for (group_key, group_vals) in eval_rollup_keys(&self.ctx, &self.group_by, &cols)? {
+                    self.update_group(Rc::new(group_key), group_vals, handle, &cols)?;
+                }
+                continue;
+            }
+            let (group_key, group_vals) = self.get_group_key(&cols)?;
+            self.update_group(Rc::new(group_key), group_vals, handle, &cols)?;
         }
+        Ok(())
+    }
 
-        if self.cursor >= self.group_keys.len() {
-            return Ok(None);
+    fn next_streaming(&mut self) -> Result<Option<Row>> {
+        while !self.executed {
+            let (handle, cols) = match self.next_row_cols()? {
+                Some(row) => row,
+                None => {
+                    self.executed = true;
+                    break;
+                }
+            };
+            let (group_key, group_vals) = self.get_group_key(&cols)?;
+            let new_group = self.group_keys
+                .last()
+                .map_or(false, |key| **key != group_key);
+            self.update_group(Rc::new(group_key), group_vals, handle, &cols)?;
+            if new_group {
+                let group_key = self.group_keys.remove(0);
+                return self.group_row(&group_key).map(Some);
+            }
+        }
+        match self.group_keys.pop() {
+            Some(group_key) => self.group_row(&group_key).map(Some),
+            None => Ok(None),
         }
-        // calc all aggr func
+    }
+
+    // group_row calculates the aggregation results of the group and removes it.
+    fn group_row(&mut self, group_key: &Rc<Vec<u8>>) -> Result<Row> {
         let mut aggr_cols = Vec::with_capacity(2 * self.aggr_func.len());
-        let group_key = &self.group_keys[self.cursor];
         let mut aggrs = self.group_key_aggrs.remove(group_key).unwrap();
-        for aggr in &mut aggrs {
+        self.first_handles.remove(group_key);
+        for (expr, aggr) in self.aggr_func.iter().zip(&mut aggrs) {
+            let offset = aggr_cols.len();
             aggr.calc(&mut aggr_cols)?;
+            if self.null_as_zero && expr.tp == ExprType::Sum && aggr_cols[offset] == Datum::Null {
+                aggr_cols[offset] = Datum::Dec(0.into());
+            }
         }
         // construct row data
         let value_size = group_key.len() + approximate_size(&aggr_cols, false);
         let mut value = Vec::with_capacity(value_size);
         box_try!(value.encode(aggr_cols.as_slice(), false));
+        if let Some(ref quota) = self.quota {
+            quota.free(self.group_size(group_key, self.group_vals.get(group_key)));
+        }
         if !self.group_by.is_empty() {
-            value.extend_from_slice(group_key);
+            let vals = self.group_vals.remove(group_key);
+            let vals = vals.as_ref().map_or(group_key.as_slice(), |v| v.as_slice());
+            if self.raw_group_key {
+                box_try!(value.encode(&[Datum::Bytes(vals.to_vec())], false));
+            } else {
+                value.extend_from_slice(vals);
+            }
         }
-        self.cursor += 1;
-        Ok(Some(Row {
+        if self.implicit_count {
+            let cnt = self.group_cnts.remove(group_key).unwrap();
+            box_try!(value.encode(&[Datum::U64(cnt)], false));
+        }
+        Ok(Row {
             handle: 0,
             data: RowColsDict::new(map![], value),
-        }))
+        })
+    }
+}
+
+/// `eval_group_key` evaluates `group_by` over `row`, returning the key identifying its
+/// group and the encoded group values if they differ from the key. Distinct keys never
+/// collide, as every datum is encoded as a type flag and a self-delimiting value, so the
+/// encoded key decodes back to the datums it was built from.
+pub fn eval_group_key(
+    ctx: &EvalContext,
+    group_by: &[Expression],
+    row: &[Datum],
+) -> Result<(Vec<u8>, Option<Vec<u8>>)> {
+    let (keys, vals, folded) = eval_group_datums(ctx, group_by, row)?;
+    encode_group_key(&keys, &vals, folded)
+}
+
+/// `eval_rollup_keys` is `eval_group_key` for every level of a rollup of `group_by`, from
+/// the full group by to none of it. The values rolled up at level `i` are the last `i`
+/// ones, which are NULL, and `i` is appended as a trailing value to tell the levels apart.
+pub fn eval_rollup_keys(
+    ctx: &EvalContext,
+    group_by: &[Expression],
+    row: &[Datum],
+) -> Result<Vec<(Vec<u8>, Option<Vec<u8>>)>> {
+    let (mut keys, mut vals, folded) = eval_group_datums(ctx, group_by, row)?;
+    let n = group_by.len();
+    keys.push(Datum::I64(0));
+    vals.push(Datum::I64(0));
+    let mut res = Vec::with_capacity(n + 1);
+    for level in 0..n + 1 {
+        if level > 0 {
+            keys[n - level] = Datum::Null;
+            vals[n - level] = Datum::Null;
+        }
+        keys[n] = Datum::I64(level as i64);
+        vals[n] = Datum::I64(level as i64);
+        res.push(encode_group_key(&keys, &vals, folded)?);
+    }
+    Ok(res)
+}
+
+// eval_group_datums evaluates `group_by` over `row`, returning the datums of the key and
+// the values, and whether any key differs from its value.
+fn eval_group_datums(
+    ctx: &EvalContext,
+    group_by: &[Expression],
+    row: &[Datum],
+) -> Result<(Vec<Datum>, Vec<Datum>, bool)> {
+    let mut vals = Vec::with_capacity(group_by.len());
+    let mut keys = Vec::with_capacity(group_by.len());
+    let mut folded = false;
+    for expr in group_by {
+        let v = box_try!(expr.eval(ctx, row));
+        let key = if charset::is_ci_collation(expr.get_tp().get_collate()) {
+            fold_case(&v)
+        } else {
+            canonical_dec(&v)
+        };
+        match key {
+            Some(k) => {
+                folded = true;
+                keys.push(k);
+            }
+            None => keys.push(v.clone()),
+        }
+        vals.push(v);
+    }
+    Ok((keys, vals, folded))
+}
+
+fn encode_group_key(
+    keys: &[Datum],
+    vals: &[Datum],
+    folded: bool,
+) -> Result<(Vec<u8>, Option<Vec<u8>>)> {
+    if !folded {
+        return Ok((box_try!(datum::encode_value(vals)), None));
+    }
+    let key = box_try!(datum::encode_value(keys));
+    let vals = box_try!(datum::encode_value(vals));
+    Ok((key, Some(vals)))
+}
+
+/// `group_by_index_prefix` checks if `group_by` refers to the leading columns of an
+/// index scan in order. The rows read from the index are then sorted by group, unless
+/// a case insensitive collation makes the values of one group differ.
+pub fn group_by_index_prefix(group_by: &[Expr]) -> bool {
+    !group_by.is_empty() &&
+        group_by.iter().enumerate().all(|(i, expr)| {
+            expr.get_tp() == ExprType::ColumnRef &&
+                !charset::is_ci_collation(expr.get_field_type().get_collate()) &&
+                expr.get_val().decode_i64().ok() == Some(i as i64)
+        })
+}
+
+// fold_case returns the uppercased string if `d` is a string which contains lowercase
+// characters, so collation-equal values share a group key. Only ASCII letters are
+// folded: other characters, whose utf8_general_ci weights may differ from their
+// Unicode case mapping, are compared as they are.
+fn fold_case(d: &Datum) -> Option<Datum> {
+    match *d {
+        Datum::Bytes(ref bs) if bs.iter().any(|b| b.is_ascii_lowercase()) => {
+            Some(Datum::Bytes(bs.to_ascii_uppercase()))
+        }
+        _ => None,
+    }
+}
+
+// canonical_dec returns the decimal `d` without trailing zeroes in its fraction,
+// so numerically equal decimals like `1.0` and `1.00` share a group key.
+fn canonical_dec(d: &Datum) -> Option<Datum> {
+    match *d {
+        Datum::Dec(ref dec) => dec.remove_trailing_zeroes().map(Datum::Dec),
+        _ => None,
+    }
+}
+
+impl<'a> Executor for AggregationExecutor<'a> {
+    fn next(&mut self) -> Result<Option<Row>> {
+        if self.streaming {
+            return self.next_streaming();
+        }
+        if !self.executed {
+            self.aggregate()?;
+            self.executed = true;
+        }
+
+        if self.cursor >= self.group_keys.len() {
+            return Ok(None);
+        }
+        let group_key = self.group_keys[self.cursor].clone();
+        self.cursor += 1;
+        self.group_row(&group_key).map(Some)
     }
 }
 
 #[cfg(test)]
 mod test {
+    use std::cell::Cell;
     use std::i64;
 
     use kvproto::kvrpcpb::IsolationLevel;
     use protobuf::RepeatedField;
-    use tipb::executor::TableScan;
+    use rand::{self, Rng};
+    use tipb::executor::{IndexScan, TableScan};
     use tipb::expression::{Expr, ExprType};
 
-    use coprocessor::codec::datum::{Datum, DatumDecoder};
+    use coprocessor::codec::datum::{self, Datum, DatumDecoder};
     use coprocessor::codec::mysql::decimal::Decimal;
     use coprocessor::codec::mysql::types;
+    use coprocessor::codec::table;
     use storage::{SnapshotStore, Statistics};
     use util::codec::number::NumberEncoder;
 
     use super::*;
     use super::super::table_scan::TableScanExecutor;
-    use super::super::scanner::test::{get_range, new_col_info, TestStore};
+    use super::super::index_scan::IndexScanExecutor;
+    use super::super::index_scan::test::{get_idx_range, prepare_index_data};
+    use super::super::scanner::test::{get_range, new_col_info, prepare_table_data, TestStore};
     use super::super::topn::test::gen_table_data;
+    use super::super::test::{new_table_scan, new_table_scan_executor};
 
     #[inline]
     fn build_expr(tp: ExprType, id: Option<i64>, child: Option<Expr>) -> Expr {
@@ -363,4 +669,500 @@ mod test {
             assert_eq!(ds[4], Datum::from(expect_cols.4));
         }
     }
+
+    fn random_datum<R: Rng>(rng: &mut R) -> Datum {
+        // small domains so that equal keys are generated too.
+        match rng.gen_range(0, 5) {
+            0 => Datum::Null,
+            1 => Datum::I64(rng.gen_range(-3, 3)),
+            2 => Datum::U64(rng.gen_range(0, 3)),
+            3 => Datum::F64(f64::from(rng.gen_range(-3i32, 3)) / 2.0),
+            _ => {
+                let len = rng.gen_range(0, 10);
+                // 0 and 255 are the bytes escaped by the encoding.
+                Datum::Bytes((0..len).map(|_| *rng.choose(&[0, 1, 255]).unwrap()).collect())
+            }
+        }
+    }
+
+    #[test]
+    fn test_group_key_collision() {
+        let mut rng = rand::thread_rng();
+        let mut keys = HashMap::default();
+        let mut values = HashMap::default();
+        for _ in 0..10000 {
+            let len = rng.gen_range(1, 4);
+            let group: Vec<_> = (0..len).map(|_| random_datum(&mut rng)).collect();
+            let key = datum::encode_key(&group).unwrap();
+            assert_eq!(key.as_slice().decode().unwrap(), group);
+            let value = datum::encode_value(&group).unwrap();
+            assert_eq!(value.as_slice().decode().unwrap(), group);
+            for (encoded, seen) in vec![(key, &mut keys), (value, &mut values)] {
+                let prev = seen.entry(encoded).or_insert_with(|| group.clone());
+                assert_eq!(*prev, group);
+            }
+        }
+        // the keys repeat, but not all of them.
+        assert!(keys.len() > 100 && keys.len() < 10000, "{}", keys.len());
+        assert_eq!(keys.len(), values.len());
+    }
+
+    #[test]
+    fn test_aggregation_with_ci_collation() {
+        let tid = 1;
+        let cis = vec![
+            new_col_info(1, types::LONG_LONG),
+            new_col_info(2, types::VARCHAR),
+            new_col_info(3, types::NEW_DECIMAL),
+        ];
+        let raw_data = vec![
+            vec![
+                Datum::I64(1),
+                Datum::Bytes(b"a".to_vec()),
+                Datum::Dec(1.into()),
+            ],
+            vec![
+                Datum::I64(2),
+                Datum::Bytes(b"A".to_vec()),
+                Datum::Dec(2.into()),
+            ],
+            vec![
+                Datum::I64(3),
+                Datum::Bytes(b"b".to_vec()),
+                Datum::Dec(4.into()),
+            ],
+            vec![
+                Datum::I64(4),
+                Datum::Bytes(b"a".to_vec()),
+                Datum::Dec(8.into()),
+            ],
+        ];
+        let table_data = gen_table_data(tid, &cis, &raw_data);
+        let mut test_store = TestStore::new(&table_data);
+        let table_scan = new_table_scan(tid, &cis);
+        let mut statistics = Statistics::default();
+        let ts_ect = new_table_scan_executor(&mut test_store, &table_scan, &mut statistics);
+
+        // group by the name column under utf8_general_ci.
+        let mut aggregation = Aggregation::default();
+        let mut group_by = build_group_by(&[1]);
+        group_by[0]
+            .mut_field_type()
+            .set_collate(charset::COLLATION_UTF8_GENERAL_CI_ID);
+        aggregation.set_group_by(RepeatedField::from_vec(group_by));
+        let aggr_funcs = build_aggr_func(&[(ExprType::Count, 2), (ExprType::Sum, 2)]);
+        aggregation.set_agg_func(RepeatedField::from_vec(aggr_funcs));
+        let mut aggr_ect = AggregationExecutor::new(
+            aggregation,
+            Rc::new(EvalContext::default()),
+            Rc::new(cis),
+            Box::new(ts_ect),
+        ).unwrap();
+        let mut row_data = vec![];
+        while let Some(row) = aggr_ect.next().unwrap() {
+            row_data.push(row.data);
+        }
+        let expect_row_data = vec![
+            (3 as u64, Decimal::from(11), b"a".as_ref()),
+            (1 as u64, Decimal::from(4), b"b".as_ref()),
+        ];
+        assert_eq!(row_data.len(), expect_row_data.len());
+        for (row, expect_cols) in row_data.into_iter().zip(expect_row_data) {
+            let ds = row.value.as_slice().decode().unwrap();
+            assert_eq!(ds.len(), 3);
+            assert_eq!(ds[0], Datum::from(expect_cols.0));
+            assert_eq!(ds[1], Datum::from(expect_cols.1));
+            assert_eq!(ds[2], Datum::from(expect_cols.2));
+        }
+    }
+
+    #[test]
+    fn test_fold_case() {
+        let cases = vec![
+            (Datum::Bytes(b"aBc".to_vec()), Some(Datum::Bytes(b"ABC".to_vec()))),
+            (Datum::Bytes(b"ABC".to_vec()), None),
+            // only ASCII letters are folded.
+            (Datum::Bytes("\u{e9}".as_bytes().to_vec()), None),
+            (
+                Datum::Bytes("\u{e9}a".as_bytes().to_vec()),
+                Some(Datum::Bytes("\u{e9}A".as_bytes().to_vec())),
+            ),
+            (Datum::Bytes(vec![0xff, b'a']), Some(Datum::Bytes(vec![0xff, b'A']))),
+            (Datum::I64(1), None),
+        ];
+        for (d, exp) in cases {
+            assert_eq!(fold_case(&d), exp);
+        }
+    }
+
+    #[test]
+    fn test_aggregation_group_by_decimal() {
+        let tid = 1;
+        let cis = vec![
+            new_col_info(1, types::LONG_LONG),
+            new_col_info(2, types::NEW_DECIMAL),
+        ];
+        let raw_data: Vec<_> = ["1.0", "2.5", "1.00", "1", "2.50", "10"]
+            .iter()
+            .enumerate()
+            .map(|(i, dec)| {
+                vec![
+                    Datum::I64(i as i64 + 1),
+                    Datum::Dec(dec.parse().unwrap()),
+                ]
+            })
+            .collect();
+        let table_data = gen_table_data(tid, &cis, &raw_data);
+        let mut test_store = TestStore::new(&table_data);
+        let table_scan = new_table_scan(tid, &cis);
+        let mut statistics = Statistics::default();
+        let ts_ect = new_table_scan_executor(&mut test_store, &table_scan, &mut statistics);
+
+        let mut aggregation = Aggregation::default();
+        aggregation.set_group_by(RepeatedField::from_vec(build_group_by(&[2])));
+        let aggr_funcs = build_aggr_func(&[(ExprType::Count, 1), (ExprType::Sum, 2)]);
+        aggregation.set_agg_func(RepeatedField::from_vec(aggr_funcs));
+        let mut aggr_ect = AggregationExecutor::new(
+            aggregation,
+            Rc::new(EvalContext::default()),
+            Rc::new(cis),
+            Box::new(ts_ect),
+        ).unwrap();
+        let mut row_data = vec![];
+        while let Some(row) = aggr_ect.next().unwrap() {
+            row_data.push(row.data);
+        }
+        // count, sum, and the group value first seen.
+        let expect_row_data = vec![(3, "3.00", "1.0"), (2, "5.00", "2.5"), (1, "10", "10")];
+        assert_eq!(row_data.len(), expect_row_data.len());
+        for (row, (cnt, sum, dec)) in row_data.into_iter().zip(expect_row_data) {
+            let ds = row.value.as_slice().decode().unwrap();
+            assert_eq!(ds.len(), 3);
+            assert_eq!(ds[0], Datum::U64(cnt));
+            assert_eq!(ds[1], Datum::Dec(sum.parse().unwrap()));
+            assert_eq!(ds[2].to_string().unwrap(), dec);
+        }
+    }
+
+    #[test]
+    fn test_aggregation_with_implicit_count() {
+        let tid = 1;
+        let cis = vec![
+            new_col_info(1, types::LONG_LONG),
+            new_col_info(2, types::VARCHAR),
+        ];
+        let raw_data: Vec<_> = [b"a", b"b", b"a", b"c", b"a", b"b"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| vec![Datum::I64(i as i64 + 1), Datum::Bytes(name.to_vec())])
+            .collect();
+        let table_data = gen_table_data(tid, &cis, &raw_data);
+        let mut test_store = TestStore::new(&table_data);
+        let table_scan = new_table_scan(tid, &cis);
+
+        for group_by_cols in vec![vec![], vec![1]] {
+            let mut statistics = Statistics::default();
+            let ts_ect = new_table_scan_executor(&mut test_store, &table_scan, &mut statistics);
+
+            let mut aggregation = Aggregation::default();
+            let group_by = build_group_by(&group_by_cols);
+            aggregation.set_group_by(RepeatedField::from_vec(group_by));
+            let aggr_funcs = build_aggr_func(&[(ExprType::Count, 0)]);
+            aggregation.set_agg_func(RepeatedField::from_vec(aggr_funcs));
+            let mut aggr_ect = AggregationExecutor::new(
+                aggregation,
+                Rc::new(EvalContext::default()),
+                Rc::new(cis.clone()),
+                Box::new(ts_ect),
+            ).unwrap();
+            aggr_ect.set_implicit_count();
+
+            let mut counts = vec![];
+            while let Some(row) = aggr_ect.next().unwrap() {
+                let ds = row.data.value.as_slice().decode().unwrap();
+                // count(), the group by values, and then the implicit count.
+                assert_eq!(ds.len(), 2 + group_by_cols.len());
+                assert_eq!(ds[0], ds[ds.len() - 1]);
+                counts.push(ds[0].clone());
+            }
+            let expect: Vec<u64> = if group_by_cols.is_empty() {
+                vec![6]
+            } else {
+                vec![3, 2, 1]
+            };
+            let expect: Vec<_> = expect.into_iter().map(Datum::U64).collect();
+            assert_eq!(counts, expect);
+        }
+    }
+
+    #[test]
+    fn test_aggregation_with_raw_group_key() {
+        let tid = 1;
+        let cis = vec![
+            new_col_info(1, types::LONG_LONG),
+            new_col_info(2, types::VARCHAR),
+            new_col_info(3, types::LONG_LONG),
+        ];
+        let raw_data: Vec<_> = [(b"a", 1), (b"b", 2), (b"a", 1), (b"a", 3)]
+            .iter()
+            .enumerate()
+            .map(|(i, &(name, n))| {
+                vec![
+                    Datum::I64(i as i64 + 1),
+                    Datum::Bytes(name.to_vec()),
+                    Datum::I64(n),
+                ]
+            })
+            .collect();
+        let table_data = gen_table_data(tid, &cis, &raw_data);
+        let mut test_store = TestStore::new(&table_data);
+        let table_scan = new_table_scan(tid, &cis);
+        let mut statistics = Statistics::default();
+        let ts_ect = new_table_scan_executor(&mut test_store, &table_scan, &mut statistics);
+
+        let mut aggregation = Aggregation::default();
+        aggregation.set_group_by(RepeatedField::from_vec(build_group_by(&[1, 2])));
+        let aggr_funcs = build_aggr_func(&[(ExprType::Count, 0)]);
+        aggregation.set_agg_func(RepeatedField::from_vec(aggr_funcs));
+        let mut aggr_ect = AggregationExecutor::new(
+            aggregation,
+            Rc::new(EvalContext::default()),
+            Rc::new(cis),
+            Box::new(ts_ect),
+        ).unwrap();
+        aggr_ect.set_raw_group_key();
+
+        let expect = vec![
+            (2, vec![Datum::Bytes(b"a".to_vec()), Datum::I64(1)]),
+            (1, vec![Datum::Bytes(b"b".to_vec()), Datum::I64(2)]),
+            (1, vec![Datum::Bytes(b"a".to_vec()), Datum::I64(3)]),
+        ];
+        for (cnt, group_vals) in expect {
+            let row = aggr_ect.next().unwrap().unwrap();
+            let mut ds = row.data.value.as_slice().decode().unwrap();
+            // count(), and then all the group by values in one column.
+            assert_eq!(ds.len(), 2);
+            assert_eq!(ds[0], Datum::U64(cnt));
+            let raw_key = match ds.pop().unwrap() {
+                Datum::Bytes(bs) => bs,
+                d => panic!("unexpected raw group key {:?}", d),
+            };
+            assert_eq!(raw_key.as_slice().decode().unwrap(), group_vals);
+        }
+        assert!(aggr_ect.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_aggregation_over_covering_index() {
+        let (tid, idx_id, key_number) = (1, 1, 10);
+        let index_data = prepare_index_data(key_number, tid, idx_id);
+        let mut kv_data = prepare_table_data(key_number, tid).kv_data;
+        kv_data.extend_from_slice(&index_data.kv_data);
+        let mut test_store = TestStore::new(&kv_data);
+
+        let cols = index_data.get_index_cols();
+        let mut scan = IndexScan::new();
+        scan.set_table_id(tid);
+        scan.set_index_id(idx_id);
+        scan.set_columns(RepeatedField::from_vec(cols.clone()));
+        let key_ranges = vec![get_idx_range(tid, idx_id, i64::MIN, i64::MAX)];
+        let (snapshot, start_ts) = test_store.get_snapshot();
+        let store = SnapshotStore::new(snapshot, start_ts, IsolationLevel::SI, true);
+        let mut statistics = Statistics::default();
+        {
+            let is_ect = IndexScanExecutor::new(scan, key_ranges, store, &mut statistics);
+            // sum the second index column, which is 0..key_number.
+            let mut aggregation = Aggregation::default();
+            let aggr_funcs = build_aggr_func(&[(ExprType::Sum, 1)]);
+            aggregation.set_agg_func(RepeatedField::from_vec(aggr_funcs));
+            let mut aggr_ect = AggregationExecutor::new(
+                aggregation,
+                Rc::new(EvalContext::default()),
+                Rc::new(cols),
+                Box::new(is_ect),
+            ).unwrap();
+            let row = aggr_ect.next().unwrap().unwrap();
+            let ds = row.data.value.as_slice().decode().unwrap();
+            assert_eq!(ds, vec![Datum::Dec(45.into())]);
+            assert!(aggr_ect.next().unwrap().is_none());
+        }
+        // only the index entries are read, there is no row lookup.
+        assert_eq!(statistics.write.processed, key_number);
+        assert_eq!(statistics.data.processed, 0);
+    }
+
+    struct CountingExecutor<'a> {
+        cnt: Rc<Cell<usize>>,
+        src: Box<Executor + 'a>,
+    }
+
+    impl<'a> Executor for CountingExecutor<'a> {
+        fn next(&mut self) -> Result<Option<Row>> {
+            let row = self.src.next()?;
+            if row.is_some() {
+                self.cnt.set(self.cnt.get() + 1);
+            }
+            Ok(row)
+        }
+    }
+
+    #[test]
+    fn test_streaming_aggregation_over_index_prefix() {
+        let (tid, idx_id) = (1, 1);
+        let cols = vec![
+            new_col_info(2, types::VARCHAR),
+            new_col_info(3, types::LONG_LONG),
+        ];
+        // an index on (name, count).
+        let rows = [(b"a", 1), (b"a", 2), (b"b", 1), (b"b", 3), (b"b", 5), (b"c", 2)];
+        let kv_data: Vec<_> = rows.iter()
+            .enumerate()
+            .map(|(handle, &(name, count))| {
+                let v = vec![
+                    Datum::Bytes(name.to_vec()),
+                    Datum::I64(count),
+                    Datum::I64(handle as i64),
+                ];
+                let encoded = datum::encode_key(&v).unwrap();
+                (table::encode_index_seek_key(tid, idx_id, &encoded), vec![0])
+            })
+            .collect();
+        let mut test_store = TestStore::new(&kv_data);
+
+        // only the leading index columns are sorted.
+        assert!(group_by_index_prefix(&build_group_by(&[0])));
+        assert!(group_by_index_prefix(&build_group_by(&[0, 1])));
+        assert!(!group_by_index_prefix(&build_group_by(&[1])));
+        assert!(!group_by_index_prefix(&build_group_by(&[])));
+        let mut ci_group_by = build_group_by(&[0]);
+        ci_group_by[0].mut_field_type().set_collate(charset::COLLATION_UTF8_GENERAL_CI_ID);
+        assert!(!group_by_index_prefix(&ci_group_by));
+
+        let mut scan = IndexScan::new();
+        scan.set_table_id(tid);
+        scan.set_index_id(idx_id);
+        scan.set_columns(RepeatedField::from_vec(cols.clone()));
+        let key_ranges = vec![get_idx_range(tid, idx_id, i64::MIN, i64::MAX)];
+        let (snapshot, start_ts) = test_store.get_snapshot();
+        let store = SnapshotStore::new(snapshot, start_ts, IsolationLevel::SI, true);
+        let mut statistics = Statistics::default();
+        let is_ect = IndexScanExecutor::new(scan, key_ranges, store, &mut statistics);
+        let read = Rc::new(Cell::new(0));
+        let src = CountingExecutor {
+            cnt: read.clone(),
+            src: Box::new(is_ect),
+        };
+
+        let mut aggregation = Aggregation::default();
+        aggregation.set_group_by(RepeatedField::from_vec(build_group_by(&[0])));
+        let aggr_funcs = build_aggr_func(&[(ExprType::Count, 1)]);
+        aggregation.set_agg_func(RepeatedField::from_vec(aggr_funcs));
+        let mut aggr_ect = AggregationExecutor::new(
+            aggregation,
+            Rc::new(EvalContext::default()),
+            Rc::new(cols),
+            Box::new(src),
+        ).unwrap();
+        aggr_ect.set_streaming();
+
+        let expect = vec![(b"a", 2, 3), (b"b", 3, 6), (b"c", 1, 6)];
+        for (name, count, read_cnt) in expect {
+            let row = aggr_ect.next().unwrap().unwrap();
+            let ds = row.data.value.as_slice().decode().unwrap();
+            assert_eq!(ds, vec![Datum::U64(count), Datum::Bytes(name.to_vec())]);
+            // a group is output once the first row of the next group is read.
+            assert_eq!(read.get(), read_cnt);
+        }
+        assert!(aggr_ect.next().unwrap().is_none());
+    }
+
+    fn first_by_group<'a>(cis: &[ColumnInfo], src: Box<Executor + 'a>) -> Vec<Vec<Datum>> {
+        let mut aggregation = Aggregation::default();
+        aggregation.set_group_by(RepeatedField::from_vec(build_group_by(&[2])));
+        let aggr_funcs = build_aggr_func(&[(ExprType::First, 1)]);
+        aggregation.set_agg_func(RepeatedField::from_vec(aggr_funcs));
+        let mut aggr_ect = AggregationExecutor::new(
+            aggregation,
+            Rc::new(EvalContext::default()),
+            Rc::new(cis.to_vec()),
+            src,
+        ).unwrap();
+        let mut rows = vec![];
+        while let Some(row) = aggr_ect.next().unwrap() {
+            rows.push(row.data.value.as_slice().decode().unwrap());
+        }
+        rows
+    }
+
+    #[test]
+    fn test_first_by_smallest_handle() {
+        let tid = 1;
+        let cis = vec![
+            new_col_info(1, types::LONG_LONG),
+            new_col_info(2, types::LONG_LONG),
+            new_col_info(3, types::LONG_LONG),
+        ];
+        let raw_data: Vec<_> = (0..100)
+            .map(|i| vec![Datum::I64(i), Datum::I64((i * 7) % 100), Datum::I64(i % 3)])
+            .collect();
+        let table_data = gen_table_data(tid, &cis, &raw_data);
+        let mut test_store = TestStore::new(&table_data);
+        let mut table_scan = new_table_scan(tid, &cis);
+
+        for &desc in &[false, true] {
+            table_scan.set_desc(desc);
+            let mut statistics = Statistics::default();
+            let scan = new_table_scan_executor(&mut test_store, &table_scan, &mut statistics);
+            let mut got = first_by_group(&cis, Box::new(scan));
+            // a reverse scan reads the groups backwards too.
+            got.sort_by_key(|row| row[1].i64());
+            // the first row of every group is the one with the smallest handle, even
+            // though a reverse scan reads the largest one first.
+            let expect: Vec<_> = (0..3)
+                .map(|h| vec![Datum::I64((h * 7) % 100), Datum::I64(h % 3)])
+                .collect();
+            assert_eq!(got, expect, "desc {}", desc);
+        }
+    }
+
+    #[test]
+    fn test_aggregation_memory_quota() {
+        let tid = 1;
+        let cis = vec![
+            new_col_info(1, types::LONG_LONG),
+            new_col_info(2, types::LONG_LONG),
+        ];
+        let raw_data: Vec<_> = (0..10)
+            .map(|i| vec![Datum::I64(i), Datum::I64(i % 5)])
+            .collect();
+        let table_data = gen_table_data(tid, &cis, &raw_data);
+        let mut test_store = TestStore::new(&table_data);
+        let table_scan = new_table_scan(tid, &cis);
+        let mut statistics = Statistics::default();
+        let ts_ect = new_table_scan_executor(&mut test_store, &table_scan, &mut statistics);
+
+        let mut aggregation = Aggregation::default();
+        aggregation.set_group_by(RepeatedField::from_vec(build_group_by(&[1])));
+        let aggr_funcs = build_aggr_func(&[(ExprType::First, 0)]);
+        aggregation.set_agg_func(RepeatedField::from_vec(aggr_funcs));
+        let mut aggr_ect = AggregationExecutor::new(
+            aggregation,
+            Rc::new(EvalContext::default()),
+            Rc::new(cis),
+            Box::new(ts_ect),
+        ).unwrap();
+        aggr_ect.set_implicit_count();
+        let quota = Rc::new(MemoryQuota::new(usize::max_value()));
+        aggr_ect.set_memory_quota(quota.clone());
+
+        // the groups not output yet are charged for their count and first handle too.
+        aggr_ect.next().unwrap().unwrap();
+        let key_len = datum::encode_value(&[Datum::I64(0)]).unwrap().len();
+        let group_size = key_len + mem::size_of::<Box<AggrFunc>>() +
+            3 * mem::size_of::<Rc<Vec<u8>>>() + mem::size_of::<Vec<Box<AggrFunc>>>() +
+            mem::size_of::<u64>() + mem::size_of::<i64>();
+        assert_eq!(quota.used(), 4 * group_size);
+        while let Some(_) = aggr_ect.next().unwrap() {}
+        assert_eq!(quota.used(), 0);
+    }
 }
@@ -0,0 +1,151 @@
+// Copyright 2017 PingCAP, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use coprocessor::Result;
+use util::time::Instant;
+
+use super::{Executor, Row};
+
+/// `BudgetExecutor` limits the time spent by its source executor. Only the
+/// executor's own time is counted: the time spent by a budgeted child is
+/// subtracted, so a slow scan never fails the stages above it.
+pub struct BudgetExecutor<'a> {
+    name: &'static str,
+    budget: Duration,
+    spent: Duration,
+    // total time spent in `src`, including its children.
+    elapsed: Rc<Cell<Duration>>,
+    src_elapsed: Option<Rc<Cell<Duration>>>,
+    src: Box<Executor + 'a>,
+}
+
+impl<'a> BudgetExecutor<'a> {
+    /// `src_elapsed` is the `elapsed` of the budgeted child of `src`, if any.
+    pub fn new(
+        name: &'static str,
+        budget: Duration,
+        src: Box<Executor + 'a>,
+        src_elapsed: Option<Rc<Cell<Duration>>>,
+    ) -> BudgetExecutor<'a> {
+        BudgetExecutor {
+            name: name,
+            budget: budget,
+            spent: Duration::default(),
+            elapsed: Rc::new(Cell::new(Duration::default())),
+            src_elapsed: src_elapsed,
+            src: src,
+        }
+    }
+
+    pub fn elapsed(&self) -> Rc<Cell<Duration>> {
+        self.elapsed.clone()
+    }
+}
+
+impl<'a> Executor for BudgetExecutor<'a> {
+    fn next(&mut self) -> Result<Option<Row>> {
+        let child_before = self.src_elapsed.as_ref().map_or_else(Duration::default, |e| e.get());
+        let start = Instant::now();
+        let row = self.src.next();
+        let total = start.elapsed();
+        self.elapsed.set(self.elapsed.get() + total);
+        let child_spent = self.src_elapsed.as_ref().map_or_else(Duration::default, |e| {
+            e.get() - child_before
+        });
+        self.spent += total.checked_sub(child_spent).unwrap_or_default();
+        if self.spent > self.budget {
+            return Err(box_err!(
+                "executor {} exceeded its time budget {:?}",
+                self.name,
+                self.budget
+            ));
+        }
+        row
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    use protobuf::RepeatedField;
+    use tipb::executor::TopN;
+
+    use coprocessor::codec::Datum;
+    use coprocessor::codec::mysql::types;
+    use coprocessor::select::xeval::EvalContext;
+    use storage::Statistics;
+
+    use super::*;
+    use super::super::topn::TopNExecutor;
+    use super::super::topn::test::new_order_by;
+    use super::super::test::{gen_table_data, new_col_info, new_table_scan, new_table_scan_executor,
+                             TestStore};
+
+    #[test]
+    fn test_budget_executor() {
+        let tid = 1;
+        let cis = vec![
+            new_col_info(1, types::LONG_LONG),
+            new_col_info(2, types::VARCHAR),
+        ];
+        let raw_data: Vec<_> = (0..100)
+            .map(|i| vec![Datum::I64(i), Datum::Bytes(format!("{}", i).into_bytes())])
+            .collect();
+        let table_data = gen_table_data(tid, &cis, &raw_data);
+        let mut test_store = TestStore::new(&table_data);
+        let table_scan = new_table_scan(tid, &cis);
+        let large = Duration::from_secs(3600);
+
+        // the scan alone fits in its budget.
+        {
+            let mut statistics = Statistics::default();
+            let scan = new_table_scan_executor(&mut test_store, &table_scan, &mut statistics);
+            let mut scan = BudgetExecutor::new("TableScan", large, Box::new(scan), None);
+            let mut count = 0;
+            while let Some(_) = scan.next().unwrap() {
+                count += 1;
+            }
+            assert_eq!(count, 100);
+        }
+
+        // while a TopN with a tiny budget fails, naming itself.
+        let mut statistics = Statistics::default();
+        let scan = new_table_scan_executor(&mut test_store, &table_scan, &mut statistics);
+        let scan = BudgetExecutor::new("TableScan", large, Box::new(scan), None);
+        let scan_elapsed = scan.elapsed();
+        let mut topn = TopN::default();
+        topn.set_order_by(RepeatedField::from_vec(vec![new_order_by(1, true)]));
+        topn.set_limit(10);
+        let topn = TopNExecutor::new(
+            topn,
+            Rc::new(EvalContext::default()),
+            Rc::new(cis),
+            Box::new(scan),
+        ).unwrap();
+        let mut topn = BudgetExecutor::new(
+            "TopN",
+            Duration::new(0, 1),
+            Box::new(topn),
+            Some(scan_elapsed.clone()),
+        );
+        let err = topn.next().unwrap_err();
+        assert!(format!("{}", err).contains("TopN"), "{:?}", err);
+        assert!(scan_elapsed.get() > Duration::default());
+    }
+}
@@ -0,0 +1,149 @@
+// Copyright 2017 PingCAP, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use tipb::select::{Chunk, RowMeta};
+
+use coprocessor::Result;
+
+use super::{Executor, Row, SelectionSummary};
+
+/// The rows produced by one executor of a DAG, tagged by the executor's index.
+/// They are kept in a single chunk, so it can be returned as is.
+pub struct StageOutput {
+    pub index: usize,
+    pub chunk: Chunk,
+    // the rows read and passed by the stage, if it's a selection.
+    pub selection: Option<Rc<Cell<SelectionSummary>>>,
+}
+
+impl StageOutput {
+    pub fn row_count(&self) -> usize {
+        self.chunk.get_rows_meta().len()
+    }
+}
+
+/// `DebugExecutor` records every row passed through by its source executor,
+/// so the intermediate result of each stage can be inspected.
+pub struct DebugExecutor<'a> {
+    output: Rc<RefCell<StageOutput>>,
+    src: Box<Executor + 'a>,
+}
+
+impl<'a> DebugExecutor<'a> {
+    pub fn new(index: usize, src: Box<Executor + 'a>) -> DebugExecutor<'a> {
+        DebugExecutor {
+            output: Rc::new(RefCell::new(StageOutput {
+                index: index,
+                chunk: Chunk::new(),
+                selection: None,
+            })),
+            src: src,
+        }
+    }
+
+    pub fn output(&self) -> Rc<RefCell<StageOutput>> {
+        self.output.clone()
+    }
+}
+
+impl<'a> Executor for DebugExecutor<'a> {
+    fn next(&mut self) -> Result<Option<Row>> {
+        let row = match self.src.next()? {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+        let chunk = &mut self.output.borrow_mut().chunk;
+        chunk.mut_rows_data().extend_from_slice(&row.data.value);
+        let mut meta = RowMeta::new();
+        meta.set_handle(row.handle);
+        meta.set_length(row.data.value.len() as i64);
+        chunk.mut_rows_meta().push(meta);
+        Ok(Some(row))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tipb::executor::{Limit, Selection};
+
+    use coprocessor::codec::mysql::types;
+    use coprocessor::codec::datum::Datum;
+    use coprocessor::select::xeval::EvalContext;
+    use storage::Statistics;
+
+    use super::*;
+    use super::super::{LimitExecutor, SelectionExecutor};
+    use super::super::test::{gen_table_data, new_col_gt_u64_expr, new_col_info, new_table_scan,
+                             new_table_scan_executor, TestStore};
+
+    #[test]
+    fn test_debug_executor() {
+        let tid = 1;
+        let cis = vec![
+            new_col_info(1, types::LONG_LONG),
+            new_col_info(2, types::LONG_LONG),
+        ];
+        let raw_data: Vec<_> = (1..8)
+            .map(|i| vec![Datum::I64(i), Datum::I64(i % 4)])
+            .collect();
+        let table_data = gen_table_data(tid, &cis, &raw_data);
+        let mut test_store = TestStore::new(&table_data);
+        let table_scan = new_table_scan(tid, &cis);
+        let mut statistics = Statistics::default();
+        let ts_ect = new_table_scan_executor(&mut test_store, &table_scan, &mut statistics);
+        let ts_ect = DebugExecutor::new(0, Box::new(ts_ect));
+        let mut outputs = vec![ts_ect.output()];
+
+        // TableScan -> Selection(col2 > 1) -> Limit(2)
+        let mut selection = Selection::new();
+        selection.mut_conditions().push(new_col_gt_u64_expr(1, 1));
+        let sel_ect = SelectionExecutor::new(
+            selection,
+            Rc::new(EvalContext::default()),
+            Rc::new(cis),
+            Box::new(ts_ect),
+        ).unwrap();
+        let sel_ect = DebugExecutor::new(1, Box::new(sel_ect));
+        outputs.push(sel_ect.output());
+
+        let mut limit = Limit::new();
+        limit.set_limit(2);
+        let limit_ect = LimitExecutor::new(limit, Box::new(sel_ect));
+        let mut limit_ect = DebugExecutor::new(2, Box::new(limit_ect));
+        outputs.push(limit_ect.output());
+
+        let mut handles = vec![];
+        while let Some(row) = limit_ect.next().unwrap() {
+            handles.push(row.handle);
+        }
+        assert_eq!(handles, vec![2, 3]);
+
+        // the scan stops as soon as the limit is reached.
+        let expect = vec![(0, 3), (1, 2), (2, 2)];
+        for (output, (index, row_cnt)) in outputs.iter().zip(expect) {
+            let output = output.borrow();
+            assert_eq!(output.index, index);
+            assert_eq!(output.row_count(), row_cnt);
+        }
+        let last = outputs[2].borrow();
+        let last_handles: Vec<_> = last.chunk
+            .get_rows_meta()
+            .iter()
+            .map(|meta| meta.get_handle())
+            .collect();
+        assert_eq!(last_handles, handles);
+    }
+}
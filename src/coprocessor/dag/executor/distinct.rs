@@ -0,0 +1,175 @@
+// Copyright 2017 PingCAP, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::mem;
+use std::rc::Rc;
+
+use tipb::expression::Expr;
+use tipb::schema::ColumnInfo;
+use util::collections::HashSet;
+
+use coprocessor::codec::table::RowColsDict;
+use coprocessor::dag::expr::Expression;
+use coprocessor::select::xeval::EvalContext;
+use coprocessor::metrics::*;
+use coprocessor::Result;
+
+use super::aggregation::eval_group_key;
+use super::{inflate_with_col_for_dag, Executor, ExprColumnRefVisitor, MemoryQuota, Row};
+
+/// `DistinctExecutor` outputs the distinct values of its group by expressions, like an
+/// aggregation without aggregate functions. Unlike the aggregation, every group is output
+/// as soon as its first row is read, so only the keys of the groups seen are kept.
+pub struct DistinctExecutor<'a> {
+    group_by: Vec<Expression>,
+    seen: HashSet<Vec<u8>>,
+    ctx: Rc<EvalContext>,
+    // every key seen is registered to `quota` until the source is drained.
+    quota: Option<Rc<MemoryQuota>>,
+    held: usize,
+    cols: Rc<Vec<ColumnInfo>>,
+    related_cols_offset: Vec<usize>,
+    src: Box<Executor + 'a>,
+}
+
+impl<'a> DistinctExecutor<'a> {
+    pub fn new(
+        group_by: Vec<Expr>,
+        ctx: Rc<EvalContext>,
+        columns: Rc<Vec<ColumnInfo>>,
+        src: Box<Executor + 'a>,
+    ) -> Result<DistinctExecutor<'a>> {
+        if group_by.is_empty() {
+            return Err(box_err!("distinct has no group by"));
+        }
+        let mut visitor = ExprColumnRefVisitor::new(columns.len());
+        visitor.batch_visit(&group_by)?;
+        COPR_EXECUTOR_COUNT.with_label_values(&["distinct"]).inc();
+        Ok(DistinctExecutor {
+            group_by: box_try!(Expression::batch_build(ctx.as_ref(), group_by)),
+            seen: HashSet::default(),
+            ctx: ctx,
+            quota: None,
+            held: 0,
+            cols: columns,
+            related_cols_offset: visitor.column_offsets(),
+            src: src,
+        })
+    }
+
+    /// Registers the keys of the groups seen to `quota`.
+    pub fn set_memory_quota(&mut self, quota: Rc<MemoryQuota>) {
+        self.quota = Some(quota);
+    }
+}
+
+impl<'a> Executor for DistinctExecutor<'a> {
+    fn next(&mut self) -> Result<Option<Row>> {
+        while let Some(row) = self.src.next()? {
+            let cols = inflate_with_col_for_dag(
+                &self.ctx,
+                &row.data,
+                self.cols.clone(),
+                &self.related_cols_offset,
+                row.handle,
+            )?;
+            let (key, vals) = eval_group_key(&self.ctx, &self.group_by, &cols)?;
+            if self.seen.contains(&key) {
+                continue;
+            }
+            if let Some(ref quota) = self.quota {
+                let size = key.len() + mem::size_of::<Vec<u8>>();
+                quota.alloc(size)?;
+                self.held += size;
+            }
+            // the values of the first row are output when they differ from the key.
+            let value = vals.unwrap_or_else(|| key.clone());
+            self.seen.insert(key);
+            return Ok(Some(Row::new(0, RowColsDict::new(map![], value))));
+        }
+        if let Some(ref quota) = self.quota {
+            quota.free(self.held);
+        }
+        self.held = 0;
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tipb::expression::ExprType;
+
+    use coprocessor::codec::Datum;
+    use coprocessor::codec::datum::DatumDecoder;
+    use coprocessor::codec::mysql::types;
+    use storage::Statistics;
+    use util::codec::number::NumberEncoder;
+
+    use super::*;
+    use super::super::test::{gen_table_data, new_col_info, new_table_scan, new_table_scan_executor,
+                             TestStore};
+
+    #[test]
+    fn test_distinct_executor() {
+        let tid = 1;
+        let cis = vec![
+            new_col_info(1, types::LONG_LONG),
+            new_col_info(2, types::VARCHAR),
+            new_col_info(3, types::LONG_LONG),
+        ];
+        let raw_data = vec![
+            vec![Datum::I64(1), Datum::Bytes(b"a".to_vec()), Datum::I64(1)],
+            vec![Datum::I64(2), Datum::Bytes(b"b".to_vec()), Datum::I64(1)],
+            vec![Datum::I64(3), Datum::Bytes(b"a".to_vec()), Datum::I64(1)],
+            vec![Datum::I64(4), Datum::Null, Datum::I64(2)],
+            vec![Datum::I64(5), Datum::Bytes(b"a".to_vec()), Datum::I64(2)],
+            vec![Datum::I64(6), Datum::Null, Datum::I64(2)],
+        ];
+        let table_data = gen_table_data(tid, &cis, &raw_data);
+        let mut test_store = TestStore::new(&table_data);
+        let table_scan = new_table_scan(tid, &cis);
+        let mut statistics = Statistics::default();
+        let ts_ect = new_table_scan_executor(&mut test_store, &table_scan, &mut statistics);
+
+        let group_by: Vec<_> = [1, 2]
+            .iter()
+            .map(|&offset| {
+                let mut expr = Expr::new();
+                expr.set_tp(ExprType::ColumnRef);
+                expr.mut_val().encode_i64(offset).unwrap();
+                expr
+            })
+            .collect();
+        let quota = Rc::new(MemoryQuota::new(usize::max_value()));
+        let mut distinct = DistinctExecutor::new(
+            group_by,
+            Rc::new(EvalContext::default()),
+            Rc::new(cis),
+            Box::new(ts_ect),
+        ).unwrap();
+        distinct.set_memory_quota(quota.clone());
+        let mut rows = vec![];
+        while let Some(row) = distinct.next().unwrap() {
+            rows.push(row.data.value.as_slice().decode().unwrap());
+            assert!(quota.used() > 0);
+        }
+        assert_eq!(quota.used(), 0);
+        let expect = vec![
+            vec![Datum::Bytes(b"a".to_vec()), Datum::I64(1)],
+            vec![Datum::Bytes(b"b".to_vec()), Datum::I64(1)],
+            vec![Datum::Null, Datum::I64(2)],
+            vec![Datum::Bytes(b"a".to_vec()), Datum::I64(2)],
+        ];
+        assert_eq!(rows, expect);
+    }
+}
@@ -11,14 +11,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use byteorder::{BigEndian, ReadBytesExt};
-
 use kvproto::coprocessor::KeyRange;
 use tipb::executor::IndexScan;
 use tipb::schema::ColumnInfo;
 
 use coprocessor::codec::{datum, mysql, table};
-use coprocessor::endpoint::prefix_next;
+use coprocessor::endpoint::{prefix_next, ReqContext, DEADLINE_CHECK_ROWS};
 use coprocessor::metrics::*;
 use coprocessor::Result;
 use storage::{SnapshotStore, Statistics};
@@ -34,6 +32,8 @@ pub struct IndexScanExecutor<'a> {
     key_ranges: Vec<KeyRange>,
     scanner: Scanner<'a>,
     pk_col: Option<ColumnInfo>,
+    req_ctx: Option<&'a ReqContext>,
+    scanned_rows: usize,
 }
 
 impl<'a> IndexScanExecutor<'a> {
@@ -63,6 +63,8 @@ impl<'a> IndexScanExecutor<'a> {
             key_ranges: key_ranges,
             cursor: Default::default(),
             pk_col: pk_col,
+            req_ctx: None,
+            scanned_rows: 0,
         }
     }
 
@@ -82,7 +84,30 @@ impl<'a> IndexScanExecutor<'a> {
             key_ranges: key_ranges,
             cursor: Default::default(),
             pk_col: None,
+            req_ctx: None,
+            scanned_rows: 0,
+        }
+    }
+
+    /// Fails the scan once the keys and values read are more than `limit` bytes.
+    pub fn set_scan_bytes_limit(&mut self, limit: u64) {
+        self.scanner.set_scan_bytes_limit(limit);
+    }
+
+    /// Fails the scan once the deadline of `ctx` passes, which is checked every
+    /// `DEADLINE_CHECK_ROWS` rows.
+    pub fn set_req_ctx(&mut self, ctx: &'a ReqContext) {
+        self.req_ctx = Some(ctx);
+    }
+
+    fn check_deadline(&mut self) -> Result<()> {
+        if let Some(ctx) = self.req_ctx {
+            self.scanned_rows += 1;
+            if self.scanned_rows % DEADLINE_CHECK_ROWS == 0 {
+                ctx.check_if_outdated()?;
+            }
         }
+        Ok(())
     }
 
     pub fn get_row_from_range(&mut self) -> Result<Option<Row>> {
@@ -105,11 +130,7 @@ impl<'a> IndexScanExecutor<'a> {
 
         let (mut values, handle) = { box_try!(table::cut_idx_key(key, &self.col_ids)) };
 
-        let handle = if handle.is_none() {
-            box_try!(value.as_slice().read_i64::<BigEndian>())
-        } else {
-            handle.unwrap()
-        };
+        let handle = box_try!(table::decode_index_handle(handle, &value));
 
         if let Some(ref pk_col) = self.pk_col {
             let handle_datum = if mysql::has_unsigned_flag(pk_col.get_flag() as u64) {
@@ -127,6 +148,7 @@ impl<'a> IndexScanExecutor<'a> {
 
 impl<'a> Executor for IndexScanExecutor<'a> {
     fn next(&mut self) -> Result<Option<Row>> {
+        self.check_deadline()?;
         while self.cursor < self.key_ranges.len() {
             let data = self.get_row_from_range()?;
             if data.is_none() {
@@ -142,8 +164,8 @@ impl<'a> Executor for IndexScanExecutor<'a> {
 }
 
 #[cfg(test)]
-mod test {
-    use std::i64;
+pub mod test {
+    use std::{i64, u64};
 
     use kvproto::kvrpcpb::IsolationLevel;
     use protobuf::RepeatedField;
@@ -349,4 +371,91 @@ mod test {
         }
         assert!(scanner.next().unwrap().is_none());
     }
+
+    #[test]
+    fn test_null_and_empty_index_values() {
+        let cols = vec![new_col_info(2, types::VARCHAR)];
+        let names = vec![
+            (1, Datum::Bytes(b"b".to_vec())),
+            (2, Datum::Null),
+            (3, Datum::Bytes(vec![])),
+            (4, Datum::Bytes(b"a".to_vec())),
+            (5, Datum::Null),
+            (6, Datum::Bytes(vec![0])),
+        ];
+        let kv_data: Vec<_> = names
+            .iter()
+            .map(|&(handle, ref name)| {
+                let encoded = datum::encode_key(&[name.clone(), Datum::I64(handle)]).unwrap();
+                let idx_key = table::encode_index_seek_key(TABLE_ID, INDEX_ID, &encoded);
+                (idx_key, vec![0])
+            })
+            .collect();
+        let mut test_store = TestStore::new(&kv_data);
+        let mut scan = IndexScan::new();
+        scan.set_columns(RepeatedField::from_vec(cols));
+        let ranges = vec![get_idx_range(TABLE_ID, INDEX_ID, i64::MIN, i64::MAX)];
+
+        let (snapshot, start_ts) = test_store.get_snapshot();
+        let store = SnapshotStore::new(snapshot, start_ts, IsolationLevel::SI, true);
+        let mut statistics = Statistics::default();
+        let mut scanner = IndexScanExecutor::new(scan, ranges, store, &mut statistics);
+
+        // NULL sorts before any string, and empty string before any non-empty one.
+        let expect = vec![
+            (2, Datum::Null),
+            (5, Datum::Null),
+            (3, Datum::Bytes(vec![])),
+            (6, Datum::Bytes(vec![0])),
+            (4, Datum::Bytes(b"a".to_vec())),
+            (1, Datum::Bytes(b"b".to_vec())),
+        ];
+        for (handle, name) in expect {
+            let row = scanner.next().unwrap().unwrap();
+            assert_eq!(row.handle, handle);
+            let v = row.data.get(2).unwrap();
+            assert_eq!(v.to_vec(), datum::encode_key(&[name]).unwrap());
+        }
+        assert!(scanner.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_unsigned_handles() {
+        let mut pk_col = new_col_info(1, types::LONG_LONG);
+        pk_col.set_pk_handle(true);
+        pk_col.set_flag(types::UNSIGNED_FLAG as i32);
+        let cols = vec![new_col_info(2, types::VARCHAR), pk_col];
+        let handles = vec![1, 1 << 63, u64::MAX, 5, (1 << 63) + 1];
+        let kv_data: Vec<_> = handles
+            .iter()
+            .map(|&handle| {
+                let values = vec![Datum::Bytes(b"a".to_vec()), Datum::U64(handle)];
+                let encoded = datum::encode_key(&values).unwrap();
+                let idx_key = table::encode_index_seek_key(TABLE_ID, INDEX_ID, &encoded);
+                (idx_key, vec![0])
+            })
+            .collect();
+        let mut test_store = TestStore::new(&kv_data);
+
+        // the handles are ordered as unsigned integers, and recovered as such.
+        let asc = vec![1, 5, 1 << 63, (1 << 63) + 1, u64::MAX];
+        let desc: Vec<_> = asc.iter().rev().cloned().collect();
+        for (is_desc, expect) in vec![(false, asc), (true, desc)] {
+            let mut scan = IndexScan::new();
+            scan.set_columns(RepeatedField::from_vec(cols.clone()));
+            scan.set_desc(is_desc);
+            let ranges = vec![get_idx_range(TABLE_ID, INDEX_ID, i64::MIN, i64::MAX)];
+            let (snapshot, start_ts) = test_store.get_snapshot();
+            let store = SnapshotStore::new(snapshot, start_ts, IsolationLevel::SI, true);
+            let mut statistics = Statistics::default();
+            let mut scanner = IndexScanExecutor::new(scan, ranges, store, &mut statistics);
+            for handle in expect {
+                let row = scanner.next().unwrap().unwrap();
+                assert_eq!(row.handle, handle as i64);
+                let pk = datum::encode_key(&[Datum::U64(handle)]).unwrap();
+                assert_eq!(row.data.get(1).unwrap().to_vec(), pk);
+            }
+            assert!(scanner.next().unwrap().is_none());
+        }
+    }
 }
@@ -54,18 +54,23 @@ impl<'a> Executor for LimitExecutor<'a> {
 
 #[cfg(test)]
 mod test {
+    use std::rc::Rc;
+
     use kvproto::kvrpcpb::IsolationLevel;
     use protobuf::RepeatedField;
-    use tipb::executor::TableScan;
+    use tipb::executor::{TableScan, TopN};
 
     use coprocessor::codec::mysql::types;
     use coprocessor::codec::datum::Datum;
+    use coprocessor::select::xeval::EvalContext;
     use storage::{SnapshotStore, Statistics};
 
     use super::*;
     use super::super::table_scan::TableScanExecutor;
     use super::super::scanner::test::{get_range, new_col_info, TestStore};
-    use super::super::topn::test::gen_table_data;
+    use super::super::test::{new_table_scan, new_table_scan_executor};
+    use super::super::topn::TopNExecutor;
+    use super::super::topn::test::{gen_table_data, new_order_by};
 
     #[test]
     fn test_limit_executor() {
@@ -116,4 +121,43 @@ mod test {
             assert_eq!(row.handle, handle);
         }
     }
+
+    #[test]
+    fn test_zero_limit() {
+        let tid = 1;
+        let cis = vec![
+            new_col_info(1, types::LONG_LONG),
+            new_col_info(2, types::VARCHAR),
+        ];
+        let raw_data: Vec<_> = (0..5)
+            .map(|i| vec![Datum::I64(i), Datum::Bytes(format!("{}", i).into_bytes())])
+            .collect();
+        let table_data = gen_table_data(tid, &cis, &raw_data);
+        let mut test_store = TestStore::new(&table_data);
+        let table_scan = new_table_scan(tid, &cis);
+
+        for with_topn in vec![false, true] {
+            let mut statistics = Statistics::default();
+            {
+                let ts_ect = new_table_scan_executor(&mut test_store, &table_scan, &mut statistics);
+                if with_topn {
+                    let mut topn = TopN::default();
+                    topn.set_order_by(RepeatedField::from_vec(vec![new_order_by(1, false)]));
+                    topn.set_limit(0);
+                    let mut topn_ect = TopNExecutor::new(
+                        topn,
+                        Rc::new(EvalContext::default()),
+                        Rc::new(cis.clone()),
+                        Box::new(ts_ect),
+                    ).unwrap();
+                    assert!(topn_ect.next().unwrap().is_none());
+                } else {
+                    let mut limit_ect = LimitExecutor::new(Limit::default(), Box::new(ts_ect));
+                    assert!(limit_ect.next().unwrap().is_none());
+                }
+            }
+            // the scan never touches the engine.
+            assert_eq!(statistics.total_op_count(), 0);
+        }
+    }
 }
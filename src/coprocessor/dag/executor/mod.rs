@@ -32,13 +32,83 @@ mod selection;
 mod topn;
 mod limit;
 mod aggregation;
+mod debug;
+mod budget;
+mod distinct;
+mod row_fetch;
+mod reverse;
+mod quota;
 
 pub use self::table_scan::TableScanExecutor;
 pub use self::index_scan::IndexScanExecutor;
-pub use self::selection::SelectionExecutor;
+pub use self::selection::{SelectionExecutor, SelectionSummary};
 pub use self::topn::TopNExecutor;
 pub use self::limit::LimitExecutor;
-pub use self::aggregation::AggregationExecutor;
+pub use self::aggregation::{group_by_index_prefix, AggregationExecutor};
+pub use self::debug::{DebugExecutor, StageOutput};
+pub use self::budget::BudgetExecutor;
+pub use self::distinct::DistinctExecutor;
+pub use self::row_fetch::RowFetchExecutor;
+pub use self::reverse::{ReverseExecutor, DEFAULT_REVERSE_MEMORY_BUDGET};
+pub use self::quota::MemoryQuota;
+
+#[cfg(test)]
+pub mod test {
+    use std::i64;
+
+    use kvproto::kvrpcpb::IsolationLevel;
+    use protobuf::RepeatedField;
+    use tipb::executor::TableScan;
+    use tipb::expression::{Expr, ExprType, ScalarFuncSig};
+    use tipb::schema::ColumnInfo;
+
+    use storage::{SnapshotStore, Statistics};
+    use util::codec::number::NumberEncoder;
+
+    use super::TableScanExecutor;
+    pub use super::scanner::test::{get_range, new_col_info, TestStore};
+    pub use super::topn::test::gen_table_data;
+
+    /// Returns the meta of a scan over the columns `cis` of table `tid`.
+    pub fn new_table_scan(tid: i64, cis: &[ColumnInfo]) -> TableScan {
+        let mut table_scan = TableScan::new();
+        table_scan.set_table_id(tid);
+        table_scan.set_columns(RepeatedField::from_vec(cis.to_vec()));
+        table_scan
+    }
+
+    /// Returns an executor scanning all the rows of `table_scan` in `test_store`.
+    pub fn new_table_scan_executor<'a>(
+        test_store: &'a mut TestStore,
+        table_scan: &TableScan,
+        statistics: &'a mut Statistics,
+    ) -> TableScanExecutor<'a> {
+        let key_ranges = vec![get_range(table_scan.get_table_id(), i64::MIN, i64::MAX)];
+        let (snapshot, start_ts) = test_store.get_snapshot();
+        let store = SnapshotStore::new(snapshot, start_ts, IsolationLevel::SI, true);
+        TableScanExecutor::new(table_scan, key_ranges, store, statistics)
+    }
+
+    /// Returns the condition `column[offset] > val`.
+    pub fn new_col_gt_u64_expr(offset: i64, val: u64) -> Expr {
+        let mut expr = Expr::new();
+        expr.set_tp(ExprType::ScalarFunc);
+        expr.set_sig(ScalarFuncSig::GTInt);
+        expr.mut_children().push({
+            let mut lhs = Expr::new();
+            lhs.set_tp(ExprType::ColumnRef);
+            lhs.mut_val().encode_i64(offset).unwrap();
+            lhs
+        });
+        expr.mut_children().push({
+            let mut rhs = Expr::new();
+            rhs.set_tp(ExprType::Uint64);
+            rhs.mut_val().encode_u64(val).unwrap();
+            rhs
+        });
+        expr
+    }
+}
 
 pub struct ExprColumnRefVisitor {
     cols_offset: HashSet<usize>,
@@ -135,6 +205,20 @@ pub fn inflate_with_col_for_dag(
     h: i64,
 ) -> Result<Vec<Datum>> {
     let mut res = vec![Datum::Null; columns.len()];
+    inflate_cols_into(ctx, values, &columns, offsets, h, &mut res)?;
+    Ok(res)
+}
+
+/// `inflate_cols_into` decodes the columns at `offsets` into `res`, leaving the others
+/// untouched, so that a row can be decoded a few columns at a time.
+pub fn inflate_cols_into(
+    ctx: &EvalContext,
+    values: &RowColsDict,
+    columns: &[ColumnInfo],
+    offsets: &[usize],
+    h: i64,
+    res: &mut [Datum],
+) -> Result<()> {
     for offset in offsets {
         let col = columns.get(*offset).unwrap();
         if col.get_pk_handle() {
@@ -156,5 +240,5 @@ pub fn inflate_with_col_for_dag(
             res[*offset] = value;
         }
     }
-    Ok(res)
+    Ok(())
 }
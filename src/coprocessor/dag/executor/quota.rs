@@ -0,0 +1,131 @@
+// Copyright 2017 PingCAP, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::Cell;
+
+use coprocessor::Result;
+
+/// `MemoryQuota` bounds the memory held by the buffers of a request, like the rows kept
+/// by a TopN or the groups of an aggregation. Every executor registers its buffers as
+/// they grow, so the request fails once they take more than the quota in total.
+pub struct MemoryQuota {
+    capacity: usize,
+    used: Cell<usize>,
+}
+
+impl MemoryQuota {
+    pub fn new(capacity: usize) -> MemoryQuota {
+        MemoryQuota {
+            capacity: capacity,
+            used: Cell::new(0),
+        }
+    }
+
+    /// Registers `bytes` more in use, failing if they don't fit in the quota.
+    pub fn alloc(&self, bytes: usize) -> Result<()> {
+        let used = self.used.get() + bytes;
+        if used > self.capacity {
+            return Err(box_err!(
+                "memory quota exceeded: {} bytes in use, more than the quota {}",
+                used,
+                self.capacity
+            ));
+        }
+        self.used.set(used);
+        Ok(())
+    }
+
+    /// Gives `bytes` registered by `alloc` back to the quota.
+    pub fn free(&self, bytes: usize) {
+        self.used.set(self.used.get().saturating_sub(bytes));
+    }
+
+    #[cfg(test)]
+    pub fn used(&self) -> usize {
+        self.used.get()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+
+    use protobuf::RepeatedField;
+    use tipb::executor::TopN;
+
+    use coprocessor::codec::Datum;
+    use coprocessor::codec::mysql::types;
+    use coprocessor::select::xeval::EvalContext;
+    use storage::Statistics;
+
+    use super::*;
+    use super::super::Executor;
+    use super::super::topn::TopNExecutor;
+    use super::super::topn::test::new_order_by;
+    use super::super::test::{gen_table_data, new_col_info, new_table_scan, new_table_scan_executor,
+                             TestStore};
+
+    #[test]
+    fn test_memory_quota() {
+        let quota = MemoryQuota::new(10);
+        quota.alloc(6).unwrap();
+        assert!(quota.alloc(5).is_err());
+        assert_eq!(quota.used(), 6);
+        quota.free(6);
+        quota.alloc(10).unwrap();
+    }
+
+    #[test]
+    fn test_topn_memory_quota() {
+        let tid = 1;
+        let cis = vec![
+            new_col_info(1, types::LONG_LONG),
+            new_col_info(2, types::VARCHAR),
+        ];
+        let raw_data: Vec<_> = (0..100)
+            .map(|i| vec![Datum::I64(i), Datum::Bytes(format!("{}", i).into_bytes())])
+            .collect();
+        let table_data = gen_table_data(tid, &cis, &raw_data);
+        let mut test_store = TestStore::new(&table_data);
+        let table_scan = new_table_scan(tid, &cis);
+
+        let mut run = |limit: u64, quota: Rc<MemoryQuota>| -> Result<usize> {
+            let mut statistics = Statistics::default();
+            let scan = new_table_scan_executor(&mut test_store, &table_scan, &mut statistics);
+            let mut topn = TopN::default();
+            topn.set_order_by(RepeatedField::from_vec(vec![new_order_by(1, true)]));
+            topn.set_limit(limit);
+            let mut topn = TopNExecutor::new(
+                topn,
+                Rc::new(EvalContext::default()),
+                Rc::new(cis.clone()),
+                Box::new(scan),
+            ).unwrap();
+            topn.set_memory_quota(quota);
+            let mut count = 0;
+            while let Some(_) = topn.next()? {
+                count += 1;
+            }
+            Ok(count)
+        };
+
+        // a few rows fit in the quota, and are given back once output.
+        let quota = Rc::new(MemoryQuota::new(4096));
+        assert_eq!(run(5, quota.clone()).unwrap(), 5);
+        assert_eq!(quota.used(), 0);
+
+        // while keeping all of them doesn't.
+        let err = run(100, Rc::new(MemoryQuota::new(4096))).unwrap_err();
+        assert!(format!("{}", err).contains("memory quota"), "{:?}", err);
+    }
+}
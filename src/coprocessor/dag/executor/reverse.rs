@@ -0,0 +1,131 @@
+// Copyright 2017 PingCAP, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::mem;
+use std::rc::Rc;
+
+use coprocessor::metrics::*;
+use coprocessor::Result;
+
+use super::{Executor, MemoryQuota, Row};
+
+/// `DEFAULT_REVERSE_MEMORY_BUDGET` bounds the rows buffered by a `ReverseExecutor` of a
+/// request without a memory quota.
+pub const DEFAULT_REVERSE_MEMORY_BUDGET: usize = 64 * 1024 * 1024;
+
+/// `ReverseExecutor` outputs the rows of its source in the reverse order, without
+/// sorting them. All the rows are buffered before the first one is output, and they
+/// are registered to the memory quota until they are all output.
+pub struct ReverseExecutor<'a> {
+    rows: Option<Vec<Row>>,
+    quota: Rc<MemoryQuota>,
+    held: usize,
+    src: Box<Executor + 'a>,
+}
+
+impl<'a> ReverseExecutor<'a> {
+    pub fn new(quota: Rc<MemoryQuota>, src: Box<Executor + 'a>) -> ReverseExecutor<'a> {
+        COPR_EXECUTOR_COUNT.with_label_values(&["reverse"]).inc();
+        ReverseExecutor {
+            rows: None,
+            quota: quota,
+            held: 0,
+            src: src,
+        }
+    }
+
+    fn fetch_all(&mut self) -> Result<Vec<Row>> {
+        let mut rows = vec![];
+        while let Some(row) = self.src.next()? {
+            let size = mem::size_of::<Row>() + row.data.value.len();
+            self.quota.alloc(size)?;
+            self.held += size;
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+}
+
+impl<'a> Executor for ReverseExecutor<'a> {
+    fn next(&mut self) -> Result<Option<Row>> {
+        if self.rows.is_none() {
+            let rows = match self.fetch_all() {
+                Ok(rows) => rows,
+                Err(e) => {
+                    // the rows read so far are dropped with the request.
+                    self.quota.free(self.held);
+                    self.held = 0;
+                    return Err(e);
+                }
+            };
+            self.rows = Some(rows);
+        }
+        match self.rows.as_mut().unwrap().pop() {
+            Some(row) => Ok(Some(row)),
+            None => {
+                self.quota.free(self.held);
+                self.held = 0;
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use coprocessor::codec::Datum;
+    use coprocessor::codec::mysql::types;
+    use storage::Statistics;
+
+    use super::*;
+    use super::super::test::{gen_table_data, new_col_info, new_table_scan, new_table_scan_executor,
+                             TestStore};
+
+    #[test]
+    fn test_reverse_executor() {
+        let tid = 1;
+        let cis = vec![
+            new_col_info(1, types::LONG_LONG),
+            new_col_info(2, types::VARCHAR),
+        ];
+        let raw_data: Vec<_> = (0..10)
+            .map(|i| vec![Datum::I64(i), Datum::Bytes(format!("{}", i).into_bytes())])
+            .collect();
+        let table_data = gen_table_data(tid, &cis, &raw_data);
+        let mut test_store = TestStore::new(&table_data);
+        let table_scan = new_table_scan(tid, &cis);
+
+        let mut run = |quota: Rc<MemoryQuota>| -> Result<Vec<i64>> {
+            let mut statistics = Statistics::default();
+            let scan = new_table_scan_executor(&mut test_store, &table_scan, &mut statistics);
+            let mut reverse = ReverseExecutor::new(quota, Box::new(scan));
+            let mut handles = vec![];
+            while let Some(row) = reverse.next()? {
+                handles.push(row.handle);
+            }
+            Ok(handles)
+        };
+
+        // the rows come in the reverse of the scan order, and are given back once output.
+        let quota = Rc::new(MemoryQuota::new(usize::max_value()));
+        let handles = run(quota.clone()).unwrap();
+        assert_eq!(handles, (0..10).rev().collect::<Vec<_>>());
+        assert_eq!(quota.used(), 0);
+
+        // while the rows can't be buffered beyond the quota.
+        let quota = Rc::new(MemoryQuota::new(mem::size_of::<Row>() * 5));
+        let err = run(quota.clone()).unwrap_err();
+        assert!(format!("{}", err).contains("memory quota"), "{:?}", err);
+        assert_eq!(quota.used(), 0);
+    }
+}
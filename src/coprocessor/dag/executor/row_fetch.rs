@@ -0,0 +1,159 @@
+// Copyright 2017 PingCAP, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use tipb::schema::ColumnInfo;
+
+use coprocessor::Result;
+use coprocessor::metrics::*;
+use util::collections::HashSet;
+
+use super::{Executor, Row, TableScanExecutor};
+
+/// A table scan shared with a `RowFetchExecutor` is scanned by the executor it is the
+/// source of, then used by the `RowFetchExecutor` to get full rows.
+impl<'a> Executor for Rc<RefCell<TableScanExecutor<'a>>> {
+    fn next(&mut self) -> Result<Option<Row>> {
+        self.borrow_mut().next()
+    }
+}
+
+/// `RowFetchExecutor` gets the full rows of the handles output by its source, whose
+/// table scan only reads the columns needed to pick them. A TopN over such a scan
+/// then only materializes the rows it keeps.
+pub struct RowFetchExecutor<'a> {
+    scan: Rc<RefCell<TableScanExecutor<'a>>>,
+    col_ids: HashSet<i64>,
+    src: Box<Executor + 'a>,
+}
+
+impl<'a> RowFetchExecutor<'a> {
+    pub fn new(
+        scan: Rc<RefCell<TableScanExecutor<'a>>>,
+        columns: &[ColumnInfo],
+        src: Box<Executor + 'a>,
+    ) -> RowFetchExecutor<'a> {
+        let col_ids = columns
+            .iter()
+            .filter(|c| !c.get_pk_handle())
+            .map(|c| c.get_column_id())
+            .collect();
+        COPR_EXECUTOR_COUNT.with_label_values(&["row_fetch"]).inc();
+        RowFetchExecutor {
+            scan: scan,
+            col_ids: col_ids,
+            src: src,
+        }
+    }
+}
+
+impl<'a> Executor for RowFetchExecutor<'a> {
+    fn next(&mut self) -> Result<Option<Row>> {
+        let handle = match self.src.next()? {
+            Some(row) => row.handle,
+            None => return Ok(None),
+        };
+        match self.scan.borrow_mut().get_row_by_handle(handle, &self.col_ids)? {
+            Some(row) => Ok(Some(row)),
+            None => Err(box_err!("row {} is missing", handle)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use protobuf::RepeatedField;
+    use tipb::executor::TopN;
+
+    use coprocessor::codec::Datum;
+    use coprocessor::codec::mysql::types;
+    use coprocessor::select::xeval::EvalContext;
+    use storage::Statistics;
+
+    use super::*;
+    use super::super::topn::TopNExecutor;
+    use super::super::topn::test::new_order_by;
+    use super::super::test::{gen_table_data, new_col_info, new_table_scan, new_table_scan_executor,
+                             TestStore};
+
+    #[test]
+    fn test_row_fetch_executor() {
+        let tid = 1;
+        let cis = vec![
+            new_col_info(1, types::LONG_LONG),
+            new_col_info(2, types::VARCHAR),
+            new_col_info(3, types::LONG_LONG),
+        ];
+        let raw_data: Vec<_> = (0..50)
+            .map(|i| {
+                vec![
+                    Datum::I64(i),
+                    Datum::Bytes(format!("name:{}", i).into_bytes()),
+                    Datum::I64((i * 7) % 50),
+                ]
+            })
+            .collect();
+        let table_data = gen_table_data(tid, &cis, &raw_data);
+        let mut test_store = TestStore::new(&table_data);
+        let limit = 5;
+        let new_topn = || {
+            let mut topn = TopN::default();
+            topn.set_order_by(RepeatedField::from_vec(vec![new_order_by(2, true)]));
+            topn.set_limit(limit);
+            topn
+        };
+
+        // the naive TopN over full rows.
+        let mut table_scan = new_table_scan(tid, &cis);
+        let mut statistics = Statistics::default();
+        let mut expect = vec![];
+        {
+            let scan = new_table_scan_executor(&mut test_store, &table_scan, &mut statistics);
+            let cols = Rc::new(cis.clone());
+            let ctx = Rc::new(EvalContext::default());
+            let mut topn = TopNExecutor::new(new_topn(), ctx, cols, Box::new(scan)).unwrap();
+            while let Some(row) = topn.next().unwrap() {
+                let values: Vec<_> = cis.iter()
+                    .map(|c| row.data.get(c.get_column_id()).unwrap().to_vec())
+                    .collect();
+                expect.push((row.handle, values));
+            }
+        }
+        assert_eq!(expect.len(), limit as usize);
+        assert_eq!(statistics.lock.get, 0);
+
+        // the TopN only scanning the column to sort by.
+        table_scan.set_columns(RepeatedField::from_vec(vec![cis[2].clone()]));
+        let mut statistics = Statistics::default();
+        let mut got = vec![];
+        {
+            let scan = new_table_scan_executor(&mut test_store, &table_scan, &mut statistics);
+            let scan = Rc::new(RefCell::new(scan));
+            let cols = Rc::new(cis.clone());
+            let ctx = Rc::new(EvalContext::default());
+            let topn = TopNExecutor::new(new_topn(), ctx, cols, Box::new(scan.clone())).unwrap();
+            let mut fetch = RowFetchExecutor::new(scan, &cis, Box::new(topn));
+            while let Some(row) = fetch.next().unwrap() {
+                let values: Vec<_> = cis.iter()
+                    .map(|c| row.data.get(c.get_column_id()).unwrap().to_vec())
+                    .collect();
+                got.push((row.handle, values));
+            }
+        }
+        assert_eq!(got, expect);
+        // every full row is read by a point get, only for the rows kept.
+        assert_eq!(statistics.lock.get, limit as usize);
+    }
+}
@@ -25,6 +25,9 @@ pub struct Scanner<'a> {
     statistics: Option<&'a mut Statistics>,
     seek_key: Option<Vec<u8>>,
     scanner: Option<StoreScanner<'a>>,
+    scan_bytes_limit: Option<u64>,
+    scanned_bytes: u64,
+    last_commit_ts: u64,
 }
 
 impl<'a> Scanner<'a> {
@@ -46,6 +49,26 @@ impl<'a> Scanner<'a> {
             statistics: Some(statistics),
             seek_key: None,
             scanner: None,
+            scan_bytes_limit: None,
+            scanned_bytes: 0,
+            last_commit_ts: 0,
+        }
+    }
+
+    /// Reads fail once the keys and values read in total are more than `limit` bytes.
+    pub fn set_scan_bytes_limit(&mut self, limit: u64) {
+        self.scan_bytes_limit = Some(limit);
+    }
+
+    fn add_scanned_bytes(&mut self, bytes: usize) -> Result<()> {
+        self.scanned_bytes += bytes as u64;
+        match self.scan_bytes_limit {
+            Some(limit) if self.scanned_bytes > limit => Err(box_err!(
+                "scanned {} bytes, more than the limit {}",
+                self.scanned_bytes,
+                limit
+            )),
+            _ => Ok(()),
         }
     }
 
@@ -57,11 +80,15 @@ impl<'a> Scanner<'a> {
         if range.get_start() > range.get_end() {
             return Ok(None);
         }
-        let scanner = self.scanner.as_mut().unwrap();
-        let kv = if self.scan_mode == ScanMode::Backward {
-            scanner.reverse_seek(Key::from_raw(&seek_key))?
-        } else {
-            scanner.seek(Key::from_raw(&seek_key))?
+        let kv = {
+            let scanner = self.scanner.as_mut().unwrap();
+            let kv = if self.scan_mode == ScanMode::Backward {
+                scanner.reverse_seek(Key::from_raw(&seek_key))?
+            } else {
+                scanner.seek(Key::from_raw(&seek_key))?
+            };
+            self.last_commit_ts = scanner.last_commit_ts();
+            kv
         };
 
         let (key, value) = match kv {
@@ -78,14 +105,32 @@ impl<'a> Scanner<'a> {
             );
             return Ok(None);
         }
+        self.add_scanned_bytes(key.len() + value.len())?;
         Ok(Some((key, value)))
     }
 
     pub fn get_row(&mut self, key: &[u8]) -> Result<Option<Value>> {
         let statistics = self.take_statistics();
-        let data = self.store.get(&Key::from_raw(key), statistics)?;
+        let data = self.store.get_with_commit_ts(&Key::from_raw(key), statistics)?;
         self.statistics = Some(statistics);
-        Ok(data)
+        match data {
+            Some((commit_ts, value)) => {
+                self.last_commit_ts = commit_ts;
+                self.add_scanned_bytes(key.len() + value.len())?;
+                Ok(Some(value))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the commit ts of the row last read by `next_row` or `get_row`.
+    pub fn last_commit_ts(&self) -> u64 {
+        self.last_commit_ts
+    }
+
+    /// Scans started after this call return keys with empty values.
+    pub fn set_key_only(&mut self, key_only: bool) {
+        self.key_only = key_only;
     }
 
     #[inline]
@@ -299,6 +344,15 @@ pub mod test {
         key_range
     }
 
+    // `get_full_range` returns a range containing every handle of the table,
+    // including `i64::MAX`, which `get_range` excludes as its end is exclusive.
+    pub fn get_full_range(table_id: i64) -> KeyRange {
+        let mut key_range = get_range(table_id, i64::MIN, i64::MAX);
+        let end = prefix_next(key_range.get_end());
+        key_range.set_end(end);
+        key_range
+    }
+
     pub fn get_point_range(table_id: i64, handle: i64) -> KeyRange {
         let mut start_buf = Vec::with_capacity(8);
         start_buf.encode_i64(handle).unwrap();
@@ -11,7 +11,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cell::Cell;
 use std::rc::Rc;
+use std::usize;
 
 use tipb::executor::Selection;
 use tipb::schema::ColumnInfo;
@@ -21,13 +23,31 @@ use coprocessor::select::xeval::EvalContext;
 use coprocessor::dag::expr::Expression;
 use coprocessor::Result;
 
-use super::{inflate_with_col_for_dag, Executor, ExprColumnRefVisitor, Row};
+use coprocessor::codec::Datum;
+use coprocessor::codec::datum::DatumDecoder;
+use util::collections::HashSet;
+
+use super::{inflate_cols_into, Executor, ExprColumnRefVisitor, Row};
+
+/// `SelectionSummary` counts the rows read by a selection and the ones that passed
+/// its conditions, which tells how selective they are.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SelectionSummary {
+    pub input_rows: usize,
+    pub passed_rows: usize,
+}
 
 pub struct SelectionExecutor<'a> {
     conditions: Vec<Expression>,
     cols: Rc<Vec<ColumnInfo>>,
-    related_cols_offset: Vec<usize>, // offset of related columns
+    // offsets of the columns every condition refers to first, which are only decoded
+    // once the conditions before it hold.
+    conditions_cols_offset: Vec<Vec<usize>>,
+    // the columns the conditions refer to at least, if they filter the rows output by an
+    // aggregation, whose values are decoded as a whole.
+    aggregated_cols: Option<usize>,
     ctx: Rc<EvalContext>,
+    summary: Rc<Cell<SelectionSummary>>,
     src: Box<Executor + 'a>,
 }
 
@@ -39,36 +59,98 @@ impl<'a> SelectionExecutor<'a> {
         src: Box<Executor + 'a>,
     ) -> Result<SelectionExecutor<'a>> {
         let conditions = meta.take_conditions().into_vec();
-        let mut visitor = ExprColumnRefVisitor::new(columns_info.len());
-        visitor.batch_visit(&conditions)?;
+        let mut decoded = HashSet::default();
+        let mut conditions_cols_offset = Vec::with_capacity(conditions.len());
+        for cond in &conditions {
+            let mut visitor = ExprColumnRefVisitor::new(columns_info.len());
+            visitor.visit(cond)?;
+            let mut offsets: Vec<_> = visitor
+                .column_offsets()
+                .into_iter()
+                .filter(|offset| decoded.insert(*offset))
+                .collect();
+            offsets.sort();
+            conditions_cols_offset.push(offsets);
+        }
         COPR_EXECUTOR_COUNT.with_label_values(&["selection"]).inc();
         Ok(SelectionExecutor {
             conditions: box_try!(Expression::batch_build(ctx.as_ref(), conditions)),
             cols: columns_info,
-            related_cols_offset: visitor.column_offsets(),
+            conditions_cols_offset: conditions_cols_offset,
+            aggregated_cols: None,
+            ctx: ctx,
+            summary: Rc::new(Cell::new(SelectionSummary::default())),
+            src: src,
+        })
+    }
+
+    /// `new_over_aggregation` builds a selection filtering the groups output by an
+    /// aggregation, like `HAVING` does. Its conditions refer to the columns of the
+    /// aggregation output: the aggregation results first, then the group by values.
+    pub fn new_over_aggregation(
+        mut meta: Selection,
+        ctx: Rc<EvalContext>,
+        src: Box<Executor + 'a>,
+    ) -> Result<SelectionExecutor<'a>> {
+        let conditions = meta.take_conditions().into_vec();
+        let mut visitor = ExprColumnRefVisitor::new(usize::MAX);
+        visitor.batch_visit(&conditions)?;
+        let cols_len = visitor.column_offsets().into_iter().max().map_or(0, |o| o + 1);
+        COPR_EXECUTOR_COUNT.with_label_values(&["selection"]).inc();
+        Ok(SelectionExecutor {
+            conditions_cols_offset: vec![vec![]; conditions.len()],
+            conditions: box_try!(Expression::batch_build(ctx.as_ref(), conditions)),
+            cols: Rc::new(vec![]),
+            aggregated_cols: Some(cols_len),
             ctx: ctx,
+            summary: Rc::new(Cell::new(SelectionSummary::default())),
             src: src,
         })
     }
+
+    pub fn summary(&self) -> Rc<Cell<SelectionSummary>> {
+        self.summary.clone()
+    }
 }
 
 #[allow(never_loop)]
 impl<'a> Executor for SelectionExecutor<'a> {
     fn next(&mut self) -> Result<Option<Row>> {
         'next: while let Some(row) = self.src.next()? {
-            let cols = inflate_with_col_for_dag(
-                &self.ctx,
-                &row.data,
-                self.cols.clone(),
-                &self.related_cols_offset,
-                row.handle,
-            )?;
-            for filter in &self.conditions {
+            let mut summary = self.summary.get();
+            summary.input_rows += 1;
+            self.summary.set(summary);
+            let mut cols = match self.aggregated_cols {
+                Some(cols_len) => {
+                    let cols = box_try!(row.data.value.as_slice().decode());
+                    if cols.len() < cols_len {
+                        return Err(box_err!(
+                            "aggregation outputs {} columns, but {} are needed",
+                            cols.len(),
+                            cols_len
+                        ));
+                    }
+                    cols
+                }
+                None => vec![Datum::Null; self.cols.len()],
+            };
+            for (filter, offsets) in self.conditions.iter().zip(&self.conditions_cols_offset) {
+                inflate_cols_into(
+                    &self.ctx,
+                    &row.data,
+                    &self.cols,
+                    offsets,
+                    row.handle,
+                    &mut cols,
+                )?;
                 let val = box_try!(filter.eval(&self.ctx, &cols));
                 if !box_try!(val.into_bool(&self.ctx)).unwrap_or(false) {
                     continue 'next;
                 }
             }
+            let mut summary = self.summary.get();
+            summary.passed_rows += 1;
+            self.summary.set(summary);
             return Ok(Some(row));
         }
         Ok(None)
@@ -93,6 +175,7 @@ mod tests {
     use super::super::topn::test::gen_table_data;
     use super::super::scanner::test::{get_range, new_col_info, TestStore};
     use super::super::table_scan::TableScanExecutor;
+    use super::super::test::{new_col_gt_u64_expr, new_table_scan, new_table_scan_executor};
 
     fn new_const_expr() -> Expr {
         let mut expr = Expr::new();
@@ -111,25 +194,6 @@ mod tests {
         expr
     }
 
-    fn new_col_gt_u64_expr(offset: i64, val: u64) -> Expr {
-        let mut expr = Expr::new();
-        expr.set_tp(ExprType::ScalarFunc);
-        expr.set_sig(ScalarFuncSig::GTInt);
-        expr.mut_children().push({
-            let mut lhs = Expr::new();
-            lhs.set_tp(ExprType::ColumnRef);
-            lhs.mut_val().encode_i64(offset).unwrap();
-            lhs
-        });
-        expr.mut_children().push({
-            let mut rhs = Expr::new();
-            rhs.set_tp(ExprType::Uint64);
-            rhs.mut_val().encode_u64(val).unwrap();
-            rhs
-        });
-        expr
-    }
-
     #[test]
     fn test_selection_executor_simple() {
         let tid = 1;
@@ -277,4 +341,207 @@ mod tests {
         let result_row = selection_rows.iter().map(|r| r.handle).collect::<Vec<_>>();
         assert_eq!(result_row, expect_row_handles);
     }
+
+    #[test]
+    fn test_selection_executor_xor() {
+        let tid = 1;
+        let cis = vec![
+            new_col_info(1, types::LONG_LONG),
+            new_col_info(2, types::LONG_LONG),
+        ];
+        // (id, count), the counts of rows 4 and 7 are NULL.
+        let raw_data = vec![
+            vec![Datum::I64(1), Datum::I64(1)],
+            vec![Datum::I64(2), Datum::I64(3)],
+            vec![Datum::I64(3), Datum::I64(2)],
+            vec![Datum::I64(4), Datum::Null],
+            vec![Datum::I64(5), Datum::I64(1)],
+            vec![Datum::I64(6), Datum::I64(4)],
+            vec![Datum::I64(7), Datum::Null],
+        ];
+
+        let table_data = gen_table_data(tid, &cis, &raw_data);
+        let mut test_store = TestStore::new(&table_data);
+
+        let table_scan = new_table_scan(tid, &cis);
+        let mut statistics = Statistics::default();
+        let inner_table_scan =
+            new_table_scan_executor(&mut test_store, &table_scan, &mut statistics);
+
+        // (count > 2) XOR (id > 4)
+        let mut expr = Expr::new();
+        expr.set_tp(ExprType::ScalarFunc);
+        expr.set_sig(ScalarFuncSig::LogicalXor);
+        expr.mut_children().push(new_col_gt_u64_expr(1, 2));
+        expr.mut_children().push(new_col_gt_u64_expr(0, 4));
+        let mut selection = Selection::new();
+        selection.mut_conditions().push(expr);
+
+        let mut selection_executor = SelectionExecutor::new(
+            selection,
+            Rc::new(EvalContext::default()),
+            Rc::new(cis),
+            Box::new(inner_table_scan),
+        ).unwrap();
+
+        let mut handles = vec![];
+        while let Some(row) = selection_executor.next().unwrap() {
+            handles.push(row.handle);
+        }
+        // rows with a NULL count are filtered out as the XOR is NULL.
+        assert_eq!(handles, vec![2, 5]);
+    }
+
+    #[test]
+    fn test_selection_executor_col_eq_col() {
+        let tid = 1;
+        let cis = vec![
+            new_col_info(1, types::LONG_LONG),
+            new_col_info(2, types::LONG_LONG),
+        ];
+        // (id, count), count of row 4 is NULL.
+        let raw_data = vec![
+            vec![Datum::I64(1), Datum::I64(1)],
+            vec![Datum::I64(2), Datum::I64(3)],
+            vec![Datum::I64(3), Datum::I64(3)],
+            vec![Datum::I64(4), Datum::Null],
+            vec![Datum::I64(5), Datum::I64(4)],
+            vec![Datum::I64(6), Datum::I64(6)],
+        ];
+
+        let table_data = gen_table_data(tid, &cis, &raw_data);
+        let mut test_store = TestStore::new(&table_data);
+
+        let table_scan = new_table_scan(tid, &cis);
+        let mut statistics = Statistics::default();
+        let inner_table_scan =
+            new_table_scan_executor(&mut test_store, &table_scan, &mut statistics);
+
+        // id = count
+        let mut expr = Expr::new();
+        expr.set_tp(ExprType::ScalarFunc);
+        expr.set_sig(ScalarFuncSig::EQInt);
+        for offset in 0..2 {
+            let mut col = Expr::new();
+            col.set_tp(ExprType::ColumnRef);
+            col.mut_val().encode_i64(offset).unwrap();
+            expr.mut_children().push(col);
+        }
+        let mut selection = Selection::new();
+        selection.mut_conditions().push(expr);
+
+        let mut selection_executor = SelectionExecutor::new(
+            selection,
+            Rc::new(EvalContext::default()),
+            Rc::new(cis),
+            Box::new(inner_table_scan),
+        ).unwrap();
+
+        let mut handles = vec![];
+        while let Some(row) = selection_executor.next().unwrap() {
+            handles.push(row.handle);
+        }
+        assert_eq!(handles, vec![1, 3, 6]);
+    }
+
+    fn new_col_is_null_expr(offset: i64) -> Expr {
+        let mut expr = Expr::new();
+        expr.set_tp(ExprType::ScalarFunc);
+        expr.set_sig(ScalarFuncSig::IntIsNull);
+        expr.mut_children().push({
+            let mut col = Expr::new();
+            col.set_tp(ExprType::ColumnRef);
+            col.mut_val().encode_i64(offset).unwrap();
+            col
+        });
+        expr
+    }
+
+    #[test]
+    fn test_selection_executor_skips_decoding() {
+        let tid = 1;
+        // enum values can't be decoded yet, so decoding any but NULL fails.
+        let cis = vec![
+            new_col_info(1, types::LONG_LONG),
+            new_col_info(2, types::ENUM),
+        ];
+        let raw_data: Vec<_> = (0..10)
+            .map(|i| {
+                let e = if i < 5 { Datum::I64(1) } else { Datum::Null };
+                vec![Datum::I64(i), e]
+            })
+            .collect();
+        let table_data = gen_table_data(tid, &cis, &raw_data);
+        let mut test_store = TestStore::new(&table_data);
+        let table_scan = new_table_scan(tid, &cis);
+
+        let cases = vec![
+            // the enum column is only decoded for the rows with id > 4.
+            (vec![new_col_gt_u64_expr(0, 4), new_col_is_null_expr(1)], true),
+            (vec![new_col_is_null_expr(1), new_col_gt_u64_expr(0, 4)], false),
+        ];
+        for (conditions, ok) in cases {
+            let mut statistics = Statistics::default();
+            let inner_table_scan =
+                new_table_scan_executor(&mut test_store, &table_scan, &mut statistics);
+            let mut selection = Selection::new();
+            selection.set_conditions(RepeatedField::from_vec(conditions));
+            let mut selection_executor = SelectionExecutor::new(
+                selection,
+                Rc::new(EvalContext::default()),
+                Rc::new(cis.clone()),
+                Box::new(inner_table_scan),
+            ).unwrap();
+            if !ok {
+                assert!(selection_executor.next().is_err());
+                continue;
+            }
+            let mut handles = vec![];
+            while let Some(row) = selection_executor.next().unwrap() {
+                handles.push(row.handle);
+            }
+            assert_eq!(handles, vec![5, 6, 7, 8, 9]);
+        }
+    }
+
+    #[test]
+    fn test_selection_summary() {
+        let tid = 1;
+        let cis = vec![new_col_info(1, types::LONG_LONG)];
+        let raw_data: Vec<_> = (0..100).map(|i| vec![Datum::I64(i)]).collect();
+        let table_data = gen_table_data(tid, &cis, &raw_data);
+        let mut test_store = TestStore::new(&table_data);
+
+        let table_scan = new_table_scan(tid, &cis);
+        let mut statistics = Statistics::default();
+        let inner_table_scan =
+            new_table_scan_executor(&mut test_store, &table_scan, &mut statistics);
+
+        // id > 95 filters out most of the rows.
+        let mut selection = Selection::new();
+        selection.mut_conditions().push(new_col_gt_u64_expr(0, 95));
+        let mut selection_executor = SelectionExecutor::new(
+            selection,
+            Rc::new(EvalContext::default()),
+            Rc::new(cis),
+            Box::new(inner_table_scan),
+        ).unwrap();
+        let summary = selection_executor.summary();
+        assert_eq!(selection_executor.next().unwrap().unwrap().handle, 96);
+        assert_eq!(
+            summary.get(),
+            SelectionSummary {
+                input_rows: 97,
+                passed_rows: 1,
+            }
+        );
+        while let Some(_) = selection_executor.next().unwrap() {}
+        assert_eq!(
+            summary.get(),
+            SelectionSummary {
+                input_rows: 100,
+                passed_rows: 4,
+            }
+        );
+    }
 }
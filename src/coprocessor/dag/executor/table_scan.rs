@@ -14,10 +14,12 @@
 use kvproto::coprocessor::KeyRange;
 use tipb::executor::TableScan;
 
-use util::collections::HashSet;
+use util::codec::number::{self, NumberEncoder};
+use util::collections::{HashMap, HashSet};
 use storage::{SnapshotStore, Statistics};
-use coprocessor::codec::table;
-use coprocessor::endpoint::{is_point, prefix_next};
+use coprocessor::codec::datum::{self, Datum};
+use coprocessor::codec::table::{self, RowColsDict};
+use coprocessor::endpoint::{is_point, prefix_next, ReqContext, DEADLINE_CHECK_ROWS};
 use coprocessor::Result;
 use coprocessor::metrics::*;
 
@@ -26,11 +28,16 @@ use super::scanner::Scanner;
 
 
 pub struct TableScanExecutor<'a> {
+    table_id: i64,
     desc: bool,
     col_ids: HashSet<i64>,
     cursor: usize,
     key_ranges: Vec<KeyRange>,
     scanner: Scanner<'a>,
+    handles_only: bool,
+    commit_ts: bool,
+    req_ctx: Option<&'a ReqContext>,
+    scanned_rows: usize,
 }
 
 impl<'a> TableScanExecutor<'a> {
@@ -45,6 +52,9 @@ impl<'a> TableScanExecutor<'a> {
             .filter(|c| !c.get_pk_handle())
             .map(|c| c.get_column_id())
             .collect();
+        let commit_ts = meta.get_columns()
+            .iter()
+            .any(|c| c.get_column_id() == table::COMMIT_TS_COL_ID);
         let desc = meta.get_desc();
         if desc {
             key_ranges.reverse();
@@ -52,12 +62,75 @@ impl<'a> TableScanExecutor<'a> {
         let scanner = Scanner::new(store, desc, false, statistics);
         COPR_EXECUTOR_COUNT.with_label_values(&["tblscan"]).inc();
         TableScanExecutor {
+            table_id: meta.get_table_id(),
             desc: desc,
             col_ids: col_ids,
             scanner: scanner,
             key_ranges: key_ranges,
             cursor: Default::default(),
+            handles_only: false,
+            commit_ts: commit_ts,
+            req_ctx: None,
+            scanned_rows: 0,
+        }
+    }
+
+    /// Rows will carry their handles only, row values are neither read nor decoded.
+    pub fn set_handles_only(&mut self) {
+        self.handles_only = true;
+        self.scanner.set_key_only(true);
+    }
+
+    fn decode_row(&self, value: Vec<u8>) -> Result<RowColsDict> {
+        if self.handles_only {
+            return Ok(RowColsDict::new(HashMap::default(), vec![]));
+        }
+        let mut row = box_try!(table::cut_row(value, &self.col_ids));
+        if self.commit_ts {
+            let commit_ts = Datum::U64(self.scanner.last_commit_ts());
+            let mut value = box_try!(datum::encode_value(&[commit_ts]));
+            row.append(table::COMMIT_TS_COL_ID, &mut value);
+        }
+        Ok(row)
+    }
+
+    /// Fails the scan once the keys and values read are more than `limit` bytes.
+    pub fn set_scan_bytes_limit(&mut self, limit: u64) {
+        self.scanner.set_scan_bytes_limit(limit);
+    }
+
+    /// Fails the scan once the deadline of `ctx` passes, which is checked every
+    /// `DEADLINE_CHECK_ROWS` rows.
+    pub fn set_req_ctx(&mut self, ctx: &'a ReqContext) {
+        self.req_ctx = Some(ctx);
+    }
+
+    fn check_deadline(&mut self) -> Result<()> {
+        if let Some(ctx) = self.req_ctx {
+            self.scanned_rows += 1;
+            if self.scanned_rows % DEADLINE_CHECK_ROWS == 0 {
+                ctx.check_if_outdated()?;
+            }
         }
+        Ok(())
+    }
+
+    /// Gets the row of `handle` with the columns of `col_ids`, which may not be the
+    /// scanned ones.
+    pub fn get_row_by_handle(
+        &mut self,
+        handle: i64,
+        col_ids: &HashSet<i64>,
+    ) -> Result<Option<Row>> {
+        let mut encoded_handle = Vec::with_capacity(number::I64_SIZE);
+        box_try!(encoded_handle.encode_i64(handle));
+        let key = table::encode_row_key(self.table_id, &encoded_handle);
+        let value = match self.scanner.get_row(&key)? {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        let row_data = box_try!(table::cut_row(value, col_ids));
+        Ok(Some(Row::new(handle, row_data)))
     }
 
     fn get_row_from_range(&mut self) -> Result<Option<Row>> {
@@ -68,7 +141,7 @@ impl<'a> TableScanExecutor<'a> {
             None => return Ok(None),
         };
         let h = box_try!(table::decode_handle(&key));
-        let row_data = box_try!(table::cut_row(value, &self.col_ids));
+        let row_data = self.decode_row(value)?;
         let seek_key = if self.desc {
             box_try!(table::truncate_as_row_key(&key)).to_vec()
         } else {
@@ -82,7 +155,7 @@ impl<'a> TableScanExecutor<'a> {
         let key = self.key_ranges[self.cursor].get_start();
         let value = self.scanner.get_row(key)?;
         if let Some(value) = value {
-            let values = box_try!(table::cut_row(value, &self.col_ids));
+            let values = self.decode_row(value)?;
             let h = box_try!(table::decode_handle(key));
             return Ok(Some(Row::new(h, values)));
         }
@@ -92,6 +165,7 @@ impl<'a> TableScanExecutor<'a> {
 
 impl<'a> Executor for TableScanExecutor<'a> {
     fn next(&mut self) -> Result<Option<Row>> {
+        self.check_deadline()?;
         while self.cursor < self.key_ranges.len() {
             if is_point(&self.key_ranges[self.cursor]) {
                 CORP_GET_OR_SCAN_COUNT.with_label_values(&["point"]).inc();
@@ -123,13 +197,25 @@ mod test {
 
     use kvproto::kvrpcpb::IsolationLevel;
     use protobuf::RepeatedField;
+    use tipb::executor::Limit;
     use tipb::schema::ColumnInfo;
 
     use storage::{SnapshotStore, Statistics};
 
     use super::*;
-    use super::super::scanner::test::{get_point_range, get_range, prepare_table_data, Data,
-                                      TestStore};
+    use coprocessor::codec::datum::{self, Datum};
+    use coprocessor::codec::mysql::types;
+    use coprocessor::select::xeval::EvalContext;
+    use coprocessor::Error;
+    use util::codec::number::NumberEncoder;
+    use util::time::Instant;
+
+    use super::super::scanner::test::{get_full_range, get_point_range, get_range, new_col_info,
+                                      prepare_table_data, Data, TestStore};
+    use super::super::topn::test::gen_table_data;
+    use super::super::test::{new_table_scan, new_table_scan_executor};
+    use super::super::inflate_with_col_for_dag;
+    use super::super::limit::LimitExecutor;
 
     const TABLE_ID: i64 = 1;
     const KEY_NUMBER: usize = 10;
@@ -269,4 +355,197 @@ mod test {
         }
         assert!(table_scanner.next().unwrap().is_none());
     }
+
+    #[test]
+    fn test_scan_edge_handles() {
+        let cis = vec![
+            new_col_info(1, types::LONG_LONG),
+            new_col_info(2, types::LONG_LONG),
+        ];
+        let handles = vec![i64::MIN, i64::MIN + 1, -1, 0, i64::MAX - 1, i64::MAX];
+        let raw_data: Vec<_> = handles
+            .iter()
+            .map(|&h| vec![Datum::I64(h), Datum::I64(1)])
+            .collect();
+        let mut test_store = TestStore::new(&gen_table_data(TABLE_ID, &cis, &raw_data));
+        let mut table_scan = TableScan::new();
+        table_scan.set_columns(RepeatedField::from_vec(cis));
+
+        let scan = |store: &mut TestStore, ranges: Vec<KeyRange>, desc: bool| {
+            let mut table_scan = table_scan.clone();
+            table_scan.set_desc(desc);
+            let (snapshot, start_ts) = store.get_snapshot();
+            let store = SnapshotStore::new(snapshot, start_ts, IsolationLevel::SI, true);
+            let mut statistics = Statistics::default();
+            let mut scanner = TableScanExecutor::new(&table_scan, ranges, store, &mut statistics);
+            let mut res = vec![];
+            while let Some(row) = scanner.next().unwrap() {
+                res.push(row.handle);
+            }
+            res
+        };
+
+        let full_range = get_full_range(TABLE_ID);
+        assert_eq!(scan(&mut test_store, vec![full_range.clone()], false), handles);
+        let mut rev_handles = handles.clone();
+        rev_handles.reverse();
+        assert_eq!(scan(&mut test_store, vec![full_range], true), rev_handles);
+
+        // the end of a range is exclusive, so `i64::MAX` is left out here.
+        let range = get_range(TABLE_ID, i64::MIN, i64::MAX);
+        assert_eq!(
+            scan(&mut test_store, vec![range], false),
+            &handles[..handles.len() - 1]
+        );
+
+        let points = vec![
+            get_point_range(TABLE_ID, i64::MIN),
+            get_point_range(TABLE_ID, i64::MAX),
+        ];
+        assert_eq!(
+            scan(&mut test_store, points, false),
+            vec![i64::MIN, i64::MAX]
+        );
+    }
+
+    #[test]
+    fn test_handles_only() {
+        // the values are not valid rows, so any decoding fails.
+        let handles = vec![1, 3, 4, 8];
+        let kv_data: Vec<_> = handles
+            .iter()
+            .map(|&h| {
+                let mut buf = vec![];
+                buf.encode_i64(h).unwrap();
+                (table::encode_row_key(TABLE_ID, &buf), vec![0xff, 0xff])
+            })
+            .collect();
+        let mut test_store = TestStore::new(&kv_data);
+        let mut table_scan = TableScan::new();
+        table_scan.set_columns(RepeatedField::from_vec(vec![
+            new_col_info(1, types::LONG_LONG),
+            new_col_info(2, types::LONG_LONG),
+        ]));
+        let ranges = vec![
+            get_range(TABLE_ID, i64::MIN, 4),
+            get_point_range(TABLE_ID, 4),
+            get_range(TABLE_ID, 5, i64::MAX),
+        ];
+
+        {
+            let (snapshot, start_ts) = test_store.get_snapshot();
+            let store = SnapshotStore::new(snapshot, start_ts, IsolationLevel::SI, true);
+            let mut statistics = Statistics::default();
+            let mut scanner =
+                TableScanExecutor::new(&table_scan, ranges.clone(), store, &mut statistics);
+            assert!(scanner.next().is_err());
+        }
+
+        let (snapshot, start_ts) = test_store.get_snapshot();
+        let store = SnapshotStore::new(snapshot, start_ts, IsolationLevel::SI, true);
+        let mut statistics = Statistics::default();
+        let mut scanner = TableScanExecutor::new(&table_scan, ranges, store, &mut statistics);
+        scanner.set_handles_only();
+        let mut res = vec![];
+        while let Some(row) = scanner.next().unwrap() {
+            assert!(row.data.is_empty());
+            assert!(row.data.value.is_empty());
+            res.push(row.handle);
+        }
+        assert_eq!(res, handles);
+    }
+
+    #[test]
+    fn test_empty_row_value() {
+        // rows may be stored without any column, either empty or as a single NULL.
+        let kv_data: Vec<_> = [vec![], vec![datum::NIL_FLAG]]
+            .iter()
+            .enumerate()
+            .map(|(h, value)| {
+                let mut buf = vec![];
+                buf.encode_i64(h as i64).unwrap();
+                (table::encode_row_key(TABLE_ID, &buf), value.clone())
+            })
+            .collect();
+        let mut test_store = TestStore::new(&kv_data);
+        let mut with_default = new_col_info(1, types::LONG_LONG);
+        with_default.set_default_val(datum::encode_value(&[Datum::I64(7)]).unwrap());
+        let cols = Rc::new(vec![with_default, new_col_info(2, types::VARCHAR)]);
+        let mut table_scan = TableScan::new();
+        table_scan.set_columns(RepeatedField::from_vec(cols.to_vec()));
+
+        let (snapshot, start_ts) = test_store.get_snapshot();
+        let store = SnapshotStore::new(snapshot, start_ts, IsolationLevel::SI, true);
+        let mut statistics = Statistics::default();
+        let ranges = vec![get_range(TABLE_ID, i64::MIN, i64::MAX)];
+        let mut scanner = TableScanExecutor::new(&table_scan, ranges, store, &mut statistics);
+        let ctx = EvalContext::default();
+        let mut handles = vec![];
+        while let Some(row) = scanner.next().unwrap() {
+            assert!(row.data.is_empty());
+            let ds = inflate_with_col_for_dag(&ctx, &row.data, cols.clone(), &[0, 1], row.handle)
+                .unwrap();
+            assert_eq!(ds, vec![Datum::I64(7), Datum::Null]);
+            handles.push(row.handle);
+        }
+        assert_eq!(handles, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_scan_bytes_limit() {
+        let cols = vec![
+            new_col_info(1, types::LONG_LONG),
+            new_col_info(2, types::VARCHAR),
+        ];
+        // every row value is more than 1KB.
+        let raw_data: Vec<_> = (0..KEY_NUMBER as i64)
+            .map(|i| vec![Datum::I64(i), Datum::Bytes(vec![b'x'; 1024])])
+            .collect();
+        let table_data = gen_table_data(TABLE_ID, &cols, &raw_data);
+        let mut test_store = TestStore::new(&table_data);
+        let table_scan = new_table_scan(TABLE_ID, &cols);
+
+        let mut statistics = Statistics::default();
+        let mut scanner = new_table_scan_executor(&mut test_store, &table_scan, &mut statistics);
+        scanner.set_scan_bytes_limit(3 * 1024 + 512);
+        let mut limit = Limit::new();
+        limit.set_limit(5);
+        let mut limit = LimitExecutor::new(limit, Box::new(scanner));
+        // the 4th row goes over the bytes limit before the 5 rows limit is reached.
+        for handle in 0..3 {
+            assert_eq!(limit.next().unwrap().unwrap().handle, handle);
+        }
+        let err = limit.next().unwrap_err();
+        assert!(format!("{}", err).contains("limit"), "{:?}", err);
+    }
+
+    #[test]
+    fn test_deadline() {
+        let cols = vec![new_col_info(1, types::LONG_LONG)];
+        let raw_data: Vec<_> = (0..2 * DEADLINE_CHECK_ROWS as i64)
+            .map(|i| vec![Datum::I64(i)])
+            .collect();
+        let table_data = gen_table_data(TABLE_ID, &cols, &raw_data);
+        let mut test_store = TestStore::new(&table_data);
+        let table_scan = new_table_scan(TABLE_ID, &cols);
+        // the deadline has passed already.
+        let req_ctx = ReqContext {
+            deadline: Instant::now_coarse(),
+            isolation_level: IsolationLevel::SI,
+            fill_cache: true,
+            table_scan: true,
+        };
+
+        let mut statistics = Statistics::default();
+        let mut scanner = new_table_scan_executor(&mut test_store, &table_scan, &mut statistics);
+        scanner.set_req_ctx(&req_ctx);
+        // it's only checked once every `DEADLINE_CHECK_ROWS` rows.
+        for handle in 0..DEADLINE_CHECK_ROWS as i64 - 1 {
+            assert_eq!(scanner.next().unwrap().unwrap().handle, handle);
+        }
+        match scanner.next() {
+            Err(Error::Outdated(..)) => {}
+            res => panic!("expect outdated error, got {:?}", res),
+        }
+    }
 }
@@ -11,7 +11,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::usize;
+use std::{mem, usize};
 use std::rc::Rc;
 use std::vec::IntoIter;
 
@@ -19,14 +19,14 @@ use tipb::executor::TopN;
 use tipb::schema::ColumnInfo;
 use tipb::expression::ByItem;
 
-use coprocessor::codec::datum::Datum;
+use coprocessor::codec::datum::{approximate_size, Datum};
 use coprocessor::Result;
 use coprocessor::select::xeval::EvalContext;
 use coprocessor::dag::expr::Expression;
 use coprocessor::select::topn_heap::{SortRow, TopNHeap};
 use coprocessor::metrics::*;
 
-use super::{inflate_with_col_for_dag, Executor, ExprColumnRefVisitor, Row};
+use super::{inflate_with_col_for_dag, Executor, ExprColumnRefVisitor, MemoryQuota, Row};
 
 struct OrderBy {
     items: Rc<Vec<ByItem>>,
@@ -58,8 +58,12 @@ pub struct TopNExecutor<'a> {
     cols: Rc<Vec<ColumnInfo>>,
     related_cols_offset: Vec<usize>, // offset of related columns
     heap: Option<TopNHeap>,
+    limit: u64,
     iter: Option<IntoIter<SortRow>>,
     ctx: Rc<EvalContext>,
+    quota: Option<Rc<MemoryQuota>>,
+    // bytes registered to `quota` for the rows kept.
+    held: usize,
     src: Box<Executor + 'a>,
 }
 
@@ -81,14 +85,34 @@ impl<'a> TopNExecutor<'a> {
         Ok(TopNExecutor {
             order_by: OrderBy::new(&ctx, order_by)?,
             heap: Some(TopNHeap::new(meta.get_limit() as usize)?),
+            limit: meta.get_limit(),
             cols: columns_info,
             related_cols_offset: visitor.column_offsets(),
             iter: None,
             ctx: ctx,
+            quota: None,
+            held: 0,
             src: src,
         })
     }
 
+    /// Registers the rows kept to `quota` until they are all output.
+    pub fn set_memory_quota(&mut self, quota: Rc<MemoryQuota>) {
+        self.quota = Some(quota);
+    }
+
+    /// Moves the NULLs of the `i`th order by item to the other end for every bit `i` set
+    /// in `flipped`. By default NULLs come first on ascending order and last on descending.
+    pub fn flip_nulls_order(&mut self, flipped: u64) {
+        let nulls_first = self.order_by
+            .items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| item.get_desc() == ((flipped >> i) & 1 == 1))
+            .collect();
+        self.heap.as_mut().unwrap().set_nulls_first(nulls_first);
+    }
+
     fn fetch_all(&mut self) -> Result<()> {
         while let Some(row) = self.src.next()? {
             let cols = inflate_with_col_for_dag(
@@ -99,13 +123,24 @@ impl<'a> TopNExecutor<'a> {
                 row.handle,
             )?;
             let ob_values = self.order_by.eval(&self.ctx, &cols)?;
-            self.heap.as_mut().unwrap().try_add_row(
+            // a row replacing a kept one doesn't grow the heap, only count the new ones.
+            let size = mem::size_of::<SortRow>() + row.data.value.len() +
+                approximate_size(&ob_values, false);
+            let heap = self.heap.as_mut().unwrap();
+            let kept = heap.len();
+            heap.try_add_row(
                 row.handle,
                 row.data,
                 ob_values,
                 self.order_by.items.clone(),
                 self.ctx.clone(),
             )?;
+            if let Some(ref quota) = self.quota {
+                if heap.len() > kept {
+                    quota.alloc(size)?;
+                    self.held += size;
+                }
+            }
         }
         Ok(())
     }
@@ -114,7 +149,10 @@ impl<'a> TopNExecutor<'a> {
 impl<'a> Executor for TopNExecutor<'a> {
     fn next(&mut self) -> Result<Option<Row>> {
         if self.iter.is_none() {
-            self.fetch_all()?;
+            // no row is output for a zero limit, so don't read the source at all.
+            if self.limit > 0 {
+                self.fetch_all()?;
+            }
             self.iter = Some(self.heap.take().unwrap().into_sorted_vec()?.into_iter());
         }
         let iter = self.iter.as_mut().unwrap();
@@ -123,7 +161,13 @@ impl<'a> Executor for TopNExecutor<'a> {
                 handle: sort_row.handle,
                 data: sort_row.data,
             })),
-            None => Ok(None),
+            None => {
+                if let Some(ref quota) = self.quota {
+                    quota.free(self.held);
+                }
+                self.held = 0;
+                Ok(None)
+            }
         }
     }
 }
@@ -150,7 +194,7 @@ pub mod test {
     use super::super::table_scan::TableScanExecutor;
     use super::super::scanner::test::{get_range, new_col_info, TestStore};
 
-    fn new_order_by(offset: i64, desc: bool) -> ByItem {
+    pub fn new_order_by(offset: i64, desc: bool) -> ByItem {
         let mut item = ByItem::new();
         let mut expr = Expr::new();
         expr.set_tp(ExprType::ColumnRef);
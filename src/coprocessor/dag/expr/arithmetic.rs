@@ -15,6 +15,7 @@ use std::{f64, i64, u64};
 use std::borrow::Cow;
 use std::ops::{Add, Mul, Sub};
 use coprocessor::codec::{mysql, Datum};
+use coprocessor::codec::datum::DIVISION_BY_ZERO_MSG;
 use coprocessor::codec::mysql::{Decimal, Res};
 use super::{Error, FnCall, Result, StatementContext};
 
@@ -24,7 +25,7 @@ impl FnCall {
         let rhs = try_opt!(self.children[1].eval_real(ctx, row));
         let res = lhs + rhs;
         if !res.is_finite() {
-            return Err(Error::Overflow);
+            return overflow(ctx);
         }
         Ok(Some(res))
     }
@@ -63,7 +64,10 @@ impl FnCall {
             },
             (false, false) => lhs.checked_add(rhs),
         };
-        res.ok_or(Error::Overflow).map(Some)
+        match res {
+            Some(res) => Ok(Some(res)),
+            None => overflow(ctx),
+        }
     }
 
     pub fn minus_real(&self, ctx: &StatementContext, row: &[Datum]) -> Result<Option<f64>> {
@@ -155,7 +159,7 @@ impl FnCall {
         let lhs = try_opt!(self.children[0].eval_real(ctx, row));
         let rhs = try_opt!(self.children[1].eval_real(ctx, row));
         if rhs == 0f64 {
-            return Ok(None);
+            return divided_by_zero(ctx);
         }
         let res = lhs / rhs;
         if res.is_infinite() {
@@ -172,16 +176,36 @@ impl FnCall {
     ) -> Result<Option<Cow<'a, Decimal>>> {
         let lhs = try_opt!(self.children[0].eval_decimal(ctx, row));
         let rhs = try_opt!(self.children[1].eval_decimal(ctx, row));
-        match lhs.into_owned() / rhs.into_owned() {
+        match lhs.into_owned().div(rhs.into_owned(), ctx.div_frac_incr) {
             Some(v) => match v {
                 Res::Ok(v) => Ok(Some(Cow::Owned(v))),
                 Res::Truncated(_) | Res::Overflow(_) => Err(Error::Overflow),
             },
-            None => Ok(None),
+            None => divided_by_zero(ctx),
         }
     }
 }
 
+/// `overflow` returns the result of an overflowed addition, which is NULL only if `ctx`
+/// ignores it.
+fn overflow<T>(ctx: &StatementContext) -> Result<Option<T>> {
+    if ctx.ignore_overflow {
+        Ok(None)
+    } else {
+        Err(Error::Overflow)
+    }
+}
+
+/// `divided_by_zero` returns the result of a division by zero, which is an error in
+/// write statements like the strict sql mode, or NULL with a warning otherwise.
+fn divided_by_zero<T>(ctx: &StatementContext) -> Result<Option<T>> {
+    if ctx.in_insert_stmt || ctx.in_update_or_delete_stmt {
+        return Err(box_err!("division by zero"));
+    }
+    ctx.append_warning(DIVISION_BY_ZERO_MSG.to_owned());
+    Ok(None)
+}
+
 #[cfg(test)]
 mod test {
     use std::{f64, i64, u64};
@@ -396,6 +420,83 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_divide_decimal_frac_incr() {
+        let tests = vec![
+            (0, "1", "3", "0"),
+            (4, "1", "3", "0.3333"),
+            (8, "1", "3", "0.33333333"),
+            (4, "1.10", "3", "0.366667"),
+            (2, "12.3", "0.3", "41.000"),
+        ];
+        for (frac_incr, lhs, rhs, exp) in tests {
+            let ctx = StatementContext {
+                div_frac_incr: frac_incr,
+                ..Default::default()
+            };
+            let lhs = datum_expr(str2dec(lhs));
+            let rhs = datum_expr(str2dec(rhs));
+            let expr = fncall_expr(ScalarFuncSig::DivideDecimal, &[lhs, rhs]);
+            let op = Expression::build(&ctx, expr).unwrap();
+            match op.eval(&ctx, &[]).unwrap() {
+                Datum::Dec(got) => assert_eq!(format!("{}", got), exp),
+                d => panic!("expect decimal, got {:?}", d),
+            }
+        }
+    }
+
+    #[test]
+    fn test_divide_by_zero() {
+        let tests = vec![
+            (ScalarFuncSig::DivideReal, Datum::F64(44.3), Datum::F64(0.000)),
+            (ScalarFuncSig::DivideDecimal, str2dec("12.3"), str2dec("0")),
+        ];
+        let insert_ctx = StatementContext {
+            in_insert_stmt: true,
+            ..Default::default()
+        };
+        let update_ctx = StatementContext {
+            in_update_or_delete_stmt: true,
+            ..Default::default()
+        };
+        let ctx = StatementContext::default();
+        for (sig, lhs, rhs) in tests {
+            let lhs = datum_expr(lhs);
+            let rhs = datum_expr(rhs);
+            let op = Expression::build(&ctx, fncall_expr(sig, &[lhs, rhs])).unwrap();
+            assert!(op.eval(&insert_ctx, &[]).is_err());
+            assert!(op.eval(&update_ctx, &[]).is_err());
+            assert_eq!(op.eval(&ctx, &[]).unwrap(), Datum::Null);
+        }
+        assert_eq!(ctx.warnings.borrow().warning_cnt, 2);
+    }
+
+    #[test]
+    fn test_plus_ignore_overflow() {
+        let tests = vec![
+            (
+                ScalarFuncSig::PlusInt,
+                Datum::I64(i64::MAX),
+                Datum::I64(i64::MAX),
+            ),
+            (
+                ScalarFuncSig::PlusReal,
+                Datum::F64(f64::MAX),
+                Datum::F64(f64::MAX),
+            ),
+        ];
+        let ctx = StatementContext {
+            ignore_overflow: true,
+            ..Default::default()
+        };
+        for (sig, lhs, rhs) in tests {
+            let lhs = datum_expr(lhs);
+            let rhs = datum_expr(rhs);
+            let op = Expression::build(&ctx, fncall_expr(sig, &[lhs, rhs])).unwrap();
+            assert_eq!(op.eval(&ctx, &[]).unwrap(), Datum::Null);
+        }
+    }
+
     #[test]
     fn test_arithmetic_overflow_int() {
         let tests = vec![
@@ -813,6 +813,35 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_cast_str_truncated() {
+        let cols = vec![Datum::Bytes(b"3x".to_vec())];
+        for &ignore_truncate in &[true, false] {
+            let mut ctx = StatementContext::default();
+            ctx.ignore_truncate = ignore_truncate;
+            let exp = fncall_expr(
+                ScalarFuncSig::CastStringAsInt,
+                &[col_expr(0, types::STRING as i32)],
+            );
+            let e = Expression::build(&ctx, exp).unwrap();
+            let res = e.eval_int(&ctx, &cols);
+            let exp = fncall_expr(
+                ScalarFuncSig::CastStringAsReal,
+                &[col_expr(0, types::STRING as i32)],
+            );
+            let e = Expression::build(&ctx, exp).unwrap();
+            let real_res = e.eval_real(&ctx, &cols);
+            // the malformed suffix is truncated only if told to ignore it.
+            if ignore_truncate {
+                assert_eq!(res.unwrap(), Some(3));
+                assert_eq!(real_res.unwrap(), Some(3f64));
+            } else {
+                assert!(res.is_err());
+                assert!(real_res.is_err());
+            }
+        }
+    }
+
     #[test]
     fn test_cast_as_real() {
         let mut ctx = StatementContext::default();
@@ -217,6 +217,8 @@ impl FnCall {
 
 #[cfg(test)]
 mod test {
+    use std::i64;
+
     use protobuf::RepeatedField;
     use tipb::expression::{Expr, ExprType, ScalarFuncSig};
 
@@ -534,4 +536,29 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_branch_not_taken_is_not_evaluated() {
+        // `i64::MAX + 1` overflows, so the row fails only if this branch is evaluated.
+        let overflow = fncall_expr(
+            ScalarFuncSig::PlusInt,
+            &[datum_expr(Datum::I64(i64::MAX)), datum_expr(Datum::I64(1))],
+        );
+        let cases = vec![
+            (
+                ScalarFuncSig::IfInt,
+                vec![col_expr(0), overflow.clone(), datum_expr(Datum::I64(2))],
+            ),
+            (
+                ScalarFuncSig::CaseWhenInt,
+                vec![col_expr(0), overflow, datum_expr(Datum::I64(2))],
+            ),
+        ];
+        let ctx = StatementContext::default();
+        for (sig, children) in cases {
+            let e = Expression::build(&ctx, fncall_expr(sig, &children)).unwrap();
+            assert_eq!(e.eval(&ctx, &[Datum::I64(0)]).unwrap(), Datum::I64(2));
+            assert_eq!(e.eval(&ctx, &[Datum::Null]).unwrap(), Datum::I64(2));
+            assert!(e.eval(&ctx, &[Datum::I64(1)]).is_err());
+        }
+    }
 }
@@ -222,6 +222,9 @@ mod test {
                 Some(0),
             ),
             (ScalarFuncSig::LogicalAnd, Datum::Null, Datum::I64(1), None),
+            (ScalarFuncSig::LogicalAnd, Datum::Null, Datum::I64(0), Some(0)),
+            (ScalarFuncSig::LogicalAnd, Datum::I64(1), Datum::Null, None),
+            (ScalarFuncSig::LogicalAnd, Datum::Null, Datum::Null, None),
             (
                 ScalarFuncSig::LogicalOr,
                 Datum::I64(1),
@@ -253,6 +256,9 @@ mod test {
                 Some(1),
             ),
             (ScalarFuncSig::LogicalOr, Datum::Null, Datum::I64(0), None),
+            (ScalarFuncSig::LogicalOr, Datum::Null, Datum::I64(1), Some(1)),
+            (ScalarFuncSig::LogicalOr, Datum::I64(0), Datum::Null, None),
+            (ScalarFuncSig::LogicalOr, Datum::Null, Datum::Null, None),
             (
                 ScalarFuncSig::LogicalXor,
                 Datum::I64(1),
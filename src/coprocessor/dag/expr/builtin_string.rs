@@ -0,0 +1,383 @@
+// Copyright 2017 PingCAP, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{i64, str};
+use std::ascii::AsciiExt;
+use std::borrow::Cow;
+
+use coprocessor::codec::Datum;
+use coprocessor::select::xeval::evaluator::{self, MAX_BLOB_WIDTH};
+use super::{FnCall, Result, StatementContext};
+
+impl FnCall {
+    /// See https://dev.mysql.com/doc/refman/5.7/en/string-functions.html#function_concat-ws
+    pub fn concat_ws<'a, 'b: 'a>(
+        &'b self,
+        ctx: &StatementContext,
+        row: &'a [Datum],
+    ) -> Result<Option<Cow<'a, [u8]>>> {
+        let sep = try_opt!(self.children[0].eval_string(ctx, row));
+        let mut res = vec![];
+        let mut first = true;
+        for child in &self.children[1..] {
+            // NULL arguments are skipped, unlike a NULL separator.
+            let s = match child.eval_string(ctx, row)? {
+                Some(s) => s,
+                None => continue,
+            };
+            if !first {
+                res.extend_from_slice(&sep);
+            }
+            res.extend_from_slice(&s);
+            first = false;
+        }
+        Ok(Some(Cow::Owned(res)))
+    }
+
+    /// See https://dev.mysql.com/doc/refman/5.7/en/string-functions.html#function_length
+    pub fn length(&self, ctx: &StatementContext, row: &[Datum]) -> Result<Option<i64>> {
+        let s = try_opt!(self.children[0].eval_string(ctx, row));
+        Ok(Some(s.len() as i64))
+    }
+
+    /// See https://dev.mysql.com/doc/refman/5.7/en/string-functions.html#function_substring
+    pub fn substring<'a, 'b: 'a>(
+        &'b self,
+        ctx: &StatementContext,
+        row: &'a [Datum],
+    ) -> Result<Option<Cow<'a, [u8]>>> {
+        let s = try_opt!(self.children[0].eval_string(ctx, row));
+        let pos = try_opt!(self.children[1].eval_int(ctx, row));
+        let len = match self.children.get(2) {
+            Some(child) => try_opt!(child.eval_int(ctx, row)),
+            None => i64::MAX,
+        };
+        let res = substring(str::from_utf8(&s)?, pos, len);
+        Ok(Some(Cow::Owned(res.as_bytes().to_vec())))
+    }
+
+    /// See https://dev.mysql.com/doc/refman/5.7/en/string-functions.html#function_concat
+    pub fn concat<'a, 'b: 'a>(
+        &'b self,
+        ctx: &StatementContext,
+        row: &'a [Datum],
+    ) -> Result<Option<Cow<'a, [u8]>>> {
+        let mut res = vec![];
+        for child in &self.children {
+            let s = try_opt!(child.eval_string(ctx, row));
+            res.extend_from_slice(&s);
+        }
+        Ok(Some(Cow::Owned(res)))
+    }
+
+    /// See https://dev.mysql.com/doc/refman/5.7/en/string-functions.html#function_upper
+    pub fn upper<'a, 'b: 'a>(
+        &'b self,
+        ctx: &StatementContext,
+        row: &'a [Datum],
+    ) -> Result<Option<Cow<'a, [u8]>>> {
+        let s = try_opt!(self.children[0].eval_string(ctx, row));
+        let mut s = s.into_owned();
+        s.make_ascii_uppercase();
+        Ok(Some(Cow::Owned(s)))
+    }
+
+    /// See https://dev.mysql.com/doc/refman/5.7/en/string-functions.html#function_lower
+    pub fn lower<'a, 'b: 'a>(
+        &'b self,
+        ctx: &StatementContext,
+        row: &'a [Datum],
+    ) -> Result<Option<Cow<'a, [u8]>>> {
+        let s = try_opt!(self.children[0].eval_string(ctx, row));
+        let mut s = s.into_owned();
+        s.make_ascii_lowercase();
+        Ok(Some(Cow::Owned(s)))
+    }
+
+    /// See https://dev.mysql.com/doc/refman/5.7/en/string-functions.html#function_ascii
+    pub fn ascii(&self, ctx: &StatementContext, row: &[Datum]) -> Result<Option<i64>> {
+        let s = try_opt!(self.children[0].eval_string(ctx, row));
+        Ok(Some(s.first().map_or(0, |&b| i64::from(b))))
+    }
+
+    /// See https://dev.mysql.com/doc/refman/5.7/en/string-functions.html#function_ord
+    pub fn ord(&self, ctx: &StatementContext, row: &[Datum]) -> Result<Option<i64>> {
+        let s = try_opt!(self.children[0].eval_string(ctx, row));
+        Ok(Some(evaluator::ord(&s)))
+    }
+
+    /// See https://dev.mysql.com/doc/refman/5.7/en/string-functions.html#function_space
+    pub fn space<'a, 'b: 'a>(
+        &'b self,
+        ctx: &StatementContext,
+        row: &'a [Datum],
+    ) -> Result<Option<Cow<'a, [u8]>>> {
+        let n = try_opt!(self.children[0].eval_int(ctx, row));
+        repeat_bytes(b" ", n)
+    }
+
+    /// See https://dev.mysql.com/doc/refman/5.7/en/string-functions.html#function_repeat
+    pub fn repeat<'a, 'b: 'a>(
+        &'b self,
+        ctx: &StatementContext,
+        row: &'a [Datum],
+    ) -> Result<Option<Cow<'a, [u8]>>> {
+        let s = try_opt!(self.children[0].eval_string(ctx, row));
+        let n = try_opt!(self.children[1].eval_int(ctx, row));
+        repeat_bytes(&s, n)
+    }
+
+    /// See https://dev.mysql.com/doc/refman/5.7/en/string-functions.html#function_reverse
+    pub fn reverse<'a, 'b: 'a>(
+        &'b self,
+        ctx: &StatementContext,
+        row: &'a [Datum],
+    ) -> Result<Option<Cow<'a, [u8]>>> {
+        let s = try_opt!(self.children[0].eval_string(ctx, row));
+        let s: String = str::from_utf8(&s)?.chars().rev().collect();
+        Ok(Some(Cow::Owned(s.into_bytes())))
+    }
+
+    /// `reverse_binary` is `REVERSE` of a binary string, which reverses bytes instead of
+    /// characters.
+    pub fn reverse_binary<'a, 'b: 'a>(
+        &'b self,
+        ctx: &StatementContext,
+        row: &'a [Datum],
+    ) -> Result<Option<Cow<'a, [u8]>>> {
+        let s = try_opt!(self.children[0].eval_string(ctx, row));
+        let mut s = s.into_owned();
+        s.reverse();
+        Ok(Some(Cow::Owned(s)))
+    }
+}
+
+// `substring` returns the characters of `s` from the 1-based position `pos`, which
+// counts from the end if negative, up to `len` characters. It's empty if `pos` is 0
+// or out of `s`.
+fn substring(s: &str, pos: i64, len: i64) -> &str {
+    let char_cnt = s.chars().count() as i64;
+    let start = if pos > 0 {
+        pos - 1
+    } else if pos < 0 {
+        char_cnt + pos
+    } else {
+        return "";
+    };
+    if start < 0 || start >= char_cnt || len <= 0 {
+        return "";
+    }
+    let byte_offset = |n: i64| s.char_indices().nth(n as usize).map_or(s.len(), |(i, _)| i);
+    let end = if len >= char_cnt - start {
+        s.len()
+    } else {
+        byte_offset(start + len)
+    };
+    &s[byte_offset(start)..end]
+}
+
+// `repeat_bytes` repeats `s` `n` times, where a negative `n` is treated as zero.
+fn repeat_bytes<'a>(s: &[u8], n: i64) -> Result<Option<Cow<'a, [u8]>>> {
+    match evaluator::repeat(s, n.max(0) as usize) {
+        Some(res) => Ok(Some(Cow::Owned(res))),
+        None => Err(box_err!(
+            "result of repeating {} bytes {} times is larger than {} bytes",
+            s.len(),
+            n,
+            MAX_BLOB_WIDTH
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::i64;
+
+    use tipb::expression::ScalarFuncSig;
+    use coprocessor::codec::Datum;
+    use coprocessor::select::xeval::evaluator::MAX_BLOB_WIDTH;
+    use coprocessor::dag::expr::test::fncall_expr;
+    use coprocessor::dag::expr::{Expression, StatementContext};
+    use coprocessor::select::xeval::evaluator::test::datum_expr;
+
+    fn bytes(s: &str) -> Datum {
+        Datum::Bytes(s.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_concat_ws() {
+        let cases = vec![
+            (vec![bytes(","), bytes("a"), bytes("b")], bytes("a,b")),
+            (vec![bytes(", "), bytes("a"), Datum::Null, bytes("c")], bytes("a, c")),
+            (vec![bytes(","), Datum::Null, bytes("b")], bytes("b")),
+            (vec![bytes(","), Datum::Null], bytes("")),
+            (vec![Datum::Null, bytes("a"), bytes("b")], Datum::Null),
+        ];
+        let ctx = StatementContext::default();
+        for (args, exp) in cases {
+            let children: Vec<_> = args.iter().cloned().map(datum_expr).collect();
+            let op = fncall_expr(ScalarFuncSig::ConcatWS, &children);
+            let op = Expression::build(&ctx, op).unwrap();
+            assert_eq!(op.eval(&ctx, &[]).unwrap(), exp, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_length_concat() {
+        let cases = vec![
+            (ScalarFuncSig::Length, vec![bytes("abc")], Datum::I64(3)),
+            (ScalarFuncSig::Length, vec![bytes("é")], Datum::I64(2)),
+            (ScalarFuncSig::Length, vec![bytes("")], Datum::I64(0)),
+            (ScalarFuncSig::Length, vec![Datum::Null], Datum::Null),
+            (ScalarFuncSig::Concat, vec![bytes("ab")], bytes("ab")),
+            (ScalarFuncSig::Concat, vec![bytes("ab"), bytes(""), bytes("c")], bytes("abc")),
+            (ScalarFuncSig::Concat, vec![bytes("ab"), Datum::Null], Datum::Null),
+        ];
+        let ctx = StatementContext::default();
+        for (sig, args, exp) in cases {
+            let children: Vec<_> = args.iter().cloned().map(datum_expr).collect();
+            let op = Expression::build(&ctx, fncall_expr(sig, &children)).unwrap();
+            assert_eq!(op.eval(&ctx, &[]).unwrap(), exp, "{:?} {:?}", sig, args);
+        }
+    }
+
+    #[test]
+    fn test_substring() {
+        let cases = vec![
+            ("quadratically", 5, None, "ratically"),
+            ("quadratically", 5, Some(6), "ratica"),
+            ("Sakila", -3, None, "ila"),
+            ("Sakila", -5, Some(3), "aki"),
+            ("Sakila", 0, None, ""),
+            ("Sakila", 7, None, ""),
+            ("Sakila", -7, None, ""),
+            ("Sakila", 1, Some(0), ""),
+            ("Sakila", 2, Some(i64::MAX), "akila"),
+            ("测试abc", 2, Some(2), "试a"),
+            ("测试abc", -4, None, "试abc"),
+        ];
+        let ctx = StatementContext::default();
+        for (s, pos, len, exp) in cases {
+            let mut args = vec![datum_expr(bytes(s)), datum_expr(Datum::I64(pos))];
+            let sig = match len {
+                Some(len) => {
+                    args.push(datum_expr(Datum::I64(len)));
+                    ScalarFuncSig::Substring3Args
+                }
+                None => ScalarFuncSig::Substring2Args,
+            };
+            let op = Expression::build(&ctx, fncall_expr(sig, &args)).unwrap();
+            assert_eq!(op.eval(&ctx, &[]).unwrap(), bytes(exp), "{} {} {:?}", s, pos, len);
+        }
+
+        let args = &[datum_expr(bytes("Sakila")), datum_expr(Datum::Null)];
+        let op = fncall_expr(ScalarFuncSig::Substring2Args, args);
+        let op = Expression::build(&ctx, op).unwrap();
+        assert_eq!(op.eval(&ctx, &[]).unwrap(), Datum::Null);
+    }
+
+    #[test]
+    fn test_upper_lower() {
+        let cases = vec![
+            (bytes("aBc 1é"), bytes("ABC 1é"), bytes("abc 1é")),
+            (bytes(""), bytes(""), bytes("")),
+            (Datum::Null, Datum::Null, Datum::Null),
+        ];
+        let ctx = StatementContext::default();
+        for (arg, upper, lower) in cases {
+            for &(sig, ref exp) in &[(ScalarFuncSig::Upper, upper), (ScalarFuncSig::Lower, lower)] {
+                let op = fncall_expr(sig, &[datum_expr(arg.clone())]);
+                let op = Expression::build(&ctx, op).unwrap();
+                assert_eq!(op.eval(&ctx, &[]).unwrap(), *exp, "{:?} {:?}", sig, arg);
+            }
+        }
+    }
+
+    #[test]
+    fn test_ascii_ord() {
+        let cases = vec![
+            (bytes("2"), 50, 50),
+            (bytes("abc"), 97, 97),
+            (bytes(""), 0, 0),
+            (bytes("é"), 0xc3, 0xc3a9),
+            (bytes("测试"), 0xe6, 0xe6b58b),
+            (bytes("😀"), 0xf0, 0xf09f9880),
+        ];
+        let ctx = StatementContext::default();
+        for (arg, ascii, ord) in cases {
+            for &(sig, exp) in &[(ScalarFuncSig::ASCII, ascii), (ScalarFuncSig::Ord, ord)] {
+                let op = fncall_expr(sig, &[datum_expr(arg.clone())]);
+                let op = Expression::build(&ctx, op).unwrap();
+                assert_eq!(op.eval(&ctx, &[]).unwrap(), Datum::I64(exp), "{:?} {:?}", sig, arg);
+            }
+        }
+
+        for &sig in &[ScalarFuncSig::ASCII, ScalarFuncSig::Ord] {
+            let op = fncall_expr(sig, &[datum_expr(Datum::Null)]);
+            let op = Expression::build(&ctx, op).unwrap();
+            assert_eq!(op.eval(&ctx, &[]).unwrap(), Datum::Null);
+        }
+    }
+
+    #[test]
+    fn test_space_repeat() {
+        let cases = vec![
+            (ScalarFuncSig::Space, vec![Datum::I64(3)], bytes("   ")),
+            (ScalarFuncSig::Space, vec![Datum::I64(-1)], bytes("")),
+            (ScalarFuncSig::Space, vec![Datum::Null], Datum::Null),
+            (ScalarFuncSig::Repeat, vec![bytes("ab"), Datum::I64(3)], bytes("ababab")),
+            (ScalarFuncSig::Repeat, vec![bytes("ab"), Datum::I64(0)], bytes("")),
+            (ScalarFuncSig::Repeat, vec![bytes(""), Datum::I64(i64::MAX)], bytes("")),
+            (ScalarFuncSig::Repeat, vec![Datum::Null, Datum::I64(1)], Datum::Null),
+            (ScalarFuncSig::Repeat, vec![bytes("ab"), Datum::Null], Datum::Null),
+        ];
+        let ctx = StatementContext::default();
+        for (sig, args, exp) in cases {
+            let children: Vec<_> = args.iter().cloned().map(datum_expr).collect();
+            let op = Expression::build(&ctx, fncall_expr(sig, &children)).unwrap();
+            assert_eq!(op.eval(&ctx, &[]).unwrap(), exp, "{:?} {:?}", sig, args);
+        }
+
+        let too_long = vec![
+            (ScalarFuncSig::Space, vec![Datum::I64(MAX_BLOB_WIDTH as i64 + 1)]),
+            (ScalarFuncSig::Repeat, vec![bytes("ab"), Datum::I64(i64::MAX)]),
+        ];
+        for (sig, args) in too_long {
+            let children: Vec<_> = args.iter().cloned().map(datum_expr).collect();
+            let op = Expression::build(&ctx, fncall_expr(sig, &children)).unwrap();
+            assert!(op.eval(&ctx, &[]).is_err(), "{:?} {:?}", sig, args);
+        }
+    }
+
+    #[test]
+    fn test_reverse() {
+        let cases = vec![
+            (ScalarFuncSig::Reverse, bytes("abc"), bytes("cba")),
+            (ScalarFuncSig::Reverse, bytes("测试a"), bytes("a试测")),
+            (ScalarFuncSig::Reverse, Datum::Null, Datum::Null),
+            (ScalarFuncSig::ReverseBinary, bytes("abc"), bytes("cba")),
+            (
+                ScalarFuncSig::ReverseBinary,
+                Datum::Bytes(vec![0xe6, 0xb5, 0x8b]),
+                Datum::Bytes(vec![0x8b, 0xb5, 0xe6]),
+            ),
+            (ScalarFuncSig::ReverseBinary, Datum::Null, Datum::Null),
+        ];
+        let ctx = StatementContext::default();
+        for (sig, arg, exp) in cases {
+            let op = fncall_expr(sig, &[datum_expr(arg.clone())]);
+            let op = Expression::build(&ctx, op).unwrap();
+            assert_eq!(op.eval(&ctx, &[]).unwrap(), exp, "{:?} {:?}", sig, arg);
+        }
+    }
+}
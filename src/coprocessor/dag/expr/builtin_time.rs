@@ -0,0 +1,210 @@
+// Copyright 2017 PingCAP, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::str;
+use std::borrow::Cow;
+
+use coprocessor::codec::Datum;
+use coprocessor::codec::mysql::{Duration, Time};
+use coprocessor::select::xeval::evaluator;
+use super::{FnCall, Result, StatementContext};
+
+impl FnCall {
+    /// See https://dev.mysql.com/doc/refman/5.7/en/date-and-time-functions.html#function_date-format
+    pub fn date_format<'a, 'b: 'a>(
+        &'b self,
+        ctx: &StatementContext,
+        row: &'a [Datum],
+    ) -> Result<Option<Cow<'a, [u8]>>> {
+        let t = try_opt!(self.children[0].eval_time(ctx, row));
+        let layout = try_opt!(self.children[1].eval_string(ctx, row));
+        let res = t.date_format(str::from_utf8(&layout)?)?;
+        Ok(Some(Cow::Owned(res.into_bytes())))
+    }
+
+    /// See https://dev.mysql.com/doc/refman/5.7/en/date-and-time-functions.html#function_datediff
+    pub fn date_diff(&self, ctx: &StatementContext, row: &[Datum]) -> Result<Option<i64>> {
+        let l = try_opt!(self.children[0].eval_time(ctx, row));
+        let r = try_opt!(self.children[1].eval_time(ctx, row));
+        if l.is_zero() || r.is_zero() {
+            return Ok(None);
+        }
+        Ok(Some(l.date_diff(&r)))
+    }
+
+    /// See https://dev.mysql.com/doc/refman/5.7/en/date-and-time-functions.html#function_timestampdiff
+    pub fn timestamp_diff(&self, ctx: &StatementContext, row: &[Datum]) -> Result<Option<i64>> {
+        let unit = try_opt!(self.children[0].eval_string(ctx, row));
+        let from = try_opt!(self.children[1].eval_time(ctx, row));
+        let to = try_opt!(self.children[2].eval_time(ctx, row));
+        if from.is_zero() || to.is_zero() {
+            return Ok(None);
+        }
+        let res = from.timestamp_diff(&to, str::from_utf8(&unit)?)?;
+        Ok(Some(res))
+    }
+
+    /// See https://dev.mysql.com/doc/refman/5.7/en/date-and-time-functions.html#function_makedate
+    pub fn make_date<'a, 'b: 'a>(
+        &'b self,
+        ctx: &StatementContext,
+        row: &'a [Datum],
+    ) -> Result<Option<Cow<'a, Time>>> {
+        let year = try_opt!(self.children[0].eval_int(ctx, row));
+        let day = try_opt!(self.children[1].eval_int(ctx, row));
+        let t = evaluator::make_date(&ctx.tz, year, day)?;
+        Ok(t.map(Cow::Owned))
+    }
+
+    /// See https://dev.mysql.com/doc/refman/5.7/en/date-and-time-functions.html#function_maketime
+    pub fn make_time<'a, 'b: 'a>(
+        &'b self,
+        ctx: &StatementContext,
+        row: &'a [Datum],
+    ) -> Result<Option<Cow<'a, Duration>>> {
+        let hour = try_opt!(self.children[0].eval_int(ctx, row));
+        let minute = try_opt!(self.children[1].eval_int(ctx, row));
+        let second = try_opt!(self.children[2].eval_real(ctx, row));
+        let d = evaluator::make_time(hour, minute, second)?;
+        Ok(d.map(Cow::Owned))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tipb::expression::{Expr, ScalarFuncSig};
+    use coprocessor::codec::Datum;
+    use coprocessor::codec::mysql::{Duration, Time, MAX_FSP};
+    use coprocessor::dag::expr::test::fncall_expr;
+    use coprocessor::dag::expr::{Expression, StatementContext};
+    use coprocessor::select::xeval::evaluator::test::datum_expr;
+
+    #[test]
+    fn test_date_format() {
+        let cases = vec![
+            ("2017-01-02 03:04:05", "%Y-%m-%d %H:%i:%s", "2017-01-02 03:04:05"),
+            ("2017-01-02 13:04:05", "%y/%c/%e %h%p", "17/1/2 01PM"),
+            ("2017-01-02 03:04:05", "%W %M %e", "Monday January 2"),
+        ];
+        let ctx = StatementContext::default();
+        for (time, layout, exp) in cases {
+            let t = Time::parse_utc_datetime(time, 0).unwrap();
+            let args = &[
+                datum_expr(Datum::Time(t)),
+                datum_expr(Datum::Bytes(layout.as_bytes().to_vec())),
+            ];
+            let op = Expression::build(&ctx, fncall_expr(ScalarFuncSig::DateFormatSig, args));
+            let got = op.unwrap().eval(&ctx, &[]).unwrap();
+            assert_eq!(got, Datum::Bytes(exp.as_bytes().to_vec()), "{} {}", time, layout);
+        }
+
+        let null = datum_expr(Datum::Null);
+        let layout = datum_expr(Datum::Bytes(b"%Y".to_vec()));
+        let op = fncall_expr(ScalarFuncSig::DateFormatSig, &[null, layout]);
+        let op = Expression::build(&ctx, op).unwrap();
+        assert_eq!(op.eval(&ctx, &[]).unwrap(), Datum::Null);
+    }
+
+    #[test]
+    fn test_date_diff() {
+        let cases = vec![
+            ("2017-03-05 00:00:00", "2017-01-01 23:59:59", Some(63)),
+            ("2017-01-01 00:00:00", "2017-03-05 12:00:00", Some(-63)),
+            ("2017-01-01 00:00:00", "0000-00-00 00:00:00", None),
+        ];
+        let ctx = StatementContext::default();
+        for (l, r, exp) in cases {
+            let args = &[time_expr(l), time_expr(r)];
+            let op = Expression::build(&ctx, fncall_expr(ScalarFuncSig::DateDiff, args));
+            let got = op.unwrap().eval(&ctx, &[]).unwrap();
+            assert_eq!(got, exp.map_or(Datum::Null, Datum::I64), "{} {}", l, r);
+        }
+    }
+
+    #[test]
+    fn test_timestamp_diff() {
+        let cases = vec![
+            ("MONTH", "2017-01-31 00:00:00", "2017-02-28 00:00:00", Some(0)),
+            ("MONTH", "2017-01-31 00:00:00", "2017-03-31 00:00:00", Some(2)),
+            ("DAY", "2017-01-02 12:00:00", "2017-01-01 00:00:00", Some(-1)),
+            ("SECOND", "2017-01-01 00:00:00", "2017-01-01 00:01:01", Some(61)),
+            ("DAY", "0000-00-00 00:00:00", "2017-01-01 00:00:00", None),
+        ];
+        let ctx = StatementContext::default();
+        for (unit, from, to, exp) in cases {
+            let args = &[
+                datum_expr(Datum::Bytes(unit.as_bytes().to_vec())),
+                time_expr(from),
+                time_expr(to),
+            ];
+            let op = Expression::build(&ctx, fncall_expr(ScalarFuncSig::TimestampDiff, args));
+            let got = op.unwrap().eval(&ctx, &[]).unwrap();
+            assert_eq!(got, exp.map_or(Datum::Null, Datum::I64), "{} {} {}", unit, from, to);
+        }
+    }
+
+    fn time_expr(s: &str) -> Expr {
+        datum_expr(Datum::Time(Time::parse_utc_datetime(s, 0).unwrap()))
+    }
+
+    #[test]
+    fn test_make_date() {
+        let cases = vec![
+            (Datum::I64(2017), Datum::I64(32), Some("2017-02-01")),
+            (Datum::I64(17), Datum::I64(1), Some("2017-01-01")),
+            (Datum::I64(71), Datum::I64(366), Some("1972-01-01")),
+            (Datum::I64(2017), Datum::I64(0), None),
+            (Datum::I64(10000), Datum::I64(1), None),
+            (Datum::I64(9999), Datum::I64(366), None),
+            (Datum::Null, Datum::I64(1), None),
+        ];
+        let ctx = StatementContext::default();
+        for (year, day, exp) in cases {
+            let args = &[datum_expr(year.clone()), datum_expr(day.clone())];
+            let op = Expression::build(&ctx, fncall_expr(ScalarFuncSig::MakeDateSig, args));
+            let got = op.unwrap().eval(&ctx, &[]).unwrap();
+            let exp = match exp {
+                Some(s) => Datum::Time(Time::parse_utc_datetime(s, 0).unwrap()),
+                None => Datum::Null,
+            };
+            assert_eq!(got, exp, "{:?} {:?}", year, day);
+        }
+    }
+
+    #[test]
+    fn test_make_time() {
+        let cases = vec![
+            (12, 15, Datum::F64(30.0), Some(("12:15:30", 0))),
+            (-1, 0, Datum::F64(1.5), Some(("-01:00:01.5", MAX_FSP))),
+            (900, 0, Datum::F64(0.0), Some(("838:59:59", 0))),
+            (0, 60, Datum::F64(0.0), None),
+            (0, 0, Datum::F64(-1.0), None),
+            (0, 0, Datum::Null, None),
+        ];
+        let ctx = StatementContext::default();
+        for (hour, minute, second, exp) in cases {
+            let args = &[
+                datum_expr(Datum::I64(hour)),
+                datum_expr(Datum::I64(minute)),
+                datum_expr(second.clone()),
+            ];
+            let op = Expression::build(&ctx, fncall_expr(ScalarFuncSig::MakeTimeSig, args));
+            let got = op.unwrap().eval(&ctx, &[]).unwrap();
+            let exp = match exp {
+                Some((s, fsp)) => Datum::Dur(Duration::parse(s.as_bytes(), fsp).unwrap()),
+                None => Datum::Null,
+            };
+            assert_eq!(got, exp, "{} {} {:?}", hour, minute, second);
+        }
+    }
+}
@@ -12,13 +12,19 @@
 // limitations under the License.
 
 use std::{char, str, i64};
+use std::ascii::AsciiExt;
 use std::str::Chars;
 use std::cmp::Ordering;
 use std::borrow::Cow;
 
+use tipb::expression::ScalarFuncSig;
+
 use coprocessor::codec::{datum, mysql, Datum};
-use coprocessor::codec::mysql::{Decimal, Duration, Json, Time};
+use coprocessor::codec::mysql::{charset, Decimal, Duration, Json, Time};
 use coprocessor::dag::expr::Expression;
+use coprocessor::select::xeval::evaluator;
+use util::codec::number::NumberEncoder;
+use util::collections::HashSet;
 use super::{Error, FnCall, Result, StatementContext};
 
 const MAX_RECURSE_LEVEL: usize = 1024;
@@ -172,6 +178,214 @@ impl FnCall {
         };
         Ok(Some(like(&target, &pattern, escape, 0)? as i64))
     }
+
+    /// See https://dev.mysql.com/doc/refman/5.7/en/string-comparison-functions.html#function_strcmp
+    pub fn strcmp(&self, ctx: &StatementContext, row: &[Datum]) -> Result<Option<i64>> {
+        let left = try_opt!(self.children[0].eval_string(ctx, row));
+        let right = try_opt!(self.children[1].eval_string(ctx, row));
+        let ci = self.children
+            .iter()
+            .any(|c| charset::is_ci_collation(c.get_tp().get_collate()));
+        let ordering = if ci {
+            // like the group keys of an aggregation, only ASCII letters are folded.
+            let l = left.iter().map(|c| c.to_ascii_uppercase());
+            l.cmp(right.iter().map(|c| c.to_ascii_uppercase()))
+        } else {
+            left.cmp(&right)
+        };
+        let r = match ordering {
+            Ordering::Less => -1,
+            Ordering::Equal => 0,
+            Ordering::Greater => 1,
+        };
+        Ok(Some(r))
+    }
+
+    pub fn locate(&self, ctx: &StatementContext, row: &[Datum]) -> Result<Option<i64>> {
+        let substr = try_opt!(self.children[0].eval_string(ctx, row));
+        let s = try_opt!(self.children[1].eval_string(ctx, row));
+        Ok(Some(evaluator::locate(&substr, &s)))
+    }
+
+    /// `build_in_set` collects the keys of the values of an IN list when they are all
+    /// constants. Only ints and strings are collected, as equal values of the other
+    /// types, like `1.0` and `1.00`, don't always have the same key.
+    pub fn build_in_set(
+        ctx: &StatementContext,
+        sig: ScalarFuncSig,
+        children: &[Expression],
+    ) -> Result<Option<HashSet<Vec<u8>>>> {
+        if sig != ScalarFuncSig::InInt && sig != ScalarFuncSig::InString {
+            return Ok(None);
+        }
+        let all_const = children[1..].iter().all(|c| match *c {
+            Expression::Constant(_) => true,
+            _ => false,
+        });
+        if !all_const {
+            return Ok(None);
+        }
+        let mut set = HashSet::default();
+        for c in &children[1..] {
+            let key = if sig == ScalarFuncSig::InInt {
+                let unsigned = mysql::has_unsigned_flag(c.get_tp().get_flag());
+                match c.eval_int(ctx, &[])? {
+                    Some(i) => int_in_key(i, unsigned)?,
+                    None => vec![],
+                }
+            } else {
+                c.eval_string(ctx, &[])?
+                    .map_or_else(Vec::new, |bs| bytes_in_key(&bs))
+            };
+            set.insert(key);
+        }
+        Ok(Some(set))
+    }
+
+    /// See https://dev.mysql.com/doc/refman/5.7/en/comparison-operators.html#function_in
+    pub fn in_int(&self, ctx: &StatementContext, row: &[Datum]) -> Result<Option<i64>> {
+        let unsigned = |i: usize| mysql::has_unsigned_flag(self.children[i].get_tp().get_flag());
+        let target = try_opt!(self.children[0].eval_int(ctx, row));
+        let target_unsigned = unsigned(0);
+        if let Some(ref set) = self.in_set {
+            return Ok(lookup_in_set(set, &int_in_key(target, target_unsigned)?));
+        }
+        do_in(
+            self,
+            |i| self.children[i].eval_int(ctx, row),
+            |v, i| Ok(cmp_i64_with_unsigned_flag(target, target_unsigned, v, unsigned(i))),
+        )
+    }
+
+    pub fn in_real(&self, ctx: &StatementContext, row: &[Datum]) -> Result<Option<i64>> {
+        let target = try_opt!(self.children[0].eval_real(ctx, row));
+        do_in(
+            self,
+            |i| self.children[i].eval_real(ctx, row),
+            |v, _| datum::cmp_f64(target, v).map_err(Error::from),
+        )
+    }
+
+    pub fn in_decimal(&self, ctx: &StatementContext, row: &[Datum]) -> Result<Option<i64>> {
+        let target = try_opt!(self.children[0].eval_decimal(ctx, row));
+        do_in(
+            self,
+            |i| self.children[i].eval_decimal(ctx, row),
+            |v, _| Ok(target.cmp(&v)),
+        )
+    }
+
+    pub fn in_string(&self, ctx: &StatementContext, row: &[Datum]) -> Result<Option<i64>> {
+        let target = try_opt!(self.children[0].eval_string(ctx, row));
+        if let Some(ref set) = self.in_set {
+            return Ok(lookup_in_set(set, &bytes_in_key(&target)));
+        }
+        do_in(
+            self,
+            |i| self.children[i].eval_string(ctx, row),
+            |v, _| Ok(target.cmp(&v)),
+        )
+    }
+
+    pub fn in_time(&self, ctx: &StatementContext, row: &[Datum]) -> Result<Option<i64>> {
+        let target = try_opt!(self.children[0].eval_time(ctx, row));
+        do_in(
+            self,
+            |i| self.children[i].eval_time(ctx, row),
+            |v, _| Ok(target.cmp(&v)),
+        )
+    }
+
+    pub fn in_duration(&self, ctx: &StatementContext, row: &[Datum]) -> Result<Option<i64>> {
+        let target = try_opt!(self.children[0].eval_duration(ctx, row));
+        do_in(
+            self,
+            |i| self.children[i].eval_duration(ctx, row),
+            |v, _| Ok(target.cmp(&v)),
+        )
+    }
+
+    pub fn in_json(&self, ctx: &StatementContext, row: &[Datum]) -> Result<Option<i64>> {
+        let target = try_opt!(self.children[0].eval_json(ctx, row));
+        do_in(
+            self,
+            |i| self.children[i].eval_json(ctx, row),
+            |v, _| Ok(target.cmp(&v)),
+        )
+    }
+
+    /// See https://dev.mysql.com/doc/refman/5.7/en/comparison-operators.html#function_greatest
+    /// and https://dev.mysql.com/doc/refman/5.7/en/comparison-operators.html#function_least
+    pub fn greatest_least_int(
+        &self,
+        ctx: &StatementContext,
+        row: &[Datum],
+        ord: Ordering,
+    ) -> Result<Option<i64>> {
+        let unsigned = mysql::has_unsigned_flag(self.tp.get_flag());
+        do_greatest_least(
+            self,
+            |i| self.children[i].eval_int(ctx, row),
+            ord,
+            |l, r| Ok(cmp_i64_with_unsigned_flag(*l, unsigned, *r, unsigned)),
+        )
+    }
+
+    pub fn greatest_least_real(
+        &self,
+        ctx: &StatementContext,
+        row: &[Datum],
+        ord: Ordering,
+    ) -> Result<Option<f64>> {
+        do_greatest_least(
+            self,
+            |i| self.children[i].eval_real(ctx, row),
+            ord,
+            |l, r| datum::cmp_f64(*l, *r).map_err(Error::from),
+        )
+    }
+
+    pub fn greatest_least_decimal<'a, 'b: 'a>(
+        &'b self,
+        ctx: &StatementContext,
+        row: &'a [Datum],
+        ord: Ordering,
+    ) -> Result<Option<Cow<'a, Decimal>>> {
+        do_greatest_least(
+            self,
+            |i| self.children[i].eval_decimal(ctx, row),
+            ord,
+            |l, r| Ok(l.cmp(r)),
+        )
+    }
+
+    pub fn greatest_least_string<'a, 'b: 'a>(
+        &'b self,
+        ctx: &StatementContext,
+        row: &'a [Datum],
+        ord: Ordering,
+    ) -> Result<Option<Cow<'a, [u8]>>> {
+        do_greatest_least(
+            self,
+            |i| self.children[i].eval_string(ctx, row),
+            ord,
+            |l, r| Ok(l.cmp(r)),
+        )
+    }
+
+    pub fn greatest_least_time<'a, 'b: 'a>(
+        &'b self,
+        ctx: &StatementContext,
+        row: &'a [Datum],
+        ord: Ordering,
+    ) -> Result<Option<Cow<'a, Time>>> {
+        do_greatest_least(
+            self,
+            |i| self.children[i].eval_time(ctx, row),
+            ord,
+            |l, r| Ok(l.cmp(r)),
+        )
+    }
 }
 
 fn do_compare<T, E, F>(e: E, op: CmpOp, get_order: F) -> Result<Option<i64>>
@@ -232,6 +446,78 @@ fn cmp_i64_with_unsigned_flag(
     }
 }
 
+// do_in compares the first child with the others one by one. Without an equal value,
+// a NULL value makes the result NULL, the same as `a = NULL OR a = b ...`.
+fn do_in<T, E, F>(expr: &FnCall, e: E, get_order: F) -> Result<Option<i64>>
+where
+    E: Fn(usize) -> Result<Option<T>>,
+    F: Fn(T, usize) -> Result<Ordering>,
+{
+    let mut has_null = false;
+    for i in 1..expr.children.len() {
+        match e(i)? {
+            Some(v) => if get_order(v, i)? == Ordering::Equal {
+                return Ok(Some(1));
+            },
+            None => has_null = true,
+        }
+    }
+    if has_null {
+        Ok(None)
+    } else {
+        Ok(Some(0))
+    }
+}
+
+// do_greatest_least returns the child whose order against all the others is `ord`,
+// so `Ordering::Greater` makes it GREATEST and `Ordering::Less` LEAST. It's NULL if
+// any child is NULL.
+fn do_greatest_least<T, E, F>(expr: &FnCall, e: E, ord: Ordering, get_order: F) -> Result<Option<T>>
+where
+    E: Fn(usize) -> Result<Option<T>>,
+    F: Fn(&T, &T) -> Result<Ordering>,
+{
+    let mut res = try_opt!(e(0));
+    for i in 1..expr.children.len() {
+        let v = try_opt!(e(i));
+        if get_order(&v, &res)? == ord {
+            res = v;
+        }
+    }
+    Ok(Some(res))
+}
+
+// lookup_in_set is `do_in` for an IN list collected by `build_in_set`, where the key of
+// NULL is empty.
+fn lookup_in_set(set: &HashSet<Vec<u8>>, key: &[u8]) -> Option<i64> {
+    if set.contains(key) {
+        Some(1)
+    } else if set.contains(&[] as &[u8]) {
+        None
+    } else {
+        Some(0)
+    }
+}
+
+// int_in_key is the key of an int in an IN list. Unsigned ints beyond `i64::MAX` get a
+// longer key, so the key of an int is the same whether it's signed or not.
+fn int_in_key(i: i64, unsigned: bool) -> Result<Vec<u8>> {
+    let mut key = Vec::with_capacity(9);
+    if unsigned && i < 0 {
+        key.push(1);
+    }
+    key.encode_i64(i)?;
+    Ok(key)
+}
+
+// bytes_in_key is the key of a string in an IN list, which is never empty.
+fn bytes_in_key(bs: &[u8]) -> Vec<u8> {
+    let mut key = Vec::with_capacity(bs.len() + 1);
+    key.push(0);
+    key.extend_from_slice(bs);
+    key
+}
+
 fn do_coalesce<'a, F, T>(expr: &'a FnCall, f: F) -> Result<Option<T>>
 where
     F: Fn(&'a Expression) -> Result<Option<T>>,
@@ -474,4 +760,190 @@ mod test {
             assert_eq!(got, exp, "{:?} like {:?}", target_str, pattern_str);
         }
     }
+
+    #[test]
+    fn test_strcmp() {
+        let cases = vec![
+            ("abc", "abd", false, -1),
+            ("abc", "abc", false, 0),
+            ("abd", "abc", false, 1),
+            ("abc", "ab", false, 1),
+            ("", "a", false, -1),
+            ("ABC", "abc", false, -1),
+            ("ABC", "abc", true, 0),
+            ("abd", "ABC", true, 1),
+            ("é", "É", true, 1),
+        ];
+        let ctx = StatementContext::default();
+        for (left, right, ci, exp) in cases {
+            let mut left_expr = datum_expr(Datum::Bytes(left.as_bytes().to_vec()));
+            if ci {
+                left_expr
+                    .mut_field_type()
+                    .set_collate(charset::COLLATION_UTF8_GENERAL_CI_ID);
+            }
+            let right_expr = datum_expr(Datum::Bytes(right.as_bytes().to_vec()));
+            let op = fncall_expr(ScalarFuncSig::Strcmp, &[left_expr, right_expr]);
+            let op = Expression::build(&ctx, op).unwrap();
+            let got = op.eval(&ctx, &[]).unwrap();
+            assert_eq!(got, Datum::I64(exp), "strcmp({:?}, {:?})", left, right);
+        }
+
+        let null = datum_expr(Datum::Null);
+        let right = datum_expr(Datum::Bytes(b"a".to_vec()));
+        let op = fncall_expr(ScalarFuncSig::Strcmp, &[null, right]);
+        let op = Expression::build(&ctx, op).unwrap();
+        assert_eq!(op.eval(&ctx, &[]).unwrap(), Datum::Null);
+    }
+
+    #[test]
+    fn test_locate() {
+        let cases = vec![
+            ("bar", "foobarbar", 4),
+            ("xbar", "foobar", 0),
+            ("", "foobar", 1),
+            ("b", "éab", 3),
+        ];
+        let ctx = StatementContext::default();
+        for (substr, s, exp) in cases {
+            let substr_expr = datum_expr(Datum::Bytes(substr.as_bytes().to_vec()));
+            let s_expr = datum_expr(Datum::Bytes(s.as_bytes().to_vec()));
+            let op = fncall_expr(ScalarFuncSig::Locate2Args, &[substr_expr, s_expr]);
+            let op = Expression::build(&ctx, op).unwrap();
+            let got = op.eval(&ctx, &[]).unwrap();
+            assert_eq!(got, Datum::I64(exp), "locate({:?}, {:?})", substr, s);
+        }
+
+        let null = datum_expr(Datum::Null);
+        let s = datum_expr(Datum::Bytes(b"a".to_vec()));
+        let op = fncall_expr(ScalarFuncSig::Locate2Args, &[null, s]);
+        let op = Expression::build(&ctx, op).unwrap();
+        assert_eq!(op.eval(&ctx, &[]).unwrap(), Datum::Null);
+    }
+
+    #[test]
+    fn test_in() {
+        let dec = |s: &str| Datum::Dec(s.parse::<Decimal>().unwrap());
+        let time = |s: &str| Datum::Time(Time::parse_utc_datetime(s, 0).unwrap());
+        let dur = |s: &str| Datum::Dur(Duration::parse(s.as_bytes(), 0).unwrap());
+        let json = |s: &str| Datum::Json(s.parse::<Json>().unwrap());
+        let bytes = |s: &str| Datum::Bytes(s.as_bytes().to_vec());
+        let cases = vec![
+            (ScalarFuncSig::InInt, Datum::I64(2), vec![Datum::I64(1), Datum::I64(2)], 1),
+            (ScalarFuncSig::InInt, Datum::I64(3), vec![Datum::I64(1), Datum::I64(2)], 0),
+            (ScalarFuncSig::InInt, Datum::I64(1), vec![Datum::Null, Datum::I64(1)], 1),
+            (ScalarFuncSig::InInt, Datum::I64(3), vec![Datum::I64(1), Datum::Null], -1),
+            (ScalarFuncSig::InInt, Datum::Null, vec![Datum::I64(1)], -1),
+            (ScalarFuncSig::InInt, Datum::U64(u64::MAX), vec![Datum::I64(-1)], 0),
+            (ScalarFuncSig::InInt, Datum::U64(u64::MAX), vec![Datum::U64(u64::MAX)], 1),
+            (ScalarFuncSig::InInt, Datum::U64(1), vec![Datum::I64(1)], 1),
+            (ScalarFuncSig::InString, bytes("b"), vec![bytes("a"), bytes("b")], 1),
+            (ScalarFuncSig::InString, bytes("B"), vec![bytes("a"), bytes("b")], 0),
+            (ScalarFuncSig::InString, bytes(""), vec![bytes("")], 1),
+            (ScalarFuncSig::InString, bytes(""), vec![Datum::Null], -1),
+            (ScalarFuncSig::InReal, Datum::F64(1.5), vec![Datum::F64(1.0), Datum::F64(1.5)], 1),
+            (ScalarFuncSig::InDecimal, dec("1.0"), vec![dec("1.00")], 1),
+            (ScalarFuncSig::InDecimal, dec("1.0"), vec![dec("1.1"), Datum::Null], -1),
+            (
+                ScalarFuncSig::InTime,
+                time("2017-01-01 00:00:00"),
+                vec![time("2017-01-02 00:00:00"), time("2017-01-01 00:00:00")],
+                1,
+            ),
+            (ScalarFuncSig::InDuration, dur("11:00:00"), vec![dur("10:00:00")], 0),
+            (ScalarFuncSig::InJson, json(r#"{"a": 1}"#), vec![json(r#"{"a": 1}"#)], 1),
+        ];
+        let ctx = StatementContext::default();
+        for (sig, target, list, exp) in cases {
+            let exp = match exp {
+                -1 => Datum::Null,
+                i => Datum::I64(i),
+            };
+            let mut row = vec![target];
+            row.extend(list);
+            // the list is made of constants, then of columns with the same types.
+            let consts: Vec<_> = row.iter().cloned().map(datum_expr).collect();
+            let op = Expression::build(&ctx, fncall_expr(sig, &consts)).unwrap();
+            assert_eq!(op.eval(&ctx, &[]).unwrap(), exp, "{:?} {:?}", sig, row);
+
+            let cols: Vec<_> = consts
+                .into_iter()
+                .enumerate()
+                .map(|(i, mut e)| {
+                    e.set_tp(ExprType::ColumnRef);
+                    e.set_val(col_expr(i as i64).take_val());
+                    e
+                })
+                .collect();
+            let op = Expression::build(&ctx, fncall_expr(sig, &cols)).unwrap();
+            assert_eq!(op.eval(&ctx, &row).unwrap(), exp, "{:?} {:?}", sig, row);
+        }
+    }
+
+    #[test]
+    fn test_greatest_least() {
+        let dec = |s: &str| Datum::Dec(s.parse::<Decimal>().unwrap());
+        let time = |s: &str| Datum::Time(Time::parse_utc_datetime(s, 0).unwrap());
+        let bytes = |s: &str| Datum::Bytes(s.as_bytes().to_vec());
+        let cases = vec![
+            (
+                ScalarFuncSig::GreatestInt,
+                ScalarFuncSig::LeastInt,
+                vec![Datum::I64(2), Datum::I64(-3), Datum::I64(1)],
+                Datum::I64(2),
+                Datum::I64(-3),
+            ),
+            (
+                ScalarFuncSig::GreatestReal,
+                ScalarFuncSig::LeastReal,
+                vec![Datum::F64(1.5), Datum::F64(-0.5)],
+                Datum::F64(1.5),
+                Datum::F64(-0.5),
+            ),
+            (
+                ScalarFuncSig::GreatestDecimal,
+                ScalarFuncSig::LeastDecimal,
+                vec![dec("1.10"), dec("1.2"), dec("-1")],
+                dec("1.2"),
+                dec("-1"),
+            ),
+            (
+                ScalarFuncSig::GreatestString,
+                ScalarFuncSig::LeastString,
+                vec![bytes("ab"), bytes("b"), bytes("B")],
+                bytes("b"),
+                bytes("B"),
+            ),
+            (
+                ScalarFuncSig::GreatestTime,
+                ScalarFuncSig::LeastTime,
+                vec![time("2017-01-02 00:00:00"), time("2016-12-31 23:59:59")],
+                time("2017-01-02 00:00:00"),
+                time("2016-12-31 23:59:59"),
+            ),
+            (
+                ScalarFuncSig::GreatestInt,
+                ScalarFuncSig::LeastInt,
+                vec![Datum::I64(1), Datum::Null],
+                Datum::Null,
+                Datum::Null,
+            ),
+        ];
+        let ctx = StatementContext::default();
+        for (greatest, least, row, greatest_exp, least_exp) in cases {
+            let children: Vec<_> = (0..row.len()).map(|i| col_expr(i as i64)).collect();
+            for &(sig, ref exp) in &[(greatest, greatest_exp), (least, least_exp)] {
+                let op = Expression::build(&ctx, fncall_expr(sig, &children)).unwrap();
+                assert_eq!(op.eval(&ctx, &row).unwrap(), *exp, "{:?} {:?}", sig, row);
+            }
+        }
+
+        // unsigned ints beyond `i64::MAX` are the greatest.
+        let children = vec![col_expr(0), col_expr(1)];
+        let mut op = fncall_expr(ScalarFuncSig::GreatestInt, &children);
+        op.mut_field_type().set_flag(mysql::types::UNSIGNED_FLAG as u32);
+        let op = Expression::build(&ctx, op).unwrap();
+        let row = vec![Datum::U64(u64::MAX), Datum::U64(1)];
+        assert_eq!(op.eval(&ctx, &row).unwrap(), Datum::U64(u64::MAX));
+    }
 }
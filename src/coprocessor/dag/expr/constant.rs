@@ -37,11 +37,15 @@ impl Datum {
         }
     }
 
+    /// `as_decimal` also takes integers, which convert to decimals exactly, so a decimal
+    /// comparison on an integer operand needs no cast that may truncate it.
     #[inline]
     pub fn as_decimal(&self) -> Result<Option<Cow<Decimal>>> {
         match *self {
             Datum::Null => Ok(None),
             Datum::Dec(ref d) => Ok(Some(Cow::Borrowed(d))),
+            Datum::I64(i) => Ok(Some(Cow::Owned(Decimal::from(i)))),
+            Datum::U64(u) => Ok(Some(Cow::Owned(Decimal::from(u)))),
             _ => Err(box_err!("Can't eval_decimal from Datum")),
         }
     }
@@ -161,8 +165,16 @@ mod test {
 
         let expecteds = vec![
             EvalResults(None, None, None, None, None, None, None),
-            EvalResults(Some(-30), None, None, None, None, None, None),
-            EvalResults(Some(-1), None, None, None, None, None, None),
+            EvalResults(Some(-30), None, Some(Decimal::from(-30i64)), None, None, None, None),
+            EvalResults(
+                Some(-1),
+                None,
+                Some(Decimal::from(u64::MAX)),
+                None,
+                None,
+                None,
+                None,
+            ),
             EvalResults(None, Some(124.32), None, None, None, None, None),
             EvalResults(None, None, Some(dec.clone()), None, None, None, None),
             EvalResults(None, None, None, Some(s.clone()), None, None, None),
@@ -12,6 +12,7 @@
 // limitations under the License.
 
 use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::usize;
 
 use tipb::expression::ScalarFuncSig;
@@ -96,6 +97,16 @@ impl FnCall {
             ScalarFuncSig::DivideReal |
             ScalarFuncSig::BitAndSig |
             ScalarFuncSig::BitOrSig |
+            ScalarFuncSig::RoundWithFracReal |
+            ScalarFuncSig::RoundWithFracInt |
+            ScalarFuncSig::RoundWithFracDec |
+            ScalarFuncSig::Strcmp |
+            ScalarFuncSig::Locate2Args |
+            ScalarFuncSig::Repeat |
+            ScalarFuncSig::Substring2Args |
+            ScalarFuncSig::DateFormatSig |
+            ScalarFuncSig::MakeDateSig |
+            ScalarFuncSig::DateDiff |
             ScalarFuncSig::BitXorSig => (2, 2),
 
             ScalarFuncSig::CastIntAsInt |
@@ -178,8 +189,20 @@ impl FnCall {
             ScalarFuncSig::FloorIntToDec |
             ScalarFuncSig::FloorDecToDec |
             ScalarFuncSig::FloorDecToInt |
+            ScalarFuncSig::RoundReal |
+            ScalarFuncSig::RoundInt |
+            ScalarFuncSig::RoundDec |
+            ScalarFuncSig::CRC32 |
             ScalarFuncSig::JsonTypeSig |
             ScalarFuncSig::JsonUnquoteSig |
+            ScalarFuncSig::Space |
+            ScalarFuncSig::ASCII |
+            ScalarFuncSig::Ord |
+            ScalarFuncSig::Length |
+            ScalarFuncSig::Upper |
+            ScalarFuncSig::Lower |
+            ScalarFuncSig::Reverse |
+            ScalarFuncSig::ReverseBinary |
             ScalarFuncSig::BitNegSig => (1, 1),
 
             ScalarFuncSig::IfInt |
@@ -189,6 +212,9 @@ impl FnCall {
             ScalarFuncSig::IfTime |
             ScalarFuncSig::IfDuration |
             ScalarFuncSig::IfJson |
+            ScalarFuncSig::MakeTimeSig |
+            ScalarFuncSig::TimestampDiff |
+            ScalarFuncSig::Substring3Args |
             ScalarFuncSig::LikeSig => (3, 3),
 
             ScalarFuncSig::JsonArraySig | ScalarFuncSig::JsonObjectSig => (0, usize::MAX),
@@ -206,11 +232,30 @@ impl FnCall {
             ScalarFuncSig::CaseWhenJson |
             ScalarFuncSig::CaseWhenReal |
             ScalarFuncSig::CaseWhenString |
-            ScalarFuncSig::CaseWhenTime => (1, usize::MAX),
+            ScalarFuncSig::CaseWhenTime |
+            ScalarFuncSig::Concat => (1, usize::MAX),
 
             ScalarFuncSig::JsonExtractSig |
             ScalarFuncSig::JsonRemoveSig |
-            ScalarFuncSig::JsonMergeSig => (2, usize::MAX),
+            ScalarFuncSig::JsonMergeSig |
+            ScalarFuncSig::InInt |
+            ScalarFuncSig::InReal |
+            ScalarFuncSig::InDecimal |
+            ScalarFuncSig::InString |
+            ScalarFuncSig::InTime |
+            ScalarFuncSig::InDuration |
+            ScalarFuncSig::InJson |
+            ScalarFuncSig::GreatestInt |
+            ScalarFuncSig::GreatestReal |
+            ScalarFuncSig::GreatestDecimal |
+            ScalarFuncSig::GreatestString |
+            ScalarFuncSig::GreatestTime |
+            ScalarFuncSig::LeastInt |
+            ScalarFuncSig::LeastReal |
+            ScalarFuncSig::LeastDecimal |
+            ScalarFuncSig::LeastString |
+            ScalarFuncSig::LeastTime |
+            ScalarFuncSig::ConcatWS => (2, usize::MAX),
 
             ScalarFuncSig::JsonSetSig |
             ScalarFuncSig::JsonInsertSig |
@@ -447,6 +492,9 @@ dispatch_call! {
         CeilDecToInt => ceil_dec_to_int,
         FloorIntToInt => floor_int_to_int,
         FloorDecToInt => floor_dec_to_int,
+        RoundInt => round_int,
+        RoundWithFracInt => round_with_frac_int,
+        CRC32 => crc32,
 
         IfNullInt => if_null_int,
         IfInt => if_int,
@@ -455,6 +503,24 @@ dispatch_call! {
         CaseWhenInt => case_when_int,
 
         LikeSig => like,
+        Strcmp => strcmp,
+        Locate2Args => locate,
+        ASCII => ascii,
+        Ord => ord,
+        Length => length,
+        DateDiff => date_diff,
+        TimestampDiff => timestamp_diff,
+
+        InInt => in_int,
+        InReal => in_real,
+        InDecimal => in_decimal,
+        InString => in_string,
+        InTime => in_time,
+        InDuration => in_duration,
+        InJson => in_json,
+
+        GreatestInt => greatest_least_int Ordering::Greater,
+        LeastInt => greatest_least_int Ordering::Less,
 
         BitAndSig => bit_and,
         BitNegSig => bit_neg,
@@ -478,6 +544,11 @@ dispatch_call! {
         AbsReal => abs_real,
         CeilReal => ceil_real,
         FloorReal => floor_real,
+        RoundReal => round_real,
+        RoundWithFracReal => round_with_frac_real,
+
+        GreatestReal => greatest_least_real Ordering::Greater,
+        LeastReal => greatest_least_real Ordering::Less,
 
         IfNullReal => if_null_real,
         IfReal => if_real,
@@ -505,6 +576,11 @@ dispatch_call! {
         CeilIntToDec => cast_int_as_decimal,
         FloorDecToDec => floor_dec_to_dec,
         FloorIntToDec => cast_int_as_decimal,
+        RoundDec => round_dec,
+        RoundWithFracDec => round_with_frac_dec,
+
+        GreatestDecimal => greatest_least_decimal Ordering::Greater,
+        LeastDecimal => greatest_least_decimal Ordering::Less,
 
         IfNullDecimal => if_null_decimal,
         IfDecimal => if_decimal,
@@ -522,6 +598,9 @@ dispatch_call! {
         CastDurationAsString => cast_duration_as_str,
         CastJsonAsString => cast_json_as_str,
 
+        GreatestString => greatest_least_string Ordering::Greater,
+        LeastString => greatest_least_string Ordering::Less,
+
         IfNullString => if_null_string,
         IfString => if_string,
 
@@ -529,6 +608,19 @@ dispatch_call! {
         CaseWhenString => case_when_string,
         JsonTypeSig => json_type,
         JsonUnquoteSig => json_unquote,
+
+        DateFormatSig => date_format,
+
+        Concat => concat,
+        ConcatWS => concat_ws,
+        Space => space,
+        Repeat => repeat,
+        Reverse => reverse,
+        ReverseBinary => reverse_binary,
+        Substring2Args => substring,
+        Substring3Args => substring,
+        Upper => upper,
+        Lower => lower,
     }
     TIME_CALLS {
         CastIntAsTime => cast_int_as_time,
@@ -539,11 +631,16 @@ dispatch_call! {
         CastDurationAsTime => cast_duration_as_time,
         CastJsonAsTime => cast_json_as_time,
 
+        GreatestTime => greatest_least_time Ordering::Greater,
+        LeastTime => greatest_least_time Ordering::Less,
+
         IfNullTime => if_null_time,
         IfTime => if_time,
 
         CoalesceTime => coalesce_time,
         CaseWhenTime => case_when_time,
+
+        MakeDateSig => make_date,
     }
     DUR_CALLS {
         CastIntAsDuration => cast_int_as_duration,
@@ -559,6 +656,8 @@ dispatch_call! {
 
         CoalesceDuration => coalesce_duration,
         CaseWhenDuration => case_when_duration,
+
+        MakeTimeSig => make_time,
     }
     JSON_CALLS {
         CastIntAsJson => cast_int_as_json,
@@ -11,10 +11,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::i64;
+use std::{cmp, i64, i8};
 use std::borrow::Cow;
+use crc::crc32;
 use coprocessor::codec::Datum;
-use coprocessor::codec::mysql::Decimal;
+use coprocessor::codec::mysql::{self, Decimal, Res};
+use coprocessor::codec::mysql::decimal::RoundMode;
 use super::{Error, FnCall, Result, StatementContext};
 
 impl FnCall {
@@ -106,6 +108,112 @@ impl FnCall {
     pub fn floor_int_to_int(&self, ctx: &StatementContext, row: &[Datum]) -> Result<Option<i64>> {
         self.children[0].eval_int(ctx, row)
     }
+
+    #[inline]
+    pub fn round_real(&self, ctx: &StatementContext, row: &[Datum]) -> Result<Option<f64>> {
+        let n = try_opt!(self.children[0].eval_real(ctx, row));
+        Ok(Some(round_f64(ctx, n, 0)))
+    }
+
+    #[inline]
+    pub fn round_int(&self, ctx: &StatementContext, row: &[Datum]) -> Result<Option<i64>> {
+        self.children[0].eval_int(ctx, row)
+    }
+
+    #[inline]
+    pub fn round_dec<'a, 'b: 'a>(
+        &'b self,
+        ctx: &StatementContext,
+        row: &'a [Datum],
+    ) -> Result<Option<Cow<'a, Decimal>>> {
+        let d = try_opt!(self.children[0].eval_decimal(ctx, row));
+        let result: Result<Decimal> = round_dec(ctx, d.into_owned(), 0).into();
+        result.map(|t| Some(Cow::Owned(t)))
+    }
+
+    #[inline]
+    pub fn round_with_frac_real(
+        &self,
+        ctx: &StatementContext,
+        row: &[Datum],
+    ) -> Result<Option<f64>> {
+        let n = try_opt!(self.children[0].eval_real(ctx, row));
+        let frac = try_opt!(self.children[1].eval_int(ctx, row));
+        Ok(Some(round_f64(ctx, n, frac)))
+    }
+
+    #[inline]
+    pub fn round_with_frac_int(
+        &self,
+        ctx: &StatementContext,
+        row: &[Datum],
+    ) -> Result<Option<i64>> {
+        let n = try_opt!(self.children[0].eval_int(ctx, row));
+        let frac = try_opt!(self.children[1].eval_int(ctx, row));
+        if frac >= 0 {
+            return Ok(Some(n));
+        }
+        // round through decimal, so an overflow is reported rather than wrapped.
+        if mysql::has_unsigned_flag(self.children[0].get_tp().get_flag()) {
+            let d: Result<u64> = round_dec(ctx, Decimal::from(n as u64), frac)
+                .unwrap()
+                .as_u64()
+                .into();
+            d.map(|u| Some(u as i64))
+        } else {
+            let d: Result<i64> = round_dec(ctx, Decimal::from(n), frac).unwrap().as_i64().into();
+            d.map(Some)
+        }
+    }
+
+    #[inline]
+    pub fn round_with_frac_dec<'a, 'b: 'a>(
+        &'b self,
+        ctx: &StatementContext,
+        row: &'a [Datum],
+    ) -> Result<Option<Cow<'a, Decimal>>> {
+        let d = try_opt!(self.children[0].eval_decimal(ctx, row));
+        let frac = try_opt!(self.children[1].eval_int(ctx, row));
+        let result: Result<Decimal> = round_dec(ctx, d.into_owned(), frac).into();
+        result.map(|t| Some(Cow::Owned(t)))
+    }
+
+    #[inline]
+    pub fn crc32(&self, ctx: &StatementContext, row: &[Datum]) -> Result<Option<i64>> {
+        let s = try_opt!(self.children[0].eval_string(ctx, row));
+        Ok(Some(i64::from(crc32::checksum_ieee(&s))))
+    }
+}
+
+// round_dec rounds `d` to `frac` digits, a tie is rounded to the even neighbour if
+// `ctx.round_half_even` is set, or away from zero otherwise.
+fn round_dec(ctx: &StatementContext, d: Decimal, frac: i64) -> Res<Decimal> {
+    let frac = cmp::max(cmp::min(frac, i8::MAX as i64), i8::MIN as i64) as i8;
+    if ctx.round_half_even {
+        d.round_half_to_even(frac)
+    } else {
+        d.round(frac, RoundMode::HalfEven)
+    }
+}
+
+// round_f64 rounds `f` to `frac` digits like `round_dec`. Very large `frac` keeps `f`
+// unchanged, while very small one rounds it to zero.
+fn round_f64(ctx: &StatementContext, f: f64, frac: i64) -> f64 {
+    let frac = cmp::max(cmp::min(frac, 308), -308) as i32;
+    let digits = 10f64.powi(frac.abs());
+    let scaled = if frac >= 0 { f * digits } else { f / digits };
+    if !scaled.is_finite() {
+        return f;
+    }
+    let mut rounded = scaled.round();
+    if ctx.round_half_even && (rounded - scaled).abs() == 0.5 && rounded % 2.0 != 0.0 {
+        rounded -= scaled.signum();
+    }
+    if frac >= 0 {
+        rounded / digits
+    } else {
+        rounded * digits
+    }
 }
 
 #[cfg(test)]
@@ -295,4 +403,113 @@ mod test {
             assert_eq!(got, exp);
         }
     }
+
+    #[test]
+    fn test_round_half_even() {
+        // the function, the arguments, rounded half up, and rounded half to even.
+        let tests = vec![
+            (
+                ScalarFuncSig::RoundReal,
+                vec![Datum::F64(2.5)],
+                Datum::F64(3f64),
+                Datum::F64(2f64),
+            ),
+            (
+                ScalarFuncSig::RoundReal,
+                vec![Datum::F64(-2.5)],
+                Datum::F64(-3f64),
+                Datum::F64(-2f64),
+            ),
+            (
+                ScalarFuncSig::RoundReal,
+                vec![Datum::F64(3.5)],
+                Datum::F64(4f64),
+                Datum::F64(4f64),
+            ),
+            (
+                ScalarFuncSig::RoundReal,
+                vec![Datum::F64(2.6)],
+                Datum::F64(3f64),
+                Datum::F64(3f64),
+            ),
+            (
+                ScalarFuncSig::RoundWithFracReal,
+                vec![Datum::F64(1.25), Datum::I64(1)],
+                Datum::F64(1.3),
+                Datum::F64(1.2),
+            ),
+            (
+                ScalarFuncSig::RoundInt,
+                vec![Datum::I64(25)],
+                Datum::I64(25),
+                Datum::I64(25),
+            ),
+            (
+                ScalarFuncSig::RoundWithFracInt,
+                vec![Datum::I64(25), Datum::I64(-1)],
+                Datum::I64(30),
+                Datum::I64(20),
+            ),
+            (
+                ScalarFuncSig::RoundWithFracInt,
+                vec![Datum::I64(35), Datum::I64(-1)],
+                Datum::I64(40),
+                Datum::I64(40),
+            ),
+            (
+                ScalarFuncSig::RoundDec,
+                vec![str2dec("2.5")],
+                str2dec("3"),
+                str2dec("2"),
+            ),
+            (
+                ScalarFuncSig::RoundDec,
+                vec![str2dec("-3.5")],
+                str2dec("-4"),
+                str2dec("-4"),
+            ),
+            (
+                ScalarFuncSig::RoundWithFracDec,
+                vec![str2dec("1.25"), Datum::I64(1)],
+                str2dec("1.3"),
+                str2dec("1.2"),
+            ),
+            (
+                ScalarFuncSig::RoundWithFracDec,
+                vec![str2dec("1.25"), Datum::Null],
+                Datum::Null,
+                Datum::Null,
+            ),
+        ];
+        let half_up = StatementContext::default();
+        let mut half_even = StatementContext::default();
+        half_even.round_half_even = true;
+        for (sig, args, half_up_exp, half_even_exp) in tests {
+            let args: Vec<_> = args.into_iter().map(datum_expr).collect();
+            let mut op = Expression::build(&half_up, fncall_expr(sig, &args)).unwrap();
+            if sig == ScalarFuncSig::RoundDec || sig == ScalarFuncSig::RoundWithFracDec {
+                op.mut_tp().set_flen(convert::UNSPECIFIED_LENGTH);
+                op.mut_tp().set_decimal(convert::UNSPECIFIED_LENGTH);
+            }
+            assert_eq!(op.eval(&half_up, &[]).unwrap(), half_up_exp);
+            assert_eq!(op.eval(&half_even, &[]).unwrap(), half_even_exp);
+        }
+    }
+
+    #[test]
+    fn test_crc32() {
+        let tests = vec![
+            (Datum::Bytes(b"name:3".to_vec()), Datum::U64(977733646)),
+            (Datum::Bytes(b"name:0".to_vec()), Datum::U64(2739820980)),
+            (Datum::Bytes(vec![]), Datum::U64(0)),
+            (Datum::Null, Datum::Null),
+        ];
+        let ctx = StatementContext::default();
+        for (arg, exp) in tests {
+            let mut f = fncall_expr(ScalarFuncSig::CRC32, &[datum_expr(arg)]);
+            f.mut_field_type().set_flag(types::UNSIGNED_FLAG as u32);
+            let op = Expression::build(&ctx, f).unwrap();
+            assert_eq!(op.eval(&ctx, &[]).unwrap(), exp);
+        }
+    }
 }
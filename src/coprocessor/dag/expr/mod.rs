@@ -17,6 +17,8 @@ mod fncall;
 mod builtin_cast;
 mod builtin_control;
 mod builtin_op;
+mod builtin_string;
+mod builtin_time;
 mod compare;
 mod arithmetic;
 mod math;
@@ -36,6 +38,7 @@ use coprocessor::codec::mysql::{charset, types};
 use coprocessor::codec::Datum;
 use util;
 use util::codec::number::NumberDecoder;
+use util::collections::HashSet;
 use util::codec::Error as CError;
 
 pub use coprocessor::select::xeval::EvalContext as StatementContext;
@@ -132,6 +135,9 @@ pub struct FnCall {
     sig: ScalarFuncSig,
     children: Vec<Expression>,
     tp: FieldType,
+    // the keys of the values of an IN list made of constants, so a row only needs a
+    // lookup instead of comparing it with every value.
+    in_set: Option<HashSet<Vec<u8>>>,
 }
 
 impl Expression {
@@ -143,7 +149,7 @@ impl Expression {
     }
 
     #[inline]
-    fn get_tp(&self) -> &FieldType {
+    pub fn get_tp(&self) -> &FieldType {
         match *self {
             Expression::Constant(ref c) => &c.tp,
             Expression::ColumnRef(ref c) => &c.tp,
@@ -277,6 +283,9 @@ impl Expression {
 }
 
 impl Expression {
+    /// `eval` evaluates the expression over `row`. An operator yields NULL once any of
+    /// its operands is NULL, except that `0 AND NULL` is 0, `1 OR NULL` is 1, and the
+    /// NULL-safe equal compares NULL as a value, so `NULL <=> NULL` is 1.
     pub fn eval(&self, ctx: &StatementContext, row: &[Datum]) -> Result<Datum> {
         match *self {
             Expression::Constant(ref constant) => Ok(constant.eval()),
@@ -347,12 +356,14 @@ impl Expression {
                     .into_iter()
                     .map(|child| Expression::build(ctx, child))
                     .collect::<Result<Vec<_>>>()
-                    .map(|children| {
-                        Expression::ScalarFn(FnCall {
+                    .and_then(|children| {
+                        let in_set = FnCall::build_in_set(ctx, expr.get_sig(), &children)?;
+                        Ok(Expression::ScalarFn(FnCall {
                             sig: expr.get_sig(),
                             children: children,
                             tp: tp,
-                        })
+                            in_set: in_set,
+                        }))
                     })
             }
             ExprType::ColumnRef => {
@@ -373,7 +384,7 @@ mod test {
     use std::{i64, u64};
     use coprocessor::codec::{convert, Datum};
     use coprocessor::codec::mysql::{types, Decimal, Duration, Json, Time};
-    use coprocessor::select::xeval::evaluator::test::col_expr;
+    use coprocessor::select::xeval::evaluator::test::{col_expr, datum_expr};
     use tipb::expression::{Expr, ExprType, FieldType, ScalarFuncSig};
     use super::{Error, Expression, StatementContext};
 
@@ -478,4 +489,112 @@ mod test {
             assert_eq!(res, exp);
         }
     }
+
+    #[test]
+    fn test_null_propagation() {
+        let int = Datum::I64(1);
+        let real = Datum::F64(1.0);
+        let dec = str2dec("1.0");
+        let bytes = Datum::Bytes(b"1".to_vec());
+        let dur = Datum::Dur(Duration::zero());
+        let json = Datum::Json(Json::I64(1));
+        // Every binary operator below must yield NULL once any operand is NULL.
+        let cases = vec![
+            (ScalarFuncSig::PlusInt, int.clone()),
+            (ScalarFuncSig::MinusInt, int.clone()),
+            (ScalarFuncSig::MultiplyInt, int.clone()),
+            (ScalarFuncSig::PlusReal, real.clone()),
+            (ScalarFuncSig::MinusReal, real.clone()),
+            (ScalarFuncSig::MultiplyReal, real.clone()),
+            (ScalarFuncSig::DivideReal, real.clone()),
+            (ScalarFuncSig::PlusDecimal, dec.clone()),
+            (ScalarFuncSig::MinusDecimal, dec.clone()),
+            (ScalarFuncSig::MultiplyDecimal, dec.clone()),
+            (ScalarFuncSig::DivideDecimal, dec.clone()),
+            (ScalarFuncSig::LTInt, int.clone()),
+            (ScalarFuncSig::LEInt, int.clone()),
+            (ScalarFuncSig::GTInt, int.clone()),
+            (ScalarFuncSig::GEInt, int.clone()),
+            (ScalarFuncSig::EQInt, int.clone()),
+            (ScalarFuncSig::NEInt, int.clone()),
+            (ScalarFuncSig::LTReal, real.clone()),
+            (ScalarFuncSig::LEReal, real.clone()),
+            (ScalarFuncSig::GTReal, real.clone()),
+            (ScalarFuncSig::GEReal, real.clone()),
+            (ScalarFuncSig::EQReal, real.clone()),
+            (ScalarFuncSig::NEReal, real.clone()),
+            (ScalarFuncSig::LTDecimal, dec.clone()),
+            (ScalarFuncSig::LEDecimal, dec.clone()),
+            (ScalarFuncSig::GTDecimal, dec.clone()),
+            (ScalarFuncSig::GEDecimal, dec.clone()),
+            (ScalarFuncSig::EQDecimal, dec.clone()),
+            (ScalarFuncSig::NEDecimal, dec.clone()),
+            (ScalarFuncSig::LTString, bytes.clone()),
+            (ScalarFuncSig::LEString, bytes.clone()),
+            (ScalarFuncSig::GTString, bytes.clone()),
+            (ScalarFuncSig::GEString, bytes.clone()),
+            (ScalarFuncSig::EQString, bytes.clone()),
+            (ScalarFuncSig::NEString, bytes.clone()),
+            (ScalarFuncSig::LTDuration, dur.clone()),
+            (ScalarFuncSig::LEDuration, dur.clone()),
+            (ScalarFuncSig::GTDuration, dur.clone()),
+            (ScalarFuncSig::GEDuration, dur.clone()),
+            (ScalarFuncSig::EQDuration, dur.clone()),
+            (ScalarFuncSig::NEDuration, dur.clone()),
+            (ScalarFuncSig::LTJson, json.clone()),
+            (ScalarFuncSig::LEJson, json.clone()),
+            (ScalarFuncSig::GTJson, json.clone()),
+            (ScalarFuncSig::GEJson, json.clone()),
+            (ScalarFuncSig::EQJson, json.clone()),
+            (ScalarFuncSig::NEJson, json.clone()),
+            (ScalarFuncSig::LogicalAnd, int.clone()),
+            (ScalarFuncSig::LogicalOr, Datum::I64(0)),
+            (ScalarFuncSig::LogicalXor, int.clone()),
+            (ScalarFuncSig::BitAndSig, int.clone()),
+            (ScalarFuncSig::BitOrSig, int.clone()),
+            (ScalarFuncSig::BitXorSig, int.clone()),
+        ];
+        let ctx = StatementContext::default();
+        for (sig, arg) in cases {
+            for args in &[
+                [Datum::Null, arg.clone()],
+                [arg.clone(), Datum::Null],
+                [Datum::Null, Datum::Null],
+            ] {
+                let children = [datum_expr(args[0].clone()), datum_expr(args[1].clone())];
+                let e = Expression::build(&ctx, fncall_expr(sig, &children)).unwrap();
+                let res = e.eval(&ctx, &[]).unwrap();
+                assert_eq!(res, Datum::Null, "{:?} {:?}", sig, args);
+            }
+        }
+
+        // The exceptions documented on `Expression::eval`: short-circuit logic and
+        // NULL-safe equal.
+        let cases = vec![
+            (ScalarFuncSig::LogicalAnd, Datum::I64(0), Datum::I64(0)),
+            (ScalarFuncSig::LogicalOr, Datum::I64(1), Datum::I64(1)),
+            (ScalarFuncSig::NullEQInt, int.clone(), Datum::I64(0)),
+            (ScalarFuncSig::NullEQReal, real.clone(), Datum::I64(0)),
+            (ScalarFuncSig::NullEQDecimal, dec.clone(), Datum::I64(0)),
+            (ScalarFuncSig::NullEQString, bytes.clone(), Datum::I64(0)),
+            (ScalarFuncSig::NullEQDuration, dur.clone(), Datum::I64(0)),
+            (ScalarFuncSig::NullEQJson, json.clone(), Datum::I64(0)),
+        ];
+        for (sig, arg, exp) in cases {
+            for args in &[[Datum::Null, arg.clone()], [arg.clone(), Datum::Null]] {
+                let children = [datum_expr(args[0].clone()), datum_expr(args[1].clone())];
+                let e = Expression::build(&ctx, fncall_expr(sig, &children)).unwrap();
+                let res = e.eval(&ctx, &[]).unwrap();
+                assert_eq!(res, exp, "{:?} {:?}", sig, args);
+            }
+            let children = [datum_expr(Datum::Null), datum_expr(Datum::Null)];
+            let e = Expression::build(&ctx, fncall_expr(sig, &children)).unwrap();
+            let res = e.eval(&ctx, &[]).unwrap();
+            if sig == ScalarFuncSig::LogicalAnd || sig == ScalarFuncSig::LogicalOr {
+                assert_eq!(res, Datum::Null, "{:?}", sig);
+            } else {
+                assert_eq!(res, Datum::I64(1), "{:?}", sig);
+            }
+        }
+    }
 }
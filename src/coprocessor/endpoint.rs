@@ -16,6 +16,7 @@ use std::time::Duration;
 use std::rc::Rc;
 use std::fmt::{self, Debug, Display, Formatter};
 use std::mem;
+use std::sync::{Arc, Mutex};
 
 use tipb::select::{self, Chunk, DAGRequest, SelectRequest};
 use tipb::analyze::{AnalyzeReq, AnalyzeType};
@@ -31,13 +32,16 @@ use util::worker::{BatchRunnable, FutureScheduler, Scheduler};
 use util::collections::HashMap;
 use util::threadpool::{Context, ContextFactory, ThreadPool, ThreadPoolBuilder};
 use server::{Config, OnResponse};
-use storage::{self, engine, Engine, FlowStatistics, Snapshot, Statistics, StatisticsSummary};
+use storage::{self, engine, Engine, FlowStatistics, Key, Snapshot, Statistics,
+              StatisticsSummary};
+use raftstore::Error as RaftStoreError;
 use storage::engine::Error as EngineError;
 use pd::PdTask;
 
 use super::codec::mysql;
 use super::codec::datum::Datum;
 use super::select::select::SelectContext;
+use super::cache::ResultCache;
 use super::select::xeval::EvalContext;
 use super::dag::DAGContext;
 use super::statistics::analyze::AnalyzeContext;
@@ -49,6 +53,8 @@ pub const REQ_TYPE_INDEX: i64 = 102;
 pub const REQ_TYPE_DAG: i64 = 103;
 pub const REQ_TYPE_ANALYZE: i64 = 104;
 pub const BATCH_ROW_COUNT: usize = 64;
+/// The number of rows read between two checks of the request deadline.
+pub const DEADLINE_CHECK_ROWS: usize = 256;
 
 // If a request has been handled for more than 60 seconds, the client should
 // be timeout already, so it can be safely aborted.
@@ -73,6 +79,15 @@ pub struct Host {
     low_priority_pool: ThreadPool<CopContext>,
     high_priority_pool: ThreadPool<CopContext>,
     max_running_task_count: usize,
+    max_warning_cnt: usize,
+    group_concat_max_len: usize,
+    executor_timeout: Option<Duration>,
+    scan_bytes_limit: Option<u64>,
+    memory_quota: Option<usize>,
+    max_ranges: Option<usize>,
+    cache: Option<Arc<Mutex<ResultCache>>>,
+    // versions older than it may have been collected.
+    gc_safe_point: u64,
 }
 
 pub type CopRequestStatistics = HashMap<u64, FlowStatistics>;
@@ -171,6 +186,36 @@ impl Host {
             reqs: HashMap::default(),
             last_req_id: 0,
             max_running_task_count: cfg.end_point_max_tasks,
+            max_warning_cnt: cfg.end_point_max_warning_cnt,
+            group_concat_max_len: cfg.end_point_group_concat_max_len.0 as usize,
+            executor_timeout: if cfg.end_point_executor_timeout.0 == Duration::default() {
+                None
+            } else {
+                Some(cfg.end_point_executor_timeout.0)
+            },
+            scan_bytes_limit: if cfg.end_point_scan_bytes_limit.0 == 0 {
+                None
+            } else {
+                Some(cfg.end_point_scan_bytes_limit.0)
+            },
+            memory_quota: if cfg.end_point_memory_quota.0 == 0 {
+                None
+            } else {
+                Some(cfg.end_point_memory_quota.0 as usize)
+            },
+            max_ranges: if cfg.end_point_max_ranges == 0 {
+                None
+            } else {
+                Some(cfg.end_point_max_ranges)
+            },
+            cache: if cfg.end_point_result_cache_size.0 == 0 {
+                None
+            } else {
+                Some(Arc::new(Mutex::new(
+                    ResultCache::new(cfg.end_point_result_cache_size.0 as usize),
+                )))
+            },
+            gc_safe_point: 0,
             pool: ThreadPoolBuilder::new(
                 thd_name!("endpoint-normal-pool"),
                 CopContextFactory { sender: r.clone() },
@@ -179,12 +224,12 @@ impl Host {
             low_priority_pool: ThreadPoolBuilder::new(
                 thd_name!("endpoint-low-pool"),
                 CopContextFactory { sender: r.clone() },
-            ).thread_count(cfg.end_point_concurrency)
+            ).thread_count(cfg.end_point_low_concurrency)
                 .build(),
             high_priority_pool: ThreadPoolBuilder::new(
                 thd_name!("endpoint-high-pool"),
                 CopContextFactory { sender: r.clone() },
-            ).thread_count(cfg.end_point_concurrency)
+            ).thread_count(cfg.end_point_high_concurrency)
                 .build(),
         }
     }
@@ -217,7 +262,15 @@ impl Host {
             COPR_PENDING_REQS
                 .with_label_values(&[type_str, pri_str])
                 .add(1.0);
-            let end_point = TiDbEndPoint::new(snap.clone());
+            let end_point = TiDbEndPoint::new(
+                snap.clone(),
+                self.max_warning_cnt,
+                self.group_concat_max_len,
+                self.executor_timeout,
+                self.scan_bytes_limit,
+                self.memory_quota,
+                self.cache.clone(),
+            );
 
             let pool = match pri {
                 CommandPri::Low => &mut self.low_priority_pool,
@@ -242,6 +295,7 @@ pub enum Task {
     SnapRes(u64, engine::Result<Box<Snapshot>>),
     BatchSnapRes(Vec<(u64, engine::Result<Box<Snapshot>>)>),
     RetryRequests(Vec<u64>),
+    UpdateSafePoint(u64),
 }
 
 impl Display for Task {
@@ -251,6 +305,7 @@ impl Display for Task {
             Task::SnapRes(req_id, _) => write!(f, "snapres [{}]", req_id),
             Task::BatchSnapRes(_) => write!(f, "batch snapres"),
             Task::RetryRequests(ref retry) => write!(f, "retry on task ids: {:?}", retry),
+            Task::UpdateSafePoint(safe_point) => write!(f, "update safe point {}", safe_point),
         }
     }
 }
@@ -367,11 +422,42 @@ impl RequestTask {
         }
     }
 
+    /// Bounds the time the request may take, from its creation until it's responded,
+    /// to `timeout` instead of `REQUEST_MAX_HANDLE_SECS`. A request running past it is
+    /// aborted with an outdated error.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.ctx.deadline = self.timer + timeout;
+    }
+
     #[inline]
     fn check_outdated(&self) -> Result<()> {
         self.ctx.check_if_outdated()
     }
 
+    // check_safe_point fails if the versions the request reads may have been collected.
+    fn check_safe_point(&self, safe_point: u64) -> Result<()> {
+        match self.start_ts {
+            Some(start_ts) if start_ts < safe_point => {
+                Err(Error::StaleSnapshot(start_ts, safe_point))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // check_ranges fails if the request carries more key ranges than `max_ranges`, before
+    // any iterator is built for them.
+    fn check_ranges(&self, max_ranges: Option<usize>) -> Result<()> {
+        let count = self.req.get_ranges().len();
+        match max_ranges {
+            Some(max) if count > max => Err(box_err!(
+                "request has {} key ranges, more than the limit {}",
+                count,
+                max
+            )),
+            _ => Ok(()),
+        }
+    }
+
     fn stop_record_waiting(&mut self) {
         if self.wait_time.is_some() {
             return;
@@ -449,6 +535,14 @@ impl BatchRunnable<Task> for Host {
                         on_error(e, req);
                         continue;
                     }
+                    if let Err(e) = req.check_safe_point(self.gc_safe_point) {
+                        on_error(e, req);
+                        continue;
+                    }
+                    if let Err(e) = req.check_ranges(self.max_ranges) {
+                        on_error(e, req);
+                        continue;
+                    }
                     let key = {
                         let ctx = req.req.get_context();
                         (
@@ -478,6 +572,9 @@ impl BatchRunnable<Task> for Host {
                         self.reqs.insert(id, reqs);
                     }
                 },
+                Task::UpdateSafePoint(safe_point) => if safe_point > self.gc_safe_point {
+                    self.gc_safe_point = safe_point;
+                },
             }
         }
 
@@ -581,6 +678,10 @@ fn err_resp(e: Error) -> Response {
             errorpb.set_server_is_busy(server_is_busy_err);
             resp.set_region_error(errorpb);
         }
+        Error::StaleSnapshot(..) => {
+            resp.set_other_error(format!("{}", e));
+            COPR_REQ_ERROR.with_label_values(&["stale_snapshot"]).inc();
+        }
         Error::Other(_) => {
             resp.set_other_error(format!("{}", e));
             COPR_REQ_ERROR.with_label_values(&["other"]).inc();
@@ -602,19 +703,47 @@ fn notify_batch_failed<E: Into<Error> + Debug>(e: E, reqs: Vec<RequestTask>) {
     }
 }
 
-fn respond(resp: Response, mut t: RequestTask) -> Statistics {
+fn respond(mut resp: Response, mut t: RequestTask) -> Statistics {
     t.stop_record_handling();
+    // the keys a request read tell its scan amplification, like an index scan against
+    // a table scan, so they are returned when asked for.
+    if t.req.get_context().get_scan_detail() {
+        resp.mut_exec_details()
+            .set_scan_detail(t.statistics.scan_detail());
+    }
     (t.on_resp)(resp);
     t.statistics
 }
 
 pub struct TiDbEndPoint {
     snap: Box<Snapshot>,
+    max_warning_cnt: usize,
+    group_concat_max_len: usize,
+    executor_timeout: Option<Duration>,
+    scan_bytes_limit: Option<u64>,
+    memory_quota: Option<usize>,
+    cache: Option<Arc<Mutex<ResultCache>>>,
 }
 
 impl TiDbEndPoint {
-    pub fn new(snap: Box<Snapshot>) -> TiDbEndPoint {
-        TiDbEndPoint { snap: snap }
+    pub fn new(
+        snap: Box<Snapshot>,
+        max_warning_cnt: usize,
+        group_concat_max_len: usize,
+        executor_timeout: Option<Duration>,
+        scan_bytes_limit: Option<u64>,
+        memory_quota: Option<usize>,
+        cache: Option<Arc<Mutex<ResultCache>>>,
+    ) -> TiDbEndPoint {
+        TiDbEndPoint {
+            snap: snap,
+            max_warning_cnt: max_warning_cnt,
+            group_concat_max_len: group_concat_max_len,
+            executor_timeout: executor_timeout,
+            scan_bytes_limit: scan_bytes_limit,
+            memory_quota: memory_quota,
+            cache: cache,
+        }
     }
 }
 
@@ -624,6 +753,16 @@ impl TiDbEndPoint {
         if let Err(e) = t.check_outdated() {
             return on_error(e, t);
         }
+        if let Err(e) = check_ranges_in_region(self.snap.as_ref(), t.req.get_ranges()) {
+            return on_error(e, t);
+        }
+        let key = self.cache.as_ref().and_then(|_| ResultCache::cache_key(&t.req));
+        if let Some(ref key) = key {
+            let cached = self.cache.as_ref().unwrap().lock().unwrap().get(key);
+            if let Some(resp) = cached {
+                return respond(resp, t);
+            }
+        }
         let resp = match t.cop_req.take().unwrap() {
             Ok(CopRequest::Select(sel)) => self.handle_select(sel, &mut t),
             Ok(CopRequest::DAG(dag)) => self.handle_dag(dag, &mut t),
@@ -631,7 +770,12 @@ impl TiDbEndPoint {
             Err(err) => Err(err),
         };
         match resp {
-            Ok(r) => respond(r, t),
+            Ok(r) => {
+                if let Some(key) = key {
+                    self.cache.as_ref().unwrap().lock().unwrap().insert(key, &r);
+                }
+                respond(r, t)
+            }
             Err(e) => on_error(e, t),
         }
     }
@@ -644,11 +788,23 @@ impl TiDbEndPoint {
 
     pub fn handle_dag(&self, dag: DAGRequest, t: &mut RequestTask) -> Result<Response> {
         let ranges = t.req.get_ranges().to_vec();
-        let eval_ctx = Rc::new(box_try!(EvalContext::new(
+        let mut eval_ctx = box_try!(EvalContext::new(
             dag.get_time_zone_offset(),
             dag.get_flags()
-        )));
-        let ctx = DAGContext::new(dag, ranges, self.snap.as_ref(), eval_ctx.clone(), &t.ctx);
+        ));
+        eval_ctx.set_max_warning_cnt(self.max_warning_cnt);
+        eval_ctx.set_group_concat_max_len(self.group_concat_max_len);
+        let eval_ctx = Rc::new(eval_ctx);
+        let mut ctx = DAGContext::new(dag, ranges, self.snap.as_ref(), eval_ctx.clone(), &t.ctx);
+        if let Some(timeout) = self.executor_timeout {
+            ctx.set_executor_timeout(timeout);
+        }
+        if let Some(limit) = self.scan_bytes_limit {
+            ctx.set_scan_bytes_limit(limit);
+        }
+        if let Some(quota) = self.memory_quota {
+            ctx.set_memory_quota(quota);
+        }
         ctx.handle_request(&mut t.statistics)
     }
 
@@ -672,6 +828,13 @@ pub fn to_pb_error(err: &Error) -> select::Error {
     e
 }
 
+pub fn to_pb_warning(msg: &str) -> select::Error {
+    let mut e = select::Error::new();
+    e.set_code(DEFAULT_ERROR_CODE);
+    e.set_msg(msg.to_owned());
+    e
+}
+
 pub fn prefix_next(key: &[u8]) -> Vec<u8> {
     let mut nk = key.to_vec();
     if nk.is_empty() {
@@ -700,6 +863,31 @@ pub fn is_point(range: &KeyRange) -> bool {
     range.get_end() == &*prefix_next(range.get_start())
 }
 
+/// `check_ranges_in_region` fails with a region error if any of `ranges` reaches out of
+/// the region `snap` is bounded to, like a range built before the region was split. Its
+/// rows out of the region would be missing from the result otherwise.
+pub fn check_ranges_in_region(snap: &Snapshot, ranges: &[KeyRange]) -> Result<()> {
+    let region = match snap.get_region() {
+        Some(region) => region,
+        None => return Ok(()),
+    };
+    let (start_key, end_key) = (region.get_start_key(), region.get_end_key());
+    for range in ranges {
+        if Key::from_raw(range.get_start()).encoded().as_slice() < start_key {
+            let e = RaftStoreError::KeyNotInRegion(range.get_start().to_vec(), region.clone());
+            return Err(Error::Region(e.into()));
+        }
+        if !end_key.is_empty() &&
+            (range.get_end().is_empty() ||
+                Key::from_raw(range.get_end()).encoded().as_slice() > end_key)
+        {
+            let e = RaftStoreError::KeyNotInRegion(range.get_end().to_vec(), region.clone());
+            return Err(Error::Region(e.into()));
+        }
+    }
+    Ok(())
+}
+
 #[inline]
 pub fn get_pk(col: &ColumnInfo, h: i64) -> Datum {
     if mysql::has_unsigned_flag(col.get_flag() as u64) {
@@ -746,7 +934,15 @@ mod tests {
     use std::time::Duration;
 
     use kvproto::coprocessor::Request;
+    use kvproto::metapb::Region;
+    use tempdir::TempDir;
+    use tipb::executor::{Executor, TableScan};
+
+    use raftstore::coprocessor::RegionSnapshot;
+    use util::rocksdb as rocksdb_util;
 
+    use storage::ALL_CFS;
+    use util::config::ReadableSize;
     use util::worker::{FutureWorker, Worker};
     use util::time::Instant;
 
@@ -818,4 +1014,230 @@ mod tests {
         }
         panic!("suppose to get ServerIsBusy error.");
     }
+
+    #[test]
+    fn test_pool_size_per_priority() {
+        let mut worker = Worker::new("test-endpoint");
+        let engine = engine::new_local_engine(TEMP_DIR, &[]).unwrap();
+        let mut cfg = Config::default();
+        cfg.end_point_concurrency = 1;
+        cfg.end_point_high_concurrency = 2;
+        cfg.end_point_low_concurrency = 1;
+        let pd_worker = FutureWorker::new("test-pd-worker");
+        let end_point = Host::new(engine, worker.scheduler(), &cfg, pd_worker.scheduler());
+        worker.start_batch(end_point, 30).unwrap();
+
+        // every low priority request holds its thread until it's released.
+        let (started_tx, started_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let release_rx = Arc::new(Mutex::new(release_rx));
+        for _ in 0..3 {
+            let mut req = Request::new();
+            req.mut_context().set_priority(CommandPri::Low);
+            let (started_tx, release_rx) = (started_tx.clone(), release_rx.clone());
+            let task = RequestTask::new(req, box move |_| {
+                started_tx.send(()).unwrap();
+                release_rx.lock().unwrap().recv().unwrap();
+            });
+            worker.schedule(Task::Request(task)).unwrap();
+        }
+        started_rx.recv_timeout(Duration::from_secs(3)).unwrap();
+
+        // both high priority requests can only finish when they run at the same time.
+        let barrier = Arc::new(Barrier::new(2));
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..2 {
+            let mut req = Request::new();
+            req.mut_context().set_priority(CommandPri::High);
+            let (tx, barrier) = (tx.clone(), barrier.clone());
+            let task = RequestTask::new(req, box move |msg| {
+                barrier.wait();
+                tx.send(msg).unwrap();
+            });
+            worker.schedule(Task::Request(task)).unwrap();
+        }
+        for _ in 0..2 {
+            rx.recv_timeout(Duration::from_secs(3)).unwrap();
+        }
+        // the low priority pool is still saturated by the first request.
+        assert!(started_rx.recv_timeout(Duration::from_millis(100)).is_err());
+
+        for _ in 0..3 {
+            release_tx.send(()).unwrap();
+        }
+        for _ in 0..2 {
+            started_rx.recv_timeout(Duration::from_secs(3)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_result_cache() {
+        let mut worker = Worker::new("test-endpoint");
+        let engine = engine::new_local_engine(TEMP_DIR, ALL_CFS).unwrap();
+        let mut cfg = Config::default();
+        cfg.end_point_concurrency = 1;
+        cfg.end_point_result_cache_size = ReadableSize::kb(8);
+        let pd_worker = FutureWorker::new("test-pd-worker");
+        let end_point = Host::new(engine, worker.scheduler(), &cfg, pd_worker.scheduler());
+        let cache = end_point.cache.clone().unwrap();
+        worker.start_batch(end_point, 30).unwrap();
+
+        let mut scan = TableScan::new();
+        scan.set_table_id(1);
+        let mut exec = Executor::new();
+        exec.set_tp(ExecType::TypeTableScan);
+        exec.set_tbl_scan(scan);
+        let mut dag = DAGRequest::new();
+        dag.set_start_ts(10);
+        dag.mut_executors().push(exec);
+        let mut range = KeyRange::new();
+        range.set_start(b"a".to_vec());
+        range.set_end(b"z".to_vec());
+        let mut req = Request::new();
+        req.set_tp(REQ_TYPE_DAG);
+        req.set_data(dag.write_to_bytes().unwrap());
+        req.mut_ranges().push(range);
+
+        let (tx, rx) = mpsc::channel();
+        let mut resps = vec![];
+        for _ in 0..2 {
+            let tx = tx.clone();
+            let task = RequestTask::new(req.clone(), box move |msg| { tx.send(msg).unwrap(); });
+            worker.schedule(Task::Request(task)).unwrap();
+            resps.push(rx.recv_timeout(Duration::from_secs(3)).unwrap());
+        }
+        assert!(resps[0].get_other_error().is_empty());
+        assert_eq!(resps[0], resps[1]);
+        assert_eq!(cache.lock().unwrap().hits(), 1);
+    }
+
+    #[test]
+    fn test_stale_snapshot() {
+        let mut worker = Worker::new("test-endpoint");
+        let engine = engine::new_local_engine(TEMP_DIR, ALL_CFS).unwrap();
+        let mut cfg = Config::default();
+        cfg.end_point_concurrency = 1;
+        let pd_worker = FutureWorker::new("test-pd-worker");
+        let end_point = Host::new(engine, worker.scheduler(), &cfg, pd_worker.scheduler());
+        worker.start_batch(end_point, 30).unwrap();
+        worker.schedule(Task::UpdateSafePoint(100)).unwrap();
+        // an older safe point never moves it back.
+        worker.schedule(Task::UpdateSafePoint(50)).unwrap();
+
+        let mut scan = TableScan::new();
+        scan.set_table_id(1);
+        let mut exec = Executor::new();
+        exec.set_tp(ExecType::TypeTableScan);
+        exec.set_tbl_scan(scan);
+        let mut range = KeyRange::new();
+        range.set_start(b"a".to_vec());
+        range.set_end(b"z".to_vec());
+        let (tx, rx) = mpsc::channel();
+        for &(start_ts, stale) in &[(80, true), (100, false), (120, false)] {
+            let mut dag = DAGRequest::new();
+            dag.set_start_ts(start_ts);
+            dag.mut_executors().push(exec.clone());
+            let mut req = Request::new();
+            req.set_tp(REQ_TYPE_DAG);
+            req.set_data(dag.write_to_bytes().unwrap());
+            req.mut_ranges().push(range.clone());
+            let tx = tx.clone();
+            let task = RequestTask::new(req, box move |msg| { tx.send(msg).unwrap(); });
+            worker.schedule(Task::Request(task)).unwrap();
+            let resp = rx.recv_timeout(Duration::from_secs(3)).unwrap();
+            if stale {
+                let err = Error::StaleSnapshot(start_ts, 100);
+                assert_eq!(resp.get_other_error(), format!("{}", err));
+            } else {
+                assert!(resp.get_other_error().is_empty(), "{:?}", resp);
+            }
+        }
+    }
+
+    #[test]
+    fn test_too_many_ranges() {
+        let mut worker = Worker::new("test-endpoint");
+        let engine = engine::new_local_engine(TEMP_DIR, ALL_CFS).unwrap();
+        let mut cfg = Config::default();
+        cfg.end_point_concurrency = 1;
+        cfg.end_point_max_ranges = 4;
+        let pd_worker = FutureWorker::new("test-pd-worker");
+        let end_point = Host::new(engine, worker.scheduler(), &cfg, pd_worker.scheduler());
+        worker.start_batch(end_point, 30).unwrap();
+
+        let mut scan = TableScan::new();
+        scan.set_table_id(1);
+        let mut exec = Executor::new();
+        exec.set_tp(ExecType::TypeTableScan);
+        exec.set_tbl_scan(scan);
+        let mut dag = DAGRequest::new();
+        dag.set_start_ts(10);
+        dag.mut_executors().push(exec);
+        let (tx, rx) = mpsc::channel();
+        for &(count, rejected) in &[(5, true), (4, false)] {
+            let mut req = Request::new();
+            req.set_tp(REQ_TYPE_DAG);
+            req.set_data(dag.write_to_bytes().unwrap());
+            for i in 0..count {
+                let mut range = KeyRange::new();
+                range.set_start(vec![b'a' + i * 2]);
+                range.set_end(vec![b'a' + i * 2 + 1]);
+                req.mut_ranges().push(range);
+            }
+            let tx = tx.clone();
+            let task = RequestTask::new(req, box move |msg| { tx.send(msg).unwrap(); });
+            worker.schedule(Task::Request(task)).unwrap();
+            let resp = rx.recv_timeout(Duration::from_secs(3)).unwrap();
+            if rejected {
+                assert!(
+                    resp.get_other_error()
+                        .contains("5 key ranges, more than the limit 4"),
+                    "{:?}",
+                    resp
+                );
+            } else {
+                assert!(resp.get_other_error().is_empty(), "{:?}", resp);
+            }
+        }
+    }
+
+    #[test]
+    fn test_ranges_out_of_region() {
+        let path = TempDir::new("_test_coprocessor_ranges_out_of_region").unwrap();
+        let db = rocksdb_util::new_engine(path.path().to_str().unwrap(), ALL_CFS).unwrap();
+        // the region was split at "m" after the ranges were built.
+        let mut region = Region::new();
+        region.set_start_key(Key::from_raw(b"b").encoded().clone());
+        region.set_end_key(Key::from_raw(b"m").encoded().clone());
+        let snap = RegionSnapshot::from_raw(Arc::new(db), region);
+
+        let range = |start: &[u8], end: &[u8]| {
+            let mut range = KeyRange::new();
+            range.set_start(start.to_vec());
+            range.set_end(end.to_vec());
+            range
+        };
+        let cases = vec![
+            (vec![range(b"b", b"m")], true),
+            (vec![range(b"c", b"d"), range(b"e", b"f")], true),
+            (vec![range(b"c", b"d"), range(b"e", b"z")], false),
+            (vec![range(b"a", b"d")], false),
+            (vec![range(b"c", b"")], false),
+        ];
+        for (ranges, in_region) in cases {
+            match check_ranges_in_region(&snap, &ranges) {
+                Ok(()) => assert!(in_region, "{:?}", ranges),
+                Err(e) => {
+                    assert!(!in_region, "{:?}", ranges);
+                    let resp = err_resp(e);
+                    assert!(resp.get_region_error().has_key_not_in_region());
+                }
+            }
+        }
+
+        // a snapshot of the whole engine has no region to check.
+        let engine = engine::new_local_engine(TEMP_DIR, ALL_CFS).unwrap();
+        let snap = engine.snapshot(&Default::default()).unwrap();
+        assert!(check_ranges_in_region(snap.as_ref(), &[range(b"a", b"")]).is_ok());
+    }
 }
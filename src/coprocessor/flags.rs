@@ -0,0 +1,107 @@
+// Copyright 2017 PingCAP, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! TiKV specific flags of `SelectRequest.flags` and `DAGRequest.flags`.
+//!
+//! TiDB owns the low 32 bits, which carry its statement context, like
+//! `FLAG_IGNORE_TRUNCATE` in `select::xeval::evaluator`. The high 32 bits are
+//! reserved for the flags below, which have no tipb field of their own, so a
+//! new one must take a free bit of this range here, never a low bit.
+//!
+//! | bits  | flag                           |
+//! |-------|--------------------------------|
+//! | 32    | `FLAG_COLLECT_EXECUTOR_OUTPUT` |
+//! | 33    | `FLAG_IMPLICIT_GROUP_COUNT`    |
+//! | 34    | `FLAG_HANDLES_ONLY`            |
+//! | 35    | `FLAG_ROUND_HALF_EVEN`         |
+//! | 36    | `FLAG_RAW_GROUP_KEY`           |
+//! | 37    | `FLAG_ROW_KEY`                 |
+//! | 38    | `FLAG_NULL_AGGR_AS_ZERO`       |
+//! | 39    | free                           |
+//! | 40    | `FLAG_IGNORE_OVERFLOW`         |
+//! | 41    | `FLAG_REVERSE_OUTPUT`          |
+//! | 42    | `FLAG_ROLLUP`                  |
+//! | 43-47 | `FLAG_DIV_FRAC_INCR_SHIFT`     |
+//! | 48-63 | `FLAG_NULLS_ORDER_SHIFT`       |
+
+/// `FLAG_COLLECT_EXECUTOR_OUTPUT` indicates if the rows produced by every executor
+/// should be returned for plan debugging. The response then ends with one chunk per
+/// executor, in executor order, and `output_counts` holds the row count of each.
+pub const FLAG_COLLECT_EXECUTOR_OUTPUT: u64 = 1 << 32;
+/// `FLAG_IMPLICIT_GROUP_COUNT` indicates if every aggregation group should output
+/// its row count as a trailing column, even without an explicit `COUNT`.
+pub const FLAG_IMPLICIT_GROUP_COUNT: u64 = 1 << 33;
+/// `FLAG_HANDLES_ONLY` indicates if a table scan should skip reading row values,
+/// so only the columns of the handle can be output.
+pub const FLAG_HANDLES_ONLY: u64 = 1 << 34;
+/// `FLAG_ROUND_HALF_EVEN` indicates if `ROUND` should round a tie to the even neighbour
+/// (banker's rounding) instead of away from zero as MySQL does.
+pub const FLAG_ROUND_HALF_EVEN: u64 = 1 << 35;
+/// `FLAG_RAW_GROUP_KEY` indicates if every aggregation group should output its
+/// encoded group by values as a single bytes column after the aggregates.
+pub const FLAG_RAW_GROUP_KEY: u64 = 1 << 36;
+/// `FLAG_ROW_KEY` indicates if every row output without aggregation should carry the
+/// key of its table row as a bytes column after the output columns.
+pub const FLAG_ROW_KEY: u64 = 1 << 37;
+/// `FLAG_NULL_AGGR_AS_ZERO` indicates if the `SUM` of a group whose values are all NULL
+/// should be output as a decimal 0 instead of NULL.
+pub const FLAG_NULL_AGGR_AS_ZERO: u64 = 1 << 38;
+/// `FLAG_IGNORE_OVERFLOW` indicates if an overflowed addition should return NULL instead
+/// of an error.
+pub const FLAG_IGNORE_OVERFLOW: u64 = 1 << 40;
+/// `FLAG_REVERSE_OUTPUT` indicates if the rows should be output in the reverse of the
+/// order the executors produce them, without sorting them. The rows are buffered within
+/// the memory quota of the request.
+pub const FLAG_REVERSE_OUTPUT: u64 = 1 << 41;
+/// `FLAG_ROLLUP` indicates if every aggregation should also output the subtotals of every
+/// prefix of its group by, like `WITH ROLLUP`. Every group then outputs the number of its
+/// group by values rolled up as a trailing column, which is 0 for the regular groups.
+pub const FLAG_ROLLUP: u64 = 1 << 42;
+/// `FLAG_DIV_FRAC_INCR_SHIFT` is the first of the 5 bits holding the number of fraction
+/// digits added to the result of decimal division plus one, like `div_precision_increment`
+/// in MySQL, which ranges from 0 to 30. `DEFAULT_DIV_FRAC_INCR` is used if they are all 0.
+pub const FLAG_DIV_FRAC_INCR_SHIFT: u64 = 43;
+/// `DIV_FRAC_INCR_MASK` masks the bits from `FLAG_DIV_FRAC_INCR_SHIFT`.
+pub const DIV_FRAC_INCR_MASK: u64 = 0x1f;
+/// `FLAG_NULLS_ORDER_SHIFT` is the first of the bits placing the NULLs of the top n
+/// order by items: bit `FLAG_NULLS_ORDER_SHIFT + i` moves the NULLs of the `i`th item
+/// to the other end, so they come last on ascending order and first on descending.
+pub const FLAG_NULLS_ORDER_SHIFT: u64 = 48;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_flags_are_disjoint() {
+        let mut used = DIV_FRAC_INCR_MASK << FLAG_DIV_FRAC_INCR_SHIFT;
+        used |= !0 << FLAG_NULLS_ORDER_SHIFT;
+        let flags = vec![
+            FLAG_COLLECT_EXECUTOR_OUTPUT,
+            FLAG_IMPLICIT_GROUP_COUNT,
+            FLAG_HANDLES_ONLY,
+            FLAG_ROUND_HALF_EVEN,
+            FLAG_RAW_GROUP_KEY,
+            FLAG_ROW_KEY,
+            FLAG_NULL_AGGR_AS_ZERO,
+            FLAG_IGNORE_OVERFLOW,
+            FLAG_REVERSE_OUTPUT,
+            FLAG_ROLLUP,
+        ];
+        for flag in flags {
+            assert!(flag >= 1 << 32, "{:#x} is owned by TiDB", flag);
+            assert_eq!(used & flag, 0, "{:#x} is used twice", flag);
+            used |= flag;
+        }
+    }
+}
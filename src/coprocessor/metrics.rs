@@ -97,4 +97,10 @@ lazy_static! {
             vec![1.0, 2.0, 4.0, 6.0, 8.0, 10.0, 12.0, 14.0, 16.0, 18.0,
             20.0, 24.0, 28.0, 32.0, 48.0, 64.0, 96.0, 128.0, 192.0, 256.0]
         ).unwrap();
+
+    pub static ref COPR_RESULT_CACHE_HIT: Counter =
+        register_counter!(
+            "tikv_coprocessor_result_cache_hit_total",
+            "Total number of coprocessor requests served from the result cache"
+        ).unwrap();
 }
@@ -13,8 +13,10 @@
 
 mod endpoint;
 mod metrics;
+mod cache;
 mod dag;
 mod statistics;
+pub mod flags;
 pub mod select;
 pub mod codec;
 
@@ -44,6 +46,10 @@ quick_error! {
         Full(allow: usize) {
             description("running queue is full")
         }
+        StaleSnapshot(start_ts: u64, safe_point: u64) {
+            description("request is older than the gc safe point")
+            display("start ts {} is older than the gc safe point {}", start_ts, safe_point)
+        }
         Other(err: Box<error::Error + Send + Sync>) {
             from()
             cause(err.as_ref())
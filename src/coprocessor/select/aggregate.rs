@@ -12,6 +12,7 @@
 // limitations under the License.
 
 use std::cmp::Ordering;
+use std::str;
 use tipb::expression::ExprType;
 
 use coprocessor::codec::Datum;
@@ -30,6 +31,14 @@ pub fn build_aggr_func(tp: ExprType) -> Result<Box<AggrFunc>> {
         }),
         ExprType::Max => Ok(box Extremum::new(Ordering::Less)),
         ExprType::Min => Ok(box Extremum::new(Ordering::Greater)),
+        ExprType::GroupConcat => Ok(box GroupConcat {
+            res: None,
+            truncated: false,
+        }),
+        ExprType::VarPop => Ok(box Variance::new(false, false)),
+        ExprType::VarSamp => Ok(box Variance::new(true, false)),
+        ExprType::StddevPop => Ok(box Variance::new(false, true)),
+        ExprType::StddevSamp => Ok(box Variance::new(true, true)),
         et => Err(box_err!("unsupport AggrExprType: {:?}", et)),
     }
 }
@@ -103,10 +112,13 @@ impl Sum {
                 args.len()
             ));
         }
-        let a = args.pop().unwrap();
-        if a == Datum::Null {
-            return Ok(false);
-        }
+        let a = match args.pop().unwrap() {
+            Datum::Null => return Ok(false),
+            // integers are summed as decimals like MySQL, so the sum never overflows.
+            Datum::I64(i) => Datum::Dec(i.into()),
+            Datum::U64(u) => Datum::Dec(u.into()),
+            a => a,
+        };
         let res = match self.res.take() {
             Some(b) => box_try!(evaluator::eval_arith(ctx, a, b, Datum::checked_add)),
             None => a,
@@ -124,7 +136,12 @@ impl AggrFunc for Sum {
 
     fn calc(&mut self, collector: &mut Vec<Datum>) -> Result<()> {
         let res = self.res.take().unwrap_or(Datum::Null);
-        if res == Datum::Null {
+        if let Datum::Null = res {
+            collector.push(res);
+            return Ok(());
+        }
+        // floats are summed as floats like MySQL, everything else as decimals.
+        if let Datum::F64(_) = res {
             collector.push(res);
             return Ok(());
         }
@@ -134,6 +151,10 @@ impl AggrFunc for Sum {
     }
 }
 
+/// `Avg` outputs its partial result as two datums: the count of the non-NULL values as a
+/// `U64`, followed by their sum as a `Dec`, which is NULL if there is no such value. The sum
+/// keeps the scale of the values, so `decode_avg` gets the average without losing precision.
+/// The sum of floats is an `F64` instead, and so is their average.
 struct Avg {
     sum: Sum,
     cnt: u64,
@@ -153,8 +174,33 @@ impl AggrFunc for Avg {
     }
 }
 
+/// `decode_avg` gets the average from the partial result of `AVG`, see `Avg` for its layout.
+pub fn decode_avg(ctx: &EvalContext, partial: &[Datum]) -> Result<Datum> {
+    if partial.len() != 2 {
+        return Err(box_err!("avg outputs 2 datums, but got {}", partial.len()));
+    }
+    let cnt = match partial[0] {
+        Datum::U64(cnt) => cnt,
+        ref d => return Err(box_err!("invalid count {:?} of avg", d)),
+    };
+    match partial[1] {
+        Datum::Null if cnt == 0 => Ok(Datum::Null),
+        Datum::Dec(ref sum) if cnt > 0 => {
+            let avg = sum.clone().div(cnt.into(), ctx.div_frac_incr).unwrap();
+            Ok(Datum::Dec(box_try!(avg.into_result())))
+        }
+        Datum::F64(sum) if cnt > 0 => Ok(Datum::F64(sum / cnt as f64)),
+        ref d => Err(box_err!("invalid sum {:?} of {} values for avg", d, cnt)),
+    }
+}
+
+/// `Extremum` computes the max or min of its first argument. With a second
+/// argument, it outputs the extreme followed by that argument's value in the
+/// first extreme row, both NULL if there is no non-NULL value.
 struct Extremum {
     datum: Option<Datum>,
+    ret: Option<Datum>,
+    has_ret: bool,
     ord: Ordering,
 }
 
@@ -162,6 +208,8 @@ impl Extremum {
     fn new(ord: Ordering) -> Extremum {
         Extremum {
             datum: None,
+            ret: None,
+            has_ret: false,
             ord: ord,
         }
     }
@@ -169,26 +217,460 @@ impl Extremum {
 
 impl AggrFunc for Extremum {
     fn update(&mut self, ctx: &EvalContext, mut args: Vec<Datum>) -> Result<()> {
-        if args.len() != 1 {
+        if args.is_empty() || args.len() > 2 {
             return Err(box_err!(
-                "max/min only support one column, but got {}",
+                "max/min only support one column and an optional one to return, but got {}",
                 args.len()
             ));
         }
+        self.has_ret = args.len() == 2;
         if args[0] == Datum::Null {
             return Ok(());
         }
         if let Some(ref d) = self.datum {
+            // ties keep the first row.
             if box_try!(d.cmp(ctx, &args[0])) != self.ord {
                 return Ok(());
             }
         }
+        if args.len() == 2 {
+            self.ret = args.pop();
+        }
         self.datum = args.pop();
         Ok(())
     }
 
     fn calc(&mut self, collector: &mut Vec<Datum>) -> Result<()> {
         collector.push(self.datum.take().unwrap_or(Datum::Null));
+        if self.has_ret {
+            collector.push(self.ret.take().unwrap_or(Datum::Null));
+        }
+        Ok(())
+    }
+}
+
+const GROUP_CONCAT_SEPARATOR: &[u8] = b",";
+
+/// `GroupConcat` joins the non-NULL values of its argument with `,`. The result is
+/// truncated to `group_concat_max_len` bytes, after which the values are skipped. A
+/// utf8 result is truncated before the character the limit falls in, if any.
+struct GroupConcat {
+    res: Option<Vec<u8>>,
+    truncated: bool,
+}
+
+impl AggrFunc for GroupConcat {
+    fn update(&mut self, ctx: &EvalContext, mut args: Vec<Datum>) -> Result<()> {
+        if args.len() != 1 {
+            return Err(box_err!(
+                "group_concat only support one column, but got {}",
+                args.len()
+            ));
+        }
+        if self.truncated {
+            return Ok(());
+        }
+        let bs = match args.pop().unwrap() {
+            Datum::Null => return Ok(()),
+            Datum::Bytes(bs) => bs,
+            d => box_try!(d.into_string()).into_bytes(),
+        };
+        match self.res {
+            Some(ref mut res) => res.extend_from_slice(GROUP_CONCAT_SEPARATOR),
+            None => self.res = Some(vec![]),
+        }
+        let res = self.res.as_mut().unwrap();
+        res.extend_from_slice(&bs);
+        if res.len() > ctx.group_concat_max_len {
+            let mut len = ctx.group_concat_max_len;
+            if let Ok(s) = str::from_utf8(res) {
+                while !s.is_char_boundary(len) {
+                    len -= 1;
+                }
+            }
+            res.truncate(len);
+            self.truncated = true;
+            ctx.append_warning(format!(
+                "group_concat result is cut to {} bytes",
+                ctx.group_concat_max_len
+            ));
+        }
+        Ok(())
+    }
+
+    fn calc(&mut self, collector: &mut Vec<Datum>) -> Result<()> {
+        collector.push(self.res.take().map_or(Datum::Null, Datum::Bytes));
+        Ok(())
+    }
+}
+
+/// `Variance` computes the population or sample variance of its argument, or the
+/// standard deviation when `stddev` is set. The mean and the sum of squared
+/// differences from it are updated with Welford's method, which doesn't lose
+/// precision the way the sum of squares does for large counts.
+///
+/// Like `Avg`, it outputs a partial result that can be merged across regions: the
+/// count of the non-NULL values as a `U64`, followed by their mean and the sum of
+/// squared differences from it, both as `F64`. `decode_variance` gets the final value.
+struct Variance {
+    cnt: u64,
+    mean: f64,
+    m2: f64,
+    sample: bool,
+    stddev: bool,
+}
+
+impl Variance {
+    fn new(sample: bool, stddev: bool) -> Variance {
+        Variance {
+            cnt: 0,
+            mean: 0.0,
+            m2: 0.0,
+            sample: sample,
+            stddev: stddev,
+        }
+    }
+}
+
+impl AggrFunc for Variance {
+    fn update(&mut self, ctx: &EvalContext, mut args: Vec<Datum>) -> Result<()> {
+        if args.len() != 1 {
+            return Err(box_err!(
+                "variance only support one column, but got {}",
+                args.len()
+            ));
+        }
+        let f = match args.pop().unwrap() {
+            Datum::Null => return Ok(()),
+            d => box_try!(d.into_f64(ctx)),
+        };
+        self.cnt += 1;
+        let delta = f - self.mean;
+        self.mean += delta / self.cnt as f64;
+        self.m2 += delta * (f - self.mean);
         Ok(())
     }
+
+    fn calc(&mut self, collector: &mut Vec<Datum>) -> Result<()> {
+        collector.push(Datum::U64(self.cnt));
+        collector.push(Datum::F64(self.mean));
+        collector.push(Datum::F64(self.m2));
+        Ok(())
+    }
+}
+
+/// `decode_variance` gets the result of the variance function `tp` from its partial
+/// result, see `Variance` for its layout.
+pub fn decode_variance(tp: ExprType, partial: &[Datum]) -> Result<Datum> {
+    let (sample, stddev) = match tp {
+        ExprType::VarPop => (false, false),
+        ExprType::VarSamp => (true, false),
+        ExprType::StddevPop => (false, true),
+        ExprType::StddevSamp => (true, true),
+        _ => return Err(box_err!("{:?} is not a variance function", tp)),
+    };
+    if partial.len() != 3 {
+        return Err(box_err!("variance outputs 3 datums, but got {}", partial.len()));
+    }
+    let (cnt, m2) = match (&partial[0], &partial[1], &partial[2]) {
+        (&Datum::U64(cnt), &Datum::F64(_), &Datum::F64(m2)) => (cnt, m2),
+        _ => return Err(box_err!("invalid partial result {:?} of variance", partial)),
+    };
+    let n = if sample { cnt.saturating_sub(1) } else { cnt };
+    if n == 0 {
+        return Ok(Datum::Null);
+    }
+    let variance = m2 / n as f64;
+    if stddev {
+        Ok(Datum::F64(variance.sqrt()))
+    } else {
+        Ok(Datum::F64(variance))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{i64, u64};
+
+    use coprocessor::codec::Datum;
+    use coprocessor::codec::mysql::{Decimal, Duration, Json, Time};
+
+    use super::*;
+
+    fn extremum(tp: ExprType, data: &[Datum]) -> Datum {
+        let ctx = EvalContext::default();
+        let mut aggr = build_aggr_func(tp).unwrap();
+        for d in data {
+            aggr.update(&ctx, vec![d.clone()]).unwrap();
+        }
+        let mut res = vec![];
+        aggr.calc(&mut res).unwrap();
+        assert_eq!(res.len(), 1);
+        res.pop().unwrap()
+    }
+
+    #[test]
+    fn test_extremum() {
+        let dec = |s: &str| Datum::Dec(s.parse::<Decimal>().unwrap());
+        let time = |s: &str| Datum::Time(Time::parse_utc_datetime(s, 0).unwrap());
+        let dur = |s: &str| Datum::Dur(Duration::parse(s.as_bytes(), 0).unwrap());
+        let json = |s: &str| Datum::Json(s.parse::<Json>().unwrap());
+        let cases = vec![
+            (
+                vec![dec("1.5"), Datum::Null, dec("-10.25"), dec("1.50001")],
+                dec("1.50001"),
+                dec("-10.25"),
+            ),
+            (
+                vec![
+                    Datum::Null,
+                    time("2017-01-02 00:00:00"),
+                    time("2016-12-31 23:59:59"),
+                    time("2017-01-01 12:00:00"),
+                ],
+                time("2017-01-02 00:00:00"),
+                time("2016-12-31 23:59:59"),
+            ),
+            (
+                vec![dur("11:30:45"), dur("-1:00:00"), Datum::Null, dur("100:00:00")],
+                dur("100:00:00"),
+                dur("-1:00:00"),
+            ),
+            (
+                vec![json("3"), json("1.5"), Datum::Null, json("10")],
+                json("10"),
+                json("1.5"),
+            ),
+            (vec![Datum::Null, Datum::Null], Datum::Null, Datum::Null),
+        ];
+        for (data, max, min) in cases {
+            assert_eq!(extremum(ExprType::Max, &data), max);
+            assert_eq!(extremum(ExprType::Min, &data), min);
+        }
+    }
+
+    #[test]
+    fn test_extremum_with_ret() {
+        let ctx = EvalContext::default();
+        // (value, handle) pairs, the first max and min are kept on ties.
+        let rows = vec![
+            (Datum::I64(3), Datum::I64(1)),
+            (Datum::Null, Datum::I64(2)),
+            (Datum::I64(5), Datum::I64(3)),
+            (Datum::I64(1), Datum::I64(4)),
+            (Datum::I64(5), Datum::I64(5)),
+            (Datum::I64(1), Datum::I64(6)),
+        ];
+        for &(tp, exp, handle) in &[(ExprType::Max, 5, 3), (ExprType::Min, 1, 4)] {
+            let mut aggr = build_aggr_func(tp).unwrap();
+            for &(ref v, ref h) in &rows {
+                aggr.update(&ctx, vec![v.clone(), h.clone()]).unwrap();
+            }
+            let mut res = vec![];
+            aggr.calc(&mut res).unwrap();
+            assert_eq!(res, vec![Datum::I64(exp), Datum::I64(handle)]);
+        }
+
+        let mut aggr = build_aggr_func(ExprType::Max).unwrap();
+        aggr.update(&ctx, vec![Datum::Null, Datum::I64(1)]).unwrap();
+        let mut res = vec![];
+        aggr.calc(&mut res).unwrap();
+        assert_eq!(res, vec![Datum::Null, Datum::Null]);
+
+        let args = vec![Datum::I64(1), Datum::I64(2), Datum::I64(3)];
+        assert!(aggr.update(&ctx, args).is_err());
+    }
+
+    #[test]
+    fn test_group_concat() {
+        let mut ctx = EvalContext::default();
+        let concat = |ctx: &EvalContext, data: Vec<Datum>| {
+            let mut aggr = build_aggr_func(ExprType::GroupConcat).unwrap();
+            for d in data {
+                aggr.update(ctx, vec![d]).unwrap();
+            }
+            let mut res = vec![];
+            aggr.calc(&mut res).unwrap();
+            res.pop().unwrap()
+        };
+        let data = vec![
+            Datum::Bytes(b"b".to_vec()),
+            Datum::Null,
+            Datum::Bytes(b"a".to_vec()),
+            Datum::I64(3),
+        ];
+        assert_eq!(concat(&ctx, data.clone()), Datum::Bytes(b"b,a,3".to_vec()));
+        assert_eq!(concat(&ctx, vec![Datum::Null, Datum::Null]), Datum::Null);
+        assert_eq!(concat(&ctx, vec![Datum::Bytes(vec![])]), Datum::Bytes(vec![]));
+        let empty_first = vec![Datum::Bytes(vec![]), Datum::Bytes(b"a".to_vec())];
+        assert_eq!(concat(&ctx, empty_first), Datum::Bytes(b",a".to_vec()));
+
+        ctx.set_group_concat_max_len(3);
+        assert_eq!(concat(&ctx, data), Datum::Bytes(b"b,a".to_vec()));
+        assert_eq!(ctx.warnings.borrow().warning_cnt, 1);
+        let data = vec![
+            Datum::Bytes(b"abc".to_vec()),
+            Datum::Bytes(b"d".to_vec()),
+            Datum::Bytes(b"e".to_vec()),
+        ];
+        assert_eq!(concat(&ctx, data), Datum::Bytes(b"abc".to_vec()));
+        // the result is truncated once, and the rest of the values are skipped.
+        assert_eq!(ctx.warnings.borrow().warning_cnt, 2);
+
+        // a character is never split, but other bytes may be.
+        let data = vec![Datum::Bytes("a\u{e9}\u{e9}".as_bytes().to_vec())];
+        assert_eq!(concat(&ctx, data), Datum::Bytes("a\u{e9}".as_bytes().to_vec()));
+        let data = vec![Datum::Bytes("ab\u{e9}".as_bytes().to_vec())];
+        assert_eq!(concat(&ctx, data), Datum::Bytes(b"ab".to_vec()));
+        let data = vec![Datum::Bytes(vec![b'a', 0xc3, 0xff, 0xff])];
+        assert_eq!(concat(&ctx, data), Datum::Bytes(vec![b'a', 0xc3, 0xff]));
+    }
+
+    #[test]
+    fn test_sum_overflow() {
+        let ctx = EvalContext::default();
+        let mut aggr = build_aggr_func(ExprType::Sum).unwrap();
+        let data = vec![
+            Datum::I64(i64::MAX),
+            Datum::Null,
+            Datum::I64(i64::MAX),
+            Datum::U64(u64::MAX),
+            Datum::I64(i64::MIN),
+        ];
+        for d in data {
+            aggr.update(&ctx, vec![d]).unwrap();
+        }
+        let mut res = vec![];
+        aggr.calc(&mut res).unwrap();
+        let exp: Decimal = "27670116110564327421".parse().unwrap();
+        assert_eq!(res, vec![Datum::Dec(exp)]);
+    }
+
+    #[test]
+    fn test_float_aggr() {
+        let ctx = EvalContext::default();
+        let data = vec![
+            Datum::F64(1.5),
+            Datum::Null,
+            Datum::F64(-0.0),
+            Datum::F64(4.25),
+            Datum::F64(-2.0),
+        ];
+        let calc = |tp: ExprType| {
+            let mut aggr = build_aggr_func(tp).unwrap();
+            for d in &data {
+                aggr.update(&ctx, vec![d.clone()]).unwrap();
+            }
+            let mut res = vec![];
+            aggr.calc(&mut res).unwrap();
+            res
+        };
+        assert_eq!(calc(ExprType::Sum), vec![Datum::F64(3.75)]);
+        assert_eq!(calc(ExprType::Max), vec![Datum::F64(4.25)]);
+        assert_eq!(calc(ExprType::Min), vec![Datum::F64(-2.0)]);
+        let partial = calc(ExprType::Avg);
+        assert_eq!(partial, vec![Datum::U64(4), Datum::F64(3.75)]);
+        assert_eq!(decode_avg(&ctx, &partial).unwrap(), Datum::F64(0.9375));
+    }
+
+    #[test]
+    fn test_decode_avg() {
+        let ctx = EvalContext::default();
+        let mut aggr = build_aggr_func(ExprType::Avg).unwrap();
+        let data = vec![
+            Datum::Dec("1.10".parse().unwrap()),
+            Datum::Null,
+            Datum::Dec("2.20".parse().unwrap()),
+            Datum::I64(3),
+        ];
+        for d in data {
+            aggr.update(&ctx, vec![d]).unwrap();
+        }
+        let mut partial = vec![];
+        aggr.calc(&mut partial).unwrap();
+        assert_eq!(partial.len(), 2);
+        assert_eq!(partial[0], Datum::U64(3));
+        match partial[1] {
+            Datum::Dec(ref sum) => assert_eq!(sum.to_string(), "6.30"),
+            ref d => panic!("expect decimal sum, got {:?}", d),
+        }
+        match decode_avg(&ctx, &partial).unwrap() {
+            Datum::Dec(avg) => assert_eq!(avg.to_string(), "2.100000"),
+            d => panic!("expect decimal avg, got {:?}", d),
+        }
+
+        let mut aggr = build_aggr_func(ExprType::Avg).unwrap();
+        aggr.update(&ctx, vec![Datum::Null]).unwrap();
+        let mut partial = vec![];
+        aggr.calc(&mut partial).unwrap();
+        assert_eq!(decode_avg(&ctx, &partial).unwrap(), Datum::Null);
+
+        assert!(decode_avg(&ctx, &[Datum::U64(1)]).is_err());
+        assert!(decode_avg(&ctx, &[Datum::U64(1), Datum::Null]).is_err());
+        assert!(decode_avg(&ctx, &[Datum::I64(1), Datum::Dec(1.into())]).is_err());
+    }
+
+    #[test]
+    fn test_variance() {
+        let ctx = EvalContext::default();
+        let calc = |tp: ExprType, data: &[Datum]| {
+            let mut aggr = build_aggr_func(tp).unwrap();
+            for d in data {
+                aggr.update(&ctx, vec![d.clone()]).unwrap();
+            }
+            let mut res = vec![];
+            aggr.calc(&mut res).unwrap();
+            match decode_variance(tp, &res).unwrap() {
+                Datum::F64(f) => Some(f),
+                Datum::Null => None,
+                d => panic!("unexpected result {:?}", d),
+            }
+        };
+        let data = vec![
+            Datum::I64(2),
+            Datum::Null,
+            Datum::I64(4),
+            Datum::I64(4),
+            Datum::Dec(4.into()),
+            Datum::F64(5.0),
+            Datum::U64(5),
+            Datum::I64(7),
+            Datum::I64(9),
+        ];
+        let cases = vec![
+            (ExprType::VarPop, 4.0),
+            (ExprType::VarSamp, 32.0 / 7.0),
+            (ExprType::StddevPop, 2.0),
+            (ExprType::StddevSamp, (32.0f64 / 7.0).sqrt()),
+        ];
+        for &(tp, exp) in &cases {
+            let got = calc(tp, &data).unwrap();
+            assert!((got - exp).abs() < 1e-9, "{:?}: {} != {}", tp, got, exp);
+        }
+
+        // a single value has no sample variance.
+        let single = vec![Datum::Null, Datum::I64(3)];
+        assert_eq!(calc(ExprType::VarPop, &single), Some(0.0));
+        assert_eq!(calc(ExprType::StddevPop, &single), Some(0.0));
+        assert_eq!(calc(ExprType::VarSamp, &single), None);
+        assert_eq!(calc(ExprType::StddevSamp, &single), None);
+        for &(tp, _) in &cases {
+            assert_eq!(calc(tp, &[Datum::Null, Datum::Null]), None);
+            assert_eq!(calc(tp, &[]), None);
+        }
+
+        // a large offset doesn't swamp a small spread.
+        let data: Vec<_> = (0..1000)
+            .map(|i| Datum::F64(1e9 + (i % 2) as f64))
+            .collect();
+        let got = calc(ExprType::VarPop, &data).unwrap();
+        assert!((got - 0.25).abs() < 1e-6, "{}", got);
+
+        let partial = vec![Datum::U64(2), Datum::F64(1.0), Datum::F64(2.0)];
+        assert_eq!(decode_variance(ExprType::VarSamp, &partial).unwrap(), Datum::F64(2.0));
+        assert!(decode_variance(ExprType::Sum, &partial).is_err());
+        assert!(decode_variance(ExprType::VarPop, &partial[..2]).is_err());
+        let partial = vec![Datum::U64(2), Datum::Null, Datum::F64(2.0)];
+        assert!(decode_variance(ExprType::VarPop, &partial).is_err());
+    }
 }
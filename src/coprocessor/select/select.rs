@@ -17,7 +17,6 @@ use tipb::select::{Chunk, RowMeta, SelectRequest, SelectResponse};
 use tipb::schema::ColumnInfo;
 use tipb::expression::{ByItem, Expr, ExprType};
 use protobuf::{Message as PbMsg, RepeatedField};
-use byteorder::{BigEndian, ReadBytesExt};
 use kvproto::coprocessor::{KeyRange, Response};
 
 use coprocessor::codec::{datum, mysql, table};
@@ -280,11 +279,7 @@ impl<'a> SelectContext<'a> {
                     }
                     box_try!(table::cut_idx_key(key, &ids))
                 };
-                let handle = if handle.is_none() {
-                    box_try!(val.as_slice().read_i64::<BigEndian>())
-                } else {
-                    handle.unwrap()
-                };
+                let handle = box_try!(table::decode_index_handle(handle, &val));
                 if let Some(ref pk_col) = self.core.pk_col {
                     let handle_datum = if mysql::has_unsigned_flag(pk_col.get_flag() as u64) {
                         // PK column is unsigned
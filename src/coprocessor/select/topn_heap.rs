@@ -25,12 +25,16 @@ use coprocessor::Result;
 use super::xeval::EvalContext;
 
 const HEAP_MAX_CAPACITY: usize = 1024;
+/// Limits over `DEFAULT_MAX_HEAP_LIMIT` keep every row and sort them all at the end, as
+/// a heap that large is rarely full but still orders every row pushed into it.
+pub const DEFAULT_MAX_HEAP_LIMIT: usize = 64 * 1024;
 
 pub struct SortRow {
     pub handle: i64,
     pub data: RowColsDict,
     pub key: Vec<Datum>,
     order_cols: Rc<Vec<ByItem>>,
+    nulls_first: Rc<Vec<bool>>,
     ctx: Rc<EvalContext>,
     err: Rc<RefCell<Option<String>>>,
 }
@@ -41,6 +45,7 @@ impl SortRow {
         data: RowColsDict,
         key: Vec<Datum>,
         order_cols: Rc<Vec<ByItem>>,
+        nulls_first: Rc<Vec<bool>>,
         ctx: Rc<EvalContext>,
         err: Rc<RefCell<Option<String>>>,
     ) -> SortRow {
@@ -49,6 +54,7 @@ impl SortRow {
             data: data,
             key: key,
             order_cols: order_cols,
+            nulls_first: nulls_first,
             ctx: ctx,
             err: err,
         }
@@ -58,7 +64,17 @@ impl SortRow {
         // check err
         self.check_err()?;
         let values = self.key.iter().zip(right.key.iter());
-        for (col, (v1, v2)) in self.order_cols.as_ref().iter().zip(values) {
+        for (i, (col, (v1, v2))) in self.order_cols.as_ref().iter().zip(values).enumerate() {
+            // an item with its NULLs placed explicitly puts them on that end in spite of `desc`.
+            if let Some(&nulls_first) = self.nulls_first.get(i) {
+                match (v1 == &Datum::Null, v2 == &Datum::Null) {
+                    (true, false) if nulls_first => return Ok(Ordering::Less),
+                    (true, false) => return Ok(Ordering::Greater),
+                    (false, true) if nulls_first => return Ok(Ordering::Greater),
+                    (false, true) => return Ok(Ordering::Less),
+                    _ => {}
+                }
+            }
             match v1.cmp(self.ctx.as_ref(), v2) {
                 Ok(Ordering::Equal) => {
                     continue;
@@ -75,7 +91,8 @@ impl SortRow {
                 }
             }
         }
-        Ok(Ordering::Equal)
+        // break ties by the handle, so the result doesn't depend on the order rows come in.
+        Ok(self.handle.cmp(&right.handle))
     }
 
     #[inline]
@@ -92,8 +109,12 @@ impl SortRow {
 }
 
 pub struct TopNHeap {
+    // never holds more than `limit` rows.
     pub rows: BinaryHeap<SortRow>,
+    // all the rows added, if the limit is over the max heap limit.
+    unsorted: Option<Vec<SortRow>>,
     limit: usize,
+    nulls_first: Rc<Vec<bool>>,
     err: Rc<RefCell<Option<String>>>,
 }
 
@@ -102,12 +123,46 @@ impl TopNHeap {
         if limit == usize::MAX {
             return Err(box_err!("invalid limit"));
         }
-        let cap = cmp::min(limit, HEAP_MAX_CAPACITY);
-        Ok(TopNHeap {
-            rows: BinaryHeap::with_capacity(cap),
+        let mut heap = TopNHeap {
+            rows: BinaryHeap::new(),
+            unsorted: None,
             limit: limit,
+            nulls_first: Rc::new(vec![]),
             err: Rc::new(RefCell::new(None)),
-        })
+        };
+        heap.set_max_heap_limit(DEFAULT_MAX_HEAP_LIMIT);
+        Ok(heap)
+    }
+
+    /// Sorts all the rows at the end instead of keeping a heap if the limit is over
+    /// `max`. It should be set before any row is added.
+    pub fn set_max_heap_limit(&mut self, max: usize) {
+        if self.limit > max {
+            self.rows = BinaryHeap::new();
+            self.unsorted = Some(vec![]);
+        } else {
+            self.rows = BinaryHeap::with_capacity(cmp::min(self.limit, HEAP_MAX_CAPACITY));
+            self.unsorted = None;
+        }
+    }
+
+    /// Returns the number of rows kept.
+    pub fn len(&self) -> usize {
+        match self.unsorted {
+            Some(ref rows) => rows.len(),
+            None => self.rows.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Sets whether the NULLs of each order by item sort before all the other values,
+    /// whatever its `desc` is. Items past the end of `nulls_first` keep NULL as the
+    /// smallest value, that is first on ascending order and last on descending.
+    pub fn set_nulls_first(&mut self, nulls_first: Vec<bool>) {
+        self.nulls_first = Rc::new(nulls_first);
     }
 
     #[inline]
@@ -126,7 +181,19 @@ impl TopNHeap {
         order_cols: Rc<Vec<ByItem>>,
         ctx: Rc<EvalContext>,
     ) -> Result<()> {
-        let row = SortRow::new(handle, data, values, order_cols, ctx, self.err.clone());
+        let row = SortRow::new(
+            handle,
+            data,
+            values,
+            order_cols,
+            self.nulls_first.clone(),
+            ctx,
+            self.err.clone(),
+        );
+        if let Some(ref mut rows) = self.unsorted {
+            rows.push(row);
+            return self.check_err();
+        }
         // push into heap when heap is not full
         if self.rows.len() < self.limit {
             self.rows.push(row);
@@ -138,11 +205,19 @@ impl TopNHeap {
                 *top_data = row;
             }
         }
+        debug_assert!(self.rows.len() <= self.limit);
         self.check_err()
     }
 
     pub fn into_sorted_vec(self) -> Result<Vec<SortRow>> {
-        let sorted_data = self.rows.into_sorted_vec();
+        let sorted_data = match self.unsorted {
+            Some(mut rows) => {
+                rows.sort();
+                rows.truncate(self.limit);
+                rows
+            }
+            None => self.rows.into_sorted_vec(),
+        };
         // check is needed here since err may caused by any call of cmp
         if let Some(ref err_msg) = *self.err.as_ref().borrow() {
             return Err(box_err!(err_msg.to_owned()));
@@ -314,6 +389,86 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_topn_heap_tie_break() {
+        let ctx = Rc::new(EvalContext::default());
+        for &desc in &[false, true] {
+            let order_cols = Rc::new(vec![new_order_by(0, desc)]);
+            let mut topn_heap = TopNHeap::new(4).unwrap();
+            for &(handle, count) in &[(5, 1), (2, 1), (8, 1), (7, 0), (1, 1), (9, 1), (3, 1)] {
+                let row_data = RowColsDict::new(HashMap::default(), vec![]);
+                let key = vec![Datum::I64(if desc { -count } else { count })];
+                topn_heap
+                    .try_add_row(handle, row_data, key, order_cols.clone(), ctx.clone())
+                    .unwrap();
+            }
+            let result = topn_heap.into_sorted_vec().unwrap();
+            let handles: Vec<_> = result.iter().map(|row| row.handle).collect();
+            // equal keys are always ordered by the ascending handles.
+            assert_eq!(handles, vec![7, 1, 2, 3]);
+        }
+    }
+
+    #[test]
+    fn test_topn_heap_nulls_first() {
+        let ctx = Rc::new(EvalContext::default());
+        let cases = vec![
+            (false, None, vec![2, 4, 1, 3]),
+            (true, None, vec![3, 1, 2, 4]),
+            (false, Some(true), vec![2, 4, 1, 3]),
+            (false, Some(false), vec![1, 3, 2, 4]),
+            (true, Some(true), vec![2, 4, 3, 1]),
+            (true, Some(false), vec![3, 1, 2, 4]),
+        ];
+        for (desc, nulls_first, exp) in cases {
+            let order_cols = Rc::new(vec![new_order_by(0, desc)]);
+            let mut topn_heap = TopNHeap::new(10).unwrap();
+            if let Some(nulls_first) = nulls_first {
+                topn_heap.set_nulls_first(vec![nulls_first]);
+            }
+            let keys = vec![Datum::I64(1), Datum::Null, Datum::I64(2), Datum::Null];
+            for (handle, key) in (1..).zip(keys) {
+                let row_data = RowColsDict::new(HashMap::default(), vec![]);
+                topn_heap
+                    .try_add_row(handle, row_data, vec![key], order_cols.clone(), ctx.clone())
+                    .unwrap();
+            }
+            let result = topn_heap.into_sorted_vec().unwrap();
+            let handles: Vec<_> = result.iter().map(|row| row.handle).collect();
+            assert_eq!(handles, exp, "desc {} nulls_first {:?}", desc, nulls_first);
+        }
+    }
+
+    #[test]
+    fn test_topn_heap_full_sort() {
+        let ctx = Rc::new(EvalContext::default());
+        let order_cols = Rc::new(vec![new_order_by(0, true)]);
+        let keys = vec![3, 1, 4, 1, 5, 9, 2, 6];
+        let sort = |limit: usize, max_heap_limit: usize| {
+            let mut topn_heap = TopNHeap::new(limit).unwrap();
+            topn_heap.set_max_heap_limit(max_heap_limit);
+            for (handle, &key) in (0..).zip(&keys) {
+                let row_data = RowColsDict::new(HashMap::default(), vec![]);
+                let key = vec![Datum::I64(key)];
+                topn_heap
+                    .try_add_row(handle, row_data, key, order_cols.clone(), ctx.clone())
+                    .unwrap();
+                if max_heap_limit >= limit {
+                    assert!(topn_heap.len() <= limit);
+                }
+            }
+            let result = topn_heap.into_sorted_vec().unwrap();
+            result.iter().map(|row| row.handle).collect::<Vec<_>>()
+        };
+        // the limit is over the row count too, all of them are output.
+        for &limit in &[3, 8, 100] {
+            let heap_sorted = sort(limit, DEFAULT_MAX_HEAP_LIMIT);
+            assert_eq!(heap_sorted.len(), cmp::min(limit, keys.len()));
+            assert_eq!(heap_sorted, sort(limit, 0));
+        }
+        assert_eq!(sort(100, 0), vec![5, 7, 4, 2, 0, 6, 1, 3]);
+    }
+
     #[test]
     fn test_topn_heap_with_cmp_error() {
         let mut order_cols = Vec::new();
@@ -12,11 +12,14 @@
 // limitations under the License.
 
 use std::cmp::Ordering;
+use std::cell::RefCell;
 use std::ascii::AsciiExt;
-use std::result;
+use std::{i64, result};
+use crc::crc32;
 
-use chrono::FixedOffset;
+use chrono::{Datelike, Duration as ChronoDuration, FixedOffset, TimeZone};
 use tipb::expression::{Expr, ExprType, ScalarFuncSig};
+use tipb::schema::ColumnInfo;
 
 use util::is_even;
 use util::codec::number::NumberDecoder;
@@ -24,12 +27,17 @@ use util::collections::{HashMap, HashMapEntry};
 
 use coprocessor::codec;
 use coprocessor::codec::datum::{Datum, DatumDecoder};
-use coprocessor::codec::mysql::{DecimalDecoder, Duration, ModifyType, Time, MAX_FSP};
+use coprocessor::codec::mysql::{types, DecimalDecoder, Duration, ModifyType, Time,
+                                DEFAULT_DIV_FRAC_INCR, MAX_FSP};
+use coprocessor::codec::mysql::duration::{MAX_TIME_IN_SECS, NANOS_PER_SEC};
 use coprocessor::codec::mysql::json::{json_array, json_object};
+use coprocessor::flags::{DIV_FRAC_INCR_MASK, FLAG_DIV_FRAC_INCR_SHIFT, FLAG_IGNORE_OVERFLOW,
+                        FLAG_ROUND_HALF_EVEN};
 use super::{Error, Result};
 
 /// Flags are used by `SelectRequest.flags` to handle execution mode, like how to handle
-/// truncate error.
+/// truncate error. These are set by TiDB in the low 32 bits, the TiKV specific flags are
+/// in `coprocessor::flags`.
 /// `FLAG_IGNORE_TRUNCATE` indicates if truncate error should be ignored.
 /// Read-only statements should ignore truncate error, write statements should not ignore
 /// truncate error.
@@ -38,6 +46,50 @@ pub const FLAG_IGNORE_TRUNCATE: u64 = 1;
 /// This flag only matters if `FLAG_IGNORE_TRUNCATE` is not set, in strict sql mode, truncate error
 /// should be returned as error, in non-strict sql mode, truncate error should be saved as warning.
 pub const FLAG_TRUNCATE_AS_WARNING: u64 = 1 << 1;
+/// `FLAG_IN_INSERT_STMT` indicates if this is an `INSERT` statement.
+pub const FLAG_IN_INSERT_STMT: u64 = 1 << 3;
+/// `FLAG_IN_UPDATE_OR_DELETE_STMT` indicates if this is an `UPDATE` or `DELETE` statement.
+/// Like the strict sql mode, division and modulo by zero are errors in these statements
+/// and `INSERT`, while they return NULL with a warning in the others.
+pub const FLAG_IN_UPDATE_OR_DELETE_STMT: u64 = 1 << 4;
+
+/// `DEFAULT_MAX_WARNING_CNT` is the default number of warnings kept by an `EvalContext`.
+pub const DEFAULT_MAX_WARNING_CNT: usize = 64;
+/// `DEFAULT_GROUP_CONCAT_MAX_LEN` is the default length limit of `GROUP_CONCAT` results.
+pub const DEFAULT_GROUP_CONCAT_MAX_LEN: usize = 1024;
+
+/// `MAX_BLOB_WIDTH` is the length limit of the strings built by `SPACE` and `REPEAT`.
+pub const MAX_BLOB_WIDTH: usize = 16 * 1024 * 1024;
+
+/// `EvalWarnings` keeps the warnings raised in an evaluation. At most `max_warning_cnt`
+/// of them are kept, and `truncated` is set once any warning is dropped.
+#[derive(Debug)]
+pub struct EvalWarnings {
+    max_warning_cnt: usize,
+    pub warning_cnt: usize,
+    pub warnings: Vec<String>,
+    pub truncated: bool,
+}
+
+impl EvalWarnings {
+    fn new(max_warning_cnt: usize) -> EvalWarnings {
+        EvalWarnings {
+            max_warning_cnt: max_warning_cnt,
+            warning_cnt: 0,
+            warnings: vec![],
+            truncated: false,
+        }
+    }
+
+    fn append(&mut self, warning: String) {
+        self.warning_cnt += 1;
+        if self.warnings.len() < self.max_warning_cnt {
+            self.warnings.push(warning);
+        } else {
+            self.truncated = true;
+        }
+    }
+}
 
 #[derive(Debug)]
 /// Some global variables needed in an evaluation.
@@ -46,6 +98,17 @@ pub struct EvalContext {
     pub tz: FixedOffset,
     pub ignore_truncate: bool,
     pub truncate_as_warning: bool,
+    /// the number of fraction digits added to the result of decimal division,
+    /// like `div_precision_increment` in MySQL.
+    pub div_frac_incr: u8,
+    pub round_half_even: bool,
+    pub in_insert_stmt: bool,
+    pub in_update_or_delete_stmt: bool,
+    pub ignore_overflow: bool,
+    /// the length in bytes `GROUP_CONCAT` results are truncated to, like
+    /// `group_concat_max_len` in MySQL.
+    pub group_concat_max_len: usize,
+    pub warnings: RefCell<EvalWarnings>,
 }
 
 impl Default for EvalContext {
@@ -54,6 +117,13 @@ impl Default for EvalContext {
             tz: FixedOffset::east(0),
             ignore_truncate: false,
             truncate_as_warning: false,
+            div_frac_incr: DEFAULT_DIV_FRAC_INCR,
+            round_half_even: false,
+            in_insert_stmt: false,
+            in_update_or_delete_stmt: false,
+            ignore_overflow: false,
+            group_concat_max_len: DEFAULT_GROUP_CONCAT_MAX_LEN,
+            warnings: RefCell::new(EvalWarnings::new(DEFAULT_MAX_WARNING_CNT)),
         }
     }
 }
@@ -74,10 +144,32 @@ impl EvalContext {
             tz: tz,
             ignore_truncate: (flags & FLAG_IGNORE_TRUNCATE) > 0,
             truncate_as_warning: (flags & FLAG_TRUNCATE_AS_WARNING) > 0,
+            div_frac_incr: match (flags >> FLAG_DIV_FRAC_INCR_SHIFT) & DIV_FRAC_INCR_MASK {
+                0 => DEFAULT_DIV_FRAC_INCR,
+                incr => (incr - 1) as u8,
+            },
+            round_half_even: (flags & FLAG_ROUND_HALF_EVEN) > 0,
+            in_insert_stmt: (flags & FLAG_IN_INSERT_STMT) > 0,
+            in_update_or_delete_stmt: (flags & FLAG_IN_UPDATE_OR_DELETE_STMT) > 0,
+            ignore_overflow: (flags & FLAG_IGNORE_OVERFLOW) > 0,
+            group_concat_max_len: DEFAULT_GROUP_CONCAT_MAX_LEN,
+            warnings: RefCell::new(EvalWarnings::new(DEFAULT_MAX_WARNING_CNT)),
         };
 
         Ok(e)
     }
+
+    pub fn set_max_warning_cnt(&mut self, max_warning_cnt: usize) {
+        self.warnings.get_mut().max_warning_cnt = max_warning_cnt;
+    }
+
+    pub fn set_group_concat_max_len(&mut self, max_len: usize) {
+        self.group_concat_max_len = max_len;
+    }
+
+    pub fn append_warning(&self, warning: String) {
+        self.warnings.borrow_mut().append(warning);
+    }
 }
 
 // `Evaluator` evaluates `tipb::Expr`.
@@ -87,11 +179,24 @@ impl EvalContext {
 pub struct Evaluator {
     // column_id -> column_value
     pub row: HashMap<i64, Datum>,
+    // the row in the order of the column offsets, a column reference that isn't
+    // a column id of `row` is resolved by its offset here, as the DAG does.
+    pub offset_row: Vec<Datum>,
     // expr pointer -> value list
     cached_value_list: HashMap<isize, Vec<Datum>>,
 }
 
 impl Evaluator {
+    /// `set_row` sets the values of `cols` in order, so the columns can be referenced
+    /// either by their ids or by their offsets.
+    pub fn set_row(&mut self, cols: &[ColumnInfo], values: Vec<Datum>) {
+        self.row.clear();
+        for (col, value) in cols.iter().zip(&values) {
+            self.row.insert(col.get_column_id(), value.clone());
+        }
+        self.offset_row = values;
+    }
+
     pub fn batch_eval(&mut self, ctx: &EvalContext, exprs: &[Expr]) -> Result<Vec<Datum>> {
         let mut res = Vec::with_capacity(exprs.len());
         for expr in exprs {
@@ -102,6 +207,10 @@ impl Evaluator {
     }
 
     /// Eval evaluates expr to a Datum.
+    ///
+    /// An operator yields NULL once any of its operands is NULL, except that `0 AND NULL`
+    /// is 0, `1 OR NULL` is 1, and `NULL <=> NULL` is 1. Functions like `IF`, `CASE`,
+    /// `COALESCE`, `IFNULL` and `IS NULL` take NULL as a value instead.
     pub fn eval(&mut self, ctx: &EvalContext, expr: &Expr) -> Result<Datum> {
         match expr.get_tp() {
             ExprType::Int64 => self.eval_int(expr),
@@ -118,6 +227,7 @@ impl Evaluator {
             ExprType::NullEQ => self.eval_null_eq(ctx, expr),
             ExprType::And => self.eval_logic(ctx, expr, Some(false), eval_and),
             ExprType::Or => self.eval_logic(ctx, expr, Some(true), eval_or),
+            ExprType::Xor => self.eval_logic(ctx, expr, None, eval_xor),
             ExprType::Not => self.eval_not(ctx, expr),
             ExprType::Like => self.eval_like(ctx, expr),
             ExprType::Float32 | ExprType::Float64 => self.eval_float(expr),
@@ -134,7 +244,30 @@ impl Evaluator {
             ExprType::Case => self.eval_case_when(ctx, expr),
             ExprType::If => self.eval_if(ctx, expr),
             ExprType::Coalesce => self.eval_coalesce(ctx, expr),
+            ExprType::Greatest => self.eval_greatest_least(ctx, expr, Ordering::Greater),
+            ExprType::Least => self.eval_greatest_least(ctx, expr, Ordering::Less),
             ExprType::IfNull => self.eval_if_null(ctx, expr),
+            ExprType::DateFormat => self.eval_date_format(ctx, expr),
+            ExprType::DateDiff => self.eval_date_diff(ctx, expr),
+            ExprType::TimestampDiff => self.eval_timestamp_diff(ctx, expr),
+            ExprType::ConcatWS => self.eval_concat_ws(ctx, expr),
+            ExprType::Bin => self.eval_int_base(ctx, expr, 2),
+            ExprType::Oct => self.eval_int_base(ctx, expr, 8),
+            ExprType::Space => self.eval_space(ctx, expr),
+            ExprType::Repeat => self.eval_repeat(ctx, expr),
+            ExprType::Locate => self.eval_locate(ctx, expr, false),
+            ExprType::Position => self.eval_locate(ctx, expr, true),
+            ExprType::Reverse => self.eval_reverse(ctx, expr),
+            ExprType::Length => self.eval_length(ctx, expr),
+            ExprType::Substring => self.eval_substring(ctx, expr),
+            ExprType::Concat => self.eval_concat(ctx, expr),
+            ExprType::Upper => self.eval_upper_lower(ctx, expr, true),
+            ExprType::Lower => self.eval_upper_lower(ctx, expr, false),
+            ExprType::Ascii => self.eval_ascii(ctx, expr),
+            ExprType::Ord => self.eval_ord(ctx, expr),
+            ExprType::Crc32 => self.eval_crc32(ctx, expr),
+            ExprType::MakeDate => self.eval_make_date(ctx, expr),
+            ExprType::MakeTime => self.eval_make_time(ctx, expr),
             ExprType::IsNull => self.eval_is_null(ctx, expr),
             ExprType::NullIf => self.eval_null_if(ctx, expr),
             ExprType::JsonSet => self.eval_json_modify(ctx, expr, ModifyType::Set),
@@ -191,10 +324,13 @@ impl Evaluator {
 
     fn eval_column_ref(&self, expr: &Expr) -> Result<Datum> {
         let i = expr.get_val().decode_i64()?;
-        self.row
-            .get(&i)
-            .cloned()
-            .ok_or_else(|| Error::Eval(format!("column {} not found", i)))
+        if let Some(d) = self.row.get(&i) {
+            return Ok(d.clone());
+        }
+        if i >= 0 && (i as usize) < self.offset_row.len() {
+            return Ok(self.offset_row[i as usize].clone());
+        }
+        Err(Error::Eval(format!("column {} not found", i)))
     }
 
     fn eval_lt(&mut self, ctx: &EvalContext, expr: &Expr) -> Result<Datum> {
@@ -424,6 +560,276 @@ impl Evaluator {
         Ok(Datum::Null)
     }
 
+    // `eval_greatest_least` returns the argument whose order against all the others
+    // is `ord`, after unifying the arguments to a common comparable type.
+    fn eval_greatest_least(
+        &mut self,
+        ctx: &EvalContext,
+        expr: &Expr,
+        ord: Ordering,
+    ) -> Result<Datum> {
+        let children = self.eval_more_children(ctx, expr, 2)?;
+        if children.iter().any(|d| *d == Datum::Null) {
+            return Ok(Datum::Null);
+        }
+        let mut args = unify_cmp_type(ctx, children)?.into_iter();
+        let mut res = args.next().unwrap();
+        for arg in args {
+            if arg.cmp(ctx, &res)? == ord {
+                res = arg;
+            }
+        }
+        Ok(res)
+    }
+
+    fn eval_date_format(&mut self, ctx: &EvalContext, expr: &Expr) -> Result<Datum> {
+        let (time, layout) = self.eval_two_children(ctx, expr)?;
+        if time == Datum::Null || layout == Datum::Null {
+            return Ok(Datum::Null);
+        }
+        let res = to_time(ctx, time)?.date_format(&layout.into_string()?)?;
+        Ok(Datum::Bytes(res.into_bytes()))
+    }
+
+    // `eval_date_diff` returns the number of days from the date of the second argument
+    // to the date of the first one.
+    fn eval_date_diff(&mut self, ctx: &EvalContext, expr: &Expr) -> Result<Datum> {
+        let (l, r) = match self.eval_two_children(ctx, expr)? {
+            (Datum::Null, _) | (_, Datum::Null) => return Ok(Datum::Null),
+            (l, r) => (to_time(ctx, l)?, to_time(ctx, r)?),
+        };
+        if l.is_zero() || r.is_zero() {
+            return Ok(Datum::Null);
+        }
+        Ok(Datum::I64(l.date_diff(&r)))
+    }
+
+    // `eval_timestamp_diff` returns the difference from the second argument to the third
+    // one in the unit named by the first one, like `TIMESTAMPDIFF(unit, from, to)`.
+    fn eval_timestamp_diff(&mut self, ctx: &EvalContext, expr: &Expr) -> Result<Datum> {
+        let children = self.eval_more_children(ctx, expr, 3)?;
+        if children.len() != 3 {
+            return Err(Error::Expr(
+                format!("expect 3 operands, got {}", children.len()),
+            ));
+        }
+        if children.iter().any(|d| *d == Datum::Null) {
+            return Ok(Datum::Null);
+        }
+        let mut children = children.into_iter();
+        let unit = children.next().unwrap().into_string()?;
+        let from = to_time(ctx, children.next().unwrap())?;
+        let to = to_time(ctx, children.next().unwrap())?;
+        if from.is_zero() || to.is_zero() {
+            return Ok(Datum::Null);
+        }
+        Ok(Datum::I64(from.timestamp_diff(&to, &unit)?))
+    }
+
+    // `eval_concat_ws` joins the arguments after the first one with the first one
+    // as separator. NULL arguments are skipped, but a NULL separator yields NULL.
+    fn eval_concat_ws(&mut self, ctx: &EvalContext, expr: &Expr) -> Result<Datum> {
+        let mut children = self.eval_more_children(ctx, expr, 2)?.into_iter();
+        let sep = match children.next().unwrap() {
+            Datum::Null => return Ok(Datum::Null),
+            d => to_bytes(d)?,
+        };
+        let mut res = vec![];
+        let mut first = true;
+        for d in children {
+            if d == Datum::Null {
+                continue;
+            }
+            if !first {
+                res.extend_from_slice(&sep);
+            }
+            res.extend_from_slice(&to_bytes(d)?);
+            first = false;
+        }
+        Ok(Datum::Bytes(res))
+    }
+
+    // `eval_int_base` formats its argument as an unsigned 64-bit integer in base `radix`,
+    // which is either 2 or 8. Negative numbers are formatted by their two's complement.
+    fn eval_int_base(&mut self, ctx: &EvalContext, expr: &Expr, radix: u32) -> Result<Datum> {
+        let n = match self.eval_one_child(ctx, expr)? {
+            Datum::Null => return Ok(Datum::Null),
+            Datum::I64(i) => i as u64,
+            Datum::U64(u) => u,
+            d => d.into_f64(ctx)? as i64 as u64,
+        };
+        let res = match radix {
+            2 => format!("{:b}", n),
+            _ => format!("{:o}", n),
+        };
+        Ok(Datum::Bytes(res.into_bytes()))
+    }
+
+    fn eval_make_date(&mut self, ctx: &EvalContext, expr: &Expr) -> Result<Datum> {
+        let (year, day) = self.eval_two_children(ctx, expr)?;
+        if year == Datum::Null || day == Datum::Null {
+            return Ok(Datum::Null);
+        }
+        let (year, day) = (datum_to_i64(ctx, year)?, datum_to_i64(ctx, day)?);
+        Ok(make_date(&ctx.tz, year, day)?.map_or(Datum::Null, Datum::Time))
+    }
+
+    fn eval_make_time(&mut self, ctx: &EvalContext, expr: &Expr) -> Result<Datum> {
+        let children = self.eval_more_children(ctx, expr, 3)?;
+        if children.len() != 3 {
+            return Err(Error::Expr(
+                format!("expect 3 operands, got {}", children.len()),
+            ));
+        }
+        if children.iter().any(|d| *d == Datum::Null) {
+            return Ok(Datum::Null);
+        }
+        let mut children = children.into_iter();
+        let hour = datum_to_i64(ctx, children.next().unwrap())?;
+        let minute = datum_to_i64(ctx, children.next().unwrap())?;
+        let second = children.next().unwrap().into_f64(ctx)?;
+        Ok(make_time(hour, minute, second)?.map_or(Datum::Null, Datum::Dur))
+    }
+
+    fn eval_space(&mut self, ctx: &EvalContext, expr: &Expr) -> Result<Datum> {
+        let n = match self.eval_one_child(ctx, expr)? {
+            Datum::Null => return Ok(Datum::Null),
+            d => repeat_count(ctx, d)?,
+        };
+        Ok(Datum::Bytes(repeat_bytes(b" ", n)?))
+    }
+
+    fn eval_repeat(&mut self, ctx: &EvalContext, expr: &Expr) -> Result<Datum> {
+        let (s, n) = match self.eval_two_children(ctx, expr)? {
+            (Datum::Null, _) | (_, Datum::Null) => return Ok(Datum::Null),
+            (s, n) => (to_bytes(s)?, repeat_count(ctx, n)?),
+        };
+        Ok(Datum::Bytes(repeat_bytes(&s, n)?))
+    }
+
+    // `eval_locate` returns the position of the first occurrence of a substring in a string.
+    // `POSITION` is `LOCATE` with its two arguments swapped.
+    fn eval_locate(&mut self, ctx: &EvalContext, expr: &Expr, swapped: bool) -> Result<Datum> {
+        let (substr, s) = match self.eval_two_children(ctx, expr)? {
+            (Datum::Null, _) | (_, Datum::Null) => return Ok(Datum::Null),
+            (l, r) => if swapped { (r, l) } else { (l, r) },
+        };
+        Ok(Datum::I64(locate(&to_bytes(substr)?, &to_bytes(s)?)))
+    }
+
+    // `eval_reverse` reverses the bytes of its argument.
+    fn eval_reverse(&mut self, ctx: &EvalContext, expr: &Expr) -> Result<Datum> {
+        let mut s = match self.eval_one_child(ctx, expr)? {
+            Datum::Null => return Ok(Datum::Null),
+            d => to_bytes(d)?,
+        };
+        s.reverse();
+        Ok(Datum::Bytes(s))
+    }
+
+    // `eval_length` returns the length of its argument in bytes.
+    fn eval_length(&mut self, ctx: &EvalContext, expr: &Expr) -> Result<Datum> {
+        let s = match self.eval_one_child(ctx, expr)? {
+            Datum::Null => return Ok(Datum::Null),
+            d => to_bytes(d)?,
+        };
+        Ok(Datum::I64(s.len() as i64))
+    }
+
+    // `eval_substring` returns the bytes of its first argument from the 1-based position
+    // of the second one, which counts from the end if negative, up to the length of the
+    // optional third one. It's empty if the position is 0 or out of the string.
+    fn eval_substring(&mut self, ctx: &EvalContext, expr: &Expr) -> Result<Datum> {
+        let children = self.eval_more_children(ctx, expr, 2)?;
+        if children.len() > 3 {
+            return Err(Error::Expr(
+                format!("expect 2 or 3 operands, got {}", children.len()),
+            ));
+        }
+        if children.iter().any(|d| *d == Datum::Null) {
+            return Ok(Datum::Null);
+        }
+        let mut children = children.into_iter();
+        let s = to_bytes(children.next().unwrap())?;
+        let pos = datum_to_i64(ctx, children.next().unwrap())?;
+        let len = match children.next() {
+            Some(d) => datum_to_i64(ctx, d)?,
+            None => i64::MAX,
+        };
+        let start = if pos > 0 {
+            pos - 1
+        } else if pos < 0 {
+            s.len() as i64 + pos
+        } else {
+            return Ok(Datum::Bytes(vec![]));
+        };
+        if start < 0 || start >= s.len() as i64 || len <= 0 {
+            return Ok(Datum::Bytes(vec![]));
+        }
+        let start = start as usize;
+        let end = if len as u64 >= (s.len() - start) as u64 {
+            s.len()
+        } else {
+            start + len as usize
+        };
+        Ok(Datum::Bytes(s[start..end].to_vec()))
+    }
+
+    // `eval_concat` joins its arguments, it's NULL if any of them is NULL.
+    fn eval_concat(&mut self, ctx: &EvalContext, expr: &Expr) -> Result<Datum> {
+        let children = self.eval_more_children(ctx, expr, 1)?;
+        let mut res = vec![];
+        for d in children {
+            if d == Datum::Null {
+                return Ok(Datum::Null);
+            }
+            res.extend_from_slice(&to_bytes(d)?);
+        }
+        Ok(Datum::Bytes(res))
+    }
+
+    // `eval_upper_lower` converts the ASCII letters of its argument to upper case if
+    // `upper`, or to lower case otherwise. Other bytes are kept as they are.
+    fn eval_upper_lower(&mut self, ctx: &EvalContext, expr: &Expr, upper: bool) -> Result<Datum> {
+        let mut s = match self.eval_one_child(ctx, expr)? {
+            Datum::Null => return Ok(Datum::Null),
+            d => to_bytes(d)?,
+        };
+        if upper {
+            s.make_ascii_uppercase();
+        } else {
+            s.make_ascii_lowercase();
+        }
+        Ok(Datum::Bytes(s))
+    }
+
+    // `eval_ascii` returns the code of the first byte of its argument, or 0 if it's empty.
+    fn eval_ascii(&mut self, ctx: &EvalContext, expr: &Expr) -> Result<Datum> {
+        let s = match self.eval_one_child(ctx, expr)? {
+            Datum::Null => return Ok(Datum::Null),
+            d => to_bytes(d)?,
+        };
+        Ok(Datum::I64(s.first().map_or(0, |&b| i64::from(b))))
+    }
+
+    fn eval_ord(&mut self, ctx: &EvalContext, expr: &Expr) -> Result<Datum> {
+        let s = match self.eval_one_child(ctx, expr)? {
+            Datum::Null => return Ok(Datum::Null),
+            d => to_bytes(d)?,
+        };
+        Ok(Datum::I64(ord(&s)))
+    }
+
+    // `eval_crc32` returns the crc32 checksum of the bytes of its argument as an unsigned
+    // integer.
+    fn eval_crc32(&mut self, ctx: &EvalContext, expr: &Expr) -> Result<Datum> {
+        let s = match self.eval_one_child(ctx, expr)? {
+            Datum::Null => return Ok(Datum::Null),
+            d => to_bytes(d)?,
+        };
+        Ok(Datum::U64(u64::from(crc32::checksum_ieee(&s))))
+    }
+
     fn eval_if_null(&mut self, ctx: &EvalContext, expr: &Expr) -> Result<Datum> {
         let children = expr.get_children();
         if children.len() != 2 {
@@ -643,18 +1049,211 @@ fn eval_or(lhs: Option<bool>, rhs: Option<bool>) -> Datum {
     }
 }
 
+// lhs and rhs can't be None
+#[inline]
+fn eval_xor(lhs: Option<bool>, rhs: Option<bool>) -> Datum {
+    match (lhs, rhs) {
+        (Some(l), Some(r)) => (l != r).into(),
+        _ => Datum::Null,
+    }
+}
+
+// `datum_to_i64` converts `d` to an integer argument, rounding non-integers.
+fn datum_to_i64(ctx: &EvalContext, d: Datum) -> Result<i64> {
+    match d {
+        Datum::I64(i) => Ok(i),
+        Datum::U64(u) if u > i64::MAX as u64 => Ok(i64::MAX),
+        Datum::U64(u) => Ok(u as i64),
+        d => Ok(d.into_f64(ctx)?.round() as i64),
+    }
+}
+
+// `to_time` converts `d` to a time, parsing it as a datetime string unless it's a time.
+fn to_time(ctx: &EvalContext, d: Datum) -> Result<Time> {
+    match d {
+        Datum::Time(t) => Ok(t),
+        d => Ok(Time::parse_datetime(&d.into_string()?, MAX_FSP, &ctx.tz)?),
+    }
+}
+
+// `to_bytes` converts `d` to a string, keeping bytes as they are.
+fn to_bytes(d: Datum) -> Result<Vec<u8>> {
+    match d {
+        Datum::Bytes(bs) => Ok(bs),
+        d => Ok(d.into_string()?.into_bytes()),
+    }
+}
+
+// `repeat_count` converts the count of `SPACE` or `REPEAT` to an integer, where a
+// negative count is treated as zero.
+fn repeat_count(ctx: &EvalContext, d: Datum) -> Result<usize> {
+    Ok(datum_to_i64(ctx, d)?.max(0) as usize)
+}
+
+// `repeat_bytes` repeats `s` `n` times, failing if the result would be longer than
+// `MAX_BLOB_WIDTH`.
+fn repeat_bytes(s: &[u8], n: usize) -> Result<Vec<u8>> {
+    repeat(s, n).ok_or_else(|| {
+        Error::Expr(format!(
+            "result of repeating {} bytes {} times is larger than {} bytes",
+            s.len(),
+            n,
+            MAX_BLOB_WIDTH
+        ))
+    })
+}
+
+/// `repeat` repeats `s` `n` times, or returns `None` if the result would be longer than
+/// `MAX_BLOB_WIDTH`.
+pub fn repeat(s: &[u8], n: usize) -> Option<Vec<u8>> {
+    if s.is_empty() {
+        return Some(vec![]);
+    }
+    match s.len().checked_mul(n) {
+        Some(len) if len <= MAX_BLOB_WIDTH => {
+            let mut res = Vec::with_capacity(len);
+            for _ in 0..n {
+                res.extend_from_slice(s);
+            }
+            Some(res)
+        }
+        _ => None,
+    }
+}
+
+/// `ord` returns the code of the first character of `s`, which is the big-endian integer
+/// of the utf8 bytes of a multi-byte character, or 0 if `s` is empty.
+pub fn ord(s: &[u8]) -> i64 {
+    let len = match s.first() {
+        None => return 0,
+        Some(&b) if b >= 0xf0 => 4,
+        Some(&b) if b >= 0xe0 => 3,
+        Some(&b) if b >= 0xc0 => 2,
+        Some(_) => 1,
+    };
+    s.iter()
+        .take(len)
+        .fold(0, |code, &b| (code << 8) | i64::from(b))
+}
+
+/// `make_date` returns the date of the day `day` of year `year`, or `None` if it's out
+/// of range. As in MySQL, a two-digit year is in 1970-2069.
+pub fn make_date(tz: &FixedOffset, year: i64, day: i64) -> codec::Result<Option<Time>> {
+    // any day beyond 10000 years is out of range, it also avoids an overflow.
+    if year < 0 || year > 9999 || day <= 0 || day > 366 * 10000 {
+        return Ok(None);
+    }
+    let year = if year < 70 {
+        year + 2000
+    } else if year < 100 {
+        year + 1900
+    } else {
+        year
+    };
+    let first_day = match tz.ymd_opt(year as i32, 1, 1).single() {
+        Some(d) => d.and_hms(0, 0, 0),
+        None => return Ok(None),
+    };
+    let date = first_day + ChronoDuration::days(day - 1);
+    if date.year() > 9999 {
+        return Ok(None);
+    }
+    Time::new(date, types::DATE, 0).map(Some)
+}
+
+/// `make_time` returns the time `hour:minute:second`, which is clipped to the range of
+/// the time type, or `None` if minute or second is out of [0, 60).
+pub fn make_time(hour: i64, minute: i64, second: f64) -> codec::Result<Option<Duration>> {
+    if minute < 0 || minute >= 60 || second < 0f64 || second >= 60f64 {
+        return Ok(None);
+    }
+    let max_nanos = MAX_TIME_IN_SECS as i64 * NANOS_PER_SEC;
+    let nanos = if hour > 838 || hour < -838 {
+        max_nanos
+    } else {
+        let nanos = (hour.abs() * 3600 + minute * 60) * NANOS_PER_SEC +
+            (second * NANOS_PER_SEC as f64).round() as i64;
+        if nanos > max_nanos { max_nanos } else { nanos }
+    };
+    let nanos = if hour < 0 { -nanos } else { nanos };
+    let fsp = if second.fract() == 0f64 { 0 } else { MAX_FSP };
+    Duration::from_nanos(nanos, fsp).map(Some)
+}
+
+/// `locate` returns the position of the first occurrence of `substr` in `s`, counted in
+/// characters from 1, or 0 if there is none.
+pub fn locate(substr: &[u8], s: &[u8]) -> i64 {
+    let idx = if substr.is_empty() {
+        0
+    } else {
+        match s.windows(substr.len()).position(|w| w == substr) {
+            Some(idx) => idx,
+            None => return 0,
+        }
+    };
+    // utf8 continuation bytes don't start a character.
+    s[..idx].iter().filter(|&&b| b & 0xc0 != 0x80).count() as i64 + 1
+}
+
+/// `unify_cmp_type` converts `args` to the type they are compared as by MySQL:
+/// strings are compared as strings, a mix of times and strings is compared as times,
+/// a mix of strings and numbers is compared as doubles, otherwise any decimal makes
+/// all the numbers compared as decimals.
+fn unify_cmp_type(ctx: &EvalContext, args: Vec<Datum>) -> Result<Vec<Datum>> {
+    let is_str = |d: &Datum| match *d {
+        Datum::Bytes(_) => true,
+        _ => false,
+    };
+    if args.iter().all(&is_str) {
+        return Ok(args);
+    }
+    let is_time = |d: &Datum| match *d {
+        Datum::Time(_) => true,
+        _ => false,
+    };
+    if args.iter().all(|d| is_time(d) || is_str(d)) {
+        return args.into_iter()
+            .map(|d| if is_str(&d) {
+                let t = Time::parse_datetime(&d.into_string()?, MAX_FSP, &ctx.tz)?;
+                Ok(Datum::Time(t))
+            } else {
+                Ok(d)
+            })
+            .collect();
+    }
+    let as_real = args.iter().any(|d| match *d {
+        Datum::Bytes(_) | Datum::F64(_) => true,
+        _ => false,
+    });
+    if as_real {
+        return args.into_iter()
+            .map(|d| d.into_f64(ctx).map(Datum::F64).map_err(From::from))
+            .collect();
+    }
+    let as_dec = args.iter().any(|d| match *d {
+        Datum::Dec(_) => true,
+        _ => false,
+    });
+    if as_dec {
+        return args.into_iter()
+            .map(|d| d.into_dec().map(Datum::Dec).map_err(From::from))
+            .collect();
+    }
+    Ok(args)
+}
+
 #[inline]
 pub fn eval_arith<F>(ctx: &EvalContext, left: Datum, right: Datum, f: F) -> Result<Datum>
 where
     F: FnOnce(Datum, &EvalContext, Datum) -> codec::Result<Datum>,
 {
-    let left = left.into_arith(ctx)?;
-    let right = right.into_arith(ctx)?;
-
-    let (left, right) = Datum::coerce(left, right)?;
+    // NULL is checked first, as the other operand may not be converted along with it.
     if left == Datum::Null || right == Datum::Null {
         return Ok(Datum::Null);
     }
+    let left = left.into_arith(ctx)?;
+    let right = right.into_arith(ctx)?;
+    let (left, right) = Datum::coerce(left, right)?;
 
     f(left, ctx, right).map_err(From::from)
 }
@@ -684,7 +1283,7 @@ pub mod test {
     use coprocessor::codec::mysql::json::JsonEncoder;
     use tipb::expression::FieldType;
 
-    use std::i32;
+    use std::{f64, i32, u64};
 
     use tipb::expression::{Expr, ExprType};
     use tipb::select::SelectRequest;
@@ -848,6 +1447,65 @@ pub mod test {
         };
     }
 
+    #[test]
+    fn test_eval_column_cmp() {
+        let mut xevaluator = Evaluator::default();
+        xevaluator.row.insert(1, Datum::I64(100));
+        xevaluator.row.insert(2, Datum::I64(100));
+        xevaluator.row.insert(3, Datum::I64(5));
+        xevaluator.row.insert(4, Datum::Null);
+        let cases = vec![
+            (1, 2, ExprType::EQ, Datum::I64(1)),
+            (1, 3, ExprType::EQ, Datum::I64(0)),
+            (1, 3, ExprType::GT, Datum::I64(1)),
+            (3, 1, ExprType::GE, Datum::I64(0)),
+            (1, 4, ExprType::EQ, Datum::Null),
+            (4, 4, ExprType::NullEQ, Datum::I64(1)),
+        ];
+        for (l, r, tp, exp) in cases {
+            let expr = build_expr_r(vec![col_expr(l), col_expr(r)], tp);
+            let res = xevaluator.eval(&Default::default(), &expr).unwrap();
+            assert_eq!(res, exp, "{:?}", expr);
+        }
+    }
+
+    #[test]
+    fn test_eval_column_ref_by_id_or_offset() {
+        let cols: Vec<_> = [10, 11]
+            .iter()
+            .map(|&id| {
+                let mut col = ColumnInfo::new();
+                col.set_column_id(id);
+                col
+            })
+            .collect();
+        let rows = vec![
+            vec![Datum::I64(1), Datum::I64(3)],
+            vec![Datum::I64(2), Datum::I64(1)],
+            vec![Datum::I64(3), Datum::Null],
+            vec![Datum::I64(4), Datum::I64(5)],
+        ];
+        // `count > 2`, with `count` referenced by its id as the select does, and by
+        // its offset as the DAG does.
+        let by_id = build_expr_r(vec![col_expr(11), datum_expr(Datum::I64(2))], ExprType::GT);
+        let by_offset = build_expr_r(vec![col_expr(1), datum_expr(Datum::I64(2))], ExprType::GT);
+        let mut xevaluator = Evaluator::default();
+        let mut res = vec![];
+        for row in rows {
+            xevaluator.set_row(&cols, row);
+            let l = xevaluator.eval(&Default::default(), &by_id).unwrap();
+            let r = xevaluator.eval(&Default::default(), &by_offset).unwrap();
+            assert_eq!(l, r);
+            res.push(l);
+        }
+        assert_eq!(
+            res,
+            vec![Datum::I64(1), Datum::I64(0), Datum::Null, Datum::I64(1)]
+        );
+
+        // neither a column id nor an offset.
+        assert!(xevaluator.eval(&Default::default(), &col_expr(2)).is_err());
+    }
 
     test_eval!(
         test_eval_datum_col,
@@ -1087,6 +1745,40 @@ pub mod test {
             (not_expr(Datum::I64(1)), Datum::I64(0)),
             (not_expr(Datum::I64(0)), Datum::I64(1)),
             (not_expr(Datum::Null), Datum::Null),
+            (
+                bin_expr(Datum::I64(0), Datum::I64(0), ExprType::Xor),
+                Datum::I64(0),
+            ),
+            (
+                bin_expr(Datum::I64(0), Datum::I64(1), ExprType::Xor),
+                Datum::I64(1),
+            ),
+            (
+                bin_expr(Datum::I64(1), Datum::I64(1), ExprType::Xor),
+                Datum::I64(0),
+            ),
+            (
+                bin_expr(Datum::I64(1), Datum::Null, ExprType::Xor),
+                Datum::Null,
+            ),
+            (
+                bin_expr(Datum::Null, Datum::I64(0), ExprType::Xor),
+                Datum::Null,
+            ),
+            (
+                bin_expr(Datum::Null, Datum::Null, ExprType::Xor),
+                Datum::Null,
+            ),
+            (
+                build_expr_r(
+                    vec![
+                        bin_expr(Datum::I64(1), Datum::I64(1), ExprType::EQ),
+                        bin_expr(Datum::I64(1), Datum::I64(2), ExprType::EQ),
+                    ],
+                    ExprType::Xor,
+                ),
+                Datum::I64(1),
+            ),
         ]
     );
 
@@ -1716,6 +2408,107 @@ pub mod test {
         ]
     );
 
+    #[test]
+    fn test_eval_null_propagation() {
+        let args = vec![
+            Datum::I64(1),
+            Datum::U64(1),
+            Datum::F64(1.0),
+            Datum::Dec(1.into()),
+            Datum::Bytes(b"1".to_vec()),
+            Datum::Dur(Duration::parse(b"00:00:01", 0).unwrap()),
+        ];
+        let ops = vec![
+            ExprType::Plus,
+            ExprType::Minus,
+            ExprType::Mul,
+            ExprType::Div,
+            ExprType::IntDiv,
+            ExprType::Mod,
+            ExprType::LT,
+            ExprType::LE,
+            ExprType::EQ,
+            ExprType::NE,
+            ExprType::GE,
+            ExprType::GT,
+            ExprType::Xor,
+        ];
+        let mut xevaluator = Evaluator::default();
+        for tp in ops {
+            for arg in &args {
+                for (l, r) in vec![(Datum::Null, arg.clone()), (arg.clone(), Datum::Null)] {
+                    let expr = bin_expr(l, r, tp);
+                    let res = xevaluator.eval(&Default::default(), &expr).unwrap();
+                    assert_eq!(res, Datum::Null, "{:?}", expr);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_eval_divide_by_zero() {
+        let mut xevaluator = Evaluator::default();
+        xevaluator.row.insert(1, Datum::I64(0));
+        let cases = vec![
+            bin_expr(Datum::I64(1), Datum::I64(0), ExprType::Div),
+            bin_expr(Datum::F64(1.0), Datum::F64(0.0), ExprType::Div),
+            bin_expr(Datum::Dec(1.into()), Datum::Dec(0.into()), ExprType::Div),
+            bin_expr(Datum::I64(1), Datum::I64(0), ExprType::IntDiv),
+            bin_expr(Datum::I64(1), Datum::U64(0), ExprType::IntDiv),
+            bin_expr(Datum::I64(1), Datum::Bytes(b"0".to_vec()), ExprType::IntDiv),
+            bin_expr(Datum::I64(1), Datum::I64(0), ExprType::Mod),
+            bin_expr(Datum::I64(1), Datum::U64(0), ExprType::Mod),
+            bin_expr(Datum::F64(1.0), Datum::F64(0.0), ExprType::Mod),
+            build_expr_r(vec![datum_expr(Datum::I64(1)), col_expr(1)], ExprType::Div),
+        ];
+        let insert_ctx = EvalContext::new(0, FLAG_IN_INSERT_STMT).unwrap();
+        let update_ctx = EvalContext::new(0, FLAG_IN_UPDATE_OR_DELETE_STMT).unwrap();
+        let ctx = EvalContext::default();
+        for expr in &cases {
+            assert!(xevaluator.eval(&insert_ctx, expr).is_err(), "{:?}", expr);
+            assert!(xevaluator.eval(&update_ctx, expr).is_err(), "{:?}", expr);
+            let res = xevaluator.eval(&ctx, expr).unwrap();
+            assert_eq!(res, Datum::Null, "{:?}", expr);
+        }
+        assert_eq!(ctx.warnings.borrow().warning_cnt, cases.len());
+    }
+
+    #[test]
+    fn test_eval_div_frac_incr() {
+        let mut xevaluator = Evaluator::default();
+        let expr = bin_expr(Datum::Dec(1.into()), Datum::Dec(3.into()), ExprType::Div);
+        let cases = vec![
+            (0, "0.3333"),
+            (1, "0"),
+            (3, "0.33"),
+            (9, "0.33333333"),
+        ];
+        for (bits, exp) in cases {
+            let ctx = EvalContext::new(0, bits << FLAG_DIV_FRAC_INCR_SHIFT).unwrap();
+            match xevaluator.eval(&ctx, &expr).unwrap() {
+                Datum::Dec(got) => assert_eq!(format!("{}", got), exp),
+                d => panic!("expect decimal, got {:?}", d),
+            }
+        }
+    }
+
+    #[test]
+    fn test_eval_plus_overflow() {
+        let mut xevaluator = Evaluator::default();
+        let cases = vec![
+            bin_expr(Datum::I64(i64::MAX), Datum::I64(1), ExprType::Plus),
+            bin_expr(Datum::I64(i64::MIN), Datum::I64(-1), ExprType::Plus),
+            bin_expr(Datum::U64(u64::MAX), Datum::I64(1), ExprType::Plus),
+            bin_expr(Datum::F64(f64::MAX), Datum::F64(f64::MAX), ExprType::Plus),
+        ];
+        let ctx = EvalContext::new(0, FLAG_IGNORE_OVERFLOW).unwrap();
+        for expr in cases {
+            assert!(xevaluator.eval(&Default::default(), &expr).is_err(), "{:?}", expr);
+            let res = xevaluator.eval(&ctx, &expr).unwrap();
+            assert_eq!(res, Datum::Null, "{:?}", expr);
+        }
+    }
+
     test_eval!(
         test_eval_case_when,
         vec![
@@ -1867,16 +2660,82 @@ pub mod test {
     );
 
     test_eval!(
-        test_eval_coalesce,
+        test_eval_if_case_branches,
         vec![
+            // IF(col < 2, 0, col) with a column of 100.
             (
-                coalesce(vec![Datum::Null, Datum::Null, Datum::Null]),
-                Datum::Null,
+                build_expr_r(
+                    vec![
+                        build_expr_r(vec![col_expr(1), datum_expr(Datum::I64(2))], ExprType::LT),
+                        datum_expr(Datum::I64(0)),
+                        col_expr(1),
+                    ],
+                    ExprType::If,
+                ),
+                Datum::I64(100),
             ),
             (
-                coalesce(vec![Datum::Null, b"not-null".as_ref().into(), Datum::Null]),
-                b"not-null".as_ref().into(),
-            ),
+                build_expr_r(
+                    vec![
+                        build_expr_r(vec![col_expr(1), datum_expr(Datum::I64(200))], ExprType::LT),
+                        datum_expr(Datum::I64(0)),
+                        col_expr(1),
+                    ],
+                    ExprType::If,
+                ),
+                Datum::I64(0),
+            ),
+            // the branches which aren't taken are never evaluated, so their errors are
+            // not raised.
+            (
+                build_expr_r(
+                    vec![
+                        datum_expr(true.into()),
+                        datum_expr(Datum::I64(1)),
+                        build_expr(vec![Datum::I64(i64::MAX)], ExprType::Space),
+                    ],
+                    ExprType::If,
+                ),
+                Datum::I64(1),
+            ),
+            (
+                build_expr_r(
+                    vec![
+                        datum_expr(false.into()),
+                        build_expr(vec![Datum::I64(i64::MAX)], ExprType::Space),
+                        datum_expr(Datum::I64(2)),
+                    ],
+                    ExprType::If,
+                ),
+                Datum::I64(2),
+            ),
+            (
+                build_expr_r(
+                    vec![
+                        datum_expr(Datum::I64(0)),
+                        build_expr(vec![Datum::I64(i64::MAX)], ExprType::Space),
+                        datum_expr(Datum::I64(1)),
+                        datum_expr(b"b".as_ref().into()),
+                        build_expr(vec![Datum::I64(i64::MAX)], ExprType::Space),
+                    ],
+                    ExprType::Case,
+                ),
+                b"b".as_ref().into(),
+            ),
+        ]
+    );
+
+    test_eval!(
+        test_eval_coalesce,
+        vec![
+            (
+                coalesce(vec![Datum::Null, Datum::Null, Datum::Null]),
+                Datum::Null,
+            ),
+            (
+                coalesce(vec![Datum::Null, b"not-null".as_ref().into(), Datum::Null]),
+                b"not-null".as_ref().into(),
+            ),
             (
                 coalesce(vec![
                     Datum::Null,
@@ -1889,6 +2748,588 @@ pub mod test {
         ]
     );
 
+    #[test]
+    fn test_eval_greatest_least_time() {
+        let time = |s: &str| Datum::Time(Time::parse_utc_datetime(s, 0).unwrap());
+        let mut xevaluator = Evaluator::default();
+        xevaluator.row.insert(1, time("2017-10-01 12:00:00"));
+        xevaluator.row.insert(2, time("2017-09-30 23:59:59"));
+        xevaluator.row.insert(3, Datum::Null);
+        let cols = |ids: &[i64]| ids.iter().map(|&id| col_expr(id)).collect::<Vec<_>>();
+        let cases = vec![
+            (build_expr_r(cols(&[1, 2]), ExprType::Greatest), time("2017-10-01 12:00:00")),
+            (build_expr_r(cols(&[1, 2]), ExprType::Least), time("2017-09-30 23:59:59")),
+            (build_expr_r(cols(&[2, 1]), ExprType::Greatest), time("2017-10-01 12:00:00")),
+            (build_expr_r(cols(&[1, 2, 3]), ExprType::Greatest), Datum::Null),
+            (build_expr_r(cols(&[3, 2]), ExprType::Least), Datum::Null),
+            // strings are compared as times, not as numbers or strings.
+            (
+                build_expr_r(
+                    vec![col_expr(1), datum_expr(b"2017-09-30 23:00:00".as_ref().into())],
+                    ExprType::Least,
+                ),
+                time("2017-09-30 23:00:00"),
+            ),
+            (
+                build_expr_r(
+                    vec![col_expr(2), datum_expr(b"2017-10-01".as_ref().into())],
+                    ExprType::Greatest,
+                ),
+                time("2017-10-01 00:00:00"),
+            ),
+        ];
+        for (expr, exp) in cases {
+            let res = xevaluator.eval(&Default::default(), &expr).unwrap();
+            assert_eq!(res, exp, "{:?}", expr);
+        }
+    }
+
+    #[test]
+    fn test_eval_date_diff_timestamp_diff() {
+        let time = |s: &str| Datum::Time(Time::parse_utc_datetime(s, 0).unwrap());
+        let unit = |s: &str| datum_expr(s.as_bytes().into());
+        let mut xevaluator = Evaluator::default();
+        xevaluator.row.insert(1, time("2017-10-01 12:00:00"));
+        xevaluator.row.insert(2, time("2017-12-03 11:00:00"));
+        xevaluator.row.insert(3, Datum::Null);
+        let cases = vec![
+            (build_expr_r(vec![col_expr(2), col_expr(1)], ExprType::DateDiff), Datum::I64(63)),
+            (build_expr_r(vec![col_expr(1), col_expr(2)], ExprType::DateDiff), Datum::I64(-63)),
+            (build_expr_r(vec![col_expr(1), col_expr(3)], ExprType::DateDiff), Datum::Null),
+            (
+                build_expr_r(
+                    vec![col_expr(1), datum_expr(b"2017-09-30 23:59:59".as_ref().into())],
+                    ExprType::DateDiff,
+                ),
+                Datum::I64(1),
+            ),
+            (
+                build_expr_r(
+                    vec![unit("MONTH"), col_expr(1), col_expr(2)],
+                    ExprType::TimestampDiff,
+                ),
+                Datum::I64(2),
+            ),
+            (
+                build_expr_r(
+                    vec![unit("day"), col_expr(1), col_expr(2)],
+                    ExprType::TimestampDiff,
+                ),
+                Datum::I64(62),
+            ),
+            (
+                build_expr_r(
+                    vec![unit("HOUR"), col_expr(2), col_expr(1)],
+                    ExprType::TimestampDiff,
+                ),
+                Datum::I64(-1511),
+            ),
+            (
+                build_expr_r(
+                    vec![unit("HOUR"), col_expr(3), col_expr(1)],
+                    ExprType::TimestampDiff,
+                ),
+                Datum::Null,
+            ),
+        ];
+        for (expr, exp) in cases {
+            let res = xevaluator.eval(&Default::default(), &expr).unwrap();
+            assert_eq!(res, exp, "{:?}", expr);
+        }
+
+        let errs = vec![
+            build_expr_r(
+                vec![unit("FORTNIGHT"), col_expr(1), col_expr(2)],
+                ExprType::TimestampDiff,
+            ),
+            build_expr_r(vec![col_expr(1), col_expr(2)], ExprType::TimestampDiff),
+            build_expr_r(vec![col_expr(1)], ExprType::DateDiff),
+        ];
+        for expr in errs {
+            assert!(xevaluator.eval(&Default::default(), &expr).is_err(), "{:?}", expr);
+        }
+    }
+
+    test_eval!(
+        test_eval_greatest_least,
+        vec![
+            (
+                build_expr(
+                    vec![Datum::I64(1), Datum::I64(3), Datum::I64(2)],
+                    ExprType::Greatest,
+                ),
+                Datum::I64(3),
+            ),
+            (
+                build_expr(
+                    vec![Datum::I64(1), Datum::Null, Datum::I64(2)],
+                    ExprType::Greatest,
+                ),
+                Datum::Null,
+            ),
+            (
+                build_expr(vec![Datum::Null, Datum::I64(2)], ExprType::Least),
+                Datum::Null,
+            ),
+            (
+                build_expr(vec![b"10".as_ref().into(), b"9".as_ref().into()], ExprType::Greatest),
+                b"9".as_ref().into(),
+            ),
+            // a mix of string and int is compared as double.
+            (
+                build_expr(vec![b"10".as_ref().into(), Datum::I64(9)], ExprType::Greatest),
+                Datum::F64(10.0),
+            ),
+            (
+                build_expr(vec![b"10".as_ref().into(), Datum::I64(9)], ExprType::Least),
+                Datum::F64(9.0),
+            ),
+            (
+                build_expr(
+                    vec![Datum::I64(2), Datum::Dec("1.5".parse().unwrap())],
+                    ExprType::Greatest,
+                ),
+                Datum::Dec(2.into()),
+            ),
+            (
+                build_expr(
+                    vec![Datum::I64(2), Datum::Dec("1.5".parse().unwrap())],
+                    ExprType::Least,
+                ),
+                Datum::Dec("1.5".parse().unwrap()),
+            ),
+        ]
+    );
+
+    test_eval!(
+        test_eval_date_format,
+        vec![
+            (
+                build_expr(
+                    vec![
+                        Datum::Time(Time::parse_utc_datetime("2017-09-05 14:03:09", 0).unwrap()),
+                        b"%Y-%m-%d %H:%i:%s".as_ref().into(),
+                    ],
+                    ExprType::DateFormat,
+                ),
+                b"2017-09-05 14:03:09".as_ref().into(),
+            ),
+            (
+                build_expr(
+                    vec![
+                        b"2017-09-05 14:03:09".as_ref().into(),
+                        b"%d/%m/%y %h%p".as_ref().into(),
+                    ],
+                    ExprType::DateFormat,
+                ),
+                b"05/09/17 02PM".as_ref().into(),
+            ),
+            (
+                build_expr(
+                    vec![Datum::Null, b"%Y".as_ref().into()],
+                    ExprType::DateFormat,
+                ),
+                Datum::Null,
+            ),
+        ]
+    );
+
+    test_eval!(
+        test_eval_concat_ws,
+        vec![
+            (
+                build_expr(
+                    vec![
+                        b",".as_ref().into(),
+                        b"a".as_ref().into(),
+                        Datum::Null,
+                        b"b".as_ref().into(),
+                    ],
+                    ExprType::ConcatWS,
+                ),
+                b"a,b".as_ref().into(),
+            ),
+            (
+                build_expr(
+                    vec![b"-".as_ref().into(), Datum::Null, b"a".as_ref().into()],
+                    ExprType::ConcatWS,
+                ),
+                b"a".as_ref().into(),
+            ),
+            (
+                build_expr(
+                    vec![b"-".as_ref().into(), Datum::I64(1), b"".as_ref().into()],
+                    ExprType::ConcatWS,
+                ),
+                b"1-".as_ref().into(),
+            ),
+            (
+                build_expr(vec![b"-".as_ref().into(), Datum::Null], ExprType::ConcatWS),
+                b"".as_ref().into(),
+            ),
+            (
+                build_expr(
+                    vec![Datum::Null, b"a".as_ref().into(), b"b".as_ref().into()],
+                    ExprType::ConcatWS,
+                ),
+                Datum::Null,
+            ),
+        ]
+    );
+
+    test_eval!(
+        test_eval_bin_oct,
+        vec![
+            (build_expr(vec![Datum::I64(12)], ExprType::Bin), b"1100".as_ref().into()),
+            (build_expr(vec![Datum::I64(12)], ExprType::Oct), b"14".as_ref().into()),
+            (build_expr(vec![Datum::I64(0)], ExprType::Bin), b"0".as_ref().into()),
+            (build_expr(vec![Datum::U64(64)], ExprType::Oct), b"100".as_ref().into()),
+            (
+                build_expr(vec![Datum::I64(-1)], ExprType::Bin),
+                Datum::Bytes(vec![b'1'; 64]),
+            ),
+            (
+                build_expr(vec![Datum::I64(-1)], ExprType::Oct),
+                b"1777777777777777777777".as_ref().into(),
+            ),
+            (build_expr(vec![b"10".as_ref().into()], ExprType::Bin), b"1010".as_ref().into()),
+            (build_expr(vec![Datum::Null], ExprType::Bin), Datum::Null),
+            (build_expr(vec![Datum::Null], ExprType::Oct), Datum::Null),
+            // column 1 holds 100.
+            (build_expr_r(vec![col_expr(1)], ExprType::Bin), b"1100100".as_ref().into()),
+            (build_expr_r(vec![col_expr(1)], ExprType::Oct), b"144".as_ref().into()),
+        ]
+    );
+
+    test_eval!(
+        test_eval_space_repeat_reverse,
+        vec![
+            (build_expr(vec![Datum::I64(3)], ExprType::Space), b"   ".as_ref().into()),
+            (build_expr(vec![Datum::I64(0)], ExprType::Space), b"".as_ref().into()),
+            (build_expr(vec![Datum::I64(-1)], ExprType::Space), b"".as_ref().into()),
+            (build_expr(vec![Datum::Null], ExprType::Space), Datum::Null),
+            (
+                build_expr(vec![b"ab".as_ref().into(), Datum::I64(3)], ExprType::Repeat),
+                b"ababab".as_ref().into(),
+            ),
+            (
+                build_expr(vec![b"ab".as_ref().into(), Datum::I64(-2)], ExprType::Repeat),
+                b"".as_ref().into(),
+            ),
+            (
+                build_expr(vec![b"".as_ref().into(), Datum::I64(i64::MAX)], ExprType::Repeat),
+                b"".as_ref().into(),
+            ),
+            (
+                build_expr(vec![Datum::I64(12), b"2".as_ref().into()], ExprType::Repeat),
+                b"1212".as_ref().into(),
+            ),
+            (build_expr(vec![Datum::Null, Datum::I64(2)], ExprType::Repeat), Datum::Null),
+            (
+                build_expr(vec![b"ab".as_ref().into(), Datum::Null], ExprType::Repeat),
+                Datum::Null,
+            ),
+            (build_expr(vec![b"abc".as_ref().into()], ExprType::Reverse), b"cba".as_ref().into()),
+            (build_expr(vec![b"".as_ref().into()], ExprType::Reverse), b"".as_ref().into()),
+            (build_expr(vec![Datum::Null], ExprType::Reverse), Datum::Null),
+            // column 1 holds 100.
+            (build_expr_r(vec![col_expr(1)], ExprType::Reverse), b"001".as_ref().into()),
+            (
+                build_expr_r(
+                    vec![build_expr(vec![Datum::I64(2)], ExprType::Space)],
+                    ExprType::Reverse,
+                ),
+                b"  ".as_ref().into(),
+            ),
+        ]
+    );
+
+    test_eval!(
+        test_eval_length_concat,
+        vec![
+            (build_expr(vec![b"abc".as_ref().into()], ExprType::Length), Datum::I64(3)),
+            (build_expr(vec!["é".as_bytes().into()], ExprType::Length), Datum::I64(2)),
+            (build_expr(vec![b"".as_ref().into()], ExprType::Length), Datum::I64(0)),
+            (build_expr(vec![Datum::Null], ExprType::Length), Datum::Null),
+            // column 1 holds 100.
+            (build_expr_r(vec![col_expr(1)], ExprType::Length), Datum::I64(3)),
+            (
+                build_expr(
+                    vec![b"a".as_ref().into(), Datum::I64(1), b"".as_ref().into()],
+                    ExprType::Concat,
+                ),
+                b"a1".as_ref().into(),
+            ),
+            (build_expr(vec![b"a".as_ref().into()], ExprType::Concat), b"a".as_ref().into()),
+            (
+                build_expr(vec![b"a".as_ref().into(), Datum::Null], ExprType::Concat),
+                Datum::Null,
+            ),
+        ]
+    );
+
+    test_eval!(
+        test_eval_substring,
+        vec![
+            (
+                build_expr(
+                    vec![b"name:0".as_ref().into(), Datum::I64(1), Datum::I64(4)],
+                    ExprType::Substring,
+                ),
+                b"name".as_ref().into(),
+            ),
+            (
+                build_expr(vec![b"name:0".as_ref().into(), Datum::I64(5)], ExprType::Substring),
+                b":0".as_ref().into(),
+            ),
+            (
+                build_expr(vec![b"name:0".as_ref().into(), Datum::I64(-2)], ExprType::Substring),
+                b":0".as_ref().into(),
+            ),
+            (
+                build_expr(
+                    vec![b"name:0".as_ref().into(), Datum::I64(-6), Datum::I64(2)],
+                    ExprType::Substring,
+                ),
+                b"na".as_ref().into(),
+            ),
+            (
+                build_expr(
+                    vec![b"name:0".as_ref().into(), Datum::I64(3), Datum::I64(100)],
+                    ExprType::Substring,
+                ),
+                b"me:0".as_ref().into(),
+            ),
+            (
+                build_expr(vec![b"name:0".as_ref().into(), Datum::I64(0)], ExprType::Substring),
+                b"".as_ref().into(),
+            ),
+            (
+                build_expr(vec![b"name:0".as_ref().into(), Datum::I64(7)], ExprType::Substring),
+                b"".as_ref().into(),
+            ),
+            (
+                build_expr(vec![b"name:0".as_ref().into(), Datum::I64(-7)], ExprType::Substring),
+                b"".as_ref().into(),
+            ),
+            (
+                build_expr(
+                    vec![b"name:0".as_ref().into(), Datum::I64(1), Datum::I64(-1)],
+                    ExprType::Substring,
+                ),
+                b"".as_ref().into(),
+            ),
+            (
+                build_expr(vec![Datum::Null, Datum::I64(1)], ExprType::Substring),
+                Datum::Null,
+            ),
+            (
+                build_expr(
+                    vec![b"name:0".as_ref().into(), Datum::I64(1), Datum::Null],
+                    ExprType::Substring,
+                ),
+                Datum::Null,
+            ),
+        ]
+    );
+
+    test_eval!(
+        test_eval_upper_lower,
+        vec![
+            (build_expr(vec![b"aBc1".as_ref().into()], ExprType::Upper), b"ABC1".as_ref().into()),
+            (build_expr(vec![b"aBc1".as_ref().into()], ExprType::Lower), b"abc1".as_ref().into()),
+            (build_expr(vec!["é".as_bytes().into()], ExprType::Upper), "é".as_bytes().into()),
+            (build_expr(vec![Datum::Null], ExprType::Upper), Datum::Null),
+            (build_expr(vec![Datum::Null], ExprType::Lower), Datum::Null),
+        ]
+    );
+
+    test_eval!(
+        test_eval_locate_position,
+        vec![
+            (
+                build_expr(
+                    vec![b"bar".as_ref().into(), b"foobarbar".as_ref().into()],
+                    ExprType::Locate,
+                ),
+                Datum::I64(4),
+            ),
+            (
+                build_expr(
+                    vec![b"xbar".as_ref().into(), b"foobar".as_ref().into()],
+                    ExprType::Locate,
+                ),
+                Datum::I64(0),
+            ),
+            (
+                build_expr(vec![b"".as_ref().into(), b"foobar".as_ref().into()], ExprType::Locate),
+                Datum::I64(1),
+            ),
+            (
+                build_expr(vec![b"b".as_ref().into(), "éab".as_bytes().into()], ExprType::Locate),
+                Datum::I64(3),
+            ),
+            (
+                build_expr(vec![Datum::Null, b"foobar".as_ref().into()], ExprType::Locate),
+                Datum::Null,
+            ),
+            (
+                build_expr(
+                    vec![b"foobarbar".as_ref().into(), b"bar".as_ref().into()],
+                    ExprType::Position,
+                ),
+                Datum::I64(4),
+            ),
+            (
+                build_expr(vec![b"foobar".as_ref().into(), Datum::Null], ExprType::Position),
+                Datum::Null,
+            ),
+            // column 1 holds 100.
+            (
+                build_expr_r(vec![col_expr(1), datum_expr(Datum::I64(0))], ExprType::Position),
+                Datum::I64(2),
+            ),
+        ]
+    );
+
+    test_eval!(
+        test_eval_ascii_ord,
+        vec![
+            (build_expr(vec![b"name".as_ref().into()], ExprType::Ascii), Datum::I64(110)),
+            (build_expr(vec![b"".as_ref().into()], ExprType::Ascii), Datum::I64(0)),
+            (build_expr(vec![Datum::Null], ExprType::Ascii), Datum::Null),
+            (build_expr(vec![b"\xc3\xa9".as_ref().into()], ExprType::Ascii), Datum::I64(0xc3)),
+            (build_expr(vec![b"name".as_ref().into()], ExprType::Ord), Datum::I64(110)),
+            (build_expr(vec![b"".as_ref().into()], ExprType::Ord), Datum::I64(0)),
+            (build_expr(vec![Datum::Null], ExprType::Ord), Datum::Null),
+            (build_expr(vec![b"\xc3\xa9".as_ref().into()], ExprType::Ord), Datum::I64(0xc3a9)),
+            (
+                build_expr(vec![b"\xe2\x82\xac1".as_ref().into()], ExprType::Ord),
+                Datum::I64(0xe282ac),
+            ),
+            (
+                build_expr(vec![b"\xf0\x9f\x98\x80".as_ref().into()], ExprType::Ord),
+                Datum::I64(0xf09f9880),
+            ),
+            // column 1 holds 100.
+            (build_expr_r(vec![col_expr(1)], ExprType::Ascii), Datum::I64(49)),
+            (build_expr_r(vec![col_expr(1)], ExprType::Ord), Datum::I64(49)),
+        ]
+    );
+
+    test_eval!(
+        test_eval_crc32,
+        vec![
+            (build_expr(vec![b"MySQL".as_ref().into()], ExprType::Crc32), Datum::U64(3259397556)),
+            (build_expr(vec![b"name:0".as_ref().into()], ExprType::Crc32), Datum::U64(2739820980)),
+            (build_expr(vec![b"".as_ref().into()], ExprType::Crc32), Datum::U64(0)),
+            (build_expr(vec![Datum::Null], ExprType::Crc32), Datum::Null),
+            // column 1 holds 100.
+            (build_expr_r(vec![col_expr(1)], ExprType::Crc32), Datum::U64(595022058)),
+        ]
+    );
+
+    test_eval_err!(
+        test_eval_space_repeat_overflow,
+        vec![
+            build_expr(vec![Datum::I64(i64::MAX)], ExprType::Space),
+            build_expr(vec![Datum::U64(u64::MAX)], ExprType::Space),
+            build_expr(vec![Datum::I64(MAX_BLOB_WIDTH as i64 + 1)], ExprType::Space),
+            build_expr(vec![b"ab".as_ref().into(), Datum::I64(i64::MAX)], ExprType::Repeat),
+            build_expr(
+                vec![b"ab".as_ref().into(), Datum::I64(MAX_BLOB_WIDTH as i64 / 2 + 1)],
+                ExprType::Repeat,
+            ),
+        ]
+    );
+
+    test_eval!(
+        test_eval_make_date_time,
+        vec![
+            (
+                build_expr(vec![Datum::I64(2017), Datum::I64(32)], ExprType::MakeDate),
+                Datum::Time(Time::parse_utc_datetime("2017-02-01", 0).unwrap()),
+            ),
+            (
+                build_expr(vec![Datum::I64(17), Datum::I64(1)], ExprType::MakeDate),
+                Datum::Time(Time::parse_utc_datetime("2017-01-01", 0).unwrap()),
+            ),
+            (
+                build_expr(vec![Datum::I64(99), Datum::I64(60)], ExprType::MakeDate),
+                Datum::Time(Time::parse_utc_datetime("1999-03-01", 0).unwrap()),
+            ),
+            (
+                build_expr(vec![Datum::U64(2016), Datum::I64(366)], ExprType::MakeDate),
+                Datum::Time(Time::parse_utc_datetime("2016-12-31", 0).unwrap()),
+            ),
+            (
+                build_expr(vec![Datum::I64(2017), Datum::I64(0)], ExprType::MakeDate),
+                Datum::Null,
+            ),
+            (
+                build_expr(vec![Datum::I64(10000), Datum::I64(1)], ExprType::MakeDate),
+                Datum::Null,
+            ),
+            (
+                build_expr(vec![Datum::I64(9999), Datum::I64(366)], ExprType::MakeDate),
+                Datum::Null,
+            ),
+            (
+                build_expr(vec![Datum::Null, Datum::I64(1)], ExprType::MakeDate),
+                Datum::Null,
+            ),
+            (
+                build_expr(
+                    vec![Datum::I64(12), Datum::I64(15), Datum::I64(30)],
+                    ExprType::MakeTime,
+                ),
+                Datum::Dur(Duration::parse(b"12:15:30", 0).unwrap()),
+            ),
+            (
+                build_expr(
+                    vec![Datum::I64(12), Datum::I64(15), Datum::F64(30.5)],
+                    ExprType::MakeTime,
+                ),
+                Datum::Dur(Duration::parse(b"12:15:30.5", MAX_FSP).unwrap()),
+            ),
+            (
+                build_expr(
+                    vec![Datum::I64(-1), Datum::I64(30), Datum::I64(0)],
+                    ExprType::MakeTime,
+                ),
+                Datum::Dur(Duration::parse(b"-01:30:00", 0).unwrap()),
+            ),
+            (
+                build_expr(
+                    vec![Datum::I64(900), Datum::I64(0), Datum::I64(0)],
+                    ExprType::MakeTime,
+                ),
+                Datum::Dur(Duration::parse(b"838:59:59", 0).unwrap()),
+            ),
+            (
+                build_expr(
+                    vec![Datum::I64(12), Datum::I64(60), Datum::I64(0)],
+                    ExprType::MakeTime,
+                ),
+                Datum::Null,
+            ),
+            (
+                build_expr(
+                    vec![Datum::I64(12), Datum::I64(0), Datum::F64(60.0)],
+                    ExprType::MakeTime,
+                ),
+                Datum::Null,
+            ),
+            (
+                build_expr(
+                    vec![Datum::I64(12), Datum::Null, Datum::I64(0)],
+                    ExprType::MakeTime,
+                ),
+                Datum::Null,
+            ),
+        ]
+    );
+
     test_eval!(
         test_eval_if_null,
         vec![
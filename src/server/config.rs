@@ -16,7 +16,9 @@ use std::ascii::AsciiExt;
 use sys_info;
 
 use util::collections::HashMap;
-use util::config::{self, ReadableSize};
+use util::config::{self, ReadableDuration, ReadableSize};
+use coprocessor::select::xeval::evaluator::{DEFAULT_GROUP_CONCAT_MAX_LEN,
+                                            DEFAULT_MAX_WARNING_CNT};
 
 use super::Result;
 
@@ -58,8 +60,26 @@ pub struct Config {
     pub grpc_concurrent_stream: usize,
     pub grpc_raft_conn_num: usize,
     pub grpc_stream_initial_window_size: ReadableSize,
+    // The size of the thread pool for normal priority coprocessor requests.
     pub end_point_concurrency: usize,
+    // The size of the thread pool for high priority coprocessor requests.
+    pub end_point_high_concurrency: usize,
+    // The size of the thread pool for low priority coprocessor requests.
+    pub end_point_low_concurrency: usize,
     pub end_point_max_tasks: usize,
+    pub end_point_max_warning_cnt: usize,
+    // The length GROUP_CONCAT results are truncated to.
+    pub end_point_group_concat_max_len: ReadableSize,
+    // The time every DAG executor may spend on its own work, 0 means no limit.
+    pub end_point_executor_timeout: ReadableDuration,
+    // The bytes of keys and values a DAG request may scan, 0 means no limit.
+    pub end_point_scan_bytes_limit: ReadableSize,
+    // The bytes of coprocessor responses to cache, 0 disables the cache.
+    pub end_point_result_cache_size: ReadableSize,
+    // The memory the rows buffered by a DAG request may take, 0 means no limit.
+    pub end_point_memory_quota: ReadableSize,
+    // The number of key ranges a coprocessor request may carry, 0 means no limit.
+    pub end_point_max_ranges: usize,
     // Server labels to specify some attributes about this server.
     #[serde(with = "config::order_map_serde")]
     pub labels: HashMap<String, String>,
@@ -85,7 +105,16 @@ impl Default for Config {
             grpc_raft_conn_num: DEFAULT_GRPC_RAFT_CONN_NUM,
             grpc_stream_initial_window_size: ReadableSize(DEFAULT_GRPC_STREAM_INITIAL_WINDOW_SIZE),
             end_point_concurrency: concurrency,
+            end_point_high_concurrency: concurrency,
+            end_point_low_concurrency: concurrency,
             end_point_max_tasks: DEFAULT_MAX_RUNNING_TASK_COUNT,
+            end_point_max_warning_cnt: DEFAULT_MAX_WARNING_CNT,
+            end_point_group_concat_max_len: ReadableSize(DEFAULT_GROUP_CONCAT_MAX_LEN as u64),
+            end_point_executor_timeout: ReadableDuration::secs(0),
+            end_point_scan_bytes_limit: ReadableSize(0),
+            end_point_result_cache_size: ReadableSize(0),
+            end_point_memory_quota: ReadableSize(0),
+            end_point_max_ranges: 0,
         }
     }
 }
@@ -110,10 +139,22 @@ impl Config {
             return Err(box_err!("server.end-point-concurrency should not be 0."));
         }
 
+        if self.end_point_high_concurrency == 0 {
+            return Err(box_err!("server.end-point-high-concurrency should not be 0."));
+        }
+
+        if self.end_point_low_concurrency == 0 {
+            return Err(box_err!("server.end-point-low-concurrency should not be 0."));
+        }
+
         if self.end_point_max_tasks == 0 {
             return Err(box_err!("server.end-point-max-tasks should not be 0."));
         }
 
+        if self.end_point_max_warning_cnt == 0 {
+            return Err(box_err!("server.end-point-max-warning-cnt should not be 0."));
+        }
+
         for (k, v) in &self.labels {
             validate_label(k, "key")?;
             validate_label(v, "value")?;
@@ -169,10 +210,22 @@ mod tests {
         invalid_cfg.end_point_concurrency = 0;
         assert!(invalid_cfg.validate().is_err());
 
+        let mut invalid_cfg = cfg.clone();
+        invalid_cfg.end_point_high_concurrency = 0;
+        assert!(invalid_cfg.validate().is_err());
+
+        let mut invalid_cfg = cfg.clone();
+        invalid_cfg.end_point_low_concurrency = 0;
+        assert!(invalid_cfg.validate().is_err());
+
         let mut invalid_cfg = cfg.clone();
         invalid_cfg.end_point_max_tasks = 0;
         assert!(invalid_cfg.validate().is_err());
 
+        let mut invalid_cfg = cfg.clone();
+        invalid_cfg.end_point_max_warning_cnt = 0;
+        assert!(invalid_cfg.validate().is_err());
+
         invalid_cfg = Config::default();
         invalid_cfg.addr = "0.0.0.0:1000".to_owned();
         assert!(invalid_cfg.validate().is_err());
@@ -16,6 +16,7 @@ use std::fmt::Debug;
 use std::io::Write;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
 use mio::Token;
 use grpc::{ClientStreamingSink, RequestStream, RpcContext, RpcStatus, RpcStatusCode, UnarySink};
 use futures::{future, Future, Stream};
@@ -515,13 +516,19 @@ impl<T: RaftStoreRouter + 'static> tikvpb_grpc::Tikv for Service<T> {
             .with_label_values(&[label])
             .start_coarse_timer();
 
+        let safe_point = req.get_safe_point();
         let (cb, future) = make_callback();
-        let res = self.storage
-            .async_gc(req.take_context(), req.get_safe_point(), cb);
+        let res = self.storage.async_gc(req.take_context(), safe_point, cb);
         if let Err(e) = res {
             self.send_fail_status(ctx, sink, Error::from(e), RpcStatusCode::ResourceExhausted);
             return;
         }
+        // coprocessor requests older than the safe point may read collected versions.
+        if let Err(e) = self.end_point_scheduler
+            .schedule(EndPointTask::UpdateSafePoint(safe_point))
+        {
+            warn!("failed to update the safe point of the end point: {:?}", e);
+        }
 
         let future = future
             .map_err(Error::from)
@@ -748,8 +755,14 @@ impl<T: RaftStoreRouter + 'static> tikvpb_grpc::Tikv for Service<T> {
             .start_coarse_timer();
 
         let (cb, future) = make_callback();
-        let res = self.end_point_scheduler
-            .schedule(EndPointTask::Request(RequestTask::new(req, cb)));
+        // the client may bound the time the request can take, which is aborted once it
+        // runs past it instead of occupying a worker.
+        let timeout = req.get_context().get_max_execution_duration_ms();
+        let mut task = RequestTask::new(req, cb);
+        if timeout > 0 {
+            task.set_timeout(Duration::from_millis(timeout));
+        }
+        let res = self.end_point_scheduler.schedule(EndPointTask::Request(task));
         if let Err(e) = res {
             self.send_fail_status(ctx, sink, Error::from(e), RpcStatusCode::ResourceExhausted);
             return;
@@ -20,8 +20,9 @@ use std::time::Duration;
 pub use self::rocksdb::EngineRocksdb;
 use rocksdb::TablePropertiesCollection;
 use storage::{CfName, Key, Value, CF_DEFAULT, CF_LOCK, CF_WRITE};
-use kvproto::kvrpcpb::Context;
+use kvproto::kvrpcpb::{Context, ScanDetail, ScanInfo};
 use kvproto::errorpb::Error as ErrorHeader;
+use kvproto::metapb::Region;
 
 mod rocksdb;
 pub mod raftkv;
@@ -42,6 +43,7 @@ const STAT_PREV: &'static str = "prev";
 const STAT_SEEK: &'static str = "seek";
 const STAT_SEEK_FOR_PREV: &'static str = "seek_for_prev";
 const STAT_OVER_SEEK_BOUND: &'static str = "over_seek_bound";
+const STAT_TOMBSTONE: &'static str = "tombstone";
 
 pub type Callback<T> = Box<FnBox((CbContext, Result<T>)) + Send>;
 pub type BatchResults<T> = Vec<Option<(CbContext, Result<T>)>>;
@@ -135,6 +137,10 @@ pub trait Snapshot: Send {
     fn get_properties_cf(&self, _: CfName) -> Result<TablePropertiesCollection> {
         Err(Error::RocksDb("no user properties".to_owned()))
     }
+    /// Returns the region the snapshot is bounded to, if any. Keys out of it are never read.
+    fn get_region(&self) -> Option<&Region> {
+        None
+    }
     fn clone(&self) -> Box<Snapshot>;
 }
 
@@ -187,6 +193,8 @@ pub struct CFStatistics {
     pub seek: usize,
     pub seek_for_prev: usize,
     pub over_seek_bound: usize,
+    // How many deleted keys are skipped by reads, which only counts for the write cf.
+    pub tombstone: usize,
     pub flow_stats: FlowStatistics,
 }
 
@@ -219,9 +227,17 @@ impl CFStatistics {
             (STAT_SEEK, self.seek),
             (STAT_SEEK_FOR_PREV, self.seek_for_prev),
             (STAT_OVER_SEEK_BOUND, self.over_seek_bound),
+            (STAT_TOMBSTONE, self.tombstone),
         ]
     }
 
+    pub fn scan_info(&self) -> ScanInfo {
+        let mut info = ScanInfo::new();
+        info.set_processed(self.processed as i64);
+        info.set_total(self.total_op_count() as i64);
+        info
+    }
+
     pub fn add(&mut self, other: &Self) {
         self.processed = self.processed.saturating_add(other.processed);
         self.get = self.get.saturating_add(other.get);
@@ -230,6 +246,7 @@ impl CFStatistics {
         self.seek = self.seek.saturating_add(other.seek);
         self.seek_for_prev = self.seek_for_prev.saturating_add(other.seek_for_prev);
         self.over_seek_bound = self.over_seek_bound.saturating_add(other.over_seek_bound);
+        self.tombstone = self.tombstone.saturating_add(other.tombstone);
         self.flow_stats.add(&other.flow_stats);
     }
 }
@@ -258,6 +275,14 @@ impl Statistics {
         ]
     }
 
+    pub fn scan_detail(&self) -> ScanDetail {
+        let mut detail = ScanDetail::new();
+        detail.set_data(self.data.scan_info());
+        detail.set_lock(self.lock.scan_info());
+        detail.set_write(self.write.scan_info());
+        detail
+    }
+
     pub fn add(&mut self, other: &Self) {
         self.lock.add(&other.lock);
         self.write.add(&other.write);
@@ -23,6 +23,7 @@ use kvproto::raft_cmdpb::{CmdType, DeleteRangeRequest, DeleteRequest, PutRequest
                           RaftCmdResponse, RaftRequestHeader, Request, Response};
 use kvproto::errorpb;
 use kvproto::kvrpcpb::Context;
+use kvproto::metapb::Region;
 
 use std::sync::Arc;
 use std::fmt::{self, Debug, Formatter};
@@ -498,6 +499,10 @@ impl Snapshot for RegionSnapshot {
         RegionSnapshot::get_properties_cf(self, cf).map_err(|e| e.into())
     }
 
+    fn get_region(&self) -> Option<&Region> {
+        Some(RegionSnapshot::get_region(self))
+    }
+
     fn clone(&self) -> Box<Snapshot> {
         Box::new(RegionSnapshot::clone(self))
     }
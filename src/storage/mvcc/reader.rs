@@ -37,6 +37,8 @@ pub struct MvccReader<'a> {
     fill_cache: bool,
     upper_bound: Option<Vec<u8>>,
     isolation_level: IsolationLevel,
+    // the commit ts of the value last returned by a seek.
+    last_commit_ts: u64,
 }
 
 impl<'a> MvccReader<'a> {
@@ -59,6 +61,7 @@ impl<'a> MvccReader<'a> {
             key_only: false,
             fill_cache: fill_cache,
             upper_bound: upper_bound,
+            last_commit_ts: 0,
         }
     }
 
@@ -70,6 +73,12 @@ impl<'a> MvccReader<'a> {
         self.key_only = key_only;
     }
 
+    /// `last_commit_ts` returns the commit ts of the value last returned by `seek` or
+    /// `reverse_seek`.
+    pub fn last_commit_ts(&self) -> u64 {
+        self.last_commit_ts
+    }
+
     pub fn load_data(&mut self, key: &Key, ts: u64) -> Result<Value> {
         if self.key_only {
             return Ok(vec![]);
@@ -211,7 +220,12 @@ impl<'a> MvccReader<'a> {
         Ok(Some(ts))
     }
 
-    pub fn get(&mut self, key: &Key, mut ts: u64) -> Result<Option<Value>> {
+    pub fn get(&mut self, key: &Key, ts: u64) -> Result<Option<Value>> {
+        Ok(self.get_with_commit_ts(key, ts)?.map(|(_, v)| v))
+    }
+
+    /// `get_with_commit_ts` is like `get`, but returns the commit ts of the value too.
+    pub fn get_with_commit_ts(&mut self, key: &Key, mut ts: u64) -> Result<Option<(u64, Value)>> {
         // Check for locks that signal concurrent writes.
         match self.isolation_level {
             IsolationLevel::SI => if let Some(new_ts) = self.check_lock(key, ts)? {
@@ -225,13 +239,16 @@ impl<'a> MvccReader<'a> {
                     WriteType::Put => {
                         if write.short_value.is_some() {
                             if self.key_only {
-                                return Ok(Some(vec![]));
+                                return Ok(Some((commit_ts, vec![])));
                             }
-                            return Ok(write.short_value.take());
+                            return Ok(write.short_value.take().map(|v| (commit_ts, v)));
                         }
-                        return self.load_data(key, write.start_ts).map(Some);
+                        let value = self.load_data(key, write.start_ts)?;
+                        return Ok(Some((commit_ts, value)));
                     }
                     WriteType::Delete => {
+                        // a tombstone has no value to load, the key is just skipped.
+                        self.statistics.write.tombstone += 1;
                         return Ok(None);
                     }
                     WriteType::Lock | WriteType::Rollback => ts = commit_ts - 1,
@@ -344,7 +361,8 @@ impl<'a> MvccReader<'a> {
                     },
                 }
             };
-            if let Some(v) = self.get(&key, ts)? {
+            if let Some((commit_ts, v)) = self.get_with_commit_ts(&key, ts)? {
+                self.last_commit_ts = commit_ts;
                 return Ok(Some((key, v)));
             }
             key = key.append_ts(0);
@@ -390,7 +408,8 @@ impl<'a> MvccReader<'a> {
                     },
                 }
             };
-            if let Some(v) = self.get(&key, ts)? {
+            if let Some((commit_ts, v)) = self.get_with_commit_ts(&key, ts)? {
+                self.last_commit_ts = commit_ts;
                 return Ok(Some((key, v)));
             }
         }
@@ -551,7 +570,7 @@ mod tests {
     use std::sync::Arc;
     use storage::{make_key, Mutation, Options, Statistics, ALL_CFS, CF_DEFAULT, CF_LOCK, CF_RAFT,
                   CF_WRITE};
-    use storage::engine::Modify;
+    use storage::engine::{Modify, ScanMode};
     use storage::mvcc::{MvccReader, MvccTxn};
     use tempdir::TempDir;
     use raftstore::coprocessor::RegionSnapshot;
@@ -724,6 +743,52 @@ mod tests {
         assert!(check_need_gc(db.clone(), region.clone(), 10, true).is_none());
     }
 
+    #[test]
+    fn test_seek_skip_tombstones() {
+        let path = TempDir::new("_test_storage_mvcc_reader_tombstones").expect("");
+        let path = path.path().to_str().unwrap();
+        let region = make_region(1, vec![0], vec![10]);
+        let db = open_db(path, false);
+        let mut engine = RegionEngine::new(db.clone(), region.clone());
+
+        for i in 1..7 {
+            engine.put(&[i], 1, 2);
+        }
+        engine.delete(&[2], 3, 4);
+        engine.delete(&[4], 5, 6);
+        engine.delete(&[5], 5, 6);
+        engine.put(&[6], 7, 8);
+        engine.delete(&[6], 9, 10);
+
+        // deleted keys are skipped, and counted as tombstones.
+        let snap = RegionSnapshot::from_raw(db.clone(), region.clone());
+        let mut stat = Statistics::default();
+        let mut keys = vec![];
+        {
+            let mut reader = MvccReader::new(
+                &snap,
+                &mut stat,
+                Some(ScanMode::Forward),
+                false,
+                None,
+                IsolationLevel::SI,
+            );
+            let mut key = make_key(&[0]);
+            while let Some((k, _)) = reader.seek(key, 10).unwrap() {
+                keys.push(k.raw().unwrap());
+                key = k.append_ts(0);
+            }
+        }
+        assert_eq!(keys, vec![vec![1], vec![3]]);
+        assert_eq!(stat.write.tombstone, 4);
+
+        // while a version before the deletion is still seen.
+        let mut stat = Statistics::default();
+        let mut reader = MvccReader::new(&snap, &mut stat, None, false, None, IsolationLevel::SI);
+        assert!(reader.get(&make_key(&[6]), 8).unwrap().is_some());
+        assert!(reader.get(&make_key(&[6]), 10).unwrap().is_none());
+    }
+
     #[allow(cyclomatic_complexity)]
     fn test_with_properties(path: &str, region: &Region) {
         let db = open_db(path, true);
@@ -51,6 +51,24 @@ impl<'a> SnapshotStore<'a> {
         Ok(v)
     }
 
+    /// `get_with_commit_ts` is like `get`, but returns the commit ts of the value too.
+    pub fn get_with_commit_ts(
+        &self,
+        key: &Key,
+        statistics: &mut Statistics,
+    ) -> Result<Option<(u64, Value)>> {
+        let mut reader = MvccReader::new(
+            self.snapshot,
+            statistics,
+            None,
+            self.fill_cache,
+            None,
+            self.isolation_level,
+        );
+        let v = reader.get_with_commit_ts(key, self.start_ts)?;
+        Ok(v)
+    }
+
     pub fn batch_get(
         &self,
         keys: &[Key],
@@ -111,6 +129,12 @@ impl<'a> StoreScanner<'a> {
         Ok(self.reader.reverse_seek(key, self.start_ts)?)
     }
 
+    /// `last_commit_ts` returns the commit ts of the value last returned by `seek` or
+    /// `reverse_seek`.
+    pub fn last_commit_ts(&self) -> u64 {
+        self.reader.last_commit_ts()
+    }
+
     #[inline]
     fn handle_mvcc_err(e: MvccError, result: &mut Vec<Result<KvPair>>) -> Result<Key> {
         let key = if let MvccError::KeyIsLocked { key: ref k, .. } = e {
@@ -275,6 +299,10 @@ mod test {
             let key = key.as_bytes();
             let data = snapshot_store.get(&make_key(key), &mut statistics).unwrap();
             assert!(data.is_some(), "{:?} expect some, but got none", key);
+            let data = snapshot_store
+                .get_with_commit_ts(&make_key(key), &mut statistics)
+                .unwrap();
+            assert_eq!(data, Some((COMMIT_TS, key.to_vec())));
         }
     }
 
@@ -363,6 +391,7 @@ mod test {
         let expect_value = expect_key.clone().into_bytes();
         let expect = Some((make_key(expect_key.as_bytes()), expect_value as Value));
         assert_eq!(result, expect, "expect {:?}, but got {:?}", expect, result);
+        assert_eq!(scanner.last_commit_ts(), COMMIT_TS);
     }
 
     #[test]
@@ -33,6 +33,8 @@ fn order_decode_i64(u: u64) -> i64 {
 }
 
 fn order_encode_f64(v: f64) -> u64 {
+    // -0.0 equals 0.0, so they must be encoded to the same bytes.
+    let v = if v == 0.0 { 0.0 } else { v };
     let u: u64 = unsafe { mem::transmute(v) };
     if v.is_sign_positive() {
         u | SIGN_MARK
@@ -427,6 +429,18 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_f64_zero() {
+        let mut pos = vec![];
+        pos.encode_f64(0.0).unwrap();
+        let mut neg = vec![];
+        neg.encode_f64(-0.0).unwrap();
+        assert_eq!(pos, neg);
+        let mut neg_one = vec![];
+        neg_one.encode_f64(-1.0).unwrap();
+        assert!(neg_one < neg);
+    }
+
     #[test]
     fn test_var_u64_codec() {
         for &v in U64_TESTS {
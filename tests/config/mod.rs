@@ -62,7 +62,16 @@ fn test_serde_custom_tikv_config() {
         grpc_raft_conn_num: 123,
         grpc_stream_initial_window_size: ReadableSize(12_345),
         end_point_concurrency: 12,
+        end_point_high_concurrency: 12,
+        end_point_low_concurrency: 12,
         end_point_max_tasks: 12,
+        end_point_max_warning_cnt: 12,
+        end_point_group_concat_max_len: ReadableSize::kb(12),
+        end_point_executor_timeout: ReadableDuration::millis(12),
+        end_point_scan_bytes_limit: ReadableSize::mb(12),
+        end_point_result_cache_size: ReadableSize::mb(12),
+        end_point_memory_quota: ReadableSize::mb(12),
+        end_point_max_ranges: 12,
     };
     value.metric = MetricConfig {
         interval: ReadableDuration::secs(12),
@@ -24,11 +24,13 @@ use tikv::coprocessor;
 use kvproto::kvrpcpb::Context;
 use tikv::coprocessor::codec::{datum, table, Datum};
 use tikv::coprocessor::codec::datum::DatumDecoder;
+use tikv::coprocessor::codec::mysql::{self, Decimal, DecimalEncoder, Time};
 use tikv::util::codec::number::*;
 use tikv::storage::{Key, Mutation, ALL_CFS};
 use tikv::server::Config;
 use tikv::storage::engine::{self, Engine, TEMP_DIR};
 use tikv::util::worker::{FutureWorker, Worker};
+use tikv::util::config::ReadableSize;
 use kvproto::coprocessor::{KeyRange, Request, Response};
 use tipb::select::{Chunk, DAGRequest, SelectRequest, SelectResponse};
 use tipb::executor::{Aggregation, ExecType, Executor, IndexScan, Limit, Selection, TableScan, TopN};
@@ -39,12 +41,18 @@ use protobuf::{Message, RepeatedField};
 use raftstore::util::MAX_LEADER_LEASE;
 use storage::sync_storage::SyncStorage;
 use storage::util::new_raft_engine;
-use tikv::coprocessor::select::xeval::evaluator::FLAG_IGNORE_TRUNCATE;
+use tikv::coprocessor::select::aggregate::{decode_avg, decode_variance};
+use tikv::coprocessor::select::xeval::EvalContext;
+use tikv::coprocessor::select::xeval::evaluator::{FLAG_IGNORE_TRUNCATE, FLAG_IN_INSERT_STMT,
+                                                  FLAG_IN_UPDATE_OR_DELETE_STMT};
+use tikv::coprocessor::flags::{FLAG_IGNORE_OVERFLOW, FLAG_NULLS_ORDER_SHIFT};
 
 static ID_GENERATOR: AtomicUsize = AtomicUsize::new(1);
 
 const TYPE_VAR_CHAR: i32 = 1;
 const TYPE_LONG: i32 = 2;
+const TYPE_DOUBLE: i32 = 5;
+const TYPE_DATETIME: i32 = 12;
 
 pub fn next_id() -> i64 {
     ID_GENERATOR.fetch_add(1, Ordering::Relaxed) as i64
@@ -148,12 +156,15 @@ pub struct Column {
     col_type: i32,
     // negative means not a index key, 0 means primary key, positive means normal index key.
     pub index: i64,
+    // whether the index key is of a unique index, which keeps the handle in the value.
+    unique: bool,
     default_val: Option<i64>, // TODO: change it to Vec<u8> if other type value is needed for test.
 }
 
 struct ColumnBuilder {
     col_type: i32,
     index: i64,
+    unique: bool,
     default_val: Option<i64>,
 }
 
@@ -162,6 +173,7 @@ impl ColumnBuilder {
         ColumnBuilder {
             col_type: TYPE_LONG,
             index: -1,
+            unique: false,
             default_val: None,
         }
     }
@@ -185,6 +197,12 @@ impl ColumnBuilder {
         self
     }
 
+    fn unique_index_key(mut self, idx_id: i64) -> ColumnBuilder {
+        self.index = idx_id;
+        self.unique = true;
+        self
+    }
+
     fn default(mut self, val: i64) -> ColumnBuilder {
         self.default_val = Some(val);
         self
@@ -195,6 +213,7 @@ impl ColumnBuilder {
             id: next_id(),
             col_type: self.col_type,
             index: self.index,
+            unique: self.unique,
             default_val: self.default_val,
         }
     }
@@ -208,6 +227,10 @@ pub struct Table {
 }
 
 impl Table {
+    fn is_unique_index(&self, idx: i64) -> bool {
+        self.cols.values().any(|c| c.index == idx && c.unique)
+    }
+
     fn get_table_info(&self) -> schema::TableInfo {
         let mut tb_info = schema::TableInfo::new();
         tb_info.set_table_id(self.id);
@@ -318,10 +341,10 @@ impl TableBuilder {
             e.push(id);
         }
         for (id, val) in &mut idx {
-            if *id == 0 {
+            // a unique index keeps the handle in the value instead of the key.
+            if *id == 0 || self.cols.values().any(|c| c.index == *id && c.unique) {
                 continue;
             }
-            // TODO: support uniq index.
             val.push(self.handle_id);
         }
         Table {
@@ -370,11 +393,11 @@ impl<'a> Insert<'a> {
         let mut kvs = vec![];
         kvs.push((key, value));
         for (&id, idxs) in &self.table.idxs {
-            let mut v: Vec<_> = idxs.iter().map(|id| self.values[id].clone()).collect();
-            v.push(handle.clone());
-            let encoded = datum::encode_key(&v).unwrap();
-            let idx_key = table::encode_index_seek_key(self.table.id, id, &encoded);
-            kvs.push((idx_key, vec![0]));
+            let v: Vec<_> = idxs.iter().map(|id| self.values[id].clone()).collect();
+            let unique = self.table.is_unique_index(id);
+            let idx_kv =
+                table::encode_index_key_value(self.table.id, id, &v, handle.i64(), unique).unwrap();
+            kvs.push(idx_kv);
         }
         self.store.put(ctx, kvs);
         handle.i64()
@@ -539,10 +562,10 @@ impl<'a> Delete<'a> {
         let mut keys = vec![];
         keys.push(key);
         for (&idx_id, idx_cols) in &self.table.idxs {
-            let mut v: Vec<_> = idx_cols.iter().map(|id| values[id].clone()).collect();
-            v.push(Datum::I64(id));
-            let encoded = datum::encode_key(&v).unwrap();
-            let idx_key = table::encode_index_seek_key(self.table.id, idx_id, &encoded);
+            let v: Vec<_> = idx_cols.iter().map(|id| values[id].clone()).collect();
+            let unique = self.table.is_unique_index(idx_id);
+            let (idx_key, _) =
+                table::encode_index_key_value(self.table.id, idx_id, &v, id, unique).unwrap();
             keys.push(idx_key);
         }
         self.store.delete(keys);
@@ -601,15 +624,18 @@ impl Store {
             .unwrap();
     }
 
-    fn commit(&mut self) {
-        self.commit_with_ctx(Context::new());
+    fn commit(&mut self) -> u64 {
+        self.commit_with_ctx(Context::new())
     }
 
-    fn commit_with_ctx(&mut self, ctx: Context) {
+    // commit_with_ctx returns the commit ts of the transaction.
+    fn commit_with_ctx(&mut self, ctx: Context) -> u64 {
         let handles = self.handles.drain(..).map(|x| Key::from_raw(&x)).collect();
+        let commit_ts = next_id() as u64;
         self.store
-            .commit(ctx, handles, self.current_ts, next_id() as u64)
+            .commit(ctx, handles, self.current_ts, commit_ts)
             .unwrap();
+        commit_ts
     }
 }
 
@@ -656,6 +682,62 @@ impl ProductTable {
             table: table,
         }
     }
+
+    // with_unique_name builds a product table with a unique index on name only.
+    pub fn with_unique_name() -> ProductTable {
+        let id = ColumnBuilder::new()
+            .col_type(TYPE_LONG)
+            .primary_key(true)
+            .build();
+        let name = ColumnBuilder::new()
+            .col_type(TYPE_VAR_CHAR)
+            .unique_index_key(next_id())
+            .build();
+        let count = ColumnBuilder::new().col_type(TYPE_LONG).build();
+        let table = TableBuilder::new()
+            .add_col(id)
+            .add_col(name)
+            .add_col(count)
+            .build();
+
+        ProductTable {
+            id: id,
+            name: name,
+            count: count,
+            table: table,
+        }
+    }
+}
+
+/// A table of periods, whose start and end are datetime columns.
+struct PeriodTable {
+    id: Column,
+    start: Column,
+    end: Column,
+    table: Table,
+}
+
+impl PeriodTable {
+    fn new() -> PeriodTable {
+        let id = ColumnBuilder::new()
+            .col_type(TYPE_LONG)
+            .primary_key(true)
+            .build();
+        let start = ColumnBuilder::new().col_type(TYPE_DATETIME).build();
+        let end = ColumnBuilder::new().col_type(TYPE_DATETIME).build();
+        let table = TableBuilder::new()
+            .add_col(id)
+            .add_col(start)
+            .add_col(end)
+            .build();
+
+        PeriodTable {
+            id: id,
+            start: start,
+            end: end,
+            table: table,
+        }
+    }
 }
 
 fn init_data_with_engine_and_commit(
@@ -682,6 +764,8 @@ fn init_data_with_engine_and_commit(
     let mut end_point = Worker::new("test select worker");
     let mut cfg = Config::default();
     cfg.end_point_concurrency = 1;
+    cfg.end_point_high_concurrency = 1;
+    cfg.end_point_low_concurrency = 1;
     let pd_worker = FutureWorker::new("test pd worker");
     let runner = EndPointHost::new(
         store.get_engine(),
@@ -711,6 +795,29 @@ fn init_with_data(
     init_data_with_commit(tbl, vals, true)
 }
 
+// init_with_periods fills a period table with `vals`, whose times are datetime strings.
+fn init_with_periods(
+    tbl: &PeriodTable,
+    vals: &[(i64, Option<&str>, Option<&str>)],
+) -> (Store, Worker<EndPointTask>) {
+    let (mut store, end_point) = init_with_data(&ProductTable::new(), &[]);
+    let time = |t: Option<&str>| match t {
+        Some(s) => Datum::Time(Time::parse_utc_datetime(s, 0).unwrap()),
+        None => Datum::Null,
+    };
+    store.begin();
+    for &(id, start, end) in vals {
+        store
+            .insert_into(&tbl.table)
+            .set(tbl.id, Datum::I64(id))
+            .set(tbl.start, time(start))
+            .set(tbl.end, time(end))
+            .execute();
+    }
+    store.commit();
+    (store, end_point)
+}
+
 fn offset_for_column(cols: &[ColumnInfo], col_id: i64) -> i64 {
     for (offset, column) in cols.iter().enumerate() {
         if column.get_column_id() == col_id {
@@ -720,6 +827,96 @@ fn offset_for_column(cols: &[ColumnInfo], col_id: i64) -> i64 {
     0 as i64
 }
 
+// column_ref builds a reference to `col` by its offset in `cols`.
+fn column_ref(cols: &[ColumnInfo], col: Column) -> Expr {
+    let mut expr = Expr::new();
+    expr.set_tp(ExprType::ColumnRef);
+    expr.mut_val()
+        .encode_i64(offset_for_column(cols, col.id))
+        .unwrap();
+    expr
+}
+
+// like builds `col LIKE pattern`, escaping with a backslash.
+fn like(cols: &[ColumnInfo], col: Column, pattern: &str) -> Expr {
+    let mut col_expr = Expr::new();
+    col_expr.set_tp(ExprType::ColumnRef);
+    col_expr
+        .mut_val()
+        .encode_i64(offset_for_column(cols, col.id))
+        .unwrap();
+    let mut pattern_expr = Expr::new();
+    pattern_expr.set_tp(ExprType::String);
+    pattern_expr.set_val(pattern.as_bytes().to_vec());
+    let mut escape = Expr::new();
+    escape.set_tp(ExprType::Int64);
+    escape.mut_val().encode_i64(i64::from(b'\\')).unwrap();
+    let mut expr = Expr::new();
+    expr.set_tp(ExprType::ScalarFunc);
+    expr.set_sig(ScalarFuncSig::LikeSig);
+    expr.mut_children().push(col_expr);
+    expr.mut_children().push(pattern_expr);
+    expr.mut_children().push(escape);
+    expr
+}
+
+// in_list builds `col IN (values)`, comparing ints or strings by the type of `col`.
+fn in_list(cols: &[ColumnInfo], col: Column, values: Vec<Datum>) -> Expr {
+    let mut col_expr = Expr::new();
+    col_expr.set_tp(ExprType::ColumnRef);
+    col_expr
+        .mut_val()
+        .encode_i64(offset_for_column(cols, col.id))
+        .unwrap();
+    let mut expr = Expr::new();
+    expr.set_tp(ExprType::ScalarFunc);
+    if col.col_type == TYPE_VAR_CHAR {
+        expr.set_sig(ScalarFuncSig::InString);
+    } else {
+        expr.set_sig(ScalarFuncSig::InInt);
+    }
+    expr.mut_children().push(col_expr);
+    for v in values {
+        let mut value = Expr::new();
+        match v {
+            Datum::I64(i) => {
+                value.set_tp(ExprType::Int64);
+                value.mut_val().encode_i64(i).unwrap();
+            }
+            Datum::Bytes(bs) => {
+                value.set_tp(ExprType::String);
+                value.set_val(bs);
+            }
+            Datum::Null => value.set_tp(ExprType::Null),
+            d => panic!("unsupported datum: {:?}", d),
+        }
+        expr.mut_children().push(value);
+    }
+    expr
+}
+
+fn scalar_func(sig: ScalarFuncSig, children: Vec<Expr>) -> Expr {
+    let mut expr = Expr::new();
+    expr.set_tp(ExprType::ScalarFunc);
+    expr.set_sig(sig);
+    expr.set_children(RepeatedField::from_vec(children));
+    expr
+}
+
+// and builds `a AND b`, which is false if either is false even if the other is NULL.
+fn and(a: Expr, b: Expr) -> Expr {
+    scalar_func(ScalarFuncSig::LogicalAnd, vec![a, b])
+}
+
+// or builds `a OR b`, which is true if either is true even if the other is NULL.
+fn or(a: Expr, b: Expr) -> Expr {
+    scalar_func(ScalarFuncSig::LogicalOr, vec![a, b])
+}
+
+fn not(a: Expr) -> Expr {
+    scalar_func(ScalarFuncSig::UnaryNot, vec![a])
+}
+
 struct DAGSelect {
     execs: Vec<Executor>,
     cols: Vec<ColumnInfo>,
@@ -727,8 +924,10 @@ struct DAGSelect {
     limit: Option<u64>,
     aggregate: Vec<Expr>,
     group_by: Vec<Expr>,
+    having: Vec<Expr>,
     key_range: KeyRange,
     output_offsets: Option<Vec<u32>>,
+    flags: u64,
 }
 
 impl DAGSelect {
@@ -757,8 +956,10 @@ impl DAGSelect {
             limit: None,
             aggregate: vec![],
             group_by: vec![],
+            having: vec![],
             key_range: range,
             output_offsets: None,
+            flags: 0,
         }
     }
 
@@ -783,22 +984,46 @@ impl DAGSelect {
             limit: None,
             aggregate: vec![],
             group_by: vec![],
+            having: vec![],
             key_range: range,
             output_offsets: None,
+            flags: 0,
         }
     }
 
+    // with_commit_ts scans the commit ts of each row as an extra column after the others.
+    fn with_commit_ts(mut self) -> DAGSelect {
+        let mut col = ColumnInfo::new();
+        col.set_column_id(table::COMMIT_TS_COL_ID);
+        col.set_tp(TYPE_LONG);
+        self.execs[0].mut_tbl_scan().mut_columns().push(col.clone());
+        self.cols.push(col);
+        self
+    }
+
     fn limit(mut self, n: u64) -> DAGSelect {
         self.limit = Some(n);
         self
     }
 
-    fn order_by(mut self, col: Column, desc: bool) -> DAGSelect {
+    fn order_by(self, col: Column, desc: bool) -> DAGSelect {
         let col_offset = offset_for_column(&self.cols, col.id);
-        let mut item = ByItem::new();
         let mut expr = Expr::new();
         expr.set_tp(ExprType::ColumnRef);
         expr.mut_val().encode_i64(col_offset).unwrap();
+        self.order_by_expr(expr, desc)
+    }
+
+    // order_by_nulls orders by `col` with its NULLs put first or last whatever `desc` is.
+    fn order_by_nulls(mut self, col: Column, desc: bool, nulls_first: bool) -> DAGSelect {
+        if nulls_first == desc {
+            self.flags |= 1 << (FLAG_NULLS_ORDER_SHIFT + self.order_by.len() as u64);
+        }
+        self.order_by(col, desc)
+    }
+
+    fn order_by_expr(mut self, expr: Expr, desc: bool) -> DAGSelect {
+        let mut item = ByItem::new();
         item.set_expr(expr);
         item.set_desc(desc);
         self.order_by.push(item);
@@ -844,6 +1069,29 @@ impl DAGSelect {
         self.aggr_col(col, ExprType::Min)
     }
 
+    fn group_concat(self, col: Column) -> DAGSelect {
+        self.aggr_col(col, ExprType::GroupConcat)
+    }
+
+    fn var_pop(self, col: Column) -> DAGSelect {
+        self.aggr_col(col, ExprType::VarPop)
+    }
+
+    fn stddev_samp(self, col: Column) -> DAGSelect {
+        self.aggr_col(col, ExprType::StddevSamp)
+    }
+
+    // arg_max returns the max `col` and `ret` of the first row with it.
+    fn arg_max(mut self, col: Column, ret: Column) -> DAGSelect {
+        self = self.aggr_col(col, ExprType::Max);
+        let ret_offset = offset_for_column(&self.cols, ret.id);
+        let mut ret_expr = Expr::new();
+        ret_expr.set_tp(ExprType::ColumnRef);
+        ret_expr.mut_val().encode_i64(ret_offset).unwrap();
+        self.aggregate.last_mut().unwrap().mut_children().push(ret_expr);
+        self
+    }
+
     fn group_by(mut self, cols: &[Column]) -> DAGSelect {
         for col in cols {
             let offset = offset_for_column(&self.cols, col.id);
@@ -855,6 +1103,17 @@ impl DAGSelect {
         self
     }
 
+    fn group_by_expr(mut self, expr: Expr) -> DAGSelect {
+        self.group_by.push(expr);
+        self
+    }
+
+    // distinct outputs the distinct values of `cols`, as an aggregation grouping by them
+    // without aggregate functions.
+    fn distinct(self, cols: &[Column]) -> DAGSelect {
+        self.group_by(cols)
+    }
+
     fn output_offsets(mut self, output_offsets: Option<Vec<u32>>) -> DAGSelect {
         self.output_offsets = output_offsets;
         self
@@ -870,6 +1129,18 @@ impl DAGSelect {
         self
     }
 
+    // having filters the groups of the aggregation by `expr`, whose column offsets are
+    // those of the aggregation output: the aggregation results, then the group by values.
+    fn having(mut self, expr: Expr) -> DAGSelect {
+        self.having.push(expr);
+        self
+    }
+
+    fn where_in(self, col: Column, values: Vec<Datum>) -> DAGSelect {
+        let cond = in_list(&self.cols, col, values);
+        self.where_expr(cond)
+    }
+
     fn build(self) -> Request {
         self.build_with(&[0])
     }
@@ -890,6 +1161,15 @@ impl DAGSelect {
             self.execs.push(exec);
         }
 
+        if !self.having.is_empty() {
+            let mut exec = Executor::new();
+            exec.set_tp(ExecType::TypeSelection);
+            let mut selection = Selection::new();
+            selection.set_conditions(RepeatedField::from_vec(self.having));
+            exec.set_selection(selection);
+            self.execs.push(exec);
+        }
+
         if !self.order_by.is_empty() {
             let mut exec = Executor::new();
             exec.set_tp(ExecType::TypeTopN);
@@ -914,7 +1194,7 @@ impl DAGSelect {
         let mut dag = DAGRequest::new();
         dag.set_executors(RepeatedField::from_vec(self.execs));
         dag.set_start_ts(next_id() as u64);
-        dag.set_flags(flags.iter().fold(0, |acc, f| acc | *f));
+        dag.set_flags(flags.iter().fold(self.flags, |acc, f| acc | *f));
 
         let output_offsets = if self.output_offsets.is_some() {
             self.output_offsets.take().unwrap()
@@ -949,7 +1229,7 @@ fn test_select() {
     assert_eq!(row_cnt(resp.get_chunks()), data.len());
     let spliter = ChunkSpliter::new(resp.take_chunks().into_vec());
     for (row, (id, name, cnt)) in spliter.zip(data.clone()) {
-        let name_datum = name.map(|s| s.as_bytes()).into();
+        let name_datum: Datum = name.map(|s| s.as_bytes()).into();
         let expected_encoded =
             datum::encode_value(&[Datum::I64(id), name_datum, cnt.into()]).unwrap();
         assert_eq!(id, row.handle);
@@ -960,7 +1240,7 @@ fn test_select() {
     let mut resp = handle_select(&end_point, req);
     let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
     for (row, (id, name, cnt)) in spliter.zip(data) {
-        let name_datum = name.map(|s| s.as_bytes()).into();
+        let name_datum: Datum = name.map(|s| s.as_bytes()).into();
         let expected_encoded =
             datum::encode_value(&[Datum::I64(id), name_datum, cnt.into()]).unwrap();
         let result_encoded = datum::encode_value(&row).unwrap();
@@ -970,6 +1250,79 @@ fn test_select() {
     end_point.stop().unwrap().join().unwrap();
 }
 
+#[test]
+fn test_point_get_for_dag() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("name:4"), 3),
+        (4, Some("name:3"), 1),
+        (5, Some("name:1"), 4),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+
+    // a range from a row key to the next one covers a single handle, which is got directly.
+    let point = |handle: i64| {
+        let mut range = KeyRange::new();
+        range.set_start(build_row_key(product.table.id, handle));
+        range.set_end(build_row_key(product.table.id, handle + 1));
+        range
+    };
+    let mut sel = DAGSelect::from(&product.table);
+    sel.key_range = point(4);
+    let mut resp = handle_select(&end_point, sel.build());
+    let mut spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+    let row = spliter.next().unwrap();
+    let (id, name, cnt) = data[2];
+    let name_datum: Datum = name.map(|s| s.as_bytes()).into();
+    let expected_encoded = datum::encode_value(&[Datum::I64(id), name_datum, cnt.into()]).unwrap();
+    let result_encoded = datum::encode_value(&row).unwrap();
+    assert_eq!(result_encoded, &*expected_encoded);
+    assert!(spliter.next().is_none());
+
+    // while an absent handle returns no rows.
+    let mut sel = DAGSelect::from(&product.table);
+    sel.key_range = point(3);
+    let resp = handle_request(&end_point, sel.build());
+    assert!(resp.get_other_error().is_empty(), "{:?}", resp);
+    let mut sel_resp = SelectResponse::new();
+    sel_resp.merge_from_bytes(resp.get_data()).unwrap();
+    assert_eq!(row_cnt(sel_resp.get_chunks()), 0);
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_exec_details_for_dag() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("name:4"), 3),
+        (4, Some("name:3"), 1),
+        (5, Some("name:1"), 4),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+
+    let mut req = DAGSelect::from(&product.table).build();
+    // the details are only returned when asked for.
+    let resp = handle_request(&end_point, req.clone());
+    assert!(!resp.has_exec_details(), "{:?}", resp);
+
+    req.mut_context().set_scan_detail(true);
+    let resp = handle_request(&end_point, req);
+    assert!(resp.get_other_error().is_empty(), "{:?}", resp);
+    // every row has a single version, with its value kept in the write cf.
+    let detail = resp.get_exec_details().get_scan_detail();
+    let processed = detail.get_lock().get_processed() + detail.get_write().get_processed() +
+        detail.get_data().get_processed();
+    assert_eq!(processed, data.len() as i64);
+    assert!(detail.get_write().get_total() >= data.len() as i64, "{:?}", detail);
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
 #[test]
 fn test_select_after_lease() {
     let data = vec![
@@ -989,7 +1342,7 @@ fn test_select_after_lease() {
     assert_eq!(row_cnt(resp.get_chunks()), data.len());
     let spliter = ChunkSpliter::new(resp.take_chunks().into_vec());
     for (row, (id, name, cnt)) in spliter.zip(data.clone()) {
-        let name_datum = name.map(|s| s.as_bytes()).into();
+        let name_datum: Datum = name.map(|s| s.as_bytes()).into();
         let expected_encoded =
             datum::encode_value(&[Datum::I64(id), name_datum, cnt.into()]).unwrap();
         assert_eq!(id, row.handle);
@@ -1003,7 +1356,7 @@ fn test_select_after_lease() {
     assert_eq!(row_cnt(resp.get_chunks()), data.len());
     let spliter = ChunkSpliter::new(resp.take_chunks().into_vec());
     for (row, (id, name, cnt)) in spliter.zip(data.clone()) {
-        let name_datum = name.map(|s| s.as_bytes()).into();
+        let name_datum: Datum = name.map(|s| s.as_bytes()).into();
         let expected_encoded =
             datum::encode_value(&[Datum::I64(id), name_datum, cnt.into()]).unwrap();
         assert_eq!(id, row.handle);
@@ -1058,130 +1411,401 @@ fn test_group_by() {
 }
 
 #[test]
-fn test_aggr_count() {
+fn test_distinct() {
     let data = vec![
         (1, Some("name:0"), 2),
-        (2, Some("name:3"), 3),
+        (2, Some("name:2"), 3),
         (4, Some("name:0"), 1),
-        (5, Some("name:5"), 4),
-        (6, Some("name:5"), 4),
-        (7, None, 4),
+        (5, Some("name:1"), 4),
+        (6, Some("name:0"), 2),
+        (7, Some("name:1"), 4),
+        (8, None, 4),
+        (9, None, 4),
     ];
 
     let product = ProductTable::new();
     let (_, mut end_point) = init_with_data(&product, &data);
-
-    let req = Select::from(&product.table).count().build();
-    let mut resp = handle_select(&end_point, req);
-    assert_eq!(row_cnt(resp.get_chunks()), 1);
-    let mut spliter = ChunkSpliter::new(resp.take_chunks().into_vec());
-    let gk = Datum::Bytes(coprocessor::SINGLE_GROUP.to_vec());
-    let mut expected_encoded = datum::encode_value(&[gk, Datum::U64(data.len() as u64)]).unwrap();
-    assert_eq!(spliter.next().unwrap().data, &*expected_encoded);
-
-    let exp = vec![
-        (Datum::Bytes(b"name:0".to_vec()), 2),
-        (Datum::Bytes(b"name:3".to_vec()), 1),
-        (Datum::Bytes(b"name:5".to_vec()), 2),
-        (Datum::Null, 1),
-    ];
-    // for selection
-    let req = Select::from(&product.table)
-        .count()
-        .group_by(&[product.name])
-        .build();
-    let mut resp = handle_select(&end_point, req);
-    assert_eq!(row_cnt(resp.get_chunks()), exp.len());
-    let spliter = ChunkSpliter::new(resp.take_chunks().into_vec());
-    for (row, (name, cnt)) in spliter.zip(exp.clone()) {
-        let gk = datum::encode_value(&[name]);
-        let expected_datum = vec![Datum::Bytes(gk.unwrap()), Datum::U64(cnt)];
-        expected_encoded = datum::encode_value(&expected_datum).unwrap();
-        assert_eq!(row.data, &*expected_encoded);
-    }
-    // for dag
     let req = DAGSelect::from(&product.table)
-        .count()
-        .group_by(&[product.name])
+        .distinct(&[product.name, product.count])
         .build();
     let mut resp = handle_select(&end_point, req);
-    let mut row_count = 0;
-    let exp_len = exp.len();
     let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 2);
-    for (row, (name, cnt)) in spliter.zip(exp) {
-        let expected_datum = vec![Datum::U64(cnt), name];
-        expected_encoded = datum::encode_value(&expected_datum).unwrap();
-        let result_encoded = datum::encode_value(&row).unwrap();
-        assert_eq!(&*result_encoded, &*expected_encoded);
-        row_count += 1;
-    }
-    assert_eq!(row_count, exp_len);
-
+    let rows: Vec<_> = spliter.collect();
+    let name = |s: &str| Datum::Bytes(s.as_bytes().to_vec());
     let exp = vec![
-        (vec![Datum::Bytes(b"name:0".to_vec()), Datum::I64(2)], 1),
-        (vec![Datum::Bytes(b"name:3".to_vec()), Datum::I64(3)], 1),
-        (vec![Datum::Bytes(b"name:0".to_vec()), Datum::I64(1)], 1),
-        (vec![Datum::Bytes(b"name:5".to_vec()), Datum::I64(4)], 2),
-        (vec![Datum::Null, Datum::I64(4)], 1),
+        vec![name("name:0"), Datum::I64(2)],
+        vec![name("name:2"), Datum::I64(3)],
+        vec![name("name:0"), Datum::I64(1)],
+        vec![name("name:1"), Datum::I64(4)],
+        vec![Datum::Null, Datum::I64(4)],
     ];
-
-    // for selection
-    let req = Select::from(&product.table)
-        .count()
-        .group_by(&[product.name, product.count])
-        .build();
-    let mut resp = handle_select(&end_point, req);
-    assert_eq!(row_cnt(resp.get_chunks()), exp.len());
-    let spliter = ChunkSpliter::new(resp.take_chunks().into_vec());
-    for (row, (gk_data, cnt)) in spliter.zip(exp.clone()) {
-        let gk = datum::encode_value(&gk_data);
-        let expected_datum = vec![Datum::Bytes(gk.unwrap()), Datum::U64(cnt)];
-        expected_encoded = datum::encode_value(&expected_datum).unwrap();
-        assert_eq!(row.data, &*expected_encoded);
-    }
-
-    // for dag
-    let req = DAGSelect::from(&product.table)
-        .count()
-        .group_by(&[product.name, product.count])
-        .build();
-    let mut resp = handle_select(&end_point, req);
-    let mut row_count = 0;
-    let exp_len = exp.len();
-    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
-    for (row, (gk_data, cnt)) in spliter.zip(exp) {
-        let mut expected_datum = vec![Datum::U64(cnt)];
-        expected_datum.extend_from_slice(gk_data.as_slice());
-        expected_encoded = datum::encode_value(&expected_datum).unwrap();
-        let result_encoded = datum::encode_value(&row).unwrap();
-        assert_eq!(&*result_encoded, &*expected_encoded);
-        row_count += 1;
-    }
-    assert_eq!(row_count, exp_len);
+    assert_eq!(rows, exp);
 
     end_point.stop().unwrap().join().unwrap();
 }
 
 #[test]
-fn test_aggr_first() {
+fn test_handle_in_expressions() {
     let data = vec![
         (1, Some("name:0"), 2),
-        (2, Some("name:3"), 3),
-        (3, Some("name:5"), 3),
-        (4, Some("name:0"), 1),
-        (5, Some("name:5"), 4),
-        (6, Some("name:5"), 4),
-        (7, None, 4),
-        (8, None, 5),
-        (9, Some("name:5"), 5),
-        (10, None, 6),
+        (2, Some("name:1"), 5),
+        (4, Some("name:2"), 1),
+        (5, Some("name:3"), 4),
     ];
 
     let product = ProductTable::new();
     let (_, mut end_point) = init_with_data(&product, &data);
+    let cols = product.table.get_table_columns();
+    let int = |v: i64| {
+        let mut expr = Expr::new();
+        expr.set_tp(ExprType::Int64);
+        expr.mut_val().encode_i64(v).unwrap();
+        expr
+    };
 
-    let exp = vec![
-        (Datum::Bytes(b"name:0".to_vec()), 1),
+    // for dag, `id + count` over the pk handle.
+    let plus = || {
+        let mut expr = Expr::new();
+        expr.set_tp(ExprType::ScalarFunc);
+        expr.set_sig(ScalarFuncSig::PlusInt);
+        expr.mut_children().push(column_ref(&cols, product.id));
+        expr.mut_children().push(column_ref(&cols, product.count));
+        expr
+    };
+    let req = DAGSelect::from(&product.table)
+        .group_by_expr(plus())
+        .build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 1);
+    let rows: Vec<_> = spliter.collect();
+    let exp: Vec<_> = data.iter()
+        .map(|&(id, _, cnt)| vec![Datum::I64(id + cnt)])
+        .collect();
+    assert_eq!(rows, exp);
+
+    let mut cond = Expr::new();
+    cond.set_tp(ExprType::ScalarFunc);
+    cond.set_sig(ScalarFuncSig::GTInt);
+    cond.mut_children().push(plus());
+    cond.mut_children().push(int(5));
+    let req = DAGSelect::from(&product.table).where_expr(cond).build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+    let ids: Vec<_> = spliter.map(|row| row[0].clone()).collect();
+    assert_eq!(ids, vec![Datum::I64(2), Datum::I64(5)]);
+
+    // `GREATEST(id, count)` and `LEAST(id, count)` over the pk handle, grouped by id
+    // along with it so equal results aren't merged.
+    let cases = vec![
+        (ScalarFuncSig::GreatestInt, vec![2, 5, 4, 5]),
+        (ScalarFuncSig::LeastInt, vec![1, 2, 1, 4]),
+    ];
+    for (sig, exp) in cases {
+        let func = scalar_func(
+            sig,
+            vec![column_ref(&cols, product.id), column_ref(&cols, product.count)],
+        );
+        let req = DAGSelect::from(&product.table)
+            .group_by(&[product.id])
+            .group_by_expr(func)
+            .build();
+        let mut resp = handle_select(&end_point, req);
+        let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 2);
+        let rows: Vec<_> = spliter.collect();
+        let exp: Vec<_> = data.iter()
+            .zip(exp)
+            .map(|(&(id, _, _), v)| vec![Datum::I64(id), Datum::I64(v)])
+            .collect();
+        assert_eq!(rows, exp, "{:?}", sig);
+    }
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_greatest_least_time_for_dag() {
+    let data = vec![
+        (1, Some("2017-01-02 00:00:00"), Some("2016-12-31 23:59:59")),
+        (2, Some("2017-01-01 09:00:00"), Some("2017-01-01 10:00:00")),
+        (3, Some("2016-02-29 00:00:00"), None),
+    ];
+
+    let period = PeriodTable::new();
+    let (_, mut end_point) = init_with_periods(&period, &data);
+    let cols = period.table.get_table_columns();
+    let args = || vec![column_ref(&cols, period.start), column_ref(&cols, period.end)];
+    let req = DAGSelect::from(&period.table)
+        .group_by_expr(scalar_func(ScalarFuncSig::GreatestTime, args()))
+        .group_by_expr(scalar_func(ScalarFuncSig::LeastTime, args()))
+        .build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 2);
+    let rows: Vec<_> = spliter.collect();
+    // times are output packed, and the result is NULL if either time is.
+    let packed = |s: &str| Datum::U64(Time::parse_utc_datetime(s, 0).unwrap().to_packed_u64());
+    let exp = vec![
+        vec![packed("2017-01-02 00:00:00"), packed("2016-12-31 23:59:59")],
+        vec![packed("2017-01-01 10:00:00"), packed("2017-01-01 09:00:00")],
+        vec![Datum::Null, Datum::Null],
+    ];
+    assert_eq!(rows, exp);
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_date_format_for_dag() {
+    let data = vec![
+        (1, Some("2017-01-02 03:04:05"), None),
+        (2, Some("2016-12-31 23:59:59"), None),
+        (3, None, None),
+    ];
+
+    let period = PeriodTable::new();
+    let (_, mut end_point) = init_with_periods(&period, &data);
+    let cols = period.table.get_table_columns();
+    let mut layout = Expr::new();
+    layout.set_tp(ExprType::String);
+    layout.set_val(b"%Y-%m-%d %H:%i:%s %p".to_vec());
+    let func = scalar_func(
+        ScalarFuncSig::DateFormatSig,
+        vec![column_ref(&cols, period.start), layout],
+    );
+    let req = DAGSelect::from(&period.table).group_by_expr(func).build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 1);
+    let rows: Vec<_> = spliter.collect();
+    let exp = vec![
+        vec![Datum::Bytes(b"2017-01-02 03:04:05 AM".to_vec())],
+        vec![Datum::Bytes(b"2016-12-31 23:59:59 PM".to_vec())],
+        vec![Datum::Null],
+    ];
+    assert_eq!(rows, exp);
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_date_diff_for_dag() {
+    let data = vec![
+        (1, Some("2017-01-01 00:00:00"), Some("2017-01-03 12:00:00")),
+        (2, Some("2017-01-02 00:00:00"), Some("2016-12-31 23:00:00")),
+        (3, Some("2017-01-01 00:00:00"), None),
+    ];
+
+    let period = PeriodTable::new();
+    let (_, mut end_point) = init_with_periods(&period, &data);
+    let cols = period.table.get_table_columns();
+    let mut unit = Expr::new();
+    unit.set_tp(ExprType::String);
+    unit.set_val(b"HOUR".to_vec());
+    // DATEDIFF(end, start), TIMESTAMPDIFF(HOUR, start, end)
+    let date_diff = scalar_func(
+        ScalarFuncSig::DateDiff,
+        vec![column_ref(&cols, period.end), column_ref(&cols, period.start)],
+    );
+    let timestamp_diff = scalar_func(
+        ScalarFuncSig::TimestampDiff,
+        vec![
+            unit,
+            column_ref(&cols, period.start),
+            column_ref(&cols, period.end),
+        ],
+    );
+    let req = DAGSelect::from(&period.table)
+        .group_by(&[period.id])
+        .group_by_expr(date_diff)
+        .group_by_expr(timestamp_diff)
+        .build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+    let rows: Vec<_> = spliter.collect();
+    let exp = vec![
+        vec![Datum::I64(1), Datum::I64(2), Datum::I64(60)],
+        vec![Datum::I64(2), Datum::I64(-2), Datum::I64(-25)],
+        vec![Datum::I64(3), Datum::Null, Datum::Null],
+    ];
+    assert_eq!(rows, exp);
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_make_date_time_for_dag() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("name:1"), 60),
+        (3, Some("name:2"), 32),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+    let cols = product.table.get_table_columns();
+    let mut year = Expr::new();
+    year.set_tp(ExprType::Int64);
+    year.mut_val().encode_i64(17).unwrap();
+    let mut second = Expr::new();
+    second.set_tp(ExprType::Float64);
+    second.mut_val().encode_f64(30.5).unwrap();
+    // MAKEDATE(17, count), MAKETIME(id, count, 30.5)
+    let make_date = scalar_func(
+        ScalarFuncSig::MakeDateSig,
+        vec![year, column_ref(&cols, product.count)],
+    );
+    let make_time = scalar_func(
+        ScalarFuncSig::MakeTimeSig,
+        vec![
+            column_ref(&cols, product.id),
+            column_ref(&cols, product.count),
+            second,
+        ],
+    );
+    let req = DAGSelect::from(&product.table)
+        .group_by(&[product.id])
+        .group_by_expr(make_date)
+        .group_by_expr(make_time)
+        .build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+    let rows: Vec<_> = spliter.collect();
+    let date = |s: &str| Datum::U64(Time::parse_utc_datetime(s, 0).unwrap().to_packed_u64());
+    let time = |s: &str| Datum::Dur(mysql::Duration::parse(s.as_bytes(), mysql::MAX_FSP).unwrap());
+    // a minute of 60 makes the time NULL.
+    let exp = vec![
+        vec![Datum::I64(1), date("2017-01-02"), time("01:02:30.5")],
+        vec![Datum::I64(2), date("2017-03-01"), Datum::Null],
+        vec![Datum::I64(3), date("2017-02-01"), time("03:32:30.5")],
+    ];
+    assert_eq!(rows, exp);
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_aggr_count() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("name:3"), 3),
+        (4, Some("name:0"), 1),
+        (5, Some("name:5"), 4),
+        (6, Some("name:5"), 4),
+        (7, None, 4),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+
+    let req = Select::from(&product.table).count().build();
+    let mut resp = handle_select(&end_point, req);
+    assert_eq!(row_cnt(resp.get_chunks()), 1);
+    let mut spliter = ChunkSpliter::new(resp.take_chunks().into_vec());
+    let gk = Datum::Bytes(coprocessor::SINGLE_GROUP.to_vec());
+    let mut expected_encoded = datum::encode_value(&[gk, Datum::U64(data.len() as u64)]).unwrap();
+    assert_eq!(spliter.next().unwrap().data, &*expected_encoded);
+
+    let exp = vec![
+        (Datum::Bytes(b"name:0".to_vec()), 2),
+        (Datum::Bytes(b"name:3".to_vec()), 1),
+        (Datum::Bytes(b"name:5".to_vec()), 2),
+        (Datum::Null, 1),
+    ];
+    // for selection
+    let req = Select::from(&product.table)
+        .count()
+        .group_by(&[product.name])
+        .build();
+    let mut resp = handle_select(&end_point, req);
+    assert_eq!(row_cnt(resp.get_chunks()), exp.len());
+    let spliter = ChunkSpliter::new(resp.take_chunks().into_vec());
+    for (row, (name, cnt)) in spliter.zip(exp.clone()) {
+        let gk = datum::encode_value(&[name]);
+        let expected_datum = vec![Datum::Bytes(gk.unwrap()), Datum::U64(cnt)];
+        expected_encoded = datum::encode_value(&expected_datum).unwrap();
+        assert_eq!(row.data, &*expected_encoded);
+    }
+    // for dag
+    let req = DAGSelect::from(&product.table)
+        .count()
+        .group_by(&[product.name])
+        .build();
+    let mut resp = handle_select(&end_point, req);
+    let mut row_count = 0;
+    let exp_len = exp.len();
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 2);
+    for (row, (name, cnt)) in spliter.zip(exp) {
+        let expected_datum = vec![Datum::U64(cnt), name];
+        expected_encoded = datum::encode_value(&expected_datum).unwrap();
+        let result_encoded = datum::encode_value(&row).unwrap();
+        assert_eq!(&*result_encoded, &*expected_encoded);
+        row_count += 1;
+    }
+    assert_eq!(row_count, exp_len);
+
+    let exp = vec![
+        (vec![Datum::Bytes(b"name:0".to_vec()), Datum::I64(2)], 1),
+        (vec![Datum::Bytes(b"name:3".to_vec()), Datum::I64(3)], 1),
+        (vec![Datum::Bytes(b"name:0".to_vec()), Datum::I64(1)], 1),
+        (vec![Datum::Bytes(b"name:5".to_vec()), Datum::I64(4)], 2),
+        (vec![Datum::Null, Datum::I64(4)], 1),
+    ];
+
+    // for selection
+    let req = Select::from(&product.table)
+        .count()
+        .group_by(&[product.name, product.count])
+        .build();
+    let mut resp = handle_select(&end_point, req);
+    assert_eq!(row_cnt(resp.get_chunks()), exp.len());
+    let spliter = ChunkSpliter::new(resp.take_chunks().into_vec());
+    for (row, (gk_data, cnt)) in spliter.zip(exp.clone()) {
+        let gk = datum::encode_value(&gk_data);
+        let expected_datum = vec![Datum::Bytes(gk.unwrap()), Datum::U64(cnt)];
+        expected_encoded = datum::encode_value(&expected_datum).unwrap();
+        assert_eq!(row.data, &*expected_encoded);
+    }
+
+    // for dag
+    let req = DAGSelect::from(&product.table)
+        .count()
+        .group_by(&[product.name, product.count])
+        .build();
+    let mut resp = handle_select(&end_point, req);
+    let mut row_count = 0;
+    let exp_len = exp.len();
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+    for (row, (gk_data, cnt)) in spliter.zip(exp) {
+        let mut expected_datum = vec![Datum::U64(cnt)];
+        expected_datum.extend_from_slice(gk_data.as_slice());
+        expected_encoded = datum::encode_value(&expected_datum).unwrap();
+        let result_encoded = datum::encode_value(&row).unwrap();
+        assert_eq!(&*result_encoded, &*expected_encoded);
+        row_count += 1;
+    }
+    assert_eq!(row_count, exp_len);
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_aggr_first() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("name:3"), 3),
+        (3, Some("name:5"), 3),
+        (4, Some("name:0"), 1),
+        (5, Some("name:5"), 4),
+        (6, Some("name:5"), 4),
+        (7, None, 4),
+        (8, None, 5),
+        (9, Some("name:5"), 5),
+        (10, None, 6),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+
+    let exp = vec![
+        (Datum::Bytes(b"name:0".to_vec()), 1),
         (Datum::Bytes(b"name:3".to_vec()), 2),
         (Datum::Bytes(b"name:5".to_vec()), 3),
         (Datum::Null, 7),
@@ -1328,13 +1952,13 @@ fn test_aggr_avg() {
 }
 
 #[test]
-fn test_aggr_sum() {
+fn test_aggr_avg_decode_for_dag() {
     let data = vec![
         (1, Some("name:0"), 2),
         (2, Some("name:3"), 3),
         (4, Some("name:0"), 1),
         (5, Some("name:5"), 4),
-        (6, Some("name:5"), 4),
+        (6, Some("name:5"), 5),
         (7, None, 4),
     ];
 
@@ -1342,59 +1966,314 @@ fn test_aggr_sum() {
     let (_, mut end_point) = init_with_data(&product, &data);
 
     let exp = vec![
-        (Datum::Bytes(b"name:0".to_vec()), 3),
-        (Datum::Bytes(b"name:3".to_vec()), 3),
-        (Datum::Bytes(b"name:5".to_vec()), 8),
-        (Datum::Null, 4),
+        (Datum::Bytes(b"name:0".to_vec()), "1.5000"),
+        (Datum::Bytes(b"name:3".to_vec()), "3.0000"),
+        (Datum::Bytes(b"name:5".to_vec()), "4.5000"),
+        (Datum::Null, "4.0000"),
     ];
-    // for selection
-    let req = Select::from(&product.table)
-        .sum(product.count)
-        .group_by(&[product.name])
-        .build();
-    let mut resp = handle_select(&end_point, req);
-    assert_eq!(row_cnt(resp.get_chunks()), exp.len());
-    let spliter = ChunkSpliter::new(resp.take_chunks().into_vec());
-    for (row, (name, cnt)) in spliter.zip(exp.clone()) {
-        let gk = datum::encode_value(&[name]).unwrap();
-        let expected_datum = vec![Datum::Bytes(gk), Datum::Dec(cnt.into())];
-        let expected_encoded = datum::encode_value(&expected_datum).unwrap();
-        assert_eq!(row.data, &*expected_encoded);
-    }
-    // for dag
     let req = DAGSelect::from(&product.table)
-        .sum(product.count)
+        .avg(product.count)
         .group_by(&[product.name])
         .build();
     let mut resp = handle_select(&end_point, req);
-    let mut row_count = 0;
-    let exp_len = exp.len();
-    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 2);
-    for (row, (name, cnt)) in spliter.zip(exp) {
-        let expected_datum = vec![Datum::Dec(cnt.into()), name];
-        let expected_encoded = datum::encode_value(&expected_datum).unwrap();
-        let result_encoded = datum::encode_value(&row).unwrap();
-        assert_eq!(&*result_encoded, &*expected_encoded);
-        row_count += 1;
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+    let rows: Vec<_> = spliter.collect();
+    assert_eq!(rows.len(), exp.len());
+    let ctx = EvalContext::default();
+    for (row, (name, avg)) in rows.into_iter().zip(exp) {
+        // the count and the sum come before the group by columns.
+        match decode_avg(&ctx, &row[..2]).unwrap() {
+            Datum::Dec(d) => assert_eq!(d.to_string(), avg),
+            d => panic!("expect decimal avg, got {:?}", d),
+        }
+        assert_eq!(row[2], name);
     }
-    assert_eq!(row_count, exp_len);
-    end_point.stop().unwrap();
+
+    end_point.stop().unwrap().join().unwrap();
 }
 
 #[test]
-fn test_aggr_extre() {
+fn test_float_column_for_dag() {
+    let id = ColumnBuilder::new()
+        .col_type(TYPE_LONG)
+        .primary_key(true)
+        .build();
+    let price = ColumnBuilder::new().col_type(TYPE_DOUBLE).build();
+    let table = TableBuilder::new().add_col(id).add_col(price).build();
     let data = vec![
-        (1, Some("name:0"), 2),
-        (2, Some("name:3"), 3),
-        (4, Some("name:0"), 1),
-        (5, Some("name:5"), 4),
-        (6, Some("name:5"), 5),
-        (7, None, 4),
+        (1, Datum::F64(1.5)),
+        (2, Datum::Null),
+        (3, Datum::F64(-0.25)),
+        (4, Datum::F64(4.0)),
+        (5, Datum::F64(-0.0)),
+        (6, Datum::F64(0.0)),
     ];
 
     let product = ProductTable::new();
-    let (mut store, mut end_point) = init_with_data(&product, &data);
-
+    let (mut store, mut end_point) = init_with_data(&product, &[]);
+    store.begin();
+    for &(handle, ref value) in &data {
+        store
+            .insert_into(&table)
+            .set(id, Datum::I64(handle))
+            .set(price, value.clone())
+            .execute();
+    }
+    store.commit();
+
+    // scan
+    let req = DAGSelect::from(&table).build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 2);
+    let rows: Vec<_> = spliter.collect();
+    assert_eq!(rows.len(), data.len());
+    for (row, &(handle, ref value)) in rows.iter().zip(&data) {
+        assert_eq!(row[0], Datum::I64(handle));
+        assert_eq!(row[1], *value);
+    }
+
+    // aggregation
+    let req = DAGSelect::from(&table)
+        .sum(price)
+        .avg(price)
+        .max(price)
+        .min(price)
+        .build();
+    let mut resp = handle_select(&end_point, req);
+    let mut spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 5);
+    let row = spliter.next().unwrap();
+    assert_eq!(row[0], Datum::F64(5.25));
+    assert_eq!(row[3], Datum::F64(4.0));
+    assert_eq!(row[4], Datum::F64(-0.25));
+    let avg = decode_avg(&EvalContext::default(), &row[1..3]).unwrap();
+    assert_eq!(avg, Datum::F64(1.05));
+    assert!(spliter.next().is_none());
+
+    // -0.0 and 0.0 fall into the same group.
+    let req = DAGSelect::from(&table)
+        .count()
+        .group_by(&[price])
+        .build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 2);
+    let exp = vec![
+        (1, Datum::F64(1.5)),
+        (1, Datum::Null),
+        (1, Datum::F64(-0.25)),
+        (1, Datum::F64(4.0)),
+        (2, Datum::F64(0.0)),
+    ];
+    let rows: Vec<_> = spliter.collect();
+    assert_eq!(rows.len(), exp.len());
+    for (row, (cnt, value)) in rows.into_iter().zip(exp) {
+        assert_eq!(row, vec![Datum::U64(cnt), value]);
+    }
+
+    // topn, NULL comes first in ascending order.
+    for &(desc, ref exp) in &[(false, vec![2i64, 3]), (true, vec![4, 1])] {
+        let req = DAGSelect::from(&table)
+            .order_by(price, desc)
+            .limit(2)
+            .build();
+        let mut resp = handle_select(&end_point, req);
+        let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 2);
+        let ids: Vec<_> = spliter.map(|row| row[0].i64()).collect();
+        assert_eq!(ids, *exp);
+    }
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_aggr_sum() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("name:3"), 3),
+        (4, Some("name:0"), 1),
+        (5, Some("name:5"), 4),
+        (6, Some("name:5"), 4),
+        (7, None, 4),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+
+    let exp = vec![
+        (Datum::Bytes(b"name:0".to_vec()), 3),
+        (Datum::Bytes(b"name:3".to_vec()), 3),
+        (Datum::Bytes(b"name:5".to_vec()), 8),
+        (Datum::Null, 4),
+    ];
+    // for selection
+    let req = Select::from(&product.table)
+        .sum(product.count)
+        .group_by(&[product.name])
+        .build();
+    let mut resp = handle_select(&end_point, req);
+    assert_eq!(row_cnt(resp.get_chunks()), exp.len());
+    let spliter = ChunkSpliter::new(resp.take_chunks().into_vec());
+    for (row, (name, cnt)) in spliter.zip(exp.clone()) {
+        let gk = datum::encode_value(&[name]).unwrap();
+        let expected_datum = vec![Datum::Bytes(gk), Datum::Dec(cnt.into())];
+        let expected_encoded = datum::encode_value(&expected_datum).unwrap();
+        assert_eq!(row.data, &*expected_encoded);
+    }
+    // for dag
+    let req = DAGSelect::from(&product.table)
+        .sum(product.count)
+        .group_by(&[product.name])
+        .build();
+    let mut resp = handle_select(&end_point, req);
+    let mut row_count = 0;
+    let exp_len = exp.len();
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 2);
+    for (row, (name, cnt)) in spliter.zip(exp) {
+        let expected_datum = vec![Datum::Dec(cnt.into()), name];
+        let expected_encoded = datum::encode_value(&expected_datum).unwrap();
+        let result_encoded = datum::encode_value(&row).unwrap();
+        assert_eq!(&*result_encoded, &*expected_encoded);
+        row_count += 1;
+    }
+    assert_eq!(row_count, exp_len);
+    end_point.stop().unwrap();
+}
+
+#[test]
+fn test_aggr_sum_overflow_for_dag() {
+    let data = vec![
+        (1, Some("name:0"), i64::MAX),
+        (2, Some("name:0"), i64::MAX),
+        (3, Some("name:1"), i64::MAX - 1),
+        (4, Some("name:1"), 1),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+    let cols = product.table.get_table_columns();
+
+    // the sum of integers is a decimal, which doesn't overflow.
+    let exp = vec![
+        ("18446744073709551614", b"name:0"),
+        ("9223372036854775807", b"name:1"),
+    ];
+    let req = DAGSelect::from(&product.table)
+        .sum(product.count)
+        .group_by(&[product.name])
+        .build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 2);
+    let rows: Vec<_> = spliter.collect();
+    assert_eq!(rows.len(), exp.len());
+    for (row, (sum, name)) in rows.into_iter().zip(exp) {
+        let sum: Decimal = sum.parse().unwrap();
+        let expected_datum = vec![Datum::Dec(sum), Datum::Bytes(name.to_vec())];
+        let expected_encoded = datum::encode_value(&expected_datum).unwrap();
+        let result_encoded = datum::encode_value(&row).unwrap();
+        assert_eq!(&*result_encoded, &*expected_encoded);
+    }
+
+    // count + 1 > 0
+    let cond = {
+        let mut col = Expr::new();
+        col.set_tp(ExprType::ColumnRef);
+        let count_offset = offset_for_column(&cols, product.count.id);
+        col.mut_val().encode_i64(count_offset).unwrap();
+        let mut one = Expr::new();
+        one.set_tp(ExprType::Int64);
+        one.mut_val().encode_i64(1).unwrap();
+        let mut plus = Expr::new();
+        plus.set_tp(ExprType::ScalarFunc);
+        plus.set_sig(ScalarFuncSig::PlusInt);
+        plus.mut_children().push(col);
+        plus.mut_children().push(one);
+
+        let mut zero = Expr::new();
+        zero.set_tp(ExprType::Int64);
+        zero.mut_val().encode_i64(0).unwrap();
+        let mut cond = Expr::new();
+        cond.set_tp(ExprType::ScalarFunc);
+        cond.set_sig(ScalarFuncSig::GTInt);
+        cond.mut_children().push(plus);
+        cond.mut_children().push(zero);
+        cond
+    };
+
+    // Ignore overflow, so `count + 1` is NULL for `i64::MAX`.
+    let req = DAGSelect::from(&product.table)
+        .where_expr(cond.clone())
+        .build_with(&[FLAG_IGNORE_OVERFLOW]);
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+    let ids: Vec<_> = spliter.map(|row| row[0].clone()).collect();
+    assert_eq!(ids, vec![Datum::I64(3), Datum::I64(4)]);
+
+    // Do NOT ignore overflow.
+    let req = DAGSelect::from(&product.table).where_expr(cond).build();
+    let resp = handle_request(&end_point, req);
+    assert!(!resp.get_other_error().is_empty());
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_aggr_having() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("name:3"), 3),
+        (4, Some("name:0"), 1),
+        (5, Some("name:5"), 4),
+        (6, Some("name:5"), 4),
+        (7, None, 4),
+        (8, Some("name:5"), 4),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+
+    // count(*) > 1, the count is the first column of the aggregation output.
+    let mut count = Expr::new();
+    count.set_tp(ExprType::ColumnRef);
+    count.mut_val().encode_i64(0).unwrap();
+    let mut one = Expr::new();
+    one.set_tp(ExprType::Int64);
+    one.mut_val().encode_i64(1).unwrap();
+    let mut cond = Expr::new();
+    cond.set_tp(ExprType::ScalarFunc);
+    cond.set_sig(ScalarFuncSig::GTInt);
+    cond.mut_children().push(count);
+    cond.mut_children().push(one);
+
+    let req = DAGSelect::from(&product.table)
+        .count()
+        .group_by(&[product.name])
+        .having(cond)
+        .build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 2);
+    let groups: Vec<_> = spliter.collect();
+    let exp = vec![
+        vec![Datum::U64(2), Datum::Bytes(b"name:0".to_vec())],
+        vec![Datum::U64(3), Datum::Bytes(b"name:5".to_vec())],
+    ];
+    assert_eq!(groups, exp);
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_aggr_extre() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("name:3"), 3),
+        (4, Some("name:0"), 1),
+        (5, Some("name:5"), 4),
+        (6, Some("name:5"), 5),
+        (7, None, 4),
+    ];
+
+    let product = ProductTable::new();
+    let (mut store, mut end_point) = init_with_data(&product, &data);
+
     store.begin();
     for &(id, name) in &[(8, b"name:5"), (9, b"name:6")] {
         store
@@ -1494,7 +2373,7 @@ fn test_order_by_column() {
     assert_eq!(row_cnt(resp.get_chunks()), 5);
     let spliter = ChunkSpliter::new(resp.take_chunks().into_vec());
     for (row, (id, name, cnt)) in spliter.zip(exp.clone()) {
-        let name_datum = name.map(|s| s.as_bytes()).into();
+        let name_datum: Datum = name.map(|s| s.as_bytes()).into();
         let expected_encoded =
             datum::encode_value(&[(id as i64).into(), name_datum, (cnt as i64).into()]).unwrap();
         assert_eq!(id as i64, row.handle);
@@ -1510,7 +2389,7 @@ fn test_order_by_column() {
     let mut row_count = 0;
     let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
     for (row, (id, name, cnt)) in spliter.zip(exp) {
-        let name_datum = name.map(|s| s.as_bytes()).into();
+        let name_datum: Datum = name.map(|s| s.as_bytes()).into();
         let expected_encoded =
             datum::encode_value(&[(id as i64).into(), name_datum, (cnt as i64).into()]).unwrap();
         let result_encoded = datum::encode_value(&row).unwrap();
@@ -1521,6 +2400,38 @@ fn test_order_by_column() {
     end_point.stop().unwrap().join().unwrap();
 }
 
+#[test]
+fn test_order_by_nulls_for_dag() {
+    let data = vec![
+        (1, Some("name:1"), 2),
+        (2, None, 3),
+        (3, Some("name:0"), 1),
+        (4, None, 4),
+        (5, Some("name:2"), 4),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+    let cases = vec![
+        (false, true, vec![2, 4, 3, 1, 5]),
+        (false, false, vec![3, 1, 5, 2, 4]),
+        (true, true, vec![2, 4, 5, 1, 3]),
+        (true, false, vec![5, 1, 3, 2, 4]),
+    ];
+    for (desc, nulls_first, exp) in cases {
+        let req = DAGSelect::from(&product.table)
+            .order_by_nulls(product.name, desc, nulls_first)
+            .order_by(product.id, false)
+            .limit(5)
+            .build();
+        let mut resp = handle_select(&end_point, req);
+        let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+        let ids: Vec<_> = spliter.map(|row| row[0].i64()).collect();
+        assert_eq!(ids, exp, "desc {} nulls_first {}", desc, nulls_first);
+    }
+    end_point.stop().unwrap().join().unwrap();
+}
+
 #[test]
 fn test_order_by_pk_with_select_from_index() {
     let mut data = vec![
@@ -1556,7 +2467,7 @@ fn test_order_by_pk_with_select_from_index() {
     let mut row_count = 0;
     let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
     for (row, (id, name, cnt)) in spliter.zip(expect) {
-        let name_datum = name.map(|s| s.as_bytes()).into();
+        let name_datum: Datum = name.map(|s| s.as_bytes()).into();
         let expected_encoded =
             datum::encode_value(&[name_datum, (cnt as i64).into(), (id as i64).into()]).unwrap();
         let result_encoded = datum::encode_value(&row).unwrap();
@@ -1587,7 +2498,7 @@ fn test_limit() {
     assert_eq!(row_cnt(resp.get_chunks()), 5);
     let spliter = ChunkSpliter::new(resp.take_chunks().into_vec());
     for (row, (id, name, cnt)) in spliter.zip(expect.clone()) {
-        let name_datum = name.map(|s| s.as_bytes()).into();
+        let name_datum: Datum = name.map(|s| s.as_bytes()).into();
         let expected_encoded = datum::encode_value(&[id.into(), name_datum, cnt.into()]).unwrap();
         assert_eq!(id, row.handle);
         assert_eq!(row.data, &*expected_encoded);
@@ -1598,7 +2509,7 @@ fn test_limit() {
     let mut row_count = 0;
     let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
     for (row, (id, name, cnt)) in spliter.zip(expect) {
-        let name_datum = name.map(|s| s.as_bytes()).into();
+        let name_datum: Datum = name.map(|s| s.as_bytes()).into();
         let expected_encoded = datum::encode_value(&[id.into(), name_datum, cnt.into()]).unwrap();
         let result_encoded = datum::encode_value(&row).unwrap();
         assert_eq!(&*result_encoded, &*expected_encoded);
@@ -1634,7 +2545,7 @@ fn test_reverse() {
     let spliter = ChunkSpliter::new(resp.take_chunks().into_vec());
 
     for (row, (id, name, cnt)) in spliter.zip(expect.clone()) {
-        let name_datum = name.map(|s| s.as_bytes()).into();
+        let name_datum: Datum = name.map(|s| s.as_bytes()).into();
         let expected_encoded = datum::encode_value(&[id.into(), name_datum, cnt.into()]).unwrap();
         assert_eq!(id, row.handle);
         assert_eq!(row.data, &*expected_encoded);
@@ -1648,7 +2559,7 @@ fn test_reverse() {
     let mut row_count = 0;
     let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
     for (row, (id, name, cnt)) in spliter.zip(expect) {
-        let name_datum = name.map(|s| s.as_bytes()).into();
+        let name_datum: Datum = name.map(|s| s.as_bytes()).into();
         let expected_encoded = datum::encode_value(&[id.into(), name_datum, cnt.into()]).unwrap();
         let result_encoded = datum::encode_value(&row).unwrap();
         assert_eq!(&*result_encoded, &*expected_encoded);
@@ -1674,6 +2585,90 @@ fn handle_select(end_point: &Worker<EndPointTask>, req: Request) -> SelectRespon
     sel_resp
 }
 
+#[test]
+fn test_memory_quota_for_dag() {
+    let names: Vec<_> = (0..200).map(|i| format!("name:{}", i)).collect();
+    let data: Vec<_> = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (i as i64, Some(name.as_str()), i as i64 % 4))
+        .collect();
+
+    let product = ProductTable::new();
+    let (store, mut end_point) = init_with_data(&product, &data);
+    let mut quota_end_point = Worker::new("test memory quota worker");
+    let mut cfg = Config::default();
+    cfg.end_point_concurrency = 1;
+    cfg.end_point_memory_quota = ReadableSize::kb(2);
+    let pd_worker = FutureWorker::new("test pd worker");
+    let runner = EndPointHost::new(
+        store.get_engine(),
+        quota_end_point.scheduler(),
+        &cfg,
+        pd_worker.scheduler(),
+    );
+    quota_end_point.start_batch(runner, 5).unwrap();
+
+    // a few groups fit in the quota.
+    let req = DAGSelect::from(&product.table)
+        .count()
+        .group_by(&[product.count])
+        .build();
+    let resp = handle_request(&quota_end_point, req);
+    assert!(resp.get_other_error().is_empty(), "{:?}", resp);
+    let mut sel_resp = SelectResponse::new();
+    sel_resp.merge_from_bytes(resp.get_data()).unwrap();
+    let spliter = DAGChunkSpliter::new(sel_resp.take_chunks().into_vec(), 2);
+    assert_eq!(spliter.count(), 4);
+
+    // while a group for every row doesn't, unless the quota is unlimited.
+    let req = DAGSelect::from(&product.table)
+        .count()
+        .group_by(&[product.name])
+        .build();
+    let resp = handle_request(&quota_end_point, req.clone());
+    assert!(
+        resp.get_other_error().contains("memory quota exceeded"),
+        "{:?}",
+        resp
+    );
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 2);
+    assert_eq!(spliter.count(), data.len());
+
+    quota_end_point.stop().unwrap().join().unwrap();
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_timeout_for_dag() {
+    let names: Vec<_> = (0..1000).map(|i| format!("name:{}", i)).collect();
+    let data: Vec<_> = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (i as i64, Some(name.as_str()), i as i64))
+        .collect();
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+    let req = DAGSelect::from(&product.table).build();
+
+    let (tx, rx) = mpsc::channel();
+    let mut task = RequestTask::new(req.clone(), box move |r| tx.send(r).unwrap());
+    task.set_timeout(Duration::from_millis(0));
+    end_point.schedule(EndPointTask::Request(task)).unwrap();
+    let resp = rx.recv_timeout(Duration::from_secs(3)).unwrap();
+    assert!(resp.get_data().is_empty(), "{:?}", resp);
+    assert_eq!(resp.get_other_error(), "request outdated.");
+
+    // all the rows are read without the timeout.
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+    assert_eq!(spliter.count(), data.len());
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
 #[test]
 fn test_index() {
     let data = vec![
@@ -1713,6 +2708,64 @@ fn test_index() {
     end_point.stop().unwrap().join().unwrap();
 }
 
+#[test]
+fn test_unique_index() {
+    let data = vec![
+        (1, Some("name:4"), 2),
+        (2, Some("name:1"), 3),
+        (4, None, 1),
+        (5, Some("name:3"), 4),
+        (6, None, 4),
+    ];
+
+    let product = ProductTable::with_unique_name();
+    let (_, mut end_point) = init_with_data(&product, &data);
+    // NULL names come first, ordered by the handles appended to their keys.
+    let expect = vec![
+        (4, None),
+        (6, None),
+        (2, Some("name:1")),
+        (5, Some("name:3")),
+        (1, Some("name:4")),
+    ];
+    // for selection
+    let req = Select::from_index(&product.table, product.name).build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = ChunkSpliter::new(resp.take_chunks().into_vec());
+    let handles: Vec<_> = spliter.map(|row| row.handle).collect();
+    let expect_handles: Vec<_> = expect.iter().map(|&(id, _)| id).collect();
+    assert_eq!(handles, expect_handles);
+    // for dag
+    let req = DAGSelect::from_index(&product.table, product.name).build();
+    let mut resp = handle_select(&end_point, req);
+    let mut row_count = 0;
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 2);
+    for (row, &(id, name)) in spliter.zip(&expect) {
+        let name_datum: Datum = name.map(|s| s.as_bytes()).into();
+        let expected_encoded = datum::encode_value(&[name_datum, id.into()]).unwrap();
+        let result_encoded = datum::encode_value(&row).unwrap();
+        assert_eq!(&*result_encoded, &*expected_encoded);
+        row_count += 1;
+    }
+    assert_eq!(row_count, expect.len());
+
+    // a non-NULL name is keyed by itself alone, with its handle in the value.
+    let name_key = datum::encode_key(&[Datum::Bytes(b"name:3".to_vec())]).unwrap();
+    let idx_key = table::encode_index_seek_key(product.table.id, product.name.index, &name_key);
+    let mut range = KeyRange::new();
+    range.set_start(idx_key.clone());
+    range.set_end(idx_key.into_iter().chain(Some(0)).collect());
+    let mut sel = DAGSelect::from_index(&product.table, product.name);
+    sel.key_range = range;
+    let mut resp = handle_select(&end_point, sel.build());
+    let mut spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 2);
+    let row = spliter.next().unwrap();
+    assert_eq!(row[1], Datum::I64(5));
+    assert!(spliter.next().is_none());
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
 #[test]
 fn test_index_reverse_limit() {
     let mut data = vec![
@@ -1803,18 +2856,50 @@ fn test_limit_oom() {
 }
 
 #[test]
-fn test_del_select() {
-    let mut data = vec![
-        (1, Some("name:0"), 2),
-        (2, Some("name:3"), 3),
-        (4, Some("name:0"), 1),
-        (5, Some("name:5"), 4),
-        (6, Some("name:5"), 4),
-        (7, None, 4),
-    ];
+fn test_topn_over_row_count() {
+    let names: Vec<_> = (0..200).map(|i| format!("name:{}", i)).collect();
+    let data: Vec<_> = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (i as i64, Some(name.as_str()), (i * 7 % 13) as i64))
+        .collect();
 
     let product = ProductTable::new();
-    let (mut store, mut end_point) = init_with_data(&product, &data);
+    let (_, mut end_point) = init_with_data(&product, &data);
+    let mut sorted = data.clone();
+    sorted.sort_by_key(|&(id, _, cnt)| (-cnt, id));
+    // the limits over the max heap limit sort all the rows at the end.
+    for &limit in &[150, 1000, 100000000] {
+        let req = DAGSelect::from(&product.table)
+            .order_by(product.count, true)
+            .limit(limit)
+            .build();
+        let mut resp = handle_select(&end_point, req);
+        let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+        let ids: Vec<_> = spliter.map(|row| row[0].i64()).collect();
+        let exp: Vec<_> = sorted
+            .iter()
+            .take(limit as usize)
+            .map(|&(id, _, _)| id)
+            .collect();
+        assert_eq!(ids, exp, "limit {}", limit);
+    }
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_del_select() {
+    let mut data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("name:3"), 3),
+        (4, Some("name:0"), 1),
+        (5, Some("name:5"), 4),
+        (6, Some("name:5"), 4),
+        (7, None, 4),
+    ];
+
+    let product = ProductTable::new();
+    let (mut store, mut end_point) = init_with_data(&product, &data);
 
     store.begin();
     let (id, name, cnt) = data.remove(3);
@@ -2057,6 +3142,146 @@ fn test_index_aggr_first() {
     end_point.stop().unwrap().join().unwrap();
 }
 
+#[test]
+fn test_aggr_arg_max_for_dag() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("name:3"), 3),
+        (4, Some("name:0"), 1),
+        (5, Some("name:5"), 4),
+        (6, Some("name:5"), 4),
+        (7, None, 4),
+        (8, Some("name:3"), 5),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+    // the max count of every name and the id of its row, the first one on ties.
+    let exp = vec![
+        (Datum::Bytes(b"name:0".to_vec()), 2, 1),
+        (Datum::Bytes(b"name:3".to_vec()), 5, 8),
+        (Datum::Bytes(b"name:5".to_vec()), 4, 5),
+        (Datum::Null, 4, 7),
+    ];
+    let req = DAGSelect::from(&product.table)
+        .arg_max(product.count, product.id)
+        .group_by(&[product.name])
+        .build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+    let mut row_count = 0;
+    for (row, (name, count, id)) in spliter.zip(exp.clone()) {
+        assert_eq!(row, vec![Datum::I64(count), Datum::I64(id), name]);
+        row_count += 1;
+    }
+    assert_eq!(row_count, exp.len());
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_aggr_group_concat_for_dag() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("name:3"), 3),
+        (4, Some("name:0"), 1),
+        (5, Some("name:5"), 4),
+        (6, None, 4),
+        (7, Some("name:6"), 4),
+        (8, Some("name:3"), 2),
+        (9, None, 5),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+    // the names of every count in the order of the rows, skipping NULLs.
+    let exp = vec![
+        (2, Datum::Bytes(b"name:0,name:3".to_vec())),
+        (3, Datum::Bytes(b"name:3".to_vec())),
+        (1, Datum::Bytes(b"name:0".to_vec())),
+        (4, Datum::Bytes(b"name:5,name:6".to_vec())),
+        (5, Datum::Null),
+    ];
+    let req = DAGSelect::from(&product.table)
+        .group_concat(product.name)
+        .group_by(&[product.count])
+        .build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 2);
+    let mut row_count = 0;
+    for (row, (count, names)) in spliter.zip(exp.clone()) {
+        assert_eq!(row, vec![names, Datum::I64(count)]);
+        row_count += 1;
+    }
+    assert_eq!(row_count, exp.len());
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_aggr_variance_for_dag() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("name:3"), 3),
+        (4, Some("name:0"), 1),
+        (5, Some("name:5"), 4),
+        (6, None, 4),
+        (7, Some("name:6"), 4),
+        (8, Some("name:3"), 8),
+        (9, Some("name:6"), 1),
+    ];
+
+    let product = ProductTable::new();
+    let (mut store, mut end_point) = init_with_data(&product, &data);
+
+    store.begin();
+    store
+        .insert_into(&product.table)
+        .set(product.id, Datum::I64(10))
+        .set(product.name, Datum::Bytes(b"name:7".to_vec()))
+        .set(product.count, Datum::Null)
+        .execute();
+    store.commit();
+
+    // the counts of every name are (2, 1), (3, 8), (4), (4), (4, 1) and (NULL).
+    let exp = vec![
+        (Datum::Bytes(b"name:0".to_vec()), Some(0.25), Some(0.5f64.sqrt())),
+        (Datum::Bytes(b"name:3".to_vec()), Some(6.25), Some(12.5f64.sqrt())),
+        (Datum::Bytes(b"name:5".to_vec()), Some(0.0), None),
+        (Datum::Null, Some(0.0), None),
+        (Datum::Bytes(b"name:6".to_vec()), Some(2.25), Some(4.5f64.sqrt())),
+        (Datum::Bytes(b"name:7".to_vec()), None, None),
+    ];
+    let req = DAGSelect::from(&product.table)
+        .var_pop(product.count)
+        .stddev_samp(product.count)
+        .group_by(&[product.name])
+        .build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 7);
+    let mut row_count = 0;
+    for (row, (name, var, stddev)) in spliter.zip(exp.clone()) {
+        assert_eq!(row.len(), 7);
+        // every function outputs its count, mean and m2 before the group by columns.
+        let partials = [
+            (ExprType::VarPop, &row[..3], var),
+            (ExprType::StddevSamp, &row[3..6], stddev),
+        ];
+        for &(tp, partial, exp) in &partials {
+            match (decode_variance(tp, partial).unwrap(), exp) {
+                (Datum::F64(f), Some(exp)) => assert!((f - exp).abs() < 1e-9, "{} != {}", f, exp),
+                (Datum::Null, None) => {}
+                (d, exp) => panic!("{:?} != {:?}", d, exp),
+            }
+        }
+        assert_eq!(row[6], name);
+        row_count += 1;
+    }
+    assert_eq!(row_count, exp.len());
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
 #[test]
 fn test_index_aggr_avg() {
     let data = vec![
@@ -2298,6 +3523,310 @@ fn test_where() {
 }
 
 
+#[test]
+fn test_where_substring() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("nam:4"), 3),
+        (4, None, 1),
+        (5, Some("name:1"), 4),
+        (6, Some("other:1"), 4),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+
+    let cond = {
+        let mut col = Expr::new();
+        col.set_tp(ExprType::ColumnRef);
+        col.mut_val().encode_i64(product.name.id).unwrap();
+        let mut substr = Expr::new();
+        substr.set_tp(ExprType::Substring);
+        substr.mut_children().push(col);
+        for &v in &[1, 4] {
+            let mut value = Expr::new();
+            value.set_tp(ExprType::Int64);
+            value.mut_val().encode_i64(v).unwrap();
+            substr.mut_children().push(value);
+        }
+
+        let mut value = Expr::new();
+        value.set_tp(ExprType::String);
+        value.set_val(b"name".to_vec());
+
+        let mut cond = Expr::new();
+        cond.set_tp(ExprType::EQ);
+        cond.mut_children().push(substr);
+        cond.mut_children().push(value);
+        cond
+    };
+
+    let req = Select::from(&product.table).where_expr(cond).build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = ChunkSpliter::new(resp.take_chunks().into_vec());
+    let handles: Vec<_> = spliter.map(|row| row.handle).collect();
+    assert_eq!(handles, vec![1, 5]);
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_where_substring_for_dag() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("nam:4"), 3),
+        (4, None, 1),
+        (5, Some("name:1"), 4),
+        (6, Some("other:1"), 4),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+    let cols = product.table.get_table_columns();
+    let name = || column_ref(&cols, product.name);
+    let int = |v: i64| {
+        let mut expr = Expr::new();
+        expr.set_tp(ExprType::Int64);
+        expr.mut_val().encode_i64(v).unwrap();
+        expr
+    };
+    let string = |s: &str| {
+        let mut expr = Expr::new();
+        expr.set_tp(ExprType::String);
+        expr.set_val(s.as_bytes().to_vec());
+        expr
+    };
+
+    // SUBSTRING(name, 1, 4) = 'name'
+    let substr = scalar_func(ScalarFuncSig::Substring3Args, vec![name(), int(1), int(4)]);
+    let cond = scalar_func(ScalarFuncSig::EQString, vec![substr, string("name")]);
+    let req = DAGSelect::from(&product.table).where_expr(cond).build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+    let ids: Vec<_> = spliter.map(|row| row[0].clone()).collect();
+    assert_eq!(ids, vec![Datum::I64(1), Datum::I64(5)]);
+
+    // LENGTH(name), UPPER(CONCAT(SUBSTRING(name, -1), '_', name)), LOWER('ID')
+    let concat = scalar_func(
+        ScalarFuncSig::Concat,
+        vec![
+            scalar_func(ScalarFuncSig::Substring2Args, vec![name(), int(-1)]),
+            string("_"),
+            name(),
+        ],
+    );
+    let req = DAGSelect::from(&product.table)
+        .group_by(&[product.id])
+        .group_by_expr(scalar_func(ScalarFuncSig::Length, vec![name()]))
+        .group_by_expr(scalar_func(ScalarFuncSig::Upper, vec![concat]))
+        .group_by_expr(scalar_func(ScalarFuncSig::Lower, vec![string("ID")]))
+        .build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 4);
+    let rows: Vec<_> = spliter.collect();
+    let bytes = |s: &str| Datum::Bytes(s.as_bytes().to_vec());
+    let exp = vec![
+        vec![Datum::I64(1), Datum::I64(6), bytes("0_NAME:0"), bytes("id")],
+        vec![Datum::I64(2), Datum::I64(5), bytes("4_NAM:4"), bytes("id")],
+        vec![Datum::I64(4), Datum::Null, Datum::Null, bytes("id")],
+        vec![Datum::I64(5), Datum::I64(6), bytes("1_NAME:1"), bytes("id")],
+        vec![Datum::I64(6), Datum::I64(7), bytes("1_OTHER:1"), bytes("id")],
+    ];
+    assert_eq!(rows, exp);
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_where_null_functions() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, None, 3),
+        (4, Some(""), 1),
+        (5, Some("name:1"), 4),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+    let name = || {
+        let mut col = Expr::new();
+        col.set_tp(ExprType::ColumnRef);
+        col.mut_val().encode_i64(product.name.id).unwrap();
+        col
+    };
+    let string = |s: &str| {
+        let mut value = Expr::new();
+        value.set_tp(ExprType::String);
+        value.set_val(s.as_bytes().to_vec());
+        value
+    };
+    let func = |tp: ExprType, children: Vec<Expr>| {
+        let mut expr = Expr::new();
+        expr.set_tp(tp);
+        expr.set_children(RepeatedField::from_vec(children));
+        expr
+    };
+
+    let cases = vec![
+        (func(ExprType::IsNull, vec![name()]), vec![2]),
+        (
+            func(ExprType::Not, vec![func(ExprType::IsNull, vec![name()])]),
+            vec![1, 4, 5],
+        ),
+        // the NULL name is selected along with the empty one.
+        (
+            func(
+                ExprType::EQ,
+                vec![func(ExprType::Coalesce, vec![name(), string("")]), string("")],
+            ),
+            vec![2, 4],
+        ),
+        (
+            func(
+                ExprType::EQ,
+                vec![func(ExprType::IfNull, vec![name(), string("none")]), string("none")],
+            ),
+            vec![2],
+        ),
+        // while comparing the NULL name itself selects nothing.
+        (func(ExprType::EQ, vec![name(), string("")]), vec![4]),
+    ];
+    for (cond, exp) in cases {
+        let req = Select::from(&product.table).where_expr(cond).build();
+        let mut resp = handle_select(&end_point, req);
+        let spliter = ChunkSpliter::new(resp.take_chunks().into_vec());
+        let handles: Vec<_> = spliter.map(|row| row.handle).collect();
+        assert_eq!(handles, exp);
+    }
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_where_null_functions_for_dag() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, None, 3),
+        (4, Some(""), 1),
+        (5, Some("name:1"), 4),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+    let cols = product.table.get_table_columns();
+    let name = || column_ref(&cols, product.name);
+    let string = |s: &str| {
+        let mut value = Expr::new();
+        value.set_tp(ExprType::String);
+        value.set_val(s.as_bytes().to_vec());
+        value
+    };
+    let is_null = || scalar_func(ScalarFuncSig::StringIsNull, vec![name()]);
+
+    let cases = vec![
+        (is_null(), vec![2]),
+        (scalar_func(ScalarFuncSig::UnaryNot, vec![is_null()]), vec![1, 4, 5]),
+        // the NULL name is selected along with the empty one.
+        (
+            scalar_func(
+                ScalarFuncSig::EQString,
+                vec![
+                    scalar_func(ScalarFuncSig::CoalesceString, vec![name(), string("")]),
+                    string(""),
+                ],
+            ),
+            vec![2, 4],
+        ),
+        (
+            scalar_func(
+                ScalarFuncSig::EQString,
+                vec![
+                    scalar_func(ScalarFuncSig::IfNullString, vec![name(), string("none")]),
+                    string("none"),
+                ],
+            ),
+            vec![2],
+        ),
+        // while comparing the NULL name itself selects nothing.
+        (scalar_func(ScalarFuncSig::EQString, vec![name(), string("")]), vec![4]),
+    ];
+    for (cond, exp) in cases {
+        let req = DAGSelect::from(&product.table).where_expr(cond).build();
+        let mut resp = handle_select(&end_point, req);
+        let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+        let ids: Vec<_> = spliter.map(|row| row[0].clone()).collect();
+        let exp: Vec<_> = exp.into_iter().map(Datum::I64).collect();
+        assert_eq!(ids, exp);
+    }
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_where_logic_for_dag() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, None, 1),
+        (4, Some("name:3"), 1),
+        (5, None, 4),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+    let cols = product.table.get_table_columns();
+    let col = |col: Column| {
+        let mut expr = Expr::new();
+        expr.set_tp(ExprType::ColumnRef);
+        expr.mut_val()
+            .encode_i64(offset_for_column(&cols, col.id))
+            .unwrap();
+        expr
+    };
+    let int = |v: i64| {
+        let mut expr = Expr::new();
+        expr.set_tp(ExprType::Int64);
+        expr.mut_val().encode_i64(v).unwrap();
+        expr
+    };
+    let string = |s: &str| {
+        let mut expr = Expr::new();
+        expr.set_tp(ExprType::String);
+        expr.set_val(s.as_bytes().to_vec());
+        expr
+    };
+    let name_is =
+        |s: &str| scalar_func(ScalarFuncSig::EQString, vec![col(product.name), string(s)]);
+    let count_lt = |v: i64| scalar_func(ScalarFuncSig::LTInt, vec![col(product.count), int(v)]);
+    let count_gt = |v: i64| scalar_func(ScalarFuncSig::GTInt, vec![col(product.count), int(v)]);
+
+    let cases = vec![
+        // count < 2 AND name IS NOT NULL
+        (
+            and(
+                count_lt(2),
+                not(scalar_func(ScalarFuncSig::StringIsNull, vec![col(product.name)])),
+            ),
+            vec![4],
+        ),
+        // a NULL name makes the AND false with a false count, and NULL with a true one.
+        (and(name_is("name:0"), count_gt(3)), vec![]),
+        (not(and(name_is("name:0"), count_gt(3))), vec![1, 2, 4]),
+        // a NULL name makes the OR true with a true count, and NULL with a false one.
+        (or(name_is("name:3"), count_gt(3)), vec![4, 5]),
+        (not(or(name_is("name:3"), count_gt(3))), vec![1]),
+    ];
+    for (cond, exp) in cases {
+        let req = DAGSelect::from(&product.table).where_expr(cond).build();
+        let mut resp = handle_select(&end_point, req);
+        let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+        let ids: Vec<_> = spliter.map(|row| row[0].clone()).collect();
+        let exp: Vec<_> = exp.into_iter().map(Datum::I64).collect();
+        assert_eq!(ids, exp);
+    }
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
 #[test]
 fn test_where_for_dag() {
     let data = vec![
@@ -2347,19 +3876,432 @@ fn test_where_for_dag() {
 }
 
 #[test]
-fn test_handle_truncate() {
+fn test_where_like_column_for_dag() {
     let data = vec![
         (1, Some("name:0"), 2),
-        (2, Some("name:4"), 3),
-        (4, Some("name:3"), 1),
+        (2, Some("name_%"), 3),
+        (4, None, 1),
         (5, Some("name:1"), 4),
     ];
 
     let product = ProductTable::new();
     let (_, mut end_point) = init_with_data(&product, &data);
-
-    let cases = vec![
-        {
+    let cols = product.table.get_table_columns();
+    let cond = {
+        let name_offset = offset_for_column(&cols, product.name.id);
+        let mut cond = Expr::new();
+        cond.set_tp(ExprType::ScalarFunc);
+        cond.set_sig(ScalarFuncSig::LikeSig);
+        // the pattern is the column itself, so every row gets its own pattern.
+        for _ in 0..2 {
+            let mut col = Expr::new();
+            col.set_tp(ExprType::ColumnRef);
+            col.mut_val().encode_i64(name_offset).unwrap();
+            cond.mut_children().push(col);
+        }
+        let mut escape = Expr::new();
+        escape.set_tp(ExprType::Int64);
+        escape.mut_val().encode_i64(i64::from(b'\\')).unwrap();
+        cond.mut_children().push(escape);
+        cond
+    };
+
+    let req = DAGSelect::from(&product.table).where_expr(cond).build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+    let mut ids = vec![];
+    for row in spliter {
+        ids.push(row[0].clone());
+    }
+    // all rows but the one with a NULL name match.
+    let expected: Vec<_> = data.iter()
+        .filter(|&&(_, name, _)| name.is_some())
+        .map(|&(id, _, _)| Datum::I64(id))
+        .collect();
+    assert_eq!(ids, expected);
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_where_strcmp_for_dag() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("name:4"), 3),
+        (4, Some("name:3"), 1),
+        (5, None, 4),
+        (6, Some("name:1"), 5),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+    let cols = product.table.get_table_columns();
+    let cond = {
+        let mut strcmp = Expr::new();
+        strcmp.set_tp(ExprType::ScalarFunc);
+        strcmp.set_sig(ScalarFuncSig::Strcmp);
+        let mut col = Expr::new();
+        col.set_tp(ExprType::ColumnRef);
+        let name_offset = offset_for_column(&cols, product.name.id);
+        col.mut_val().encode_i64(name_offset).unwrap();
+        strcmp.mut_children().push(col);
+        let mut threshold = Expr::new();
+        threshold.set_tp(ExprType::String);
+        threshold.set_val(b"name:3".to_vec());
+        strcmp.mut_children().push(threshold);
+
+        let mut zero = Expr::new();
+        zero.set_tp(ExprType::Int64);
+        zero.mut_val().encode_i64(0).unwrap();
+
+        let mut cond = Expr::new();
+        cond.set_tp(ExprType::ScalarFunc);
+        cond.set_sig(ScalarFuncSig::LTInt);
+        cond.mut_children().push(strcmp);
+        cond.mut_children().push(zero);
+        cond
+    };
+
+    let req = DAGSelect::from(&product.table).where_expr(cond).build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+    let mut ids = vec![];
+    for row in spliter {
+        ids.push(row[0].clone());
+    }
+    // STRCMP(name, 'name:3') < 0 keeps the names before the threshold, not the NULL one.
+    assert_eq!(ids, vec![Datum::I64(1), Datum::I64(6)]);
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_where_like_for_dag() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("name:1"), 3),
+        (3, Some("name_2"), 1),
+        (4, None, 4),
+        (5, Some("name%3"), 1),
+        (6, Some("name:10"), 2),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+    let cols = product.table.get_table_columns();
+    let cases = vec![
+        ("name:_", vec![1, 2]),
+        ("%:1%", vec![2, 6]),
+        ("%0", vec![1, 6]),
+        // escaped wildcards only match themselves.
+        ("name\\_%", vec![3]),
+        ("name\\%_", vec![5]),
+        // a NULL name is never matched.
+        ("%", vec![1, 2, 3, 5, 6]),
+    ];
+    for (pattern, exp) in cases {
+        let req = DAGSelect::from(&product.table)
+            .where_expr(like(&cols, product.name, pattern))
+            .build();
+        let mut resp = handle_select(&end_point, req);
+        let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+        let ids: Vec<_> = spliter.map(|row| row[0].clone()).collect();
+        let exp: Vec<_> = exp.into_iter().map(Datum::I64).collect();
+        assert_eq!(ids, exp, "{}", pattern);
+    }
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_where_position_for_dag() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("my name:1"), 3),
+        (3, Some("name_2"), 1),
+        (4, None, 4),
+        (5, Some("name:3"), 1),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+    let cols = product.table.get_table_columns();
+    // POSITION('name:' IN name) = 1
+    let cond = {
+        let mut substr = Expr::new();
+        substr.set_tp(ExprType::String);
+        substr.set_val(b"name:".to_vec());
+        let mut col = Expr::new();
+        col.set_tp(ExprType::ColumnRef);
+        let name_offset = offset_for_column(&cols, product.name.id);
+        col.mut_val().encode_i64(name_offset).unwrap();
+
+        let mut position = Expr::new();
+        position.set_tp(ExprType::ScalarFunc);
+        position.set_sig(ScalarFuncSig::Locate2Args);
+        position.mut_children().push(substr);
+        position.mut_children().push(col);
+
+        let mut one = Expr::new();
+        one.set_tp(ExprType::Int64);
+        one.mut_val().encode_i64(1).unwrap();
+
+        let mut cond = Expr::new();
+        cond.set_tp(ExprType::ScalarFunc);
+        cond.set_sig(ScalarFuncSig::EQInt);
+        cond.mut_children().push(position);
+        cond.mut_children().push(one);
+        cond
+    };
+
+    let req = DAGSelect::from(&product.table).where_expr(cond).build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+    let ids: Vec<_> = spliter.map(|row| row[0].clone()).collect();
+    assert_eq!(ids, vec![Datum::I64(1), Datum::I64(5)]);
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_concat_ws_for_dag() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, None, 3),
+        (4, Some(""), 1),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+    let cols = product.table.get_table_columns();
+    let string = |s: &str| {
+        let mut expr = Expr::new();
+        expr.set_tp(ExprType::String);
+        expr.set_val(s.as_bytes().to_vec());
+        expr
+    };
+    // CONCAT_WS('-', 'p', name, 'q') skips a NULL name, but not an empty one.
+    let func = scalar_func(
+        ScalarFuncSig::ConcatWS,
+        vec![
+            string("-"),
+            string("p"),
+            column_ref(&cols, product.name),
+            string("q"),
+        ],
+    );
+    let req = DAGSelect::from(&product.table).group_by_expr(func).build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 1);
+    let rows: Vec<_> = spliter.collect();
+    let exp = vec![
+        vec![Datum::Bytes(b"p-name:0-q".to_vec())],
+        vec![Datum::Bytes(b"p-q".to_vec())],
+        vec![Datum::Bytes(b"p--q".to_vec())],
+    ];
+    assert_eq!(rows, exp);
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_repeat_reverse_for_dag() {
+    let data = vec![
+        (1, Some("ab"), 2),
+        (2, None, 3),
+        (3, Some("测试"), 1),
+        (4, Some("cd"), -1),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+    let cols = product.table.get_table_columns();
+    // REPEAT(name, count), REVERSE(name)
+    let repeat = scalar_func(
+        ScalarFuncSig::Repeat,
+        vec![
+            column_ref(&cols, product.name),
+            column_ref(&cols, product.count),
+        ],
+    );
+    let reverse = scalar_func(ScalarFuncSig::Reverse, vec![column_ref(&cols, product.name)]);
+    let req = DAGSelect::from(&product.table)
+        .group_by(&[product.id])
+        .group_by_expr(repeat)
+        .group_by_expr(reverse)
+        .build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+    let rows: Vec<_> = spliter.collect();
+    let bytes = |s: &str| Datum::Bytes(s.as_bytes().to_vec());
+    let exp = vec![
+        vec![Datum::I64(1), bytes("abab"), bytes("ba")],
+        vec![Datum::I64(2), Datum::Null, Datum::Null],
+        vec![Datum::I64(3), bytes("测试"), bytes("试测")],
+        vec![Datum::I64(4), bytes(""), bytes("dc")],
+    ];
+    assert_eq!(rows, exp);
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_ascii_ord_for_dag() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("测试"), 3),
+        (3, Some(""), 1),
+        (4, None, 1),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+    let cols = product.table.get_table_columns();
+    let name = || vec![column_ref(&cols, product.name)];
+    let req = DAGSelect::from(&product.table)
+        .group_by(&[product.id])
+        .group_by_expr(scalar_func(ScalarFuncSig::ASCII, name()))
+        .group_by_expr(scalar_func(ScalarFuncSig::Ord, name()))
+        .build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+    let rows: Vec<_> = spliter.collect();
+    // ASCII only reads the first byte, while ORD reads the first utf8 character.
+    let exp = vec![
+        vec![Datum::I64(1), Datum::I64(110), Datum::I64(110)],
+        vec![Datum::I64(2), Datum::I64(0xe6), Datum::I64(0xe6b58b)],
+        vec![Datum::I64(3), Datum::I64(0), Datum::I64(0)],
+        vec![Datum::I64(4), Datum::Null, Datum::Null],
+    ];
+    assert_eq!(rows, exp);
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_where_commit_ts_for_dag() {
+    let product = ProductTable::new();
+    let (mut store, mut end_point) = init_data_with_commit(&product, &[], false);
+    let mut commit_ts = vec![];
+    for id in 1..4 {
+        store.begin();
+        store
+            .insert_into(&product.table)
+            .set(product.id, Datum::I64(id))
+            .set(product.name, Datum::Bytes(format!("name:{}", id).into_bytes()))
+            .set(product.count, Datum::I64(id))
+            .execute();
+        commit_ts.push(store.commit());
+    }
+
+    for (i, &ts) in commit_ts.iter().enumerate() {
+        // commit_ts < ts
+        let cond = {
+            let mut col = Expr::new();
+            col.set_tp(ExprType::ColumnRef);
+            col.mut_val().encode_i64(3).unwrap();
+            let mut value = Expr::new();
+            value.set_tp(ExprType::Int64);
+            value.mut_val().encode_i64(ts as i64).unwrap();
+            let mut cond = Expr::new();
+            cond.set_tp(ExprType::ScalarFunc);
+            cond.set_sig(ScalarFuncSig::LTInt);
+            cond.mut_children().push(col);
+            cond.mut_children().push(value);
+            cond
+        };
+        let req = DAGSelect::from(&product.table)
+            .with_commit_ts()
+            .where_expr(cond)
+            .build();
+        let mut resp = handle_select(&end_point, req);
+        let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 4);
+        let rows: Vec<_> = spliter.collect();
+        // only the rows committed before `ts` are returned.
+        assert_eq!(rows.len(), i);
+        for (row, (id, &ts)) in rows.iter().zip((1..).zip(&commit_ts)) {
+            assert_eq!(row[0], Datum::I64(id));
+            assert_eq!(row[3], Datum::U64(ts));
+        }
+    }
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_where_in_for_dag() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("name:3"), 3),
+        (4, Some("name:0"), 1),
+        (5, Some("name:5"), 4),
+        (6, None, 4),
+        (7, Some("name:6"), 2),
+    ];
+
+    let product = ProductTable::new();
+    let (mut store, mut end_point) = init_with_data(&product, &data);
+    store.begin();
+    store
+        .insert_into(&product.table)
+        .set(product.id, Datum::I64(8))
+        .set(product.name, Datum::Bytes(b"name:3".to_vec()))
+        .set(product.count, Datum::Null)
+        .execute();
+    store.commit();
+
+    {
+        let ids = |req: Request| -> Vec<i64> {
+            let mut resp = handle_select(&end_point, req);
+            let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+            spliter.map(|row| row[0].i64()).collect()
+        };
+        let name = |s: &str| Datum::Bytes(s.as_bytes().to_vec());
+        let cases = vec![
+            (product.count, vec![Datum::I64(2), Datum::I64(4)], vec![1, 5, 6, 7]),
+            (product.count, vec![Datum::I64(5)], vec![]),
+            (product.name, vec![name("name:0"), name("name:3")], vec![1, 2, 4, 8]),
+            // NULL never matches, whether in the column or in the list.
+            (product.count, vec![Datum::I64(3), Datum::Null], vec![2]),
+            (product.name, vec![Datum::Null], vec![]),
+        ];
+        for (col, values, exp) in cases {
+            let req = DAGSelect::from(&product.table)
+                .where_in(col, values)
+                .build();
+            assert_eq!(ids(req), exp);
+        }
+
+        // with NULL in the list, NOT IN is NULL for every row without a match.
+        let cols = product.table.get_table_columns();
+        let not_in = |values: Vec<Datum>| {
+            let mut cond = Expr::new();
+            cond.set_tp(ExprType::ScalarFunc);
+            cond.set_sig(ScalarFuncSig::UnaryNot);
+            cond.mut_children().push(in_list(&cols, product.count, values));
+            DAGSelect::from(&product.table).where_expr(cond).build()
+        };
+        assert_eq!(ids(not_in(vec![Datum::I64(2), Datum::I64(4)])), vec![2, 4]);
+        assert_eq!(ids(not_in(vec![Datum::I64(2), Datum::Null])), Vec::<i64>::new());
+    }
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_handle_truncate() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("name:4"), 3),
+        (4, Some("name:3"), 1),
+        (5, Some("name:1"), 4),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+
+    let cases = vec![
+        {
             // count > "2x"
             let mut col = Expr::new();
             col.set_tp(ExprType::ColumnRef);
@@ -2416,7 +4358,7 @@ fn test_handle_truncate() {
         let mut spliter = ChunkSpliter::new(resp.take_chunks().into_vec());
         let row = spliter.next().unwrap();
         let (id, name, cnt) = data[2];
-        let name_datum = name.map(|s| s.as_bytes()).into();
+        let name_datum: Datum = name.map(|s| s.as_bytes()).into();
         let expected_encoded =
             datum::encode_value(&[Datum::I64(id), name_datum, cnt.into()]).unwrap();
         assert_eq!(id, row.handle);
@@ -2436,6 +4378,91 @@ fn test_handle_truncate() {
     end_point.stop().unwrap().join().unwrap();
 }
 
+#[test]
+fn test_where_crc32() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("name:4"), 3),
+        (4, Some("name:3"), 1),
+        (5, None, 4),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+
+    // crc32(name) = crc32("name:3")
+    let mut col = Expr::new();
+    col.set_tp(ExprType::ColumnRef);
+    col.mut_val().encode_i64(product.name.id).unwrap();
+    let mut crc32 = Expr::new();
+    crc32.set_tp(ExprType::Crc32);
+    crc32.mut_children().push(col);
+    let mut value = Expr::new();
+    value.set_tp(ExprType::Uint64);
+    value.mut_val().encode_u64(977733646).unwrap();
+    let mut cond = Expr::new();
+    cond.set_tp(ExprType::EQ);
+    cond.mut_children().push(crc32);
+    cond.mut_children().push(value);
+
+    let req = Select::from(&product.table).where_expr(cond).build();
+    let mut resp = handle_select(&end_point, req);
+    assert_eq!(row_cnt(resp.get_chunks()), 1);
+    let mut spliter = ChunkSpliter::new(resp.take_chunks().into_vec());
+    let row = spliter.next().unwrap();
+    let (id, name, cnt) = data[2];
+    let name_datum: Datum = name.map(|s| s.as_bytes()).into();
+    let expected_encoded = datum::encode_value(&[Datum::I64(id), name_datum, cnt.into()]).unwrap();
+    assert_eq!(id, row.handle);
+    assert_eq!(row.data, &*expected_encoded);
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_where_crc32_for_dag() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("name:4"), 3),
+        (4, Some("name:3"), 1),
+        (5, None, 4),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+    let cols = product.table.get_table_columns();
+    // crc32(name) = crc32("name:3")
+    let crc32 = || scalar_func(ScalarFuncSig::CRC32, vec![column_ref(&cols, product.name)]);
+    let mut value = Expr::new();
+    value.set_tp(ExprType::Int64);
+    value.mut_val().encode_i64(977733646).unwrap();
+    let cond = scalar_func(ScalarFuncSig::EQInt, vec![crc32(), value]);
+
+    let req = DAGSelect::from(&product.table).where_expr(cond).build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+    let ids: Vec<_> = spliter.map(|row| row[0].clone()).collect();
+    assert_eq!(ids, vec![Datum::I64(4)]);
+
+    // the checksums projected over all the names.
+    let req = DAGSelect::from(&product.table)
+        .group_by(&[product.id])
+        .group_by_expr(crc32())
+        .build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 2);
+    let rows: Vec<_> = spliter.collect();
+    let exp = vec![
+        vec![Datum::I64(1), Datum::I64(2739820980)],
+        vec![Datum::I64(2), Datum::I64(2753797549)],
+        vec![Datum::I64(4), Datum::I64(977733646)],
+        vec![Datum::I64(5), Datum::Null],
+    ];
+    assert_eq!(rows, exp);
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
 #[test]
 fn test_handle_truncate_for_dag() {
     let data = vec![
@@ -2523,7 +4550,7 @@ fn test_handle_truncate_for_dag() {
         let mut spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
         let row = spliter.next().unwrap();
         let (id, name, cnt) = data[2];
-        let name_datum = name.map(|s| s.as_bytes()).into();
+        let name_datum: Datum = name.map(|s| s.as_bytes()).into();
         let expected_encoded =
             datum::encode_value(&[Datum::I64(id), name_datum, cnt.into()]).unwrap();
         let result_encoded = datum::encode_value(&row).unwrap();
@@ -2544,6 +4571,346 @@ fn test_handle_truncate_for_dag() {
     end_point.stop().unwrap().join().unwrap();
 }
 
+#[test]
+fn test_cast_column_for_dag() {
+    let data = vec![
+        (1, Some("3x"), 2),
+        (2, Some("10"), 3),
+        (4, Some("3"), 1),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+    let cols = product.table.get_table_columns();
+    // cast builds the cast of `col` by `sig`, without a length to fit the result in.
+    let cast = |sig: ScalarFuncSig, col: Column| {
+        let mut col_expr = Expr::new();
+        col_expr.set_tp(ExprType::ColumnRef);
+        col_expr
+            .mut_val()
+            .encode_i64(offset_for_column(&cols, col.id))
+            .unwrap();
+        let mut expr = scalar_func(sig, vec![col_expr]);
+        expr.mut_field_type().set_flen(-1);
+        expr.mut_field_type().set_decimal(-1);
+        expr
+    };
+    let project = |end_point: &Worker<EndPointTask>, expr: Expr, flags: &[u64]| {
+        let req = DAGSelect::from(&product.table)
+            .group_by_expr(expr)
+            .build_with(flags);
+        let mut resp = handle_select(end_point, req);
+        let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 1);
+        spliter.map(|mut row| row.pop().unwrap()).collect::<Vec<_>>()
+    };
+
+    let strs = project(&end_point, cast(ScalarFuncSig::CastIntAsString, product.count), &[0]);
+    let exp: Vec<Datum> = vec![b"2".as_ref().into(), b"3".as_ref().into(), b"1".as_ref().into()];
+    assert_eq!(strs, exp);
+
+    let decs = project(&end_point, cast(ScalarFuncSig::CastIntAsDecimal, product.count), &[0]);
+    let exp: Vec<_> = data.iter().map(|&(_, _, cnt)| Datum::Dec(cnt.into())).collect();
+    assert_eq!(decs, exp);
+
+    // "3x" is cast to 3 only if the truncation is ignored.
+    let ints = project(
+        &end_point,
+        cast(ScalarFuncSig::CastStringAsInt, product.name),
+        &[FLAG_IGNORE_TRUNCATE],
+    );
+    assert_eq!(ints, vec![Datum::I64(3), Datum::I64(10)]);
+    let req = DAGSelect::from(&product.table)
+        .group_by_expr(cast(ScalarFuncSig::CastStringAsInt, product.name))
+        .build();
+    let resp = handle_request(&end_point, req);
+    assert!(!resp.get_other_error().is_empty(), "{:?}", resp);
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_arithmetic_for_dag() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("name:4"), 3),
+        (4, Some("name:3"), 1),
+        (5, Some("name:1"), 4),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+    let cols = product.table.get_table_columns();
+    // id = "3x" - count, id = "4x" * count
+    let cases = vec![
+        (ScalarFuncSig::MinusInt, "3x", data[0]),
+        (ScalarFuncSig::MultiplyInt, "4x", data[2]),
+    ];
+
+    for (sig, lit, (id, name, cnt)) in cases {
+        let mut col_id = Expr::new();
+        col_id.set_tp(ExprType::ColumnRef);
+        let id_offset = offset_for_column(&cols, product.id.id);
+        col_id.mut_val().encode_i64(id_offset).unwrap();
+
+        let mut value = Expr::new();
+        value.set_tp(ExprType::String);
+        value.set_val(String::from(lit).into_bytes());
+        let mut int_value = Expr::new();
+        int_value.set_tp(ExprType::ScalarFunc);
+        int_value.set_sig(ScalarFuncSig::CastStringAsInt);
+        int_value.mut_children().push(value);
+
+        let mut col_count = Expr::new();
+        col_count.set_tp(ExprType::ColumnRef);
+        let count_offset = offset_for_column(&cols, product.count.id);
+        col_count.mut_val().encode_i64(count_offset).unwrap();
+
+        let mut arith = Expr::new();
+        arith.set_tp(ExprType::ScalarFunc);
+        arith.set_sig(sig);
+        arith.mut_children().push(int_value);
+        arith.mut_children().push(col_count);
+
+        let mut cond = Expr::new();
+        cond.set_tp(ExprType::ScalarFunc);
+        cond.set_sig(ScalarFuncSig::EQInt);
+        cond.mut_children().push(col_id);
+        cond.mut_children().push(arith);
+
+        let req = DAGSelect::from(&product.table)
+            .where_expr(cond)
+            .build_with(&[FLAG_IGNORE_TRUNCATE]);
+        let mut resp = handle_select(&end_point, req);
+        let mut spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+        let row = spliter.next().unwrap();
+        let name_datum: Datum = name.map(|s| s.as_bytes()).into();
+        let expected_encoded =
+            datum::encode_value(&[Datum::I64(id), name_datum, cnt.into()]).unwrap();
+        let result_encoded = datum::encode_value(&row).unwrap();
+        assert_eq!(&*result_encoded, &*expected_encoded);
+        assert_eq!(spliter.next().is_none(), true);
+    }
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_divide_by_zero_for_dag() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("name:4"), 3),
+        (4, Some("name:3"), 1),
+        (5, Some("name:1"), 4),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+    let cols = product.table.get_table_columns();
+    // count / 0.0 is null
+    let cond = {
+        let mut col = Expr::new();
+        col.set_tp(ExprType::ColumnRef);
+        let count_offset = offset_for_column(&cols, product.count.id);
+        col.mut_val().encode_i64(count_offset).unwrap();
+        let mut real_col = Expr::new();
+        real_col.set_tp(ExprType::ScalarFunc);
+        real_col.set_sig(ScalarFuncSig::CastIntAsReal);
+        real_col.mut_children().push(col);
+
+        let mut zero = Expr::new();
+        zero.set_tp(ExprType::Float64);
+        zero.mut_val().encode_f64(0.0).unwrap();
+
+        let mut div = Expr::new();
+        div.set_tp(ExprType::ScalarFunc);
+        div.set_sig(ScalarFuncSig::DivideReal);
+        div.mut_children().push(real_col);
+        div.mut_children().push(zero);
+
+        let mut cond = Expr::new();
+        cond.set_tp(ExprType::ScalarFunc);
+        cond.set_sig(ScalarFuncSig::RealIsNull);
+        cond.mut_children().push(div);
+        cond
+    };
+
+    // a read-only statement gets NULL.
+    let req = DAGSelect::from(&product.table).where_expr(cond.clone()).build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+    assert_eq!(spliter.count(), data.len());
+
+    // while write statements fail like the strict sql mode.
+    for flag in &[FLAG_IN_INSERT_STMT, FLAG_IN_UPDATE_OR_DELETE_STMT] {
+        let req = DAGSelect::from(&product.table)
+            .where_expr(cond.clone())
+            .build_with(&[*flag]);
+        let resp = handle_request(&end_point, req);
+        assert!(!resp.get_other_error().is_empty());
+    }
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_where_decimal_literal_for_dag() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("name:4"), 3),
+        (4, Some("name:3"), 1),
+        (5, Some("name:1"), 4),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+    let cols = product.table.get_table_columns();
+    let cond = {
+        // count > 2.5, the literal is an encoded decimal rather than a string.
+        let mut col = Expr::new();
+        col.set_tp(ExprType::ColumnRef);
+        let count_offset = offset_for_column(&cols, product.count.id);
+        col.mut_val().encode_i64(count_offset).unwrap();
+        let mut left = Expr::new();
+        left.set_tp(ExprType::ScalarFunc);
+        left.set_sig(ScalarFuncSig::CastIntAsDecimal);
+        left.mut_children().push(col);
+
+        let dec: Decimal = "2.5".parse().unwrap();
+        let (prec, frac) = dec.prec_and_frac();
+        let mut value = Expr::new();
+        value.set_tp(ExprType::MysqlDecimal);
+        value.mut_val().encode_decimal(&dec, prec, frac).unwrap();
+
+        let mut cond = Expr::new();
+        cond.set_tp(ExprType::ScalarFunc);
+        cond.set_sig(ScalarFuncSig::GTDecimal);
+        cond.mut_children().push(left);
+        cond.mut_children().push(value);
+        cond
+    };
+
+    // truncation is an error without flags, so none happened if the request succeeds.
+    let req = DAGSelect::from(&product.table).where_expr(cond).build();
+    let mut resp = handle_select(&end_point, req);
+    assert!(!resp.has_error(), "{:?}", resp.get_error());
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+    let ids: Vec<_> = spliter.map(|row| row[0].clone()).collect();
+    assert_eq!(ids, vec![Datum::I64(2), Datum::I64(5)]);
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_where_decimal_expr_for_dag() {
+    let data = vec![
+        (1, Some("name:0"), 2),
+        (2, Some("name:4"), 3),
+        (4, Some("name:3"), 1),
+        (5, Some("name:1"), 4),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+    let cols = product.table.get_table_columns();
+    let decimal = |s: &str| {
+        let dec: Decimal = s.parse().unwrap();
+        let (prec, frac) = dec.prec_and_frac();
+        let mut value = Expr::new();
+        value.set_tp(ExprType::MysqlDecimal);
+        value.mut_val().encode_decimal(&dec, prec, frac).unwrap();
+        value
+    };
+    let cond = {
+        // count * 1.5 > 3.25, the integer column is taken as a decimal without a cast.
+        let mut col = Expr::new();
+        col.set_tp(ExprType::ColumnRef);
+        let count_offset = offset_for_column(&cols, product.count.id);
+        col.mut_val().encode_i64(count_offset).unwrap();
+
+        let mut left = Expr::new();
+        left.set_tp(ExprType::ScalarFunc);
+        left.set_sig(ScalarFuncSig::MultiplyDecimal);
+        left.mut_children().push(col);
+        left.mut_children().push(decimal("1.5"));
+
+        let mut cond = Expr::new();
+        cond.set_tp(ExprType::ScalarFunc);
+        cond.set_sig(ScalarFuncSig::GTDecimal);
+        cond.mut_children().push(left);
+        cond.mut_children().push(decimal("3.25"));
+        cond
+    };
+
+    // truncation is an error without flags, so none happened if the request succeeds.
+    let req = DAGSelect::from(&product.table).where_expr(cond).build();
+    let mut resp = handle_select(&end_point, req);
+    assert!(!resp.has_error(), "{:?}", resp.get_error());
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+    let ids: Vec<_> = spliter.map(|row| row[0].clone()).collect();
+    assert_eq!(ids, vec![Datum::I64(2), Datum::I64(5)]);
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_order_by_decimal_expr_for_dag() {
+    let data = vec![
+        (1, Some("name:0"), 1),
+        (2, Some("name:1"), 30),
+        (4, Some("name:2"), 2),
+        (5, Some("name:3"), 10),
+        (6, Some("name:4"), 29),
+        (7, None, 3),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+    let cols = product.table.get_table_columns();
+    // count / 3, the quotients are decimals.
+    let quotient = || {
+        let mut col = Expr::new();
+        col.set_tp(ExprType::ColumnRef);
+        let count_offset = offset_for_column(&cols, product.count.id);
+        col.mut_val().encode_i64(count_offset).unwrap();
+        let mut left = Expr::new();
+        left.set_tp(ExprType::ScalarFunc);
+        left.set_sig(ScalarFuncSig::CastIntAsDecimal);
+        left.mut_children().push(col);
+
+        let dec = Decimal::from(3i64);
+        let (prec, frac) = dec.prec_and_frac();
+        let mut value = Expr::new();
+        value.set_tp(ExprType::MysqlDecimal);
+        value.mut_val().encode_decimal(&dec, prec, frac).unwrap();
+
+        let mut expr = Expr::new();
+        expr.set_tp(ExprType::ScalarFunc);
+        expr.set_sig(ScalarFuncSig::DivideDecimal);
+        expr.mut_children().push(left);
+        expr.mut_children().push(value);
+        expr
+    };
+
+    // 10.0000 sorts after 9.6667 although it's smaller as a string.
+    let cases = vec![
+        (false, 6, vec![1, 4, 7, 5, 6, 2]),
+        (true, 3, vec![2, 6, 5]),
+    ];
+    for (desc, limit, exp) in cases {
+        let req = DAGSelect::from(&product.table)
+            .order_by_expr(quotient(), desc)
+            .limit(limit)
+            .build();
+        let mut resp = handle_select(&end_point, req);
+        let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 3);
+        let ids: Vec<_> = spliter.map(|row| row[0].clone()).collect();
+        let exp: Vec<_> = exp.into_iter().map(Datum::I64).collect();
+        assert_eq!(ids, exp);
+    }
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
 #[test]
 fn test_default_val() {
     let mut data = vec![
@@ -2573,7 +4940,7 @@ fn test_default_val() {
     assert_eq!(row_cnt(resp.get_chunks()), 5);
     let spliter = ChunkSpliter::new(resp.take_chunks().into_vec());
     for (row, (id, name, cnt)) in spliter.zip(expect.clone()) {
-        let name_datum = name.map(|s| s.as_bytes()).into();
+        let name_datum: Datum = name.map(|s| s.as_bytes()).into();
         let expected_encoded =
             datum::encode_value(&[id.into(), name_datum, cnt.into(), Datum::I64(3)]).unwrap();
         assert_eq!(id, row.handle);
@@ -2585,7 +4952,7 @@ fn test_default_val() {
     let mut row_count = 0;
     let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 4);
     for (row, (id, name, cnt)) in spliter.zip(expect) {
-        let name_datum = name.map(|s| s.as_bytes()).into();
+        let name_datum: Datum = name.map(|s| s.as_bytes()).into();
         let expected_encoded =
             datum::encode_value(&[id.into(), name_datum, cnt.into(), Datum::I64(3)]).unwrap();
         let result_encoded = datum::encode_value(&row).unwrap();
@@ -2597,6 +4964,105 @@ fn test_default_val() {
     end_point.stop().unwrap().join().unwrap();
 }
 
+#[test]
+fn test_default_val_with_stored_null() {
+    let data = vec![(1, Some("name:0"), 2), (2, Some("name:3"), 3)];
+
+    let product = ProductTable::new();
+    let added = ColumnBuilder::new().col_type(TYPE_LONG).default(3).build();
+    let mut tbl = TableBuilder::new()
+        .add_col(product.id)
+        .add_col(product.name)
+        .add_col(product.count)
+        .add_col(added)
+        .build();
+    tbl.id = product.table.id;
+
+    // rows 1 and 2 are stored without the added column, while rows 3 and 4 store
+    // a NULL and a value for it.
+    let (mut store, mut end_point) = init_with_data(&product, &data);
+    store.begin();
+    for &(id, ref val) in &[(3, Datum::Null), (4, Datum::I64(7))] {
+        store
+            .insert_into(&tbl)
+            .set(product.id, Datum::I64(id))
+            .set(product.name, Datum::Null)
+            .set(product.count, Datum::I64(id))
+            .set(added, val.clone())
+            .execute();
+    }
+    store.commit();
+
+    let req = DAGSelect::from(&tbl).build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 4);
+    let expect = vec![
+        (1, Some("name:0"), 2, Datum::I64(3)),
+        (2, Some("name:3"), 3, Datum::I64(3)),
+        (3, None, 3, Datum::Null),
+        (4, None, 4, Datum::I64(7)),
+    ];
+    let mut row_count = 0;
+    for (row, (id, name, cnt, added)) in spliter.zip(expect) {
+        let name_datum: Datum = name.map(|s| s.as_bytes()).into();
+        let expected_encoded =
+            datum::encode_value(&[Datum::I64(id), name_datum, Datum::I64(cnt), added]).unwrap();
+        let result_encoded = datum::encode_value(&row).unwrap();
+        assert_eq!(&*result_encoded, &*expected_encoded);
+        row_count += 1;
+    }
+    assert_eq!(row_count, 4);
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
+#[test]
+fn test_null_and_empty_values() {
+    let data = vec![(1, None, 1), (2, Some(""), 2), (3, Some("name:3"), 3)];
+    let names = vec![
+        Datum::Null,
+        Datum::Bytes(vec![]),
+        Datum::Bytes(b"name:3".to_vec()),
+    ];
+
+    let product = ProductTable::new();
+    let (_, mut end_point) = init_with_data(&product, &data);
+    // for selection
+    let req = Select::from(&product.table).build();
+    let mut resp = handle_select(&end_point, req);
+    let spliter = ChunkSpliter::new(resp.take_chunks().into_vec());
+    let decoded: Vec<_> = spliter
+        .map(|row| row.data.as_slice().decode().unwrap()[1].clone())
+        .collect();
+    assert_eq!(decoded, names);
+    // for dag, the name is the second column of the table and the first of its index,
+    // in which NULL sorts before the empty bytes.
+    let reqs = vec![
+        (DAGSelect::from(&product.table).build(), 3, 1),
+        (
+            DAGSelect::from(&product.table)
+                .output_offsets(Some(vec![1]))
+                .build(),
+            1,
+            0,
+        ),
+        (
+            DAGSelect::from_index(&product.table, product.name).build(),
+            2,
+            0,
+        ),
+    ];
+    for (req, ncols, offset) in reqs {
+        let mut resp = handle_select(&end_point, req);
+        let decoded: Vec<_> = DAGChunkSpliter::new(resp.take_chunks().into_vec(), ncols)
+            .map(|row| row[offset].clone())
+            .collect();
+        assert_eq!(decoded, names);
+    }
+
+    end_point.stop().unwrap().join().unwrap();
+}
+
 
 #[test]
 fn test_output_offsets() {
@@ -2616,7 +5082,7 @@ fn test_output_offsets() {
     let mut resp = handle_select(&end_point, req);
     let spliter = DAGChunkSpliter::new(resp.take_chunks().into_vec(), 1);
     for (row, (_, name, _)) in spliter.zip(data) {
-        let name_datum = name.map(|s| s.as_bytes()).into();
+        let name_datum: Datum = name.map(|s| s.as_bytes()).into();
         let expected_encoded = datum::encode_value(&[name_datum]).unwrap();
         let result_encoded = datum::encode_value(&row).unwrap();
         assert_eq!(&*result_encoded, &*expected_encoded);
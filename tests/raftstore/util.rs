@@ -108,6 +108,8 @@ pub fn new_server_config(cluster_id: u64) -> ServerConfig {
         // use 2 or larger value here?
         grpc_raft_conn_num: 1,
         end_point_concurrency: 1,
+        end_point_high_concurrency: 1,
+        end_point_low_concurrency: 1,
         ..ServerConfig::default()
     }
 }